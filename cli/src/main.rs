@@ -1,44 +1,1329 @@
-use std::{collections::HashSet, fs};
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::process::ExitCode;
 
 use endfield_planner_core::config::GameData;
-use endfield_planner_core::constants::{MACHINE_DEFINITION_PATH, RECIPE_DEFINITION_PATH};
+use endfield_planner_core::constants::{
+    LOCALE_DIRECTORY_PATH, MACHINE_DEFINITION_PATH, RECIPE_DEFINITION_PATH,
+};
 use endfield_planner_core::error::ProductionError;
-use endfield_planner_core::output::print_summary;
-use endfield_planner_core::planner::plan_production;
+use endfield_planner_core::i18n::{
+    IdResolver, Locale, LocalizedResolver, Localizer, NameResolver, SearchIndex,
+};
+use endfield_planner_core::models::{AmountKind, ItemId, ProductionNode, RoundingPolicy};
+use endfield_planner_core::output::{
+    GraphFilter, RecipeGraph, SummaryOptions, SummarySort, TreeStyle, format_duration,
+    format_power, print_warnings, recipes_to_json, to_json, write_summary, write_tag_breakdown,
+};
+use endfield_planner_core::planner::{
+    MachineLimitViolation, PlanImpact, PlanOptions, PlanSummary, PlanWarning, PlanWarnings,
+    RecipeChangeKind, SavedPlan, ShareState, TraceFormat, Tracer, WriterTracer, bootstrap_plan,
+    plan_production_with_options, plan_within_machine_limits, ranked_recipes, unbuildable_machines,
+};
+
+const DEFAULT_ITEM: &str = "lc_wuling_battery";
+const DEFAULT_AMOUNT: u32 = 12; // per minute
+const DEFAULT_BOOTSTRAP_SECS: f64 = 600.0;
+
+// Exit codes for scripting
+const EXIT_FAILURE: u8 = 1;
+const EXIT_UNKNOWN_ITEM: u8 = 2;
+const EXIT_PARSE_FAILURE: u8 = 3;
+const EXIT_MISSING_ITEMS: u8 = 4;
+const EXIT_IO_ERROR: u8 = 5;
+
+/// What the CLI does.
+#[derive(Debug, Default, PartialEq)]
+enum Command {
+    /// Plan production for an item.
+    #[default]
+    Plan,
+    /// List the items that have recipes (`list`).
+    List,
+    /// Check the data files and print their warnings (`validate`).
+    Validate,
+    /// List the recipes for an item, best first (`recipes <item>`).
+    Recipes,
+    /// List what can be crafted from owned items (`what-can-i-make`).
+    WhatCanIMake,
+    /// Show how new data files change a saved plan
+    /// (`impact <plan.toml> --recipes <file>`).
+    Impact,
+    /// Print the recipe database as a graph (`graph`).
+    Graph,
+}
+
+/// Output format of `graph` (`--format dot|mermaid`).
+#[derive(Debug, Default, PartialEq)]
+enum GraphFormat {
+    #[default]
+    Dot,
+    Mermaid,
+}
+
+/// Command line options.
+#[derive(Debug, Default, PartialEq)]
+struct CliOptions {
+    command: Command,
+    /// Item ID or localized name to plan for (`--item`, or the argument
+    /// after `recipes`).
+    item: Option<String>,
+    /// Print only a one-line summary (`--quiet`).
+    quiet: bool,
+    /// Print the plan as canonical JSON (`--json`).
+    json: bool,
+    /// Fail when the plan contains unresolved items (`--fail-on-missing`).
+    fail_on_missing: bool,
+    /// Order of the totals lists (`--sort count|name`).
+    sort: SummarySort,
+    /// Draw the tree with ASCII instead of box-drawing characters
+    /// (`--ascii`).
+    ascii: bool,
+    /// Locale for item and machine names in the tree and totals
+    /// (`--lang`, or `--locale`).
+    locale: Option<Locale>,
+    /// Recipe tags the plan must not use (`--exclude-tag`, repeatable).
+    excluded_tags: Vec<String>,
+    /// How machine totals are rounded
+    /// (`--rounding ceil-per-node|ceil-per-type|exact`).
+    rounding: RoundingPolicy,
+    /// Show item descriptions in `list` (`--verbose`).
+    verbose: bool,
+    /// Owned items for `what-can-i-make` (`--have`, repeatable).
+    owned: Vec<String>,
+    /// Longest crafting chain `what-can-i-make` follows (`--depth`).
+    max_depth: Option<u32>,
+    /// Print every planner step to stderr, as JSON lines with `--json`
+    /// (`--trace`).
+    trace: bool,
+    /// Largest number of machines per node, reported as layout blocks
+    /// (`--max-machines`).
+    max_machines_per_node: Option<u32>,
+    /// Most machines available per type (`--limit machine=count`,
+    /// repeatable).
+    machine_limits: Vec<(String, u32)>,
+    /// Scale the target down to fit the machine limits (`--fit`).
+    fit: bool,
+    /// Also plan building the machines of the plan (`--bootstrap`).
+    bootstrap: bool,
+    /// Seconds to build the machines of `--bootstrap` in
+    /// (`--bootstrap-within`), 10 minutes if not given.
+    bootstrap_within_secs: Option<f64>,
+    /// Saved plan file for `impact` (the argument after `impact`).
+    plan_path: Option<String>,
+    /// New recipe file for `impact` (`--recipes`).
+    recipes_path: Option<String>,
+    /// New machine file for `impact` (`--machines`), the current one
+    /// if not given.
+    machines_path: Option<String>,
+    /// Factory code copied from the web app (`--code`). Sets the item,
+    /// amount and externally supplied items.
+    code: Option<String>,
+    /// Output format of `graph` (`--format`).
+    graph_format: GraphFormat,
+    /// Item whose ingredients `graph` is limited to (`--root`).
+    graph_root: Option<String>,
+    /// Recipe tag `graph` is limited to (`--tag`).
+    graph_tag: Option<String>,
+}
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<CliOptions, CliError> {
+    let mut options = CliOptions::default();
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "list" => options.command = Command::List,
+            "validate" => options.command = Command::Validate,
+            "recipes" => options.command = Command::Recipes,
+            "what-can-i-make" => options.command = Command::WhatCanIMake,
+            "impact" => options.command = Command::Impact,
+            "graph" => options.command = Command::Graph,
+            "--verbose" | "-v" => options.verbose = true,
+            "--item" => options.item = args.next(),
+            "--code" => options.code = args.next(),
+            "--quiet" | "-q" => options.quiet = true,
+            "--json" => options.json = true,
+            "--fail-on-missing" => options.fail_on_missing = true,
+            "--trace" => options.trace = true,
+            "--ascii" => options.ascii = true,
+            "--sort" => match args.next().as_deref() {
+                Some("count") => options.sort = SummarySort::Count,
+                Some("name") => options.sort = SummarySort::Name,
+                other => {
+                    let value = other.unwrap_or_default().to_string();
+                    return Err(CliError::InvalidArgument("--sort", value));
+                }
+            },
+            "--lang" | "--locale" => {
+                let code = args.next().unwrap_or_default();
+                match Locale::from_code(&code) {
+                    Some(locale) => options.locale = Some(locale),
+                    None => return Err(CliError::InvalidArgument("--lang", code)),
+                }
+            }
+            "--exclude-tag" => options.excluded_tags.extend(args.next()),
+            "--have" => options.owned.extend(args.next()),
+            "--max-machines" => {
+                let max = args.next().unwrap_or_default();
+                match max.parse() {
+                    Ok(max) => options.max_machines_per_node = Some(max),
+                    Err(_) => return Err(CliError::InvalidArgument("--max-machines", max)),
+                }
+            }
+            "--limit" => {
+                let limit = args.next().unwrap_or_default();
+                let parsed = limit.split_once('=').and_then(|(machine_id, count)| {
+                    Some((machine_id.to_string(), count.parse().ok()?))
+                });
+                match parsed {
+                    Some(limit) => options.machine_limits.push(limit),
+                    None => return Err(CliError::InvalidArgument("--limit", limit)),
+                }
+            }
+            "--fit" => options.fit = true,
+            "--bootstrap" => options.bootstrap = true,
+            "--bootstrap-within" => {
+                let secs = args.next().unwrap_or_default();
+                match secs.parse::<f64>() {
+                    Ok(within) if within.is_finite() && within > 0.0 => {
+                        options.bootstrap_within_secs = Some(within)
+                    }
+                    _ => return Err(CliError::InvalidArgument("--bootstrap-within", secs)),
+                }
+            }
+            "--format" => match args.next().as_deref() {
+                Some("dot") => options.graph_format = GraphFormat::Dot,
+                Some("mermaid") => options.graph_format = GraphFormat::Mermaid,
+                other => {
+                    let value = other.unwrap_or_default().to_string();
+                    return Err(CliError::InvalidArgument("--format", value));
+                }
+            },
+            "--root" => options.graph_root = args.next(),
+            "--tag" => options.graph_tag = args.next(),
+            "--recipes" => options.recipes_path = args.next(),
+            "--machines" => options.machines_path = args.next(),
+            "--depth" => {
+                let depth = args.next().unwrap_or_default();
+                match depth.parse() {
+                    Ok(depth) => options.max_depth = Some(depth),
+                    Err(_) => return Err(CliError::InvalidArgument("--depth", depth)),
+                }
+            }
+            "--rounding" => match args.next().as_deref() {
+                Some("ceil-per-node") => options.rounding = RoundingPolicy::CeilPerNode,
+                Some("ceil-per-type") => options.rounding = RoundingPolicy::CeilPerType,
+                Some("exact") => options.rounding = RoundingPolicy::Exact,
+                other => {
+                    let value = other.unwrap_or_default().to_string();
+                    return Err(CliError::InvalidArgument("--rounding", value));
+                }
+            },
+            other
+                if options.command == Command::Recipes
+                    && options.item.is_none()
+                    && !other.starts_with('-') =>
+            {
+                options.item = Some(other.to_string());
+            }
+            other
+                if options.command == Command::Impact
+                    && options.plan_path.is_none()
+                    && !other.starts_with('-') =>
+            {
+                options.plan_path = Some(other.to_string());
+            }
+            _ => {}
+        }
+    }
+
+    Ok(options)
+}
+
+/// Everything that can make the CLI fail.
+#[derive(Debug)]
+enum CliError {
+    Production(ProductionError),
+    Io(io::Error),
+    /// The plan has items without a recipe and `--fail-on-missing` was set.
+    MissingItems(Vec<String>),
+    /// A command was given without an argument it needs.
+    MissingArgument(&'static str),
+    /// An argument was given a value it doesn't accept.
+    InvalidArgument(&'static str, String),
+    /// Two arguments were given that can't be used together.
+    ConflictingArguments(&'static str, &'static str),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CliError::Production(error) => write!(f, "{}", error),
+            CliError::Io(error) => write!(f, "IO error: {}", error),
+            CliError::MissingItems(items) => {
+                write!(f, "No recipe found for: {}", items.join(", "))
+            }
+            CliError::MissingArgument(usage) => write!(f, "Missing argument, usage: {}", usage),
+            CliError::InvalidArgument(flag, value) => {
+                write!(f, "Invalid value for {}: '{}'", flag, value)
+            }
+            CliError::ConflictingArguments(first, second) => {
+                write!(f, "{} can't be used together with {}", first, second)
+            }
+        }
+    }
+}
+
+impl From<ProductionError> for CliError {
+    fn from(error: ProductionError) -> Self {
+        CliError::Production(error)
+    }
+}
+
+impl From<io::Error> for CliError {
+    fn from(error: io::Error) -> Self {
+        CliError::Io(error)
+    }
+}
+
+/// Maps an error to the documented process exit code.
+fn exit_code(error: &CliError) -> u8 {
+    match error {
+        CliError::Production(ProductionError::RecipeNotFound(_)) => EXIT_UNKNOWN_ITEM,
+        CliError::Production(ProductionError::ParseError(_)) => EXIT_PARSE_FAILURE,
+        CliError::Production(ProductionError::FileNotFound(_)) | CliError::Io(_) => EXIT_IO_ERROR,
+        CliError::Production(ProductionError::CyclicDependency(_))
+        | CliError::MissingArgument(_)
+        | CliError::InvalidArgument(..)
+        | CliError::ConflictingArguments(..) => EXIT_FAILURE,
+        CliError::Production(ProductionError::UnresolvedItems(_)) | CliError::MissingItems(_) => {
+            EXIT_MISSING_ITEMS
+        }
+    }
+}
+
+/// Fails with the unresolved items if the planner reported any.
+fn check_missing_items(warnings: &PlanWarnings) -> Result<(), CliError> {
+    let missing: Vec<String> = warnings
+        .iter()
+        .filter_map(|warning| match warning {
+            PlanWarning::MissingRecipe { item_id } => Some(item_id.to_string()),
+            _ => None,
+        })
+        .collect();
+
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(CliError::MissingItems(missing))
+    }
+}
+
+/// Loads whichever locale files exist for the locale, on top of the
+/// English files so untranslated names and descriptions fall back to English.
+fn load_localizer(locale: Locale) -> Localizer {
+    let mut codes = vec![Locale::English.code()];
+    if locale != Locale::English {
+        codes.push(locale.code());
+    }
+
+    let parts: Vec<String> = codes
+        .iter()
+        .flat_map(|code| {
+            let dir = Path::new(LOCALE_DIRECTORY_PATH).join(code);
+            ["items.toml", "machines.toml", "ui.toml"]
+                .iter()
+                .filter_map(move |file| fs::read_to_string(dir.join(file)).ok())
+        })
+        .collect();
+    let parts: Vec<&str> = parts.iter().map(String::as_str).collect();
+
+    Localizer::from_parts(&parts).unwrap_or_else(|_| Localizer::empty())
+}
+
+/// Maps an item ID or a name in any supported locale to an item ID.
+fn resolve_item(data: &GameData, name: &str) -> Result<ItemId, ProductionError> {
+    let localizers: Vec<Localizer> = Locale::ALL.into_iter().map(load_localizer).collect();
+
+    if let Some(item_id) = localizers
+        .iter()
+        .find_map(|localizer| data.resolve_item_name(name, localizer))
+    {
+        return Ok(item_id);
+    }
+
+    let mut candidates: Vec<String> = localizers
+        .iter()
+        .flat_map(|localizer| data.find_items_by_name(name, localizer))
+        .map(|item_id| item_id.to_string())
+        .collect();
+    candidates.sort();
+    candidates.dedup();
+    if !candidates.is_empty() {
+        eprintln!(
+            "'{}' matches several items: {}",
+            name,
+            candidates.join(", ")
+        );
+    } else {
+        let suggestions = near_matches(data, name, &localizers);
+        if !suggestions.is_empty() {
+            eprintln!("Did you mean: {}?", suggestions.join(", "));
+        }
+    }
+
+    Err(ProductionError::RecipeNotFound(name.to_string()))
+}
+
+/// Maximum number of items suggested for an unknown name.
+const MAX_SUGGESTIONS: usize = 5;
+
+/// Finds items whose ID, localized name or reading contains `name`, as
+/// searched by `SearchIndex`.
+fn near_matches(data: &GameData, name: &str, localizers: &[Localizer]) -> Vec<String> {
+    if name.trim().is_empty() {
+        return Vec::new();
+    }
+
+    let item_ids: Vec<&str> = data.recipes_by_output.keys().map(ItemId::as_str).collect();
+    // Names fall back to IDs, so this also searches the IDs alone
+    let ids_only = Localizer::empty();
+    let mut matches: Vec<String> = localizers
+        .iter()
+        .chain([&ids_only])
+        .flat_map(|localizer| {
+            SearchIndex::new(item_ids.iter().copied(), localizer)
+                .search(name)
+                .into_iter()
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    matches.sort();
+    matches.dedup();
+    matches.truncate(MAX_SUGGESTIONS);
+    matches
+}
+
+/// Formats the single line printed by `--quiet`.
+fn summary_line(item_id: &ItemId, amount: u32, node: &ProductionNode) -> String {
+    let machines: u32 = node.total_machines().values().sum();
+    format!(
+        "{} x{}: {} machines, {} power, {} raw materials",
+        item_id,
+        amount,
+        machines,
+        format_power(node.total_power() as u64),
+        node.total_source_materials().len()
+    )
+}
+
+/// Formats one line per craftable item for `list`, sorted by ID.
+///
+/// With `verbose`, the item's description follows its name when the
+/// locale has one.
+fn list_lines(data: &GameData, localizer: &Localizer, verbose: bool) -> Vec<String> {
+    let mut item_ids: Vec<&ItemId> = data.recipes_by_output.keys().collect();
+    item_ids.sort();
+
+    item_ids
+        .into_iter()
+        .map(|item_id| {
+            let item_id = item_id.as_str();
+            let mut line = format!("{}  {}", item_id, localizer.get_item(item_id));
+            if verbose && let Some(description) = localizer.get_description(item_id) {
+                line.push_str(" - ");
+                line.push_str(&description);
+            }
+            line
+        })
+        .collect()
+}
+
+/// Formats the recipes for an item for `recipes`, in the planner's
+/// selection order, marking the one it would use.
+fn recipe_lines(data: &GameData, item_id: &ItemId, localizer: Option<&Localizer>) -> Vec<String> {
+    let item_name = |id: &str| localizer.map_or(id.to_string(), |l| l.get_item(id));
+    let machine_name = |id: &str| localizer.map_or(id.to_string(), |l| l.get_machine(id));
+    let tier_name =
+        |tier: u32| localizer.map_or_else(|| data.tier_label(tier), |l| l.get_tier(tier, data));
+    let amounts = |amounts: &HashMap<ItemId, u32>| {
+        let mut amounts: Vec<(&ItemId, &u32)> = amounts.iter().collect();
+        amounts.sort();
+        if amounts.is_empty() {
+            return "none".to_string();
+        }
+        amounts
+            .iter()
+            .map(|(id, count)| format!("{} x{}", item_name(id.as_str()), count))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    let recipes = ranked_recipes(data, item_id, &PlanOptions::default());
+    let mut lines = vec![format!(
+        "{}: {} recipe(s), best first",
+        item_name(item_id.as_str()),
+        recipes.len()
+    )];
+
+    for (rank, recipe) in recipes.into_iter().enumerate() {
+        let (marker, selected) = if rank == 0 {
+            ("*", " (selected)")
+        } else {
+            (" ", "")
+        };
+        lines.push(format!(
+            "{} {}{}",
+            marker,
+            recipe.compute_unique_id(),
+            selected
+        ));
+
+        let machine = match data.machines.get(&recipe.by) {
+            Some(machine) => format!(
+                "{} ({}, power {})",
+                machine_name(recipe.by.as_str()),
+                tier_name(machine.tier),
+                machine.power
+            ),
+            None => format!("{} (unknown machine)", recipe.by),
+        };
+        lines.push(format!("    machine: {}", machine));
+        lines.push(format!("    time: {}", format_duration(recipe.time)));
+        lines.push(format!("    inputs: {}", amounts(&recipe.inputs)));
+        lines.push(format!("    outputs: {}", amounts(&recipe.outputs)));
+        for group in &recipe.any_of {
+            let items: Vec<String> = group
+                .items
+                .iter()
+                .map(|id| item_name(id.as_str()))
+                .collect();
+            lines.push(format!(
+                "    any of: {} x{}",
+                items.join(" | "),
+                group.count
+            ));
+        }
+        if !recipe.returns.is_empty() {
+            lines.push(format!("    returns: {}", amounts(&recipe.returns)));
+        }
+        if recipe.is_source {
+            lines.push("    source: yes".to_string());
+        }
+        if !recipe.tags.is_empty() {
+            lines.push(format!("    tags: {}", recipe.tags.join(", ")));
+        }
+    }
+
+    lines
+}
+
+/// Formats the items craftable from `owned` for `what-can-i-make`,
+/// direct ones first.
+fn craftable_lines(
+    data: &GameData,
+    owned: &HashSet<ItemId>,
+    max_depth: Option<u32>,
+    localizer: Option<&Localizer>,
+) -> Vec<String> {
+    let item_name = |id: &str| localizer.map_or(id.to_string(), |l| l.get_item(id));
+
+    let entries = data.craftable_from(owned, max_depth);
+    if entries.is_empty() {
+        return vec!["Nothing can be crafted from these items.".to_string()];
+    }
+
+    entries
+        .iter()
+        .map(|entry| {
+            let reach = if entry.is_direct() {
+                "direct".to_string()
+            } else {
+                format!("{} steps", entry.depth)
+            };
+            format!(
+                "{}  ({}, via {})",
+                item_name(entry.item_id.as_str()),
+                reach,
+                entry.recipe_id
+            )
+        })
+        .collect()
+}
+
+/// Formats what new data changes about a saved plan for `impact`.
+fn impact_lines(impact: &PlanImpact) -> Vec<String> {
+    if impact.is_unchanged() {
+        return vec!["The new data does not change this plan.".to_string()];
+    }
+
+    let signed = |delta: i64| format!("{:+}", delta);
+    let mut lines = Vec::new();
+
+    if !impact.changed_recipes.is_empty() {
+        lines.push("Changed recipes:".to_string());
+        for change in &impact.changed_recipes {
+            let changes: Vec<String> = change
+                .changes
+                .iter()
+                .map(|kind| match kind {
+                    RecipeChangeKind::Time { old, new } => format!("time {} -> {}", old, new),
+                    RecipeChangeKind::Inputs => "inputs".to_string(),
+                    RecipeChangeKind::Outputs => "outputs".to_string(),
+                })
+                .collect();
+            lines.push(format!("  {}: {}", change.recipe_id, changes.join(", ")));
+        }
+    }
+    if !impact.removed_recipes.is_empty() {
+        lines.push("Removed recipes:".to_string());
+        for recipe_id in &impact.removed_recipes {
+            lines.push(format!("  {}", recipe_id));
+        }
+    }
+    if !impact.machine_deltas.is_empty() {
+        lines.push("Machines:".to_string());
+        for (machine_id, delta) in &impact.machine_deltas {
+            lines.push(format!("  {}: {}", machine_id, signed(*delta)));
+        }
+    }
+    if impact.power_delta != 0 {
+        lines.push(format!(
+            "Power: {} (now {})",
+            signed(impact.power_delta),
+            impact.new_plan.node.total_power()
+        ));
+    }
+    if !impact.raw_material_deltas.is_empty() {
+        lines.push("Raw materials:".to_string());
+        for (item_id, delta) in &impact.raw_material_deltas {
+            lines.push(format!("  {}: {}", item_id, signed(*delta)));
+        }
+    }
+
+    lines
+}
+
+/// Lists the machine types the plan needs more of than are available.
+fn limit_lines(violations: &[MachineLimitViolation]) -> Vec<String> {
+    if violations.is_empty() {
+        return Vec::new();
+    }
+
+    let mut lines = vec!["Machine Limits Exceeded:".to_string()];
+    for violation in violations {
+        lines.push(format!(
+            " - {}: needs {}, {} available ({} short)",
+            violation.machine_id,
+            violation.needed,
+            violation.available,
+            violation.shortfall()
+        ));
+    }
+    lines
+}
+
+fn run(options: &CliOptions) -> Result<(), CliError> {
+    if options.code.is_some() && options.item.is_some() {
+        return Err(CliError::ConflictingArguments("--code", "--item"));
+    }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
     let recipes = fs::read_to_string(RECIPE_DEFINITION_PATH)?;
     let machines = fs::read_to_string(MACHINE_DEFINITION_PATH)?;
 
     let data = GameData::new(&recipes, &machines)?;
 
-    println!(
-        "Loaded {} recipes and {} machines.\n",
-        data.recipes.len(),
-        data.machines.len()
-    );
+    let localizer = options.locale.map(load_localizer);
+    let localized = localizer
+        .as_ref()
+        .map(|localizer| LocalizedResolver::new(localizer, &data));
+    let names: &dyn NameResolver = match &localized {
+        Some(localized) => localized,
+        None => &IdResolver,
+    };
 
-    let item_id = "lc_wuling_battery";
-    let amount = 12; // per minute
+    if options.command == Command::List {
+        let localizer = load_localizer(options.locale.unwrap_or_default());
+        for line in list_lines(&data, &localizer, options.verbose) {
+            println!("{}", line);
+        }
+        return Ok(());
+    }
 
-    if !data.recipes_by_output.contains_key(item_id) {
-        return Err(Box::new(ProductionError::RecipeNotFound(
-            item_id.to_string(),
-        )));
+    if options.command == Command::Validate {
+        for warning in &data.warnings {
+            println!("Data warning: {}", warning);
+        }
+        for recipe_id in data.dominated_recipes() {
+            println!("Never selected (dominated): {}", recipe_id);
+        }
+        println!(
+            "Checked {} recipes and {} machines: {} warning(s).",
+            data.recipes.len(),
+            data.machines.len(),
+            data.warnings.len()
+        );
+        return Ok(());
     }
 
-    let mut visiting = HashSet::new();
+    if options.command == Command::Recipes {
+        let item_id = match &options.item {
+            Some(name) => resolve_item(&data, name)?,
+            None => ItemId::from(DEFAULT_ITEM),
+        };
 
-    let node = plan_production(
-        &data.recipes,
-        &data.recipes_by_output,
-        &data.machines,
-        item_id,
-        amount,
-        &mut visiting,
-    );
+        if options.json {
+            println!("{}", recipes_to_json(&data, &item_id));
+        } else {
+            for line in recipe_lines(&data, &item_id, localizer.as_ref()) {
+                println!("{}", line);
+            }
+        }
+        return Ok(());
+    }
+
+    if options.command == Command::Impact {
+        let plan_path = options
+            .plan_path
+            .as_deref()
+            .ok_or(CliError::MissingArgument("impact <plan.toml>"))?;
+        let plan = SavedPlan::from_toml(&fs::read_to_string(plan_path)?)?;
+
+        let new_recipes = match &options.recipes_path {
+            Some(path) => fs::read_to_string(path)?,
+            None => recipes.clone(),
+        };
+        let new_machines = match &options.machines_path {
+            Some(path) => fs::read_to_string(path)?,
+            None => machines.clone(),
+        };
+        let new_data = GameData::new(&new_recipes, &new_machines)?;
+
+        for line in impact_lines(&plan.impact(&data, &new_data)) {
+            println!("{}", line);
+        }
+        return Ok(());
+    }
+
+    if options.command == Command::Graph {
+        let filter = GraphFilter {
+            root: match &options.graph_root {
+                Some(name) => Some(resolve_item(&data, name)?),
+                None => None,
+            },
+            tag: options.graph_tag.clone(),
+        };
+        let graph = RecipeGraph::from_data(&data, &filter);
+        match options.graph_format {
+            GraphFormat::Dot => print!("{}", graph.to_dot(names)),
+            GraphFormat::Mermaid => print!("{}", graph.to_mermaid(names)),
+        }
+        return Ok(());
+    }
+
+    if options.command == Command::WhatCanIMake {
+        let owned = options
+            .owned
+            .iter()
+            .map(|name| resolve_item(&data, name))
+            .collect::<Result<HashSet<ItemId>, _>>()?;
+
+        for line in craftable_lines(&data, &owned, options.max_depth, localizer.as_ref()) {
+            println!("{}", line);
+        }
+        return Ok(());
+    }
+
+    if !options.quiet && !options.json {
+        println!(
+            "Loaded {} recipes and {} machines.\n",
+            data.recipes.len(),
+            data.machines.len()
+        );
+
+        for warning in &data.warnings {
+            println!("Data warning: {}", warning);
+        }
+    }
+
+    let shared = options
+        .code
+        .as_deref()
+        .map(ShareState::from_code)
+        .transpose()?;
+    let item_id = match (&shared, &options.item) {
+        (Some(shared), _) => shared.item.clone(),
+        (None, Some(name)) => resolve_item(&data, name)?,
+        (None, None) => ItemId::from(DEFAULT_ITEM),
+    };
+    let amount = shared
+        .as_ref()
+        .map_or(DEFAULT_AMOUNT, |shared| shared.amount);
+
+    if !data.recipes_by_output.contains_key(&item_id) {
+        return Err(ProductionError::RecipeNotFound(item_id.to_string()).into());
+    }
 
-    print_summary(&node);
+    let trace_format = if options.json {
+        TraceFormat::JsonLines
+    } else {
+        TraceFormat::Text
+    };
+    let plan_options = PlanOptions {
+        sorted_inputs: options.json || shared.is_some(),
+        excluded_tags: options.excluded_tags.iter().cloned().collect(),
+        tracer: options
+            .trace
+            .then(|| Tracer::new(WriterTracer::new(io::stderr(), trace_format))),
+        max_machines_per_node: options.max_machines_per_node,
+        machine_limits: options
+            .machine_limits
+            .iter()
+            .map(|(machine_id, count)| (machine_id.into(), *count))
+            .collect(),
+        ..shared
+            .map(|shared| shared.plan_options())
+            .unwrap_or_default()
+    };
+    let result = if options.fit {
+        plan_within_machine_limits(&data, &item_id, amount, &plan_options)?
+    } else {
+        plan_production_with_options(&data, &item_id, amount, &plan_options)?
+    };
+    let planned = result.node.amount();
+
+    if options.json {
+        println!("{}", to_json(&result, &data, true));
+    } else if options.quiet {
+        println!("{}", summary_line(&item_id, planned, &result.node));
+    } else {
+        let deposits = result.warnings.deposits_needed();
+        let summary_options = SummaryOptions {
+            localizer: localizer.as_ref(),
+            names,
+            sort: options.sort,
+            amounts: plan_options.amount_kind(),
+            rounding: options.rounding,
+            tree_style: if options.ascii {
+                TreeStyle::Ascii
+            } else {
+                TreeStyle::Unicode
+            },
+            deposits: Some(&deposits),
+        };
+        let mut out = io::stdout().lock();
+        write_summary(&mut out, &result.node, &summary_options)?;
+        write_tag_breakdown(&mut out, &PlanSummary::new(&result.node, &data))?;
+        print_warnings(&result.warnings);
+
+        if planned < amount {
+            println!(
+                "\nScaled down from {} to {} per minute to fit the machine limits.",
+                amount, planned
+            );
+        }
+        let limits = limit_lines(&result.machine_limit_violations);
+        if !limits.is_empty() {
+            println!();
+        }
+        for line in limits {
+            println!("{}", line);
+        }
+
+        if options.bootstrap {
+            let within_secs = options
+                .bootstrap_within_secs
+                .unwrap_or(DEFAULT_BOOTSTRAP_SECS);
+            let bootstrap = bootstrap_plan(&data, &result.node, within_secs, &plan_options)?;
+            let deposits = bootstrap.warnings.deposits_needed();
+            println!("\n=== Machine Bootstrap ===");
+            write_summary(
+                &mut out,
+                &bootstrap.node,
+                &SummaryOptions {
+                    amounts: AmountKind::Total,
+                    deposits: Some(&deposits),
+                    ..summary_options
+                },
+            )?;
+
+            let unbuildable: Vec<String> = unbuildable_machines(&bootstrap.node)
+                .iter()
+                .map(ItemId::to_string)
+                .collect();
+            if !unbuildable.is_empty() {
+                println!("\nNo construction recipe: {}", unbuildable.join(", "));
+            }
+        }
+    }
+
+    if options.fail_on_missing {
+        check_missing_items(&result.warnings)?;
+    }
 
     Ok(())
 }
+
+fn main() -> ExitCode {
+    match parse_args(env::args().skip(1)).and_then(|options| run(&options)) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("Error: {}", error);
+            ExitCode::from(exit_code(&error))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let options = parse_args(args(&[
+            "--quiet",
+            "--json",
+            "--item",
+            "origocrust",
+            "--fail-on-missing",
+            "--trace",
+            "--sort",
+            "count",
+            "--lang",
+            "ja",
+            "--exclude-tag",
+            "farming",
+            "--exclude-tag",
+            "smelting",
+            "--rounding",
+            "exact",
+            "--ascii",
+        ]))
+        .unwrap();
+
+        assert_eq!(
+            options,
+            CliOptions {
+                item: Some("origocrust".to_string()),
+                quiet: true,
+                json: true,
+                fail_on_missing: true,
+                trace: true,
+                sort: SummarySort::Count,
+                locale: Some(Locale::Japanese),
+                excluded_tags: vec!["farming".to_string(), "smelting".to_string()],
+                rounding: RoundingPolicy::Exact,
+                ascii: true,
+                ..Default::default()
+            }
+        );
+        assert_eq!(parse_args(args(&[])).unwrap(), CliOptions::default());
+        let error = parse_args(args(&["--sort", "size"])).unwrap_err();
+        assert!(matches!(error, CliError::InvalidArgument("--sort", _)));
+        for rounding in [&["--rounding", "floor"][..], &["--rounding"]] {
+            let error = parse_args(args(rounding)).unwrap_err();
+            assert!(matches!(error, CliError::InvalidArgument("--rounding", _)));
+        }
+
+        let list = parse_args(args(&["list", "--verbose"])).unwrap();
+        assert_eq!(list.command, Command::List);
+        assert!(list.verbose);
+        assert_eq!(
+            parse_args(args(&["validate"])).unwrap().command,
+            Command::Validate
+        );
+
+        let recipes =
+            parse_args(args(&["recipes", "--locale", "ja", "origocrust", "extra"])).unwrap();
+        assert_eq!(recipes.command, Command::Recipes);
+        assert_eq!(recipes.item.as_deref(), Some("origocrust"));
+        assert_eq!(recipes.locale, Some(Locale::Japanese));
+        for lang in [&["--lang", "klingon"][..], &["--locale", "xx"], &["--lang"]] {
+            let error = parse_args(args(lang)).unwrap_err();
+            assert!(matches!(error, CliError::InvalidArgument("--lang", _)));
+        }
+
+        let craftable = parse_args(args(&[
+            "what-can-i-make",
+            "--have",
+            "originium_ore",
+            "--have",
+            "carbon",
+            "--depth",
+            "2",
+        ]))
+        .unwrap();
+        assert_eq!(craftable.command, Command::WhatCanIMake);
+        assert_eq!(craftable.owned, vec!["originium_ore", "carbon"]);
+        assert_eq!(craftable.max_depth, Some(2));
+        for depth in [&["what-can-i-make", "--depth", "two"][..], &["--depth"]] {
+            let error = parse_args(args(depth)).unwrap_err();
+            assert!(matches!(error, CliError::InvalidArgument("--depth", _)));
+        }
+
+        let impact = parse_args(args(&["impact", "plan.toml", "--recipes", "new.toml"])).unwrap();
+        assert_eq!(impact.command, Command::Impact);
+        assert_eq!(impact.plan_path.as_deref(), Some("plan.toml"));
+        assert_eq!(impact.recipes_path.as_deref(), Some("new.toml"));
+        assert_eq!(impact.machines_path, None);
+
+        let graph = parse_args(args(&[
+            "graph",
+            "--format",
+            "mermaid",
+            "--root",
+            "origocrust",
+            "--tag",
+            "smelting",
+        ]))
+        .unwrap();
+        assert_eq!(graph.command, Command::Graph);
+        assert_eq!(graph.graph_format, GraphFormat::Mermaid);
+        assert_eq!(graph.graph_root.as_deref(), Some("origocrust"));
+        assert_eq!(graph.graph_tag.as_deref(), Some("smelting"));
+        let error = parse_args(args(&["graph", "--format", "svg"])).unwrap_err();
+        assert!(matches!(error, CliError::InvalidArgument("--format", _)));
+
+        let code = parse_args(args(&["--code", "AW9yaWdvY3J1c3QKMzAK"])).unwrap();
+        assert_eq!(code.code.as_deref(), Some("AW9yaWdvY3J1c3QKMzAK"));
+
+        let split = parse_args(args(&[
+            "--max-machines",
+            "8",
+            "--bootstrap",
+            "--bootstrap-within",
+            "300",
+        ]))
+        .unwrap();
+        assert_eq!(split.max_machines_per_node, Some(8));
+        assert!(split.bootstrap);
+        assert_eq!(split.bootstrap_within_secs, Some(300.0));
+        for within in ["ten", "0", "-60", "inf", ""] {
+            let error = parse_args(args(&["--bootstrap-within", within])).unwrap_err();
+            assert!(matches!(
+                error,
+                CliError::InvalidArgument("--bootstrap-within", _)
+            ));
+        }
+        for max in [
+            &["--max-machines", "eight"][..],
+            &["--max-machines", "-1"],
+            &["--max-machines"],
+        ] {
+            let error = parse_args(args(max)).unwrap_err();
+            assert!(matches!(
+                error,
+                CliError::InvalidArgument("--max-machines", _)
+            ));
+        }
+
+        let limited = parse_args(args(&[
+            "--limit",
+            "gearing_unit=3",
+            "--limit",
+            "shredding_unit=2",
+            "--fit",
+        ]))
+        .unwrap();
+        assert_eq!(
+            limited.machine_limits,
+            vec![
+                ("gearing_unit".to_string(), 3),
+                ("shredding_unit".to_string(), 2)
+            ]
+        );
+        assert!(limited.fit);
+
+        for limit in ["refining_unit", "refining_unit=many"] {
+            let error = parse_args(args(&["--limit", limit])).unwrap_err();
+            assert!(matches!(error, CliError::InvalidArgument("--limit", _)));
+        }
+    }
+
+    #[test]
+    fn test_craftable_lines_mark_direct_items() {
+        let recipes = r#"
+[[recipes]]
+id = "origocrust"
+by = "refining_unit"
+time = 2
+out = 1
+[recipes.inputs]
+originium_ore = 1
+
+[[recipes]]
+id = "origocrust_powder"
+by = "shredding_unit"
+time = 2
+out = 1
+[recipes.inputs]
+origocrust = 1
+"#;
+        let data = GameData::new(recipes, "machines = []").unwrap();
+        let owned: HashSet<ItemId> = ["originium_ore".into()].into_iter().collect();
+
+        assert_eq!(
+            craftable_lines(&data, &owned, None, None),
+            vec![
+                "origocrust  (direct, via origocrust@refining_unit[originium_ore:1]->[origocrust:1]/2s)",
+                "origocrust_powder  (2 steps, via origocrust_powder@shredding_unit[origocrust:1]->[origocrust_powder:1]/2s)",
+            ]
+        );
+        assert_eq!(
+            craftable_lines(&data, &HashSet::new(), None, None),
+            vec!["Nothing can be crafted from these items."]
+        );
+    }
+
+    #[test]
+    fn test_limit_lines_show_shortfall() {
+        let violations = [MachineLimitViolation {
+            machine_id: "gearing_unit".into(),
+            needed: 5,
+            available: 3,
+        }];
+
+        assert_eq!(
+            limit_lines(&violations),
+            vec![
+                "Machine Limits Exceeded:",
+                " - gearing_unit: needs 5, 3 available (2 short)",
+            ]
+        );
+        assert!(limit_lines(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_impact_lines_report_time_change() {
+        let recipes = |time: u32| {
+            format!(
+                r#"
+[[recipes]]
+id = "origocrust"
+by = "refining_unit"
+time = {}
+out = 1
+"#,
+                time
+            )
+        };
+        let machines = r#"
+[[machines]]
+id = "refining_unit"
+tier = 1
+power = 5
+"#;
+        let old = GameData::new(&recipes(2), machines).unwrap();
+        let new = GameData::new(&recipes(4), machines).unwrap();
+        let plan = SavedPlan::new("origocrust", 30);
+
+        assert_eq!(
+            impact_lines(&plan.impact(&old, &new)),
+            vec![
+                "Changed recipes:",
+                "  origocrust@refining_unit[]->[origocrust:1]/2s: time 2 -> 4",
+                "Machines:",
+                "  refining_unit: +1",
+                "Power: +5 (now 10)",
+            ]
+        );
+        assert_eq!(
+            impact_lines(&plan.impact(&old, &old)),
+            vec!["The new data does not change this plan."]
+        );
+    }
+
+    #[test]
+    fn test_recipe_lines_mark_selected_first() {
+        let recipes = r#"
+[[recipes]]
+id = "origocrust"
+by = "refining_unit"
+time = 2
+out = 1
+[recipes.inputs]
+originium_ore = 1
+
+[[recipes]]
+id = "origocrust"
+by = "refining_unit_mk2"
+tags = ["smelting"]
+time = 1
+out = 1
+[recipes.inputs]
+originium_ore = 1
+"#;
+        let machines = r#"
+[tier_names]
+2 = "Standard"
+
+[[machines]]
+id = "refining_unit"
+tier = 1
+power = 5
+
+[[machines]]
+id = "refining_unit_mk2"
+tier = 2
+power = 10
+"#;
+        let data = GameData::new(recipes, machines).unwrap();
+
+        assert_eq!(
+            recipe_lines(&data, &"origocrust".into(), None),
+            vec![
+                "origocrust: 2 recipe(s), best first",
+                "* origocrust@refining_unit_mk2[originium_ore:1]->[origocrust:1]/1s (selected)",
+                "    machine: refining_unit_mk2 (Standard, power 10)",
+                "    time: 1s",
+                "    inputs: originium_ore x1",
+                "    outputs: origocrust x1",
+                "    tags: smelting",
+                "  origocrust@refining_unit[originium_ore:1]->[origocrust:1]/2s",
+                "    machine: refining_unit (Tier 1, power 5)",
+                "    time: 2s",
+                "    inputs: originium_ore x1",
+                "    outputs: origocrust x1",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_near_matches_search_ids_and_names() {
+        let recipes = r#"
+[[recipes]]
+id = "origocrust"
+by = "refining_unit"
+time = 2
+out = 1
+
+[[recipes]]
+id = "origocrust_powder"
+by = "refining_unit"
+time = 2
+out = 1
+
+[[recipes]]
+id = "amethyst_fiber"
+by = "refining_unit"
+time = 2
+out = 1
+"#;
+        let data = GameData::new(recipes, "machines = []").unwrap();
+        let localizer = Localizer::new("[items]\namethyst_fiber = \"Violet Fiber\"").unwrap();
+
+        assert_eq!(
+            near_matches(&data, "CRUST", &[]),
+            vec!["origocrust", "origocrust_powder"]
+        );
+        assert_eq!(
+            near_matches(&data, "violet", &[localizer]),
+            vec!["amethyst_fiber"]
+        );
+        assert!(near_matches(&data, "  ", &[]).is_empty());
+    }
+
+    #[test]
+    fn test_list_lines_show_descriptions_when_verbose() {
+        let recipes = r#"
+[[recipes]]
+id = "origocrust"
+by = "refining_unit"
+time = 2
+out = 1
+
+[[recipes]]
+id = "amethyst_fiber"
+by = "refining_unit"
+time = 2
+out = 1
+"#;
+        let machines = r#"
+[[machines]]
+id = "refining_unit"
+tier = 1
+power = 5
+"#;
+        let locale = r#"
+[items]
+origocrust = "Origocrust"
+amethyst_fiber = "Amethyst Fiber"
+
+[descriptions]
+origocrust = "A common building material."
+"#;
+        let data = GameData::new(recipes, machines).unwrap();
+        let localizer = Localizer::new(locale).unwrap();
+
+        assert_eq!(
+            list_lines(&data, &localizer, true),
+            vec![
+                "amethyst_fiber  Amethyst Fiber",
+                "origocrust  Origocrust - A common building material.",
+            ]
+        );
+        assert_eq!(
+            list_lines(&data, &localizer, false)[1],
+            "origocrust  Origocrust"
+        );
+    }
+
+    #[test]
+    fn test_exit_codes() {
+        let unknown = CliError::Production(ProductionError::RecipeNotFound("x".to_string()));
+        let parse = CliError::Production(ProductionError::ParseError("x".to_string()));
+        let missing_file = CliError::Production(ProductionError::FileNotFound("x".to_string()));
+        let io = CliError::Io(io::Error::new(io::ErrorKind::NotFound, "x"));
+        let missing_items = CliError::MissingItems(vec!["x".to_string()]);
+
+        assert_eq!(exit_code(&unknown), 2);
+        assert_eq!(exit_code(&parse), 3);
+        assert_eq!(exit_code(&missing_items), 4);
+        assert_eq!(exit_code(&missing_file), 5);
+        assert_eq!(exit_code(&io), 5);
+    }
+
+    #[test]
+    fn test_code_conflicts_with_item() {
+        let options = parse_args(args(&[
+            "--code",
+            "AW9yaWdvY3J1c3QKMzAK",
+            "--item",
+            "origocrust",
+        ]))
+        .unwrap();
+
+        let error = run(&options).unwrap_err();
+        assert!(matches!(
+            error,
+            CliError::ConflictingArguments("--code", "--item")
+        ));
+        assert_eq!(exit_code(&error), EXIT_FAILURE);
+    }
+
+    #[test]
+    fn test_missing_recipe_warning_fails() {
+        let mut warnings = PlanWarnings::new();
+        warnings.push(PlanWarning::OutputDefaulted {
+            item_id: "origocrust".into(),
+            recipe_id: "origocrust@refining_unit[]".into(),
+        });
+        assert!(check_missing_items(&warnings).is_ok());
+
+        warnings.push(PlanWarning::MissingRecipe {
+            item_id: "originium_ore".into(),
+        });
+        let error = check_missing_items(&warnings).unwrap_err();
+        assert_eq!(exit_code(&error), EXIT_MISSING_ITEMS);
+    }
+}
@@ -0,0 +1,30 @@
+use std::path::Path;
+use std::process::Command;
+
+/// Runs the CLI from the workspace root, where the data files live.
+fn run_cli(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_endfield_planner_cli"))
+        .args(args)
+        .current_dir(Path::new(env!("CARGO_MANIFEST_DIR")).join(".."))
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn test_lang_ja_localizes_tree_and_totals() {
+    let output = run_cli(&["--item", "origocrust", "--lang", "ja"]);
+
+    assert!(output.contains("結晶外殻 x12 [精錬炉 x1]"));
+    assert!(output.contains(" - 源石鉱物"));
+    assert!(!output.contains("origocrust"));
+}
+
+#[test]
+fn test_without_lang_shows_ids() {
+    let output = run_cli(&["--item", "origocrust"]);
+
+    assert!(output.contains("origocrust x12 [refining_unit x1]"));
+}
@@ -0,0 +1,230 @@
+//! Game data assembled from recipes and machines already in memory.
+
+use crate::config::{DataWarning, GameData};
+use crate::error::ProductionError;
+use crate::models::{IdInterner, ItemId, Machine, MachineId, Recipe, RecipeUid};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+/// Collects recipes and machines into `GameData` without the TOML files.
+///
+/// Recipe times are taken as seconds. `build` applies the same output
+/// normalization, machine expansion and checks as `GameData::new`.
+#[derive(Debug, Default)]
+pub struct GameDataBuilder {
+    recipes: Vec<Recipe>,
+    machines: Vec<Machine>,
+    tier_names: HashMap<u32, String>,
+}
+
+impl GameDataBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a recipe. A recipe with several machines becomes one variant
+    /// per machine.
+    pub fn add_recipe(mut self, recipe: Recipe) -> Self {
+        self.recipes.push(recipe);
+        self
+    }
+
+    /// Adds a machine, available to every recipe naming its ID.
+    pub fn add_machine(mut self, machine: Machine) -> Self {
+        self.machines.push(machine);
+        self
+    }
+
+    /// Names a machine tier, replacing any earlier name for it.
+    pub fn add_tier_name(mut self, tier: u32, name: impl Into<String>) -> Self {
+        self.tier_names.insert(tier, name.into());
+        self
+    }
+
+    /// Indexes the collected data. Fails if a recipe has no machine or
+    /// conflicting counts for its own output.
+    pub fn build(self) -> Result<GameData, ProductionError> {
+        // Every copy of an ID in the data shares one allocation
+        let mut interner = IdInterner::new();
+
+        let machines: HashMap<MachineId, Machine> = self
+            .machines
+            .into_iter()
+            .map(|mut m| {
+                m.id = interner.intern(m.id.as_str());
+                (m.id.clone(), m)
+            })
+            .collect();
+
+        let mut recipes = HashMap::new();
+        let mut recipes_by_output: HashMap<ItemId, Vec<RecipeUid>> = HashMap::new();
+        let mut recipes_by_tag: HashMap<String, Vec<RecipeUid>> = HashMap::new();
+        let mut warnings = Vec::new();
+
+        for mut r in self.recipes {
+            if r.machine_options().is_empty() {
+                return Err(ProductionError::ParseError(format!(
+                    "recipe '{}' has no machine",
+                    r.id
+                )));
+            }
+
+            // Interned first, so the output checks see canonical IDs
+            r.intern_ids(&mut interner)?;
+            r.normalize()?;
+
+            // A recipe listing several machines becomes one variant per machine
+            for variant in r.expand_machines() {
+                let unique_id = variant.compute_unique_id();
+
+                if !variant.outputs.contains_key(&variant.id) {
+                    warnings.push(DataWarning::MissingSelfOutput {
+                        recipe_id: unique_id.clone(),
+                        item_id: variant.id.clone(),
+                    });
+                }
+
+                // Machines without port counts are not checked
+                if let Some(machine) = machines.get(&variant.by) {
+                    for (direction, count, ports) in [
+                        (
+                            "input",
+                            variant.inputs.len() + variant.any_of.len(),
+                            machine.input_ports,
+                        ),
+                        ("output", variant.outputs.len(), machine.output_ports),
+                    ] {
+                        if let Some(ports) = ports
+                            && count > ports as usize
+                        {
+                            warnings.push(DataWarning::PortsExceeded {
+                                recipe_id: unique_id.clone(),
+                                machine_id: machine.id.clone(),
+                                direction,
+                                count,
+                                ports,
+                            });
+                        }
+                    }
+                }
+
+                // Equivalent recipes share a unique ID; keep a single entry for them
+                // The recipe is found under its ID as well as each output
+                let output_items = std::iter::once(&variant.id).chain(variant.outputs.keys());
+                for output_item in output_items {
+                    let ids = recipes_by_output.entry(output_item.clone()).or_default();
+                    if !ids.contains(&unique_id) {
+                        ids.push(unique_id.clone());
+                    }
+                }
+
+                for tag in &variant.tags {
+                    let ids = recipes_by_tag.entry(tag.clone()).or_default();
+                    if !ids.contains(&unique_id) {
+                        ids.push(unique_id.clone());
+                    }
+                }
+
+                recipes.insert(unique_id, variant);
+            }
+        }
+
+        // Stable order for listing alternatives: higher tier machines first
+        for ids in recipes_by_output.values_mut() {
+            ids.sort_by_cached_key(|id| {
+                let tier = recipes
+                    .get(id)
+                    .and_then(|recipe| machines.get(&recipe.by))
+                    .map_or(0, |machine| machine.tier);
+                (Reverse(tier), id.clone())
+            });
+        }
+
+        Ok(GameData {
+            recipes,
+            recipes_by_output,
+            recipes_by_tag,
+            machines,
+            tier_names: self.tier_names,
+            warnings,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ProductionNode;
+    use crate::planner::plan_production_with;
+
+    fn machine(id: &str, tier: u32) -> Machine {
+        Machine {
+            id: id.into(),
+            tier,
+            power: 5,
+            input_ports: None,
+            output_ports: None,
+            idle_power: None,
+        }
+    }
+
+    fn origocrust() -> Recipe {
+        let mut recipe = Recipe::new("origocrust", vec!["refining_unit".into()], 2.0);
+        recipe.inputs.insert("originium_ore".into(), 1);
+        recipe.outputs.insert("origocrust".into(), 1);
+        recipe
+    }
+
+    #[test]
+    fn test_build_indexes_recipes() {
+        let data = GameData::builder()
+            .add_machine(machine("refining_unit", 1))
+            .add_recipe(origocrust())
+            .build()
+            .unwrap();
+
+        assert_eq!(data.recipes.len(), 1);
+        assert_eq!(data.recipes_by_output["origocrust"].len(), 1);
+        assert!(data.warnings.is_empty());
+
+        let result = plan_production_with(&data, "origocrust", 30);
+        assert!(matches!(
+            result.node,
+            ProductionNode::Resolved {
+                machine_count: 1,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_build_expands_machine_options() {
+        let mut recipe = Recipe::new(
+            "origocrust",
+            vec!["refining_unit".into(), "refining_unit_mk2".into()],
+            2.0,
+        );
+        recipe.outputs.insert("origocrust".into(), 1);
+
+        let data = GameData::builder()
+            .add_machine(machine("refining_unit", 1))
+            .add_machine(machine("refining_unit_mk2", 2))
+            .add_recipe(recipe)
+            .build()
+            .unwrap();
+
+        let ids = &data.recipes_by_output["origocrust"];
+        assert_eq!(ids.len(), 2);
+        // Higher tier machine first
+        assert_eq!(data.recipes[&ids[0]].by, "refining_unit_mk2");
+    }
+
+    #[test]
+    fn test_build_rejects_recipe_without_machine() {
+        let result = GameData::builder()
+            .add_recipe(Recipe::new("origocrust", Vec::new(), 2.0))
+            .build();
+
+        assert!(matches!(result, Err(ProductionError::ParseError(_))));
+    }
+}
@@ -0,0 +1,331 @@
+//! Items that can be crafted from an inventory of owned items.
+
+use crate::config::GameData;
+use crate::models::{ItemId, MachineId, Recipe, RecipeUid};
+use std::collections::{HashMap, HashSet};
+
+/// An item that can be crafted from the owned items, see
+/// `GameData::craftable_from`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "data-files", derive(serde::Serialize))]
+pub struct CraftableEntry {
+    pub item_id: ItemId,
+    /// Unique ID of the recipe used to craft the item.
+    pub recipe_id: RecipeUid,
+    /// Number of crafting steps in the longest chain needed; 1 means
+    /// every input is owned.
+    pub depth: u32,
+}
+
+impl CraftableEntry {
+    /// Returns true if the item is crafted from owned items alone.
+    pub fn is_direct(&self) -> bool {
+        self.depth == 1
+    }
+}
+
+/// Recipes and machines available for crafting, see
+/// `GameData::newly_craftable`. Unlocking a machine unlocks every recipe
+/// made on it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Unlocked {
+    pub recipes: HashSet<RecipeUid>,
+    pub machines: HashSet<MachineId>,
+}
+
+impl Unlocked {
+    /// Only the recipe with this unique ID.
+    pub fn recipe(recipe_id: impl Into<RecipeUid>) -> Self {
+        Unlocked {
+            recipes: HashSet::from([recipe_id.into()]),
+            ..Default::default()
+        }
+    }
+
+    /// Only the recipes made on this machine.
+    pub fn machine(machine_id: impl Into<MachineId>) -> Self {
+        Unlocked {
+            machines: HashSet::from([machine_id.into()]),
+            ..Default::default()
+        }
+    }
+
+    pub fn allows(&self, recipe_id: &RecipeUid, recipe: &Recipe) -> bool {
+        self.recipes.contains(recipe_id) || self.machines.contains(&recipe.by)
+    }
+
+    /// Everything unlocked in either set.
+    pub fn union(&self, other: &Unlocked) -> Unlocked {
+        Unlocked {
+            recipes: self.recipes.union(&other.recipes).cloned().collect(),
+            machines: self.machines.union(&other.machines).cloned().collect(),
+        }
+    }
+}
+
+impl GameData {
+    /// Lists the items whose recipe chains can be satisfied from `owned`.
+    ///
+    /// Source recipes (mining, gathering) are not used, so raw materials
+    /// have to be owned. Chains longer than `max_depth` steps are left out;
+    /// `None` follows them fully. Owned items are not listed. The result is
+    /// sorted by depth, then by item ID.
+    pub fn craftable_from(
+        &self,
+        owned: &HashSet<ItemId>,
+        max_depth: Option<u32>,
+    ) -> Vec<CraftableEntry> {
+        self.craftable_with(owned, max_depth, |_, _| true)
+    }
+
+    /// Lists the items that become craftable from `owned` once `unlock` is
+    /// added to the `unlocked` recipes and machines, like `craftable_from`
+    /// with only unlocked recipes usable. Items craftable before are left
+    /// out, so an empty list means the unlock opens up nothing new.
+    pub fn newly_craftable(
+        &self,
+        owned: &HashSet<ItemId>,
+        unlocked: &Unlocked,
+        unlock: &Unlocked,
+    ) -> Vec<CraftableEntry> {
+        let before: HashSet<ItemId> = self
+            .craftable_with(owned, None, |id, recipe| unlocked.allows(id, recipe))
+            .into_iter()
+            .map(|entry| entry.item_id)
+            .collect();
+
+        let after = unlocked.union(unlock);
+        self.craftable_with(owned, None, |id, recipe| after.allows(id, recipe))
+            .into_iter()
+            .filter(|entry| !before.contains(&entry.item_id))
+            .collect()
+    }
+
+    /// `craftable_from` with only the recipes passing `allowed` usable.
+    fn craftable_with(
+        &self,
+        owned: &HashSet<ItemId>,
+        max_depth: Option<u32>,
+        allowed: impl Fn(&RecipeUid, &Recipe) -> bool,
+    ) -> Vec<CraftableEntry> {
+        // Recipes indexed by each of their inputs
+        let mut consumers: HashMap<&ItemId, Vec<&Recipe>> = HashMap::new();
+        let usable = self
+            .recipes
+            .iter()
+            .filter(|(id, recipe)| !recipe.is_source && allowed(id, recipe));
+        for (_, recipe) in usable {
+            let group_items = recipe.any_of.iter().flat_map(|group| &group.items);
+            for input_id in recipe.inputs.keys().chain(group_items) {
+                consumers.entry(input_id).or_default().push(recipe);
+            }
+        }
+
+        let mut available: HashSet<ItemId> = owned.clone();
+        let mut frontier: Vec<ItemId> = owned.iter().cloned().collect();
+        let mut entries = Vec::new();
+        let mut depth = 0;
+
+        // Each round crafts what the items available so far allow
+        while !frontier.is_empty() && max_depth.is_none_or(|max| depth < max) {
+            depth += 1;
+
+            let mut candidates: Vec<&ItemId> = frontier
+                .iter()
+                .filter_map(|item_id| consumers.get(item_id))
+                .flatten()
+                .flat_map(|recipe| recipe.output_items())
+                .filter(|item_id| !available.contains(*item_id))
+                .collect();
+            candidates.sort();
+            candidates.dedup();
+
+            let mut crafted = Vec::new();
+            for item_id in candidates {
+                let recipe = self.recipes_by_output[item_id]
+                    .iter()
+                    .filter_map(|recipe_id| {
+                        let recipe = self.recipes.get(recipe_id)?;
+                        allowed(recipe_id, recipe).then_some(recipe)
+                    })
+                    .filter(|recipe| {
+                        let has_inputs = !recipe.inputs.is_empty() || !recipe.any_of.is_empty();
+                        !recipe.is_source && has_inputs
+                    })
+                    .find(|recipe| {
+                        recipe.inputs.keys().all(|input| available.contains(input))
+                            && recipe.any_of.iter().all(|group| {
+                                group.items.iter().any(|item| available.contains(item))
+                            })
+                    });
+
+                if let Some(recipe) = recipe {
+                    entries.push(CraftableEntry {
+                        item_id: item_id.clone(),
+                        recipe_id: recipe.compute_unique_id(),
+                        depth,
+                    });
+                    crafted.push(item_id.clone());
+                }
+            }
+
+            available.extend(crafted.iter().cloned());
+            frontier = crafted;
+        }
+
+        entries
+    }
+}
+
+#[cfg(all(test, feature = "data-files"))]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::REFINING_UNIT_TOML;
+
+    const RECIPES: &str = r#"
+[[recipes]]
+id = "originium_ore"
+by = "electric_mining_rig"
+time = 2
+out = 1
+is_source = true
+
+[[recipes]]
+id = "origocrust"
+by = "refining_unit"
+time = 2
+out = 1
+[recipes.inputs]
+originium_ore = 1
+
+[[recipes]]
+id = "origocrust_powder"
+by = "shredding_unit"
+time = 2
+out = 1
+[recipes.inputs]
+origocrust = 1
+
+[[recipes]]
+id = "amethyst_component"
+by = "gearing_unit"
+time = 2
+out = 1
+[recipes.inputs]
+origocrust = 1
+amethyst_fiber = 1
+"#;
+
+    fn owned(items: &[&str]) -> HashSet<ItemId> {
+        items.iter().map(|&item| item.into()).collect()
+    }
+
+    fn data() -> GameData {
+        GameData::new(RECIPES, REFINING_UNIT_TOML).unwrap()
+    }
+
+    #[test]
+    fn test_direct_and_transitive() {
+        let entries = data().craftable_from(&owned(&["originium_ore"]), None);
+
+        assert_eq!(
+            entries,
+            vec![
+                CraftableEntry {
+                    item_id: "origocrust".into(),
+                    recipe_id: "origocrust@refining_unit[originium_ore:1]->[origocrust:1]/2s"
+                        .into(),
+                    depth: 1,
+                },
+                CraftableEntry {
+                    item_id: "origocrust_powder".into(),
+                    recipe_id:
+                        "origocrust_powder@shredding_unit[origocrust:1]->[origocrust_powder:1]/2s"
+                            .into(),
+                    depth: 2,
+                },
+            ]
+        );
+        assert!(entries[0].is_direct());
+        assert!(!entries[1].is_direct());
+    }
+
+    #[test]
+    fn test_depth_limit() {
+        let entries = data().craftable_from(&owned(&["originium_ore"]), Some(1));
+
+        let items: Vec<&ItemId> = entries.iter().map(|entry| &entry.item_id).collect();
+        assert_eq!(items, vec!["origocrust"]);
+    }
+
+    #[test]
+    fn test_unsatisfiable_inputs() {
+        // amethyst_fiber has no recipe, so the component can never be made
+        let entries = data().craftable_from(&owned(&["origocrust"]), None);
+
+        let items: Vec<&ItemId> = entries.iter().map(|entry| &entry.item_id).collect();
+        assert_eq!(items, vec!["origocrust_powder"]);
+
+        let with_fiber = data().craftable_from(&owned(&["origocrust", "amethyst_fiber"]), None);
+        assert!(
+            with_fiber
+                .iter()
+                .any(|entry| entry.item_id == "amethyst_component" && entry.is_direct())
+        );
+    }
+
+    #[test]
+    fn test_unlock_makes_downstream_items_craftable() {
+        let recipes = format!(
+            "{}{}",
+            RECIPES,
+            r#"
+[[recipes]]
+id = "amethyst_fiber"
+by = "refining_unit"
+time = 2
+out = 1
+[recipes.inputs]
+origocrust_powder = 1
+"#
+        );
+        let data = GameData::new(&recipes, REFINING_UNIT_TOML).unwrap();
+        let owned = owned(&["originium_ore"]);
+        let unlocked = Unlocked {
+            machines: HashSet::from(["refining_unit".into(), "gearing_unit".into()]),
+            ..Default::default()
+        };
+        let items = |entries: Vec<CraftableEntry>| -> Vec<ItemId> {
+            entries.into_iter().map(|entry| entry.item_id).collect()
+        };
+
+        // Without the shredding unit only origocrust can be made
+        let shredding = Unlocked::recipe(
+            "origocrust_powder@shredding_unit[origocrust:1]->[origocrust_powder:1]/2s",
+        );
+        assert_eq!(
+            items(data.newly_craftable(&owned, &Unlocked::default(), &unlocked)),
+            vec!["origocrust"]
+        );
+        assert_eq!(
+            items(data.newly_craftable(&owned, &unlocked, &shredding)),
+            vec!["origocrust_powder", "amethyst_fiber", "amethyst_component"]
+        );
+        assert_eq!(
+            data.newly_craftable(&owned, &unlocked, &Unlocked::machine("shredding_unit")),
+            data.newly_craftable(&owned, &unlocked, &shredding)
+        );
+
+        // Unlocking what is already usable adds nothing
+        let all = unlocked.union(&shredding);
+        assert!(
+            data.newly_craftable(&owned, &all, &Unlocked::machine("refining_unit"))
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_source_recipes_need_owned_materials() {
+        assert!(data().craftable_from(&HashSet::new(), None).is_empty());
+    }
+}
@@ -0,0 +1,190 @@
+//! Recipes the planner can never pick, to help prune dead data.
+
+use crate::config::GameData;
+use crate::models::{ItemId, Recipe, RecipeUid};
+use crate::planner::{CyclePolicy, compare_for_selection};
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashSet};
+
+/// What the context-dependent selection rules (cyclic inputs, consumed
+/// byproducts) and the tag filters look at. Recipes with equal keys are
+/// treated alike by them in every plan.
+fn selection_key(recipe: &Recipe) -> impl PartialEq + '_ {
+    let inputs: BTreeSet<&ItemId> = recipe.inputs.keys().collect();
+    let consumed: BTreeSet<&ItemId> = recipe
+        .inputs
+        .keys()
+        .filter(|input_id| recipe.net_input(input_id) > 0)
+        .collect();
+    let groups: Vec<BTreeSet<&ItemId>> = recipe
+        .any_of
+        .iter()
+        .map(|group| group.items.iter().collect())
+        .collect();
+    let tags: BTreeSet<&String> = recipe.tags.iter().collect();
+
+    (inputs, consumed, groups, tags)
+}
+
+/// Returns true if the selector ranks `better` above `worse` in every
+/// plan, see `select_best_recipe`. Both must have the same `selection_key`,
+/// so the context-dependent rules tie whatever the context; it is enough
+/// to compare them in an empty one.
+fn dominates(data: &GameData, better: &Recipe, worse: &Recipe) -> bool {
+    compare_for_selection(
+        better,
+        worse,
+        &data.machines,
+        &HashSet::new(),
+        &[],
+        CyclePolicy::default(),
+    ) == Ordering::Greater
+}
+
+impl GameData {
+    /// Lists the recipes that are never selected automatically because
+    /// another recipe for the same item always ranks above them.
+    ///
+    /// Only recipes with the same inputs, input groups and tags are
+    /// compared, since those decide the cyclic and byproduct rules and the
+    /// tag filters, which depend on the plan. Dominated recipes can still be
+    /// chosen explicitly. The result is sorted.
+    pub fn dominated_recipes(&self) -> Vec<RecipeUid> {
+        let mut dominated: Vec<RecipeUid> = self
+            .recipes_by_output
+            .values()
+            .flat_map(|recipe_ids| {
+                let candidates: Vec<(&RecipeUid, &Recipe)> = recipe_ids
+                    .iter()
+                    .filter_map(|id| self.recipes.get(id).map(|recipe| (id, recipe)))
+                    .collect();
+
+                candidates
+                    .iter()
+                    .filter(|(_, worse)| {
+                        candidates.iter().any(|(_, better)| {
+                            selection_key(better) == selection_key(worse)
+                                && dominates(self, better, worse)
+                        })
+                    })
+                    .map(|(id, _)| (*id).clone())
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+
+        dominated.sort();
+        dominated.dedup();
+        dominated
+    }
+}
+
+#[cfg(all(test, feature = "data-files"))]
+mod tests {
+    use super::*;
+
+    // refining_unit_mk2 beats refining_unit on tier and power; the gearing
+    // unit draws more power, but tier is ranked first
+    const RECIPES: &str = r#"
+[[recipes]]
+id = "origocrust"
+by = ["refining_unit", "refining_unit_mk2"]
+time = 2
+out = 1
+[recipes.inputs]
+originium_ore = 1
+
+[[recipes]]
+id = "origocrust"
+by = "refining_unit"
+time = 1
+out = 1
+[recipes.inputs]
+amethyst_ore = 1
+
+[[recipes]]
+id = "amethyst_fiber"
+by = ["refining_unit", "gearing_unit"]
+time = 2
+out = 1
+[recipes.inputs]
+amethyst_ore = 1
+
+[[recipes]]
+id = "amethyst_component"
+by = "refining_unit"
+time = 2
+out = 1
+tags = ["smelting"]
+[recipes.inputs]
+amethyst_fiber = 1
+
+[[recipes]]
+id = "amethyst_component"
+by = "refining_unit_mk2"
+time = 2
+out = 1
+[recipes.inputs]
+amethyst_fiber = 1
+"#;
+
+    const MACHINES: &str = r#"
+[[machines]]
+id = "refining_unit"
+tier = 1
+power = 10
+
+[[machines]]
+id = "refining_unit_mk2"
+tier = 2
+power = 5
+
+[[machines]]
+id = "gearing_unit"
+tier = 2
+power = 20
+"#;
+
+    #[test]
+    fn test_dominated_recipe_is_flagged() {
+        let data = GameData::new(RECIPES, MACHINES).unwrap();
+
+        assert_eq!(
+            data.dominated_recipes(),
+            vec![
+                RecipeUid::from(
+                    "amethyst_fiber@refining_unit[amethyst_ore:1]->[amethyst_fiber:1]/2s"
+                ),
+                RecipeUid::from("origocrust@refining_unit[originium_ore:1]->[origocrust:1]/2s"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unknown_machine_is_dominated() {
+        let recipes = format!(
+            "{}{}",
+            RECIPES,
+            r#"
+[[recipes]]
+id = "amethyst_fiber"
+by = "unknown_unit"
+time = 2
+out = 1
+[recipes.inputs]
+amethyst_ore = 1
+"#
+        );
+        let data = GameData::new(&recipes, MACHINES).unwrap();
+
+        assert!(data.dominated_recipes().contains(&RecipeUid::from(
+            "amethyst_fiber@unknown_unit[amethyst_ore:1]->[amethyst_fiber:1]/2s"
+        )));
+        // The best variant is never dominated
+        assert!(
+            !data
+                .dominated_recipes()
+                .iter()
+                .any(|id| id.as_str().starts_with("amethyst_fiber@gearing_unit"))
+        );
+    }
+}
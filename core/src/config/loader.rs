@@ -1,65 +1,167 @@
+use crate::config::{DataWarning, GameDataBuilder};
+use crate::i18n::{Localizer, normalize_for_search};
+use crate::models::{ItemId, Machine, MachineId, Recipe, RecipeUid, canonical_id};
+use std::collections::HashMap;
+
+#[cfg(feature = "data-files")]
 use crate::error::ProductionError;
-use crate::models::{Machine, Recipe};
+#[cfg(feature = "data-files")]
+use crate::models::TimeUnit;
+#[cfg(feature = "data-files")]
 use serde::Deserialize;
-use std::collections::HashMap;
 
+#[cfg(feature = "data-files")]
 #[derive(Debug, Deserialize)]
 struct RecipeConfig {
+    #[serde(default)]
+    time_unit: TimeUnit,
+    ticks_per_second: Option<f64>,
     recipes: Vec<Recipe>,
 }
 
+#[cfg(feature = "data-files")]
 #[derive(Debug, Deserialize)]
 struct MachineConfig {
+    /// Display names of machine tiers, keyed by tier number.
+    #[serde(default)]
+    tier_names: HashMap<String, String>,
     machines: Vec<Machine>,
 }
 
 pub struct GameData {
-    pub recipes: HashMap<String, Recipe>,
-    pub recipes_by_output: HashMap<String, Vec<String>>,
-    pub machines: HashMap<String, Machine>,
+    pub recipes: HashMap<RecipeUid, Recipe>,
+    /// Recipe IDs indexed by output item, ordered by machine tier
+    /// (highest first), then by ID.
+    pub recipes_by_output: HashMap<ItemId, Vec<RecipeUid>>,
+    /// Recipe IDs indexed by tag, in data file order.
+    pub recipes_by_tag: HashMap<String, Vec<RecipeUid>>,
+    pub machines: HashMap<MachineId, Machine>,
+    /// Display names of machine tiers, such as "Portable" for tier 1.
+    pub tier_names: HashMap<u32, String>,
+    /// Non-fatal problems found while loading, in data file order.
+    pub warnings: Vec<DataWarning>,
 }
 
 impl GameData {
+    /// Starts building game data from recipes and machines in memory.
+    pub fn builder() -> GameDataBuilder {
+        GameDataBuilder::new()
+    }
+
+    #[cfg(feature = "data-files")]
     pub fn new(recipes_content: &str, machines_content: &str) -> Result<Self, ProductionError> {
-        let recipe_config: RecipeConfig = toml::from_str(&recipes_content)
+        let recipe_config: RecipeConfig = toml::from_str(recipes_content)
             .map_err(|e| ProductionError::ParseError(format!("recipes.toml: {}", e)))?;
-        let machine_config: MachineConfig = toml::from_str(&machines_content)
+        let machine_config: MachineConfig = toml::from_str(machines_content)
             .map_err(|e| ProductionError::ParseError(format!("machines.toml: {}", e)))?;
 
-        let mut recipes = HashMap::new();
-        let mut recipes_by_output: HashMap<String, Vec<String>> = HashMap::new();
+        if let Some(rate) = recipe_config.ticks_per_second
+            && !(rate.is_finite() && rate > 0.0)
+        {
+            return Err(ProductionError::ParseError(format!(
+                "recipes.toml: ticks_per_second must be a positive number, got {}",
+                rate
+            )));
+        }
+
+        let mut builder = GameData::builder();
+        for (tier, name) in machine_config.tier_names {
+            let tier = tier.parse().map_err(|_| {
+                ProductionError::ParseError(format!(
+                    "machines.toml: tier_names key '{}' is not a tier number",
+                    tier
+                ))
+            })?;
+            builder = builder.add_tier_name(tier, name);
+        }
+        for machine in machine_config.machines {
+            builder = builder.add_machine(machine);
+        }
 
         for mut r in recipe_config.recipes {
-            r.normalize();
+            if r.machine_options().is_empty() {
+                return Err(ProductionError::ParseError(format!(
+                    "recipes.toml: recipe '{}' has no machine in `by`",
+                    r.id
+                )));
+            }
 
-            let unique_id = r.compute_unique_id();
-            let output_item = r.id.clone();
+            r.normalize_time(recipe_config.time_unit, recipe_config.ticks_per_second)?;
+            builder = builder.add_recipe(r);
+        }
 
-            recipes_by_output
-                .entry(output_item)
-                .or_default()
-                .push(unique_id.clone());
+        builder.build()
+    }
+
+    /// Gets the display name of a machine tier, if the data names it.
+    pub fn tier_name(&self, tier: u32) -> Option<&str> {
+        self.tier_names.get(&tier).map(String::as_str)
+    }
+
+    /// Gets the display name of a machine tier, falling back to "Tier N".
+    pub fn tier_label(&self, tier: u32) -> String {
+        self.tier_name(tier)
+            .map_or_else(|| format!("Tier {}", tier), str::to_string)
+    }
 
-            recipes.insert(unique_id, r);
+    /// Finds the items whose ID, localized name or reading matches `name`,
+    /// ignoring case, surrounding whitespace, full-width forms and kana
+    /// variants (see `normalize_for_search`). The result is sorted by ID.
+    pub fn find_items_by_name(&self, name: &str, localizer: &Localizer) -> Vec<ItemId> {
+        let needle = normalize_for_search(name);
+
+        let mut matches: Vec<ItemId> = self
+            .recipes_by_output
+            .keys()
+            .filter(|item_id| {
+                let item_id = item_id.as_str();
+                normalize_for_search(item_id) == needle
+                    || normalize_for_search(&localizer.get_item(item_id)) == needle
+                    || normalize_for_search(&localizer.get_reading(item_id)) == needle
+            })
+            .cloned()
+            .collect();
+        matches.sort();
+        matches
+    }
+
+    /// Maps a localized name (or reading, or ID) back to an item ID.
+    ///
+    /// An exact match is preferred over case-insensitive ones. Returns `None`
+    /// when nothing matches or the name is ambiguous; use
+    /// `find_items_by_name` to list the candidates.
+    pub fn resolve_item_name(&self, name: &str, localizer: &Localizer) -> Option<ItemId> {
+        let name = name.trim();
+        if let Some((item_id, _)) = self
+            .recipes_by_output
+            .get_key_value(canonical_id(name).as_ref())
+        {
+            return Some(item_id.clone());
         }
 
-        let machines = machine_config
-            .machines
-            .into_iter()
-            .map(|m| (m.id.clone(), m))
+        let matches = self.find_items_by_name(name, localizer);
+        let exact: Vec<&ItemId> = matches
+            .iter()
+            .filter(|item_id| {
+                localizer.get_item(item_id.as_str()) == name
+                    || localizer.get_reading(item_id.as_str()) == name
+            })
             .collect();
 
-        Ok(GameData {
-            recipes,
-            recipes_by_output,
-            machines,
-        })
+        match (exact.as_slice(), matches.as_slice()) {
+            ([item_id], _) => Some((*item_id).clone()),
+            ([], [item_id]) => Some(item_id.clone()),
+            _ => None,
+        }
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "data-files"))]
 mod tests {
     use super::*;
+    use crate::models::ProductionNode;
+    use crate::planner::{PlanWarning, plan_production_with};
+    use crate::test_fixtures::REFINING_UNIT_TOML;
 
     #[test]
     fn test_parse_valid_toml() {
@@ -175,4 +277,558 @@ power = 5
         // Total recipes should be 3
         assert_eq!(data.recipes.len(), 3);
     }
+
+    #[test]
+    fn test_recipes_by_output_sorted_by_tier() {
+        let recipes_toml = r#"
+[[recipes]]
+id = "originium_ore"
+by = "portable_originium_rig"
+time = 2
+out = 1
+is_source = true
+
+[[recipes]]
+id = "originium_ore"
+by = "electric_mining_rig"
+time = 2
+out = 1
+is_source = true
+"#;
+
+        let machines_toml = r#"
+[[machines]]
+id = "portable_originium_rig"
+tier = 1
+power = 0
+
+[[machines]]
+id = "electric_mining_rig"
+tier = 2
+power = 5
+"#;
+
+        let data = GameData::new(recipes_toml, machines_toml).unwrap();
+
+        assert_eq!(
+            data.recipes_by_output["originium_ore"],
+            vec![
+                "originium_ore@electric_mining_rig[]->[originium_ore:1]/2s",
+                "originium_ore@portable_originium_rig[]->[originium_ore:1]/2s",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_seconds_time_unchanged() {
+        let recipes_toml = r#"
+[[recipes]]
+id = "origocrust"
+by = "refining_unit"
+time = 2
+out = 1
+"#;
+
+        let data = GameData::new(recipes_toml, REFINING_UNIT_TOML).unwrap();
+        let recipe = data.recipes.values().next().unwrap();
+
+        assert_eq!(recipe.time, 2.0);
+    }
+
+    #[test]
+    fn test_fractional_time() {
+        let recipes_toml = r#"
+[[recipes]]
+id = "origocrust"
+by = "refining_unit"
+time = 1.5
+out = 1
+"#;
+
+        let data = GameData::new(recipes_toml, REFINING_UNIT_TOML).unwrap();
+        let recipe = data.recipes.values().next().unwrap();
+
+        assert_eq!(recipe.time, 1.5);
+    }
+
+    #[test]
+    fn test_tick_time_converted_to_seconds() {
+        let recipes_toml = r#"
+time_unit = "ticks"
+ticks_per_second = 20
+
+[[recipes]]
+id = "origocrust"
+by = "refining_unit"
+time = 40
+out = 1
+
+[[recipes]]
+id = "amethyst_fiber"
+by = "refining_unit"
+time = 3
+time_unit = "seconds"
+out = 1
+"#;
+
+        let data = GameData::new(recipes_toml, REFINING_UNIT_TOML).unwrap();
+
+        let crust_id = &data.recipes_by_output["origocrust"][0];
+        let fiber_id = &data.recipes_by_output["amethyst_fiber"][0];
+
+        assert_eq!(data.recipes[crust_id].time, 2.0);
+        assert_eq!(data.recipes[fiber_id].time, 3.0);
+    }
+
+    #[test]
+    fn test_recipes_differing_only_in_time_are_kept() {
+        let recipes_toml = r#"
+ticks_per_second = 20
+
+[[recipes]]
+id = "origocrust"
+by = "refining_unit"
+time = 2
+out = 1
+
+[[recipes]]
+id = "origocrust"
+by = "refining_unit"
+time = 3
+out = 1
+
+[[recipes]]
+id = "origocrust"
+by = "refining_unit"
+time = 40
+time_unit = "ticks"
+out = 1
+"#;
+
+        let data = GameData::new(recipes_toml, REFINING_UNIT_TOML).unwrap();
+
+        // The 40 tick recipe is the 2 second one
+        let times: Vec<f64> = data.recipes_by_output["origocrust"]
+            .iter()
+            .map(|uid| data.recipes[uid].time)
+            .collect();
+        assert_eq!(data.recipes.len(), 2);
+        assert_eq!(times, vec![2.0, 3.0]);
+    }
+
+    #[test]
+    fn test_invalid_tick_rate() {
+        for rate in ["0", "-20", "nan", "inf"] {
+            let recipes_toml = format!(
+                r#"
+time_unit = "ticks"
+ticks_per_second = {}
+
+[[recipes]]
+id = "origocrust"
+by = "refining_unit"
+time = 40
+out = 1
+"#,
+                rate
+            );
+
+            match GameData::new(&recipes_toml, REFINING_UNIT_TOML) {
+                Err(ProductionError::ParseError(msg)) => {
+                    assert!(msg.contains("ticks_per_second"));
+                }
+                _ => panic!("Expected ParseError for ticks_per_second = {}", rate),
+            }
+        }
+    }
+
+    #[test]
+    fn test_ticks_without_rate() {
+        let recipes_toml = r#"
+[[recipes]]
+id = "origocrust"
+by = "refining_unit"
+time = 40
+time_unit = "ticks"
+out = 1
+"#;
+
+        let result = GameData::new(recipes_toml, REFINING_UNIT_TOML);
+        assert!(matches!(result, Err(ProductionError::ParseError(_))));
+    }
+
+    #[test]
+    fn test_multi_machine_recipe_selects_best_machine() {
+        let recipes_toml = r#"
+[[recipes]]
+id = "originium_ore"
+by = ["portable_originium_rig", "electric_mining_rig"]
+time = 2
+out = 1
+is_source = true
+"#;
+
+        let machines_toml = r#"
+[[machines]]
+id = "portable_originium_rig"
+tier = 1
+power = 0
+
+[[machines]]
+id = "electric_mining_rig"
+tier = 2
+power = 5
+"#;
+
+        let data = GameData::new(recipes_toml, machines_toml).unwrap();
+
+        assert_eq!(data.recipes.len(), 2);
+        assert_eq!(data.recipes_by_output["originium_ore"].len(), 2);
+
+        let plan = plan_production_with(&data, "originium_ore", 30).node;
+        match plan {
+            ProductionNode::Resolved { machine_id, .. } => {
+                assert_eq!(machine_id, "electric_mining_rig");
+            }
+            _ => panic!("Expected Resolved node"),
+        }
+    }
+
+    #[test]
+    fn test_ids_are_trimmed_and_lowercased() {
+        let recipes_toml = r#"
+[[recipes]]
+id = "Origocrust "
+by = " Refining_Unit"
+time = 2
+out = 1
+[recipes.inputs]
+" originium_ore" = 1
+
+[[recipes]]
+id = "originium_ore"
+by = "refining_unit"
+time = 2
+out = 1
+is_source = true
+"#;
+        let machines_toml = r#"
+[[machines]]
+id = "refining_unit  "
+tier = 1
+power = 5
+"#;
+
+        let data = GameData::new(recipes_toml, machines_toml).unwrap();
+
+        let result = plan_production_with(&data, "origocrust", 30);
+        assert!(result.warnings.is_empty(), "{:?}", result.warnings);
+        match result.node {
+            ProductionNode::Resolved {
+                machine_id, inputs, ..
+            } => {
+                assert_eq!(machine_id, "refining_unit");
+                assert!(matches!(
+                    &inputs[..],
+                    [ProductionNode::Resolved { item_id, .. }] if item_id == "originium_ore"
+                ));
+            }
+            _ => panic!("Expected Resolved node"),
+        }
+    }
+
+    #[test]
+    fn test_ids_differing_only_in_case_fail() {
+        let recipes_toml = r#"
+[[recipes]]
+id = "origocrust"
+by = "refining_unit"
+time = 2
+out = 1
+[recipes.inputs]
+originium_ore = 1
+Originium_Ore = 1
+"#;
+
+        let result = GameData::new(recipes_toml, REFINING_UNIT_TOML);
+
+        assert!(
+            matches!(result, Err(ProductionError::ParseError(msg)) if msg.contains("Originium_Ore"))
+        );
+    }
+
+    #[test]
+    fn test_recipe_without_machine() {
+        let recipes_toml = r#"
+[[recipes]]
+id = "origocrust"
+by = []
+time = 2
+out = 1
+"#;
+
+        let result = GameData::new(recipes_toml, REFINING_UNIT_TOML);
+        assert!(matches!(result, Err(ProductionError::ParseError(_))));
+    }
+
+    #[test]
+    fn test_tier_names() {
+        let machines_toml = r#"
+[tier_names]
+1 = "Portable"
+
+[[machines]]
+id = "refining_unit"
+tier = 1
+power = 5
+"#;
+
+        let data = GameData::new("recipes = []", machines_toml).unwrap();
+        assert_eq!(data.tier_name(1), Some("Portable"));
+        assert_eq!(data.tier_name(2), None);
+        assert_eq!(data.tier_label(2), "Tier 2");
+
+        let invalid = machines_toml.replace("1 = ", "first = ");
+        let result = GameData::new("recipes = []", &invalid);
+        assert!(matches!(result, Err(ProductionError::ParseError(_))));
+    }
+
+    #[test]
+    fn test_warns_when_outputs_omit_recipe_id() {
+        let recipes_toml = r#"
+[[recipes]]
+id = "origocrust"
+by = "refining_unit"
+time = 2
+[recipes.outputs]
+origocrust_powder = 1
+
+[[recipes]]
+id = "amethyst_fiber"
+by = "refining_unit"
+time = 2
+out = 1
+"#;
+
+        let data = GameData::new(recipes_toml, REFINING_UNIT_TOML).unwrap();
+
+        assert_eq!(
+            data.warnings,
+            vec![DataWarning::MissingSelfOutput {
+                recipe_id: "origocrust@refining_unit[]->[origocrust_powder:1]/2s".into(),
+                item_id: "origocrust".into(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_recipe_indexed_under_every_output() {
+        let recipes_toml = r#"
+[[recipes]]
+id = "crack_origocrust"
+by = "refining_unit"
+time = 2
+[recipes.inputs]
+origocrust = 1
+[recipes.outputs]
+originium_ore = 2
+amethyst_ore = 1
+"#;
+
+        let data = GameData::new(recipes_toml, REFINING_UNIT_TOML).unwrap();
+
+        assert_eq!(
+            data.recipes_by_output["originium_ore"],
+            data.recipes_by_output["amethyst_ore"]
+        );
+        assert_eq!(
+            data.recipes_by_output["originium_ore"],
+            data.recipes_by_output["crack_origocrust"]
+        );
+
+        // Each output is planned with its own count per craft
+        for (item_id, crafts) in [("originium_ore", 15), ("amethyst_ore", 30)] {
+            let result = plan_production_with(&data, item_id, 30);
+            let ProductionNode::Resolved { inputs, .. } = &result.node else {
+                panic!("Expected Resolved node for {}", item_id);
+            };
+            assert_eq!(inputs[0].amount(), crafts);
+            assert!(
+                !result
+                    .warnings
+                    .iter()
+                    .any(|warning| matches!(warning, PlanWarning::OutputDefaulted { .. }))
+            );
+        }
+    }
+
+    const PORTED_MACHINE_TOML: &str = r#"
+[[machines]]
+id = "refining_unit"
+tier = 1
+power = 5
+input_ports = 2
+output_ports = 1
+"#;
+
+    const FOUR_INPUT_RECIPE_TOML: &str = r#"
+[[recipes]]
+id = "amethyst_component"
+by = "refining_unit"
+time = 2
+out = 1
+[recipes.inputs]
+originium_ore = 1
+amethyst_ore = 1
+ferrium_ore = 1
+origocrust = 1
+"#;
+
+    #[test]
+    fn test_warns_when_recipe_exceeds_machine_ports() {
+        let data = GameData::new(FOUR_INPUT_RECIPE_TOML, PORTED_MACHINE_TOML).unwrap();
+
+        assert_eq!(
+            data.warnings,
+            vec![DataWarning::PortsExceeded {
+                recipe_id: data.recipes_by_output["amethyst_component"][0].clone(),
+                machine_id: "refining_unit".into(),
+                direction: "input",
+                count: 4,
+                ports: 2,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_machine_without_ports_is_not_checked() {
+        let data = GameData::new(FOUR_INPUT_RECIPE_TOML, REFINING_UNIT_TOML).unwrap();
+
+        assert!(data.machines["refining_unit"].input_ports.is_none());
+        assert_eq!(data.warnings, vec![]);
+    }
+
+    const NAMED_ITEMS_TOML: &str = r#"
+[[recipes]]
+id = "originium_ore"
+by = "electric_mining_rig"
+time = 2
+out = 1
+
+[[recipes]]
+id = "amethyst_ore"
+by = "electric_mining_rig"
+time = 2
+out = 1
+
+[[recipes]]
+id = "origocrust"
+by = "refining_unit"
+time = 2
+out = 1
+[recipes.inputs]
+originium_ore = 1
+"#;
+
+    const NAMED_MACHINES_TOML: &str = r#"
+[[machines]]
+id = "electric_mining_rig"
+tier = 2
+power = 5
+
+[[machines]]
+id = "refining_unit"
+tier = 1
+power = 5
+"#;
+
+    #[test]
+    fn test_resolve_japanese_item_name() {
+        let localizer = Localizer::new(
+            r#"
+[items]
+originium_ore = "源石鉱物"
+origocrust = "オリゴクラスト"
+
+[readings]
+originium_ore = "げんせきこうぶつ"
+"#,
+        )
+        .unwrap();
+        let data = GameData::new(NAMED_ITEMS_TOML, NAMED_MACHINES_TOML).unwrap();
+
+        assert_eq!(
+            data.resolve_item_name("源石鉱物", &localizer),
+            Some("originium_ore".into())
+        );
+        assert_eq!(
+            data.resolve_item_name("げんせきこうぶつ", &localizer),
+            Some("originium_ore".into())
+        );
+        assert_eq!(
+            data.resolve_item_name(" オリゴクラスト ", &localizer),
+            Some("origocrust".into())
+        );
+        assert_eq!(data.resolve_item_name("紫晶鉱物", &localizer), None);
+    }
+
+    #[test]
+    fn test_resolve_item_name_ignores_case() {
+        let localizer = Localizer::new(
+            r#"
+[items]
+originium_ore = "Originium Ore"
+"#,
+        )
+        .unwrap();
+        let data = GameData::new(NAMED_ITEMS_TOML, NAMED_MACHINES_TOML).unwrap();
+
+        assert_eq!(
+            data.resolve_item_name("originium ore", &localizer),
+            Some("originium_ore".into())
+        );
+        assert_eq!(
+            data.resolve_item_name("ORIGOCRUST", &localizer),
+            Some("origocrust".into())
+        );
+        assert_eq!(
+            data.resolve_item_name("Originium_Ore", &localizer),
+            Some("originium_ore".into())
+        );
+    }
+
+    #[test]
+    fn test_resolve_ambiguous_item_name() {
+        let localizer = Localizer::new(
+            r#"
+[items]
+originium_ore = "Ore"
+amethyst_ore = "ore"
+"#,
+        )
+        .unwrap();
+        let data = GameData::new(NAMED_ITEMS_TOML, NAMED_MACHINES_TOML).unwrap();
+
+        // The exact spelling picks one of the candidates
+        assert_eq!(
+            data.resolve_item_name("Ore", &localizer),
+            Some("originium_ore".into())
+        );
+        // Otherwise both match and nothing is chosen
+        assert_eq!(data.resolve_item_name("ORE", &localizer), None);
+        assert_eq!(
+            data.find_items_by_name("ORE", &localizer),
+            vec!["amethyst_ore", "originium_ore"]
+        );
+    }
+
+    #[test]
+    fn test_bundled_data_has_no_warnings() {
+        let recipes = include_str!("../../../res/recipes.toml");
+        let machines = include_str!("../../../res/machines.toml");
+
+        let data = GameData::new(recipes, machines).unwrap();
+
+        assert_eq!(data.warnings, vec![]);
+    }
 }
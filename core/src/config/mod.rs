@@ -1,3 +1,12 @@
+mod builder;
+mod craftable;
+mod dominated;
 mod loader;
+mod shared;
+mod warnings;
 
+pub use builder::GameDataBuilder;
+pub use craftable::{CraftableEntry, Unlocked};
 pub use loader::GameData;
+pub use shared::{ReloadReport, SharedGameData};
+pub use warnings::DataWarning;
@@ -0,0 +1,225 @@
+//! Reloadable game data shared between long-running consumers.
+
+use crate::config::GameData;
+#[cfg(feature = "data-files")]
+use crate::error::ProductionError;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+
+/// Summary of what changed between two game data snapshots.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReloadReport {
+    /// Number of recipes present only in the new data.
+    pub recipes_added: usize,
+    /// Number of recipes present only in the old data.
+    pub recipes_removed: usize,
+    /// Number of machines present only in the new data.
+    pub machines_added: usize,
+    /// Number of machines present only in the old data.
+    pub machines_removed: usize,
+    /// Generation number of the data after the reload.
+    pub generation: u64,
+}
+
+impl ReloadReport {
+    /// Returns true if the reload added or removed anything.
+    pub fn has_changes(&self) -> bool {
+        self.recipes_added > 0
+            || self.recipes_removed > 0
+            || self.machines_added > 0
+            || self.machines_removed > 0
+    }
+}
+
+/// Thread-safe handle to game data that can be swapped at runtime.
+///
+/// Consumers take a `snapshot()` and plan against it; a reload replaces
+/// the data for later snapshots without affecting ones already in use.
+#[derive(Clone)]
+pub struct SharedGameData {
+    current: Arc<RwLock<Arc<GameData>>>,
+    generation: Arc<AtomicU64>,
+}
+
+impl SharedGameData {
+    /// Wraps already-loaded game data.
+    pub fn new(data: GameData) -> Self {
+        SharedGameData {
+            current: Arc::new(RwLock::new(Arc::new(data))),
+            generation: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Loads game data from TOML strings and wraps it.
+    #[cfg(feature = "data-files")]
+    pub fn from_strings(
+        recipes_content: &str,
+        machines_content: &str,
+    ) -> Result<Self, ProductionError> {
+        GameData::new(recipes_content, machines_content).map(SharedGameData::new)
+    }
+
+    /// Returns the current data. The snapshot stays valid across reloads.
+    pub fn snapshot(&self) -> Arc<GameData> {
+        let guard = self
+            .current
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        Arc::clone(&guard)
+    }
+
+    /// Returns how many successful reloads have happened.
+    ///
+    /// Consumers can compare this against a stored value to detect changes.
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    /// Parses new TOML content and swaps it in.
+    ///
+    /// On parse failure the current data is kept and the error is returned.
+    #[cfg(feature = "data-files")]
+    pub fn reload_from_strings(
+        &self,
+        recipes_content: &str,
+        machines_content: &str,
+    ) -> Result<ReloadReport, ProductionError> {
+        let new_data = GameData::new(recipes_content, machines_content)?;
+        Ok(self.replace(new_data))
+    }
+
+    /// Swaps in new game data and reports the difference.
+    pub fn replace(&self, new_data: GameData) -> ReloadReport {
+        let mut guard = self
+            .current
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        let (recipes_added, recipes_removed) = count_key_changes(&guard.recipes, &new_data.recipes);
+        let (machines_added, machines_removed) =
+            count_key_changes(&guard.machines, &new_data.machines);
+
+        *guard = Arc::new(new_data);
+        let generation = self.generation.fetch_add(1, Ordering::AcqRel) + 1;
+
+        ReloadReport {
+            recipes_added,
+            recipes_removed,
+            machines_added,
+            machines_removed,
+            generation,
+        }
+    }
+}
+
+fn count_key_changes<K: Eq + Hash, V>(old: &HashMap<K, V>, new: &HashMap<K, V>) -> (usize, usize) {
+    let added = new.keys().filter(|k| !old.contains_key(*k)).count();
+    let removed = old.keys().filter(|k| !new.contains_key(*k)).count();
+    (added, removed)
+}
+
+#[cfg(all(test, feature = "data-files"))]
+mod tests {
+    use super::*;
+    use crate::models::ProductionNode;
+    use crate::planner::plan_production_with;
+
+    const MACHINES_TOML: &str = r#"
+[[machines]]
+id = "refining_unit"
+tier = 1
+power = 5
+
+[[machines]]
+id = "shredding_unit"
+tier = 1
+power = 10
+"#;
+
+    const RECIPES_OLD: &str = r#"
+[[recipes]]
+id = "origocrust"
+by = "refining_unit"
+time = 2
+out = 1
+[recipes.inputs]
+originium_ore = 1
+"#;
+
+    const RECIPES_NEW: &str = r#"
+[[recipes]]
+id = "origocrust"
+by = "shredding_unit"
+time = 2
+out = 1
+[recipes.inputs]
+originium_ore = 1
+
+[[recipes]]
+id = "originium_powder"
+by = "shredding_unit"
+time = 2
+out = 1
+[recipes.inputs]
+originium_ore = 1
+"#;
+
+    fn machine_of(node: &ProductionNode) -> &str {
+        match node {
+            ProductionNode::Resolved { machine_id, .. } => machine_id.as_str(),
+            _ => panic!("Expected Resolved node"),
+        }
+    }
+
+    #[test]
+    fn test_reload_reports_changes() {
+        let shared = SharedGameData::from_strings(RECIPES_OLD, MACHINES_TOML).unwrap();
+        assert_eq!(shared.generation(), 0);
+
+        let report = shared
+            .reload_from_strings(RECIPES_NEW, MACHINES_TOML)
+            .unwrap();
+
+        // The old origocrust recipe is replaced, and originium_powder is new
+        assert_eq!(report.recipes_added, 2);
+        assert_eq!(report.recipes_removed, 1);
+        assert_eq!(report.machines_added, 0);
+        assert_eq!(report.machines_removed, 0);
+        assert_eq!(report.generation, 1);
+        assert!(report.has_changes());
+        assert_eq!(shared.generation(), 1);
+    }
+
+    #[test]
+    fn test_failed_reload_keeps_current_data() {
+        let shared = SharedGameData::from_strings(RECIPES_OLD, MACHINES_TOML).unwrap();
+
+        let result = shared.reload_from_strings("[[recipes", MACHINES_TOML);
+
+        assert!(matches!(result, Err(ProductionError::ParseError(_))));
+        assert_eq!(shared.generation(), 0);
+        assert_eq!(shared.snapshot().recipes.len(), 1);
+    }
+
+    #[test]
+    fn test_snapshot_isolated_from_reload() {
+        let shared = SharedGameData::from_strings(RECIPES_OLD, MACHINES_TOML).unwrap();
+
+        // A plan in flight holds the snapshot taken before the reload
+        let before = shared.snapshot();
+        shared
+            .reload_from_strings(RECIPES_NEW, MACHINES_TOML)
+            .unwrap();
+        let after = shared.snapshot();
+
+        let old_plan = plan_production_with(&before, "origocrust", 30).node;
+        let new_plan = plan_production_with(&after, "origocrust", 30).node;
+
+        assert_eq!(machine_of(&old_plan), "refining_unit");
+        assert_eq!(machine_of(&new_plan), "shredding_unit");
+        assert_eq!(before.recipes.len(), 1);
+        assert_eq!(after.recipes.len(), 2);
+    }
+}
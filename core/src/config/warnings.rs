@@ -0,0 +1,66 @@
+//! Problems found in the data files that do not prevent loading.
+
+use crate::models::{ItemId, MachineId, RecipeUid};
+use std::fmt;
+
+/// A suspicious pattern detected while loading game data.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "data-files", derive(serde::Serialize))]
+pub enum DataWarning {
+    /// The recipe's outputs do not include its own `id`, so planning for
+    /// that item would fall back to one output per craft.
+    MissingSelfOutput {
+        recipe_id: RecipeUid,
+        item_id: ItemId,
+    },
+    /// The recipe uses more distinct items on one side than its machine
+    /// has ports for. `direction` is `"input"` or `"output"`.
+    PortsExceeded {
+        recipe_id: RecipeUid,
+        machine_id: MachineId,
+        direction: &'static str,
+        count: usize,
+        ports: u32,
+    },
+}
+
+impl DataWarning {
+    /// Returns a stable identifier for the kind of warning, for grouping.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            DataWarning::MissingSelfOutput { .. } => "missing_self_output",
+            DataWarning::PortsExceeded { .. } => "ports_exceeded",
+        }
+    }
+
+    /// Returns the unique ID of the recipe the warning is about.
+    pub fn recipe_id(&self) -> &RecipeUid {
+        match self {
+            DataWarning::MissingSelfOutput { recipe_id, .. }
+            | DataWarning::PortsExceeded { recipe_id, .. } => recipe_id,
+        }
+    }
+}
+
+impl fmt::Display for DataWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DataWarning::MissingSelfOutput { recipe_id, item_id } => write!(
+                f,
+                "Recipe {} does not list its own item {} as an output",
+                recipe_id, item_id
+            ),
+            DataWarning::PortsExceeded {
+                recipe_id,
+                machine_id,
+                direction,
+                count,
+                ports,
+            } => write!(
+                f,
+                "Recipe {} has {} distinct {}s but machine {} has only {} {} ports",
+                recipe_id, count, direction, machine_id, ports, direction
+            ),
+        }
+    }
+}
@@ -4,3 +4,4 @@ pub const SELF_REFERENCE_KEYWORD: &str = "this";
 
 pub const RECIPE_DEFINITION_PATH: &str = "res/recipes.toml";
 pub const MACHINE_DEFINITION_PATH: &str = "res/machines.toml";
+pub const LOCALE_DIRECTORY_PATH: &str = "res/locales";
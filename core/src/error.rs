@@ -6,6 +6,8 @@ pub enum ProductionError {
     FileNotFound(String),
     ParseError(String),
     RecipeNotFound(String),
+    CyclicDependency(String),
+    UnresolvedItems(Vec<String>),
 }
 
 impl fmt::Display for ProductionError {
@@ -14,6 +16,10 @@ impl fmt::Display for ProductionError {
             ProductionError::FileNotFound(path) => write!(f, "File not found: {}", path),
             ProductionError::ParseError(msg) => write!(f, "Parse error: {}", msg),
             ProductionError::RecipeNotFound(id) => write!(f, "Recipe not found: {}", id),
+            ProductionError::CyclicDependency(id) => write!(f, "Cyclic dependency on: {}", id),
+            ProductionError::UnresolvedItems(ids) => {
+                write!(f, "Unresolved items: {}", ids.join(", "))
+            }
         }
     }
 }
@@ -0,0 +1,75 @@
+//! FNV-1a hashing, which unlike the standard library's hashers gives the
+//! same result on every run and platform.
+
+/// Hash width of `Fnv1a`, either `u32` or `u64`.
+pub(crate) trait FnvWord: Copy {
+    const OFFSET_BASIS: Self;
+    const PRIME: Self;
+
+    fn mix(self, byte: u8) -> Self;
+}
+
+impl FnvWord for u32 {
+    const OFFSET_BASIS: u32 = 0x811c_9dc5;
+    const PRIME: u32 = 0x0100_0193;
+
+    fn mix(self, byte: u8) -> u32 {
+        (self ^ u32::from(byte)).wrapping_mul(Self::PRIME)
+    }
+}
+
+impl FnvWord for u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    fn mix(self, byte: u8) -> u64 {
+        (self ^ u64::from(byte)).wrapping_mul(Self::PRIME)
+    }
+}
+
+/// Incremental FNV-1a hasher producing a `T`.
+pub(crate) struct Fnv1a<T: FnvWord>(T);
+
+impl<T: FnvWord> Fnv1a<T> {
+    pub(crate) fn new() -> Self {
+        Fnv1a(T::OFFSET_BASIS)
+    }
+
+    pub(crate) fn write(&mut self, bytes: &[u8]) {
+        self.0 = bytes.iter().fold(self.0, |hash, byte| hash.mix(*byte));
+    }
+
+    pub(crate) fn finish(&self) -> T {
+        self.0
+    }
+}
+
+/// Hashes `bytes` in one go.
+pub(crate) fn fnv1a<T: FnvWord>(bytes: &[u8]) -> T {
+    let mut hasher = Fnv1a::new();
+    hasher.write(bytes);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fnv1a_reference_values() {
+        assert_eq!(fnv1a::<u32>(b""), 0x811c_9dc5);
+        assert_eq!(fnv1a::<u32>(b"a"), 0xe40c_292c);
+        assert_eq!(fnv1a::<u32>(b"foobar"), 0xbf9c_f968);
+        assert_eq!(fnv1a::<u64>(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a::<u64>(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(fnv1a::<u64>(b"foobar"), 0x8594_4171_f739_67e8);
+    }
+
+    #[test]
+    fn test_incremental_writes_match_one_shot() {
+        let mut hasher = Fnv1a::<u64>::new();
+        hasher.write(b"foo");
+        hasher.write(b"bar");
+        assert_eq!(hasher.finish(), fnv1a::<u64>(b"foobar"));
+    }
+}
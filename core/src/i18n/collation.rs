@@ -0,0 +1,275 @@
+//! Reading-based ordering for localized item lists.
+
+use std::cmp::Ordering;
+
+use super::loader::Locale;
+
+/// Section key for readings that do not fit any section.
+pub const OTHER_SECTION: &str = "#";
+
+/// Gojūon rows, keyed by the first kana of each row.
+/// Voiced, semi-voiced, and small kana belong to the row of their base kana.
+const GOJUON_ROWS: [(char, &str); 10] = [
+    ('あ', "あいうえおぁぃぅぇぉゔ"),
+    ('か', "かきくけこがぎぐげごゕゖ"),
+    ('さ', "さしすせそざじずぜぞ"),
+    ('た', "たちつてとだぢづでどっ"),
+    ('な', "なにぬねの"),
+    ('は', "はひふへほばびぶべぼぱぴぷぺぽ"),
+    ('ま', "まみむめも"),
+    ('や', "やゆよゃゅょ"),
+    ('ら', "らりるれろ"),
+    ('わ', "わゐゑをんゎ"),
+];
+
+/// Broad script classes, in the order they appear in sorted lists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Script {
+    Latin,
+    Kana,
+    Han,
+    Other,
+}
+
+fn script_of(c: char) -> Script {
+    match c {
+        c if c.is_ascii() => Script::Latin,
+        '\u{00C0}'..='\u{024F}' => Script::Latin,
+        '\u{3040}'..='\u{30FF}' | '\u{31F0}'..='\u{31FF}' | '\u{FF66}'..='\u{FF9F}' => Script::Kana,
+        '\u{3400}'..='\u{4DBF}' | '\u{4E00}'..='\u{9FFF}' | '\u{F900}'..='\u{FAFF}' => Script::Han,
+        _ => Script::Other,
+    }
+}
+
+/// Strips pinyin tone marks so `zhōng` sorts next to `zhong`.
+/// `ü` is written as `v`, following the common pinyin input convention.
+fn strip_tone(c: char) -> char {
+    match c {
+        'ā' | 'á' | 'ǎ' | 'à' => 'a',
+        'ē' | 'é' | 'ě' | 'è' => 'e',
+        'ī' | 'í' | 'ǐ' | 'ì' => 'i',
+        'ō' | 'ó' | 'ǒ' | 'ò' => 'o',
+        'ū' | 'ú' | 'ǔ' | 'ù' => 'u',
+        'ǖ' | 'ǘ' | 'ǚ' | 'ǜ' | 'ü' => 'v',
+        _ => c,
+    }
+}
+
+/// Voiced kana and the plain kana they are written with.
+const VOICED_KANA: (&str, &str) = (
+    "がぎぐげござじずぜぞだぢづでどばびぶべぼゔ",
+    "かきくけこさしすせそたちつてとはひふへほう",
+);
+/// Semi-voiced kana and their plain kana.
+const SEMI_VOICED_KANA: (&str, &str) = ("ぱぴぷぺぽ", "はひふへほ");
+/// Small kana and their full-size kana.
+const SMALL_KANA: (&str, &str) = ("ぁぃぅぇぉっゃゅょゎゕゖ", "あいうえおつやゆよわかけ");
+
+/// Kana ending in each vowel, used to read the long vowel mark `ー`.
+const VOWEL_COLUMNS: [(char, &str); 5] = [
+    ('あ', "あかさたなはまやらわ"),
+    ('い', "いきしちにひみり"),
+    ('う', "うくすつぬふむゆる"),
+    ('え', "えけせてねへめれ"),
+    ('お', "おこそとのほもよろを"),
+];
+
+/// Tie-break weights, following dictionary order: small kana, plain kana,
+/// voiced (dakuten), semi-voiced (handakuten).
+const WEIGHT_SMALL: u8 = 0;
+const WEIGHT_PLAIN: u8 = 1;
+const WEIGHT_VOICED: u8 = 2;
+const WEIGHT_SEMI_VOICED: u8 = 3;
+
+/// Splits a hiragana character into its plain kana and tie-break weight.
+fn kana_base(c: char) -> (char, u8) {
+    for ((variants, bases), weight) in [
+        (VOICED_KANA, WEIGHT_VOICED),
+        (SEMI_VOICED_KANA, WEIGHT_SEMI_VOICED),
+        (SMALL_KANA, WEIGHT_SMALL),
+    ] {
+        if let Some(index) = variants.chars().position(|v| v == c) {
+            return (bases.chars().nth(index).unwrap_or(c), weight);
+        }
+    }
+    (c, WEIGHT_PLAIN)
+}
+
+fn vowel_of(c: char) -> Option<char> {
+    VOWEL_COLUMNS
+        .iter()
+        .find(|(_, members)| members.contains(c))
+        .map(|(vowel, _)| *vowel)
+}
+
+/// Builds the key used to order a reading (kana, pinyin, or plain text).
+///
+/// Readings are grouped by the script of their first character, then
+/// compared case-insensitively with pinyin tone marks removed. Kana are
+/// compared as in a Japanese dictionary: katakana as hiragana, voiced and
+/// small kana as their plain kana, and `ー` as the preceding vowel. Voicing
+/// and size only break ties, through the last element of the key.
+pub fn reading_sort_key(reading: &str) -> (u8, String, Vec<u8>) {
+    let script = reading
+        .chars()
+        .next()
+        .map(script_of)
+        .unwrap_or(Script::Other);
+
+    let mut primary = String::new();
+    let mut weights = Vec::new();
+    for c in reading
+        .chars()
+        .flat_map(char::to_lowercase)
+        .map(strip_tone)
+        .map(to_hiragana)
+    {
+        let (base, weight) = match c {
+            'ー' => match primary.chars().last().and_then(vowel_of) {
+                Some(vowel) => (vowel, WEIGHT_PLAIN),
+                None => (c, WEIGHT_PLAIN),
+            },
+            _ => kana_base(c),
+        };
+        primary.push(base);
+        weights.push(weight);
+    }
+
+    (script as u8, primary, weights)
+}
+
+/// Converts full-width katakana to the matching hiragana.
+pub(super) fn to_hiragana(c: char) -> char {
+    match c {
+        '\u{30A1}'..='\u{30F6}' => char::from_u32(c as u32 - 0x60).unwrap_or(c),
+        _ => c,
+    }
+}
+
+/// Returns the list section a reading belongs to.
+///
+/// Japanese readings are grouped by gojūon row (あ, か, さ, …), while other
+/// locales use the initial Latin letter (A–Z), which also covers pinyin.
+/// Anything else goes into `OTHER_SECTION`.
+pub fn section_key(locale: Locale, reading: &str) -> String {
+    let Some(first) = reading.chars().next() else {
+        return OTHER_SECTION.to_string();
+    };
+
+    match locale {
+        Locale::Japanese => {
+            let kana = to_hiragana(first);
+            GOJUON_ROWS
+                .iter()
+                .find(|(_, members)| members.contains(kana))
+                .map(|(row, _)| row.to_string())
+                .unwrap_or_else(|| OTHER_SECTION.to_string())
+        }
+        Locale::English | Locale::ChineseSimplified => {
+            let letter = strip_tone(first.to_lowercase().next().unwrap_or(first));
+            if letter.is_ascii_alphabetic() {
+                letter.to_ascii_uppercase().to_string()
+            } else {
+                OTHER_SECTION.to_string()
+            }
+        }
+    }
+}
+
+/// Compares two readings using `reading_sort_key`.
+pub fn compare_readings(a: &str, b: &str) -> Ordering {
+    reading_sort_key(a).cmp(&reading_sort_key(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pinyin_tones_ignored() {
+        assert_eq!(compare_readings("yuánshí", "yuanshi"), Ordering::Equal);
+        assert_eq!(compare_readings("lǜ", "lv"), Ordering::Equal);
+        assert_eq!(compare_readings("Tiě", "tie"), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_kana_dictionary_order() {
+        let mut readings = vec!["かさ", "きんぞく", "ガラス", "がき", "はいく", "ぱーつ"];
+
+        // Code point order puts voiced kana and katakana after plain hiragana
+        let mut naive = readings.clone();
+        naive.sort();
+        assert_eq!(
+            naive,
+            vec!["かさ", "がき", "きんぞく", "はいく", "ぱーつ", "ガラス"]
+        );
+
+        readings.sort_by(|a, b| compare_readings(a, b));
+        assert_eq!(
+            readings,
+            vec!["がき", "かさ", "ガラス", "きんぞく", "ぱーつ", "はいく"]
+        );
+    }
+
+    #[test]
+    fn test_kana_marks_break_ties() {
+        let mut readings = vec!["ぱん", "はん", "ばん"];
+        readings.sort_by(|a, b| compare_readings(a, b));
+        assert_eq!(readings, vec!["はん", "ばん", "ぱん"]);
+
+        assert_eq!(compare_readings("しよう", "しょう"), Ordering::Greater);
+        assert_eq!(compare_readings("オリジン", "おりじん"), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_section_key_gojuon_rows() {
+        assert_eq!(section_key(Locale::Japanese, "あめじすと"), "あ");
+        assert_eq!(section_key(Locale::Japanese, "こうせき"), "か");
+        assert_eq!(section_key(Locale::Japanese, "ろ"), "ら");
+        assert_eq!(section_key(Locale::Japanese, "んー"), "わ");
+    }
+
+    #[test]
+    fn test_section_key_dakuten_and_handakuten() {
+        assert_eq!(section_key(Locale::Japanese, "げんせき"), "か");
+        assert_eq!(section_key(Locale::Japanese, "ぶどう"), "は");
+        assert_eq!(section_key(Locale::Japanese, "ぱーつ"), "は");
+        assert_eq!(section_key(Locale::Japanese, "ゔぁ"), "あ");
+    }
+
+    #[test]
+    fn test_section_key_katakana_and_small_kana() {
+        assert_eq!(section_key(Locale::Japanese, "オリジニウム"), "あ");
+        assert_eq!(section_key(Locale::Japanese, "ガラス"), "か");
+        assert_eq!(section_key(Locale::Japanese, "ポンプ"), "は");
+        assert_eq!(section_key(Locale::Japanese, "ょう"), "や");
+    }
+
+    #[test]
+    fn test_section_key_fallbacks() {
+        assert_eq!(section_key(Locale::Japanese, "源石"), OTHER_SECTION);
+        assert_eq!(section_key(Locale::Japanese, "origocrust"), OTHER_SECTION);
+        assert_eq!(section_key(Locale::Japanese, ""), OTHER_SECTION);
+        assert_eq!(section_key(Locale::English, "3d_part"), OTHER_SECTION);
+        assert_eq!(section_key(Locale::English, "げんせき"), OTHER_SECTION);
+    }
+
+    #[test]
+    fn test_section_key_latin() {
+        assert_eq!(section_key(Locale::English, "origocrust"), "O");
+        assert_eq!(section_key(Locale::English, "Amethyst Ore"), "A");
+        assert_eq!(section_key(Locale::ChineseSimplified, "zhōng"), "Z");
+        assert_eq!(section_key(Locale::ChineseSimplified, "Ōu"), "O");
+    }
+
+    #[test]
+    fn test_mixed_scripts_sort_without_panic() {
+        let mut readings = vec!["源石", "", "げんせき", "zhōng", "Amethyst", "★", "ámber"];
+        readings.sort_by(|a, b| compare_readings(a, b));
+
+        assert_eq!(
+            readings,
+            vec!["ámber", "Amethyst", "zhōng", "げんせき", "源石", "", "★"]
+        );
+    }
+}
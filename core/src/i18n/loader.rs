@@ -1,7 +1,14 @@
 //! Locale loading and text retrieval.
 
+#[cfg(feature = "data-files")]
 use serde::Deserialize;
+use std::cmp::Ordering;
 use std::collections::HashMap;
+use std::fmt;
+
+use super::collation::compare_readings;
+use crate::config::GameData;
+use crate::models::ItemId;
 
 /// Supported locales.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
@@ -9,14 +16,28 @@ pub enum Locale {
     #[default]
     English,
     Japanese,
+    ChineseSimplified,
 }
 
 impl Locale {
+    /// Every supported locale, in the order a language selector lists them.
+    pub const ALL: [Locale; 3] = [Locale::English, Locale::Japanese, Locale::ChineseSimplified];
+
     /// Returns the locale code string.
     pub fn code(&self) -> &'static str {
         match self {
             Locale::English => "en",
             Locale::Japanese => "ja",
+            Locale::ChineseSimplified => "zh",
+        }
+    }
+
+    /// Returns the name of the language in itself, e.g. "日本語".
+    pub fn native_name(&self) -> &'static str {
+        match self {
+            Locale::English => "English",
+            Locale::Japanese => "日本語",
+            Locale::ChineseSimplified => "简体中文",
         }
     }
 
@@ -25,12 +46,46 @@ impl Locale {
         match code.to_lowercase().as_str() {
             "en" | "english" => Some(Locale::English),
             "ja" | "jp" | "japanese" => Some(Locale::Japanese),
+            "zh" | "zh-cn" | "zh_cn" | "zh-hans" | "chinese" => Some(Locale::ChineseSimplified),
             _ => None,
         }
     }
 }
 
+/// Picks the locale for a list of preferred language tags, most preferred
+/// first, e.g. the browser's `navigator.languages`.
+///
+/// A tag matches a locale by its code (`"ja"`, `"ja-JP"`) or by any name
+/// `Locale::from_code` accepts (`"zh-Hans"`). Chinese only matches the
+/// Simplified tags `zh`, `zh-CN`, `zh-SG` and `zh-Hans`, so `zh-TW` moves on
+/// to the next tag. The first tag matching one of `available` wins; without
+/// a match the default locale is used.
+pub fn pick_locale(preferred: &[String], available: &[Locale]) -> Locale {
+    preferred
+        .iter()
+        .find_map(|tag| {
+            let tag = tag.trim().to_lowercase();
+            let language = tag.split(['-', '_']).next().unwrap_or_default();
+            available.iter().copied().find(|locale| match locale {
+                Locale::ChineseSimplified => matches!(
+                    tag.replace('_', "-").as_str(),
+                    "zh" | "zh-cn" | "zh-sg" | "zh-hans"
+                ),
+                _ => locale.code() == language || Locale::from_code(&tag) == Some(*locale),
+            })
+        })
+        .unwrap_or_default()
+}
+
+/// Formats as the native name, see `Locale::native_name`.
+impl fmt::Display for Locale {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.native_name())
+    }
+}
+
 /// Raw structure for parsing locale TOML files.
+#[cfg(feature = "data-files")]
 #[derive(Debug, Deserialize)]
 struct LocaleData {
     #[serde(default)]
@@ -38,9 +93,23 @@ struct LocaleData {
     #[serde(default)]
     machines: HashMap<String, String>,
     #[serde(default)]
-    ui: HashMap<String, String>,
+    tiers: HashMap<String, String>,
+    #[serde(default)]
+    ui: HashMap<String, UiEntry>,
     #[serde(default)]
     readings: HashMap<String, String>,
+    #[serde(default)]
+    descriptions: HashMap<String, String>,
+}
+
+/// A UI string, or a `[ui.<key>]` table of plural forms such as `one` and
+/// `other`, stored as `<key>.one` and `<key>.other`.
+#[cfg(feature = "data-files")]
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum UiEntry {
+    Text(String),
+    Forms(HashMap<String, String>),
 }
 
 /// Provides localized text retrieval.
@@ -48,8 +117,10 @@ struct LocaleData {
 pub struct Localizer {
     items: HashMap<String, String>,
     machines: HashMap<String, String>,
+    tiers: HashMap<String, String>,
     ui: HashMap<String, String>,
     readings: HashMap<String, String>,
+    descriptions: HashMap<String, String>,
 }
 
 impl Localizer {
@@ -60,16 +131,50 @@ impl Localizer {
     ///
     /// # Returns
     /// A Result containing the Localizer or an error message.
+    #[cfg(feature = "data-files")]
     pub fn new(toml_content: &str) -> Result<Self, String> {
-        let data: LocaleData = toml::from_str(toml_content)
-            .map_err(|e| format!("Failed to parse locale file: {}", e))?;
-
-        Ok(Localizer {
-            items: data.items,
-            machines: data.machines,
-            ui: data.ui,
-            readings: data.readings,
-        })
+        Self::from_parts(&[toml_content])
+    }
+
+    /// Creates a new Localizer by merging several TOML fragments.
+    ///
+    /// Fragments are applied in order, so keys in later fragments override
+    /// the same keys from earlier ones. A key repeated within a single
+    /// fragment is rejected by the TOML parser and reported as an error.
+    ///
+    /// # Arguments
+    /// * `parts` - TOML fragments such as `items.toml`, `machines.toml`, `ui.toml`
+    ///
+    /// # Returns
+    /// A Result containing the merged Localizer or an error message.
+    #[cfg(feature = "data-files")]
+    pub fn from_parts(parts: &[&str]) -> Result<Self, String> {
+        let mut localizer = Localizer::empty();
+
+        for (index, part) in parts.iter().enumerate() {
+            let data: LocaleData = toml::from_str(part)
+                .map_err(|e| format!("Failed to parse locale file (part {}): {}", index + 1, e))?;
+
+            localizer.items.extend(data.items);
+            localizer.machines.extend(data.machines);
+            localizer.tiers.extend(data.tiers);
+            for (key, entry) in data.ui {
+                match entry {
+                    UiEntry::Text(text) => {
+                        localizer.ui.insert(key, text);
+                    }
+                    UiEntry::Forms(forms) => {
+                        for (form, text) in forms {
+                            localizer.ui.insert(format!("{}.{}", key, form), text);
+                        }
+                    }
+                }
+            }
+            localizer.readings.extend(data.readings);
+            localizer.descriptions.extend(data.descriptions);
+        }
+
+        Ok(localizer)
     }
 
     /// Creates an empty Localizer (fallback only).
@@ -77,8 +182,10 @@ impl Localizer {
         Localizer {
             items: HashMap::new(),
             machines: HashMap::new(),
+            tiers: HashMap::new(),
             ui: HashMap::new(),
             readings: HashMap::new(),
+            descriptions: HashMap::new(),
         }
     }
 
@@ -98,7 +205,19 @@ impl Localizer {
         self.readings
             .get(item_id)
             .cloned()
-            .unwrap_or_else(|| item_id.to_string())
+            .unwrap_or_else(|| self.get_item(item_id))
+    }
+
+    /// Compares two items by their readings.
+    /// Handles kana, pinyin, and mixed-script readings consistently.
+    pub fn compare_by_reading(&self, item_a: &str, item_b: &str) -> Ordering {
+        compare_readings(&self.get_reading(item_a), &self.get_reading(item_b))
+    }
+
+    /// Gets the one-line description of an item, if the locale has one.
+    /// Unlike names, there is no fallback to the item ID.
+    pub fn get_description(&self, item_id: &str) -> Option<String> {
+        self.descriptions.get(item_id).cloned()
     }
 
     /// Gets the localized name for a machine.
@@ -110,9 +229,386 @@ impl Localizer {
             .unwrap_or_else(|| machine_id.to_string())
     }
 
+    /// Gets the localized name of a machine tier from the `[tiers]` table.
+    /// Falls back to the name in the game data, then to "Tier N".
+    pub fn get_tier(&self, tier: u32, game_data: &GameData) -> String {
+        self.tiers
+            .get(&tier.to_string())
+            .cloned()
+            .unwrap_or_else(|| game_data.tier_label(tier))
+    }
+
+    /// Gets the localized name for an ID that may be a machine or an item.
+    /// Machine IDs in the game data use the machine name, everything else
+    /// the item name.
+    pub fn get_name(&self, id: &str, game_data: &GameData) -> String {
+        self.get_name_of_kind(id, game_data.machines.contains_key(id))
+    }
+
+    /// Gets the machine name for `id` if it names a machine, else the item
+    /// name. Shared with `LocalizedResolver`.
+    pub(crate) fn get_name_of_kind(&self, id: &str, is_machine: bool) -> String {
+        if is_machine {
+            self.get_machine(id)
+        } else {
+            self.get_item(id)
+        }
+    }
+
+    /// Localizes the keys of an amount map such as
+    /// `ProductionNode::total_source_materials`, yielding
+    /// `(localized_name, amount)` pairs sorted by reading. The amounts may
+    /// carry extra details, e.g. `(amount, deposits)`.
+    pub fn localize_amounts<T>(
+        &self,
+        amounts: HashMap<ItemId, T>,
+        game_data: &GameData,
+    ) -> impl Iterator<Item = (String, T)> {
+        let mut amounts: Vec<(ItemId, T)> = amounts.into_iter().collect();
+        amounts.sort_by(|(a, _), (b, _)| {
+            self.compare_by_reading(a.as_str(), b.as_str())
+                .then_with(|| a.cmp(b))
+        });
+
+        amounts
+            .into_iter()
+            .map(move |(id, amount)| (self.get_name(id.as_str(), game_data), amount))
+    }
+
+    /// Returns the decimal separator for numbers, `.` unless the locale
+    /// sets `decimal_separator`.
+    pub fn decimal_separator(&self) -> &str {
+        self.ui
+            .get("decimal_separator")
+            .map(String::as_str)
+            .unwrap_or(".")
+    }
+
     /// Gets a localized UI string.
     /// Falls back to the key if no translation exists.
     pub fn get_ui(&self, key: &str) -> String {
         self.ui.get(key).cloned().unwrap_or_else(|| key.to_string())
     }
+
+    /// Gets the template of a pluralized UI string for `count`: the `one`
+    /// form of the `[ui.<key>]` table for a count of one, the `other` form
+    /// otherwise. Falls back to the `other` form, then to `get_ui(key)`.
+    pub fn get_ui_plural_template(&self, key: &str, count: u64) -> String {
+        let form = if count == 1 { "one" } else { "other" };
+        [form, "other"]
+            .iter()
+            .find_map(|form| self.ui.get(&format!("{}.{}", key, form)))
+            .cloned()
+            .unwrap_or_else(|| self.get_ui(key))
+    }
+
+    /// Gets a pluralized UI string for `count` with `{count}` filled in,
+    /// e.g. "1 machine" or "3 machines". See `get_ui_plural_template`.
+    pub fn get_ui_plural(&self, key: &str, count: u64) -> String {
+        self.get_ui_plural_template(key, count)
+            .replace("{count}", &count.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "data-files")]
+    use crate::test_fixtures::REFINING_UNIT_TOML;
+
+    #[test]
+    fn test_native_names_and_codes_in_sync() {
+        assert_eq!(Locale::English.native_name(), "English");
+        assert_eq!(Locale::Japanese.native_name(), "日本語");
+        assert_eq!(Locale::ChineseSimplified.native_name(), "简体中文");
+        assert_eq!(Locale::Japanese.to_string(), "日本語");
+
+        for locale in Locale::ALL {
+            assert_eq!(Locale::from_code(locale.code()), Some(locale));
+        }
+    }
+
+    #[test]
+    fn test_pick_locale_first_available_match() {
+        let tags = |tags: &[&str]| -> Vec<String> { tags.iter().map(|t| t.to_string()).collect() };
+
+        assert_eq!(
+            pick_locale(&tags(&["ja-JP", "en-US"]), &Locale::ALL),
+            Locale::Japanese
+        );
+        // An unsupported first choice falls through to the next
+        assert_eq!(
+            pick_locale(&tags(&["fr-FR", "ZH-hans", "ja"]), &Locale::ALL),
+            Locale::ChineseSimplified
+        );
+        assert_eq!(
+            pick_locale(&tags(&["en-US", "ja"]), &Locale::ALL),
+            Locale::English
+        );
+        // Only available locales are picked
+        assert_eq!(
+            pick_locale(
+                &tags(&["ja", "zh"]),
+                &[Locale::English, Locale::ChineseSimplified]
+            ),
+            Locale::ChineseSimplified
+        );
+        // Traditional Chinese tags don't pick Simplified Chinese
+        assert_eq!(
+            pick_locale(&tags(&["zh-TW", "en"]), &Locale::ALL),
+            Locale::English
+        );
+        assert_eq!(
+            pick_locale(&tags(&["zh-HK", "zh-Hant", "ja"]), &Locale::ALL),
+            Locale::Japanese
+        );
+        assert_eq!(
+            pick_locale(&tags(&["zh-SG"]), &Locale::ALL),
+            Locale::ChineseSimplified
+        );
+        assert_eq!(
+            pick_locale(&tags(&["fr", "de"]), &Locale::ALL),
+            Locale::English
+        );
+        assert_eq!(pick_locale(&[], &Locale::ALL), Locale::English);
+    }
+
+    #[test]
+    fn test_locale_from_code_chinese() {
+        assert_eq!(Locale::from_code("zh"), Some(Locale::ChineseSimplified));
+        assert_eq!(Locale::from_code("zh-CN"), Some(Locale::ChineseSimplified));
+        assert_eq!(Locale::ChineseSimplified.code(), "zh");
+    }
+
+    #[test]
+    #[cfg(feature = "data-files")]
+    fn test_compare_by_reading_mixed_list() {
+        let readings = r#"
+[readings]
+origocrust = "yuánshí jīngtǐ"
+amethyst_ore = "zǐjīng kuàng"
+ferrium_ore = "げんせき"
+"#;
+
+        let localizer = Localizer::new(readings).unwrap();
+
+        let mut items = vec!["ferrium_ore", "amethyst_ore", "origocrust", "carbon"];
+        items.sort_by(|a, b| localizer.compare_by_reading(a, b));
+
+        // "carbon" has no reading and falls back to its ID
+        assert_eq!(
+            items,
+            vec!["carbon", "origocrust", "amethyst_ore", "ferrium_ore"]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "data-files")]
+    fn test_get_name_distinguishes_machines_and_items() {
+        let locale = r#"
+[items]
+refining_unit = "Refined Unit Item"
+origocrust = "Origocrust"
+
+[machines]
+refining_unit = "Refining Unit"
+"#;
+        let recipes = r#"
+[[recipes]]
+id = "origocrust"
+by = "refining_unit"
+time = 2
+out = 1
+"#;
+
+        let localizer = Localizer::new(locale).unwrap();
+        let data = GameData::new(recipes, REFINING_UNIT_TOML).unwrap();
+
+        assert_eq!(localizer.get_name("refining_unit", &data), "Refining Unit");
+        assert_eq!(localizer.get_name("origocrust", &data), "Origocrust");
+    }
+
+    #[test]
+    #[cfg(feature = "data-files")]
+    fn test_get_tier_falls_back_to_data_then_number() {
+        let localizer = Localizer::new(
+            r#"
+[tiers]
+1 = "携帯型"
+"#,
+        )
+        .unwrap();
+        let data = GameData::builder()
+            .add_tier_name(1, "Portable")
+            .add_tier_name(2, "Standard")
+            .build()
+            .unwrap();
+
+        assert_eq!(localizer.get_tier(1, &data), "携帯型");
+        assert_eq!(localizer.get_tier(2, &data), "Standard");
+        assert_eq!(localizer.get_tier(3, &data), "Tier 3");
+    }
+
+    #[test]
+    #[cfg(feature = "data-files")]
+    fn test_localize_amounts_sorted_by_reading() {
+        let locale = r#"
+[items]
+originium_ore = "源石鉱石"
+amethyst_ore = "紫晶鉱石"
+ferrium_ore = "鉄鉱石"
+
+[readings]
+originium_ore = "げんせきこうせき"
+amethyst_ore = "しょうこうせき"
+ferrium_ore = "てっこうせき"
+"#;
+        let recipes = r#"
+[[recipes]]
+id = "origocrust"
+by = "refining_unit"
+time = 2
+out = 1
+"#;
+
+        let localizer = Localizer::new(locale).unwrap();
+        let data = GameData::new(recipes, REFINING_UNIT_TOML).unwrap();
+        let amounts: HashMap<ItemId, u32> = [
+            ("ferrium_ore".into(), 3),
+            ("originium_ore".into(), 1),
+            ("amethyst_ore".into(), 2),
+        ]
+        .into_iter()
+        .collect();
+
+        let localized: Vec<(String, u32)> = localizer.localize_amounts(amounts, &data).collect();
+
+        assert_eq!(
+            localized,
+            vec![
+                ("源石鉱石".to_string(), 1),
+                ("紫晶鉱石".to_string(), 2),
+                ("鉄鉱石".to_string(), 3),
+            ]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "data-files")]
+    fn test_from_parts_merges_namespaces() {
+        let items = r#"
+[items]
+originium_ore = "Originium Ore"
+"#;
+        let ui = r#"
+[ui]
+settings = "Settings"
+"#;
+
+        let localizer = Localizer::from_parts(&[items, ui]).unwrap();
+
+        assert_eq!(localizer.get_item("originium_ore"), "Originium Ore");
+        assert_eq!(localizer.get_ui("settings"), "Settings");
+    }
+
+    #[test]
+    #[cfg(feature = "data-files")]
+    fn test_from_parts_later_overrides_earlier() {
+        let base = r#"
+[items]
+originium_ore = "Originium Ore"
+origocrust = "Origocrust"
+"#;
+        let patch = r#"
+[items]
+originium_ore = "Raw Originium"
+"#;
+
+        let localizer = Localizer::from_parts(&[base, patch]).unwrap();
+
+        assert_eq!(localizer.get_item("originium_ore"), "Raw Originium");
+        assert_eq!(localizer.get_item("origocrust"), "Origocrust");
+    }
+
+    #[test]
+    #[cfg(feature = "data-files")]
+    fn test_descriptions_merge_and_fall_back() {
+        let english = r#"
+[items]
+origocrust = "Origocrust"
+
+[descriptions]
+origocrust = "Refined from Originium Ore."
+originium_ore = "Mined from Originium deposits."
+"#;
+        let patch = r#"
+[descriptions]
+origocrust = "由源石矿物精炼而成。"
+"#;
+
+        let localizer = Localizer::from_parts(&[english, patch]).unwrap();
+
+        assert_eq!(
+            localizer.get_description("origocrust").as_deref(),
+            Some("由源石矿物精炼而成。")
+        );
+        assert_eq!(
+            localizer.get_description("originium_ore").as_deref(),
+            Some("Mined from Originium deposits.")
+        );
+        assert_eq!(localizer.get_description("amethyst_ore"), None);
+    }
+
+    #[test]
+    #[cfg(feature = "data-files")]
+    fn test_from_parts_reports_duplicate_keys() {
+        let valid = r#"
+[items]
+origocrust = "Origocrust"
+"#;
+        let duplicated = r#"
+[items]
+originium_ore = "Originium Ore"
+originium_ore = "Raw Originium"
+"#;
+
+        let result = Localizer::from_parts(&[valid, duplicated]);
+
+        let message = result.unwrap_err();
+        assert!(message.contains("part 2"));
+    }
+
+    #[test]
+    #[cfg(feature = "data-files")]
+    fn test_get_ui_plural_selects_form_by_count() {
+        let english = Localizer::new(include_str!("../../../res/locales/en/ui.toml")).unwrap();
+        let japanese = Localizer::new(include_str!("../../../res/locales/ja/ui.toml")).unwrap();
+
+        assert_eq!(english.get_ui_plural("machine_count", 1), "1 machine");
+        assert_eq!(english.get_ui_plural("machine_count", 3), "3 machines");
+        assert_eq!(english.get_ui_plural("machine_count", 0), "0 machines");
+        assert_eq!(japanese.get_ui_plural("machine_count", 1), "1 台");
+        assert_eq!(japanese.get_ui_plural("machine_count", 3), "3 台");
+        assert_eq!(english.get_ui_plural("power_amount", 1), "1 unit");
+        assert_eq!(japanese.get_ui_plural("rate_per_min", 30), "30/分");
+    }
+
+    #[test]
+    #[cfg(feature = "data-files")]
+    fn test_get_ui_plural_falls_back() {
+        let localizer = Localizer::new(
+            r#"
+[ui]
+per_min = "/min"
+
+[ui.machine_count]
+other = "{count} machines"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(localizer.get_ui_plural("machine_count", 1), "1 machines");
+        assert_eq!(localizer.get_ui_plural_template("per_min", 2), "/min");
+        assert_eq!(localizer.get_ui("machine_count.other"), "{count} machines");
+    }
 }
@@ -1,5 +1,11 @@
 //! Internationalization (i18n) module for Endfield Production Planner.
 
+mod collation;
 mod loader;
+mod names;
+mod search;
 
-pub use loader::{Locale, Localizer};
+pub use collation::{OTHER_SECTION, compare_readings, reading_sort_key, section_key};
+pub use loader::{Locale, Localizer, pick_locale};
+pub use names::{IdResolver, LocalizedResolver, NameResolver};
+pub use search::{SearchIndex, normalize_for_search};
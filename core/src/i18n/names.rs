@@ -0,0 +1,86 @@
+//! Display names for the IDs in exported plans.
+
+use super::Localizer;
+use crate::config::GameData;
+use crate::models::MachineId;
+use std::collections::HashSet;
+use std::fmt;
+
+/// Turns an item or machine ID into the name shown to the user. Every
+/// exporter takes one, so all output formats name things the same way.
+pub trait NameResolver {
+    fn name_of(&self, id: &str) -> String;
+}
+
+impl fmt::Debug for dyn NameResolver + '_ {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("NameResolver")
+    }
+}
+
+/// Shows IDs as they are.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct IdResolver;
+
+impl NameResolver for IdResolver {
+    fn name_of(&self, id: &str) -> String {
+        id.to_string()
+    }
+}
+
+/// Shows localized names. IDs of machines use the machine name and every
+/// other ID the item name, like `Localizer::get_name`.
+#[derive(Debug, Clone)]
+pub struct LocalizedResolver<'a> {
+    pub localizer: &'a Localizer,
+    pub machine_ids: HashSet<MachineId>,
+}
+
+impl<'a> LocalizedResolver<'a> {
+    /// Names with `localizer`, taking the machines from `game_data`.
+    pub fn new(localizer: &'a Localizer, game_data: &GameData) -> Self {
+        LocalizedResolver {
+            localizer,
+            machine_ids: game_data.machines.keys().cloned().collect(),
+        }
+    }
+}
+
+impl NameResolver for LocalizedResolver<'_> {
+    fn name_of(&self, id: &str) -> String {
+        self.localizer
+            .get_name_of_kind(id, self.machine_ids.contains(id))
+    }
+}
+
+#[cfg(all(test, feature = "data-files"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_localized_resolver_tells_machines_from_items() {
+        let localizer = Localizer::new(
+            r#"
+[items]
+origocrust = "Origocrust"
+refining_unit = "Refining Unit (item)"
+
+[machines]
+refining_unit = "Refining Unit"
+"#,
+        )
+        .unwrap();
+        let data = GameData::new(
+            "recipes = []",
+            "[[machines]]\nid = \"refining_unit\"\ntier = 1\npower = 5\n",
+        )
+        .unwrap();
+
+        let names = LocalizedResolver::new(&localizer, &data);
+
+        assert_eq!(names.name_of("origocrust"), "Origocrust");
+        assert_eq!(names.name_of("refining_unit"), "Refining Unit");
+        assert_eq!(names.name_of("unknown"), "unknown");
+        assert_eq!(IdResolver.name_of("origocrust"), "origocrust");
+    }
+}
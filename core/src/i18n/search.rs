@@ -0,0 +1,111 @@
+//! Item search that tolerates IME input variants.
+
+use unicode_normalization::UnicodeNormalization;
+
+use super::collation::to_hiragana;
+use super::loader::Localizer;
+
+/// Folds text for search comparisons: NFKC normalization (full-width
+/// letters and digits to half-width, half-width katakana to full-width),
+/// katakana to hiragana, then lowercase.
+pub fn normalize_for_search(text: &str) -> String {
+    text.trim()
+        .nfkc()
+        .map(to_hiragana)
+        .collect::<String>()
+        .to_lowercase()
+}
+
+/// An item with its normalized ID, name and reading.
+#[derive(Debug, Clone)]
+struct SearchEntry {
+    item_id: String,
+    keys: Vec<String>,
+}
+
+/// Items of one locale with their search keys normalized once, so each
+/// query only normalizes the query itself.
+#[derive(Debug, Clone, Default)]
+pub struct SearchIndex {
+    entries: Vec<SearchEntry>,
+}
+
+impl SearchIndex {
+    /// Indexes the ID, localized name and reading of each item.
+    pub fn new<'a>(item_ids: impl IntoIterator<Item = &'a str>, localizer: &Localizer) -> Self {
+        let entries = item_ids
+            .into_iter()
+            .map(|item_id| {
+                let mut keys = vec![
+                    normalize_for_search(item_id),
+                    normalize_for_search(&localizer.get_item(item_id)),
+                    normalize_for_search(&localizer.get_reading(item_id)),
+                ];
+                keys.dedup();
+                SearchEntry {
+                    item_id: item_id.to_string(),
+                    keys,
+                }
+            })
+            .collect();
+
+        SearchIndex { entries }
+    }
+
+    /// Lists the items with a key containing `query`, in index order. An
+    /// empty query matches every item.
+    pub fn search(&self, query: &str) -> Vec<&str> {
+        let query = normalize_for_search(query);
+        self.entries
+            .iter()
+            .filter(|entry| entry.keys.iter().any(|key| key.contains(&query)))
+            .map(|entry| entry.item_id.as_str())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_full_width_and_case() {
+        assert_eq!(normalize_for_search("１２３ＡＢＣ"), "123abc");
+        assert_eq!(normalize_for_search("  Origocrust "), "origocrust");
+    }
+
+    #[test]
+    fn test_normalize_kana_variants() {
+        assert_eq!(normalize_for_search("ゲンセキ"), "げんせき");
+        assert_eq!(normalize_for_search("ｹﾞﾝｾｷ"), "げんせき");
+        assert_eq!(normalize_for_search("げんせき"), "げんせき");
+    }
+
+    #[test]
+    #[cfg(feature = "data-files")]
+    fn test_search_matches_normalized_queries() {
+        let localizer = Localizer::new(
+            r#"
+[items]
+originium_ore = "源石鉱石"
+lc_wuling_battery = "武陵バッテリーLC"
+
+[readings]
+originium_ore = "げんせきこうせき"
+lc_wuling_battery = "ぶりょうばってりーLC"
+"#,
+        )
+        .unwrap();
+        let index = SearchIndex::new(["originium_ore", "lc_wuling_battery"], &localizer);
+
+        // Full-width query against a half-width name
+        assert_eq!(index.search("ｌｃ"), vec!["lc_wuling_battery"]);
+        // Katakana and half-width katakana against a hiragana reading
+        assert_eq!(index.search("ゲンセキ"), vec!["originium_ore"]);
+        assert_eq!(index.search("ｹﾞﾝｾｷ"), vec!["originium_ore"]);
+        // Hiragana against a katakana name
+        assert_eq!(index.search("ばってりー"), vec!["lc_wuling_battery"]);
+        assert_eq!(index.search("").len(), 2);
+        assert!(index.search("ferrium").is_empty());
+    }
+}
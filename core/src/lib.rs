@@ -1,10 +1,13 @@
 pub mod config;
 pub mod constants;
 pub mod error;
+mod hash;
 pub mod i18n;
 pub mod models;
 pub mod output;
 pub mod planner;
+#[cfg(test)]
+mod test_fixtures;
 
 pub use config::GameData;
 pub use error::ProductionError;
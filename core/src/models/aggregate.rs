@@ -0,0 +1,307 @@
+//! A production tree merged into one row per item, e.g. for table views.
+
+use super::ids::{ItemId, MachineId, RecipeUid};
+use super::production::{ProductionNode, ROUNDING_EPSILON};
+use std::collections::BTreeMap;
+
+/// How much of an item one consumer takes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "data-files", derive(serde::Serialize))]
+pub struct Consumer {
+    pub item_id: ItemId,
+    pub amount: u32,
+}
+
+/// Every occurrence of an item in the plan, merged into one production line.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "data-files", derive(serde::Serialize))]
+pub struct AggregatedRow {
+    pub item_id: ItemId,
+    /// Total amount over every place the item appears.
+    pub amount: u32,
+    /// Recipe of the item, `None` when it is not made in the plan (missing
+    /// recipe or supplied externally). The planner picks one recipe per
+    /// item, so this is the recipe of its first occurrence.
+    pub recipe_id: Option<RecipeUid>,
+    pub machine_id: Option<MachineId>,
+    /// Machines making the whole amount as one line, rounded up once.
+    pub machine_count: u32,
+    /// Machines needed at full load, before rounding.
+    pub required_machines: f64,
+    /// Average load of the merged machines.
+    pub load: f64,
+    /// Power drawn with every merged machine crafting.
+    pub power_usage: u32,
+    pub is_source: bool,
+    /// Items consuming this one and how much each takes, by item ID. Empty
+    /// for the target.
+    pub consumers: Vec<Consumer>,
+}
+
+/// A plan aggregated by item, see `ProductionNode::aggregate`.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "data-files", derive(serde::Serialize))]
+pub struct AggregatedPlan {
+    /// One row per distinct item, sorted by item ID.
+    pub rows: Vec<AggregatedRow>,
+}
+
+impl AggregatedPlan {
+    pub fn row(&self, item_id: &str) -> Option<&AggregatedRow> {
+        self.rows.iter().find(|row| row.item_id == item_id)
+    }
+}
+
+#[derive(Default)]
+struct RowTotals {
+    amount: u32,
+    recipe: Option<(RecipeUid, MachineId)>,
+    required_machines: f64,
+    power_per_machine: u32,
+    is_source: bool,
+    consumers: BTreeMap<ItemId, u32>,
+}
+
+impl ProductionNode {
+    /// Merges every occurrence of each item into one row, with the machines
+    /// the merged line needs and the items consuming it.
+    ///
+    /// Cycle breaks and shared items are left out, like in the flow
+    /// diagram, since the item is produced by another node of the plan.
+    pub fn aggregate(&self) -> AggregatedPlan {
+        let mut totals = BTreeMap::new();
+        self.collect_rows(None, &mut totals);
+
+        let rows = totals
+            .into_iter()
+            .map(|(item_id, totals)| {
+                let machine_count = (totals.required_machines - ROUNDING_EPSILON)
+                    .ceil()
+                    .max(0.0) as u32;
+                let load = if machine_count > 0 {
+                    totals.required_machines / machine_count as f64
+                } else {
+                    0.0
+                };
+                let (recipe_id, machine_id) = totals.recipe.unzip();
+                AggregatedRow {
+                    item_id,
+                    amount: totals.amount,
+                    recipe_id,
+                    machine_id,
+                    machine_count,
+                    required_machines: totals.required_machines,
+                    load,
+                    power_usage: totals.power_per_machine.saturating_mul(machine_count),
+                    is_source: totals.is_source,
+                    consumers: totals
+                        .consumers
+                        .into_iter()
+                        .map(|(item_id, amount)| Consumer { item_id, amount })
+                        .collect(),
+                }
+            })
+            .collect();
+
+        AggregatedPlan { rows }
+    }
+
+    fn collect_rows(&self, consumer: Option<&ItemId>, totals: &mut BTreeMap<ItemId, RowTotals>) {
+        if let ProductionNode::Cycle { .. } | ProductionNode::Shared { .. } = self {
+            return;
+        }
+
+        let row = totals.entry(self.item_id().clone()).or_default();
+        row.amount += self.amount();
+        if let Some(consumer) = consumer {
+            *row.consumers.entry(consumer.clone()).or_insert(0) += self.amount();
+        }
+
+        if let ProductionNode::Resolved {
+            item_id,
+            machine_id,
+            recipe_id,
+            machine_count,
+            power_usage,
+            load,
+            inputs,
+            is_source,
+            ..
+        } = self
+        {
+            if !machine_id.as_str().is_empty() {
+                row.required_machines += *machine_count as f64 * load;
+                if row.recipe.is_none() {
+                    row.recipe = Some((recipe_id.clone(), machine_id.clone()));
+                    row.power_per_machine = power_usage.checked_div(*machine_count).unwrap_or(0);
+                    row.is_source = *is_source;
+                }
+            }
+
+            for child in inputs {
+                child.collect_rows(Some(item_id), totals);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::{self, on_machines};
+
+    fn resolved(
+        item_id: &str,
+        machine_id: &str,
+        amount: u32,
+        machine_count: u32,
+        load: f64,
+        inputs: Vec<ProductionNode>,
+    ) -> ProductionNode {
+        let mut node = on_machines(
+            test_fixtures::resolved(item_id, amount, inputs),
+            machine_id,
+            machine_count,
+        );
+        if let ProductionNode::Resolved {
+            load: node_load,
+            is_source,
+            ..
+        } = &mut node
+        {
+            *node_load = load;
+            *is_source = machine_id == "electric_mining_rig";
+        }
+        node
+    }
+
+    /// amethyst_component needs origocrust and amethyst_fiber, which both
+    /// need originium_ore.
+    fn diamond() -> ProductionNode {
+        let ore = |amount, load| {
+            resolved(
+                "originium_ore",
+                "electric_mining_rig",
+                amount,
+                1,
+                load,
+                vec![],
+            )
+        };
+        resolved(
+            "amethyst_component",
+            "gearing_unit",
+            10,
+            1,
+            1.0,
+            vec![
+                resolved(
+                    "origocrust",
+                    "refining_unit",
+                    15,
+                    1,
+                    0.5,
+                    vec![ore(15, 0.25)],
+                ),
+                resolved(
+                    "amethyst_fiber",
+                    "refining_unit",
+                    30,
+                    1,
+                    1.0,
+                    vec![
+                        ore(30, 0.5),
+                        ProductionNode::Cycle {
+                            item_id: "amethyst_component".into(),
+                            amount: 1,
+                        },
+                    ],
+                ),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_shared_input_is_merged_into_one_row() {
+        let plan = diamond().aggregate();
+
+        let items: Vec<&str> = plan.rows.iter().map(|row| row.item_id.as_str()).collect();
+        assert_eq!(
+            items,
+            vec![
+                "amethyst_component",
+                "amethyst_fiber",
+                "originium_ore",
+                "origocrust"
+            ]
+        );
+
+        let ore = plan.row("originium_ore").unwrap();
+        assert_eq!(ore.amount, 45);
+        assert_eq!(ore.machine_id, Some("electric_mining_rig".into()));
+        assert_eq!(
+            ore.consumers,
+            vec![
+                Consumer {
+                    item_id: "amethyst_fiber".into(),
+                    amount: 30,
+                },
+                Consumer {
+                    item_id: "origocrust".into(),
+                    amount: 15,
+                },
+            ]
+        );
+        assert!(ore.is_source);
+    }
+
+    #[test]
+    fn test_machines_are_rounded_after_merging() {
+        let plan = diamond().aggregate();
+
+        // Two rigs at 25% and 50% load fit into one at 75%
+        let ore = plan.row("originium_ore").unwrap();
+        assert!((ore.required_machines - 0.75).abs() < 1e-9);
+        assert_eq!(ore.machine_count, 1);
+        assert!((ore.load - 0.75).abs() < 1e-9);
+        assert_eq!(ore.power_usage, 5);
+
+        let crust = plan.row("origocrust").unwrap();
+        assert_eq!(crust.machine_count, 1);
+        assert_eq!(crust.consumers.len(), 1);
+        assert_eq!(crust.recipe_id, Some("origocrust@refining_unit[]".into()));
+    }
+
+    #[test]
+    fn test_target_has_no_consumers_and_cycles_are_skipped() {
+        let plan = diamond().aggregate();
+
+        let target = plan.row("amethyst_component").unwrap();
+        assert_eq!(target.amount, 10);
+        assert!(target.consumers.is_empty());
+        assert_eq!(plan.rows.len(), 4);
+    }
+
+    #[test]
+    fn test_unmade_items_have_no_machines() {
+        let plan = resolved(
+            "origocrust",
+            "refining_unit",
+            30,
+            1,
+            1.0,
+            vec![ProductionNode::External {
+                item_id: "originium_ore".into(),
+                amount: 30,
+            }],
+        )
+        .aggregate();
+
+        let ore = plan.row("originium_ore").unwrap();
+        assert_eq!(ore.machine_id, None);
+        assert_eq!(ore.recipe_id, None);
+        assert_eq!(ore.machine_count, 0);
+        assert_eq!(ore.load, 0.0);
+        assert_eq!(ore.consumers[0].amount, 30);
+    }
+}
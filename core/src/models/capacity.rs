@@ -0,0 +1,366 @@
+//! What-if edits of machine counts in a finished production tree.
+
+use super::ids::ItemId;
+use super::production::ProductionNode;
+use crate::planner::split_into_blocks;
+
+/// Location of a node in a production tree, as child indices from the root.
+///
+/// The empty path is the root itself.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "data-files", derive(serde::Serialize))]
+pub struct NodePath(pub Vec<usize>);
+
+impl NodePath {
+    pub fn root() -> Self {
+        NodePath(Vec::new())
+    }
+
+    /// Returns the path of this node's `index`-th input.
+    pub fn child(&self, index: usize) -> Self {
+        let mut indices = self.0.clone();
+        indices.push(index);
+        NodePath(indices)
+    }
+}
+
+/// A demand that a consumer no longer gets in full.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "data-files", derive(serde::Serialize))]
+pub struct Shortfall {
+    /// The input that is undersupplied.
+    pub item_id: ItemId,
+    /// The item whose node consumes it.
+    pub consumer_id: ItemId,
+    pub required: u32,
+    pub available: u32,
+}
+
+impl Shortfall {
+    pub fn missing(&self) -> u32 {
+        self.required.saturating_sub(self.available)
+    }
+}
+
+/// Effects of a machine count edit, see `ProductionNode::with_machine_count`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "data-files", derive(serde::Serialize))]
+pub struct CapacityReport {
+    /// Unmet demands, from the edited node up to the root.
+    pub shortfalls: Vec<Shortfall>,
+    /// Output of the root node after the edit.
+    pub achievable_output: u32,
+}
+
+impl ProductionNode {
+    /// Returns the node at `path`, if it exists.
+    pub fn node_at(&self, path: &NodePath) -> Option<&ProductionNode> {
+        path.0.iter().try_fold(self, |node, &index| match node {
+            ProductionNode::Resolved { inputs, .. } => inputs.get(index),
+            _ => None,
+        })
+    }
+
+    /// Returns a copy of the tree with the node at `path` limited to
+    /// `count` machines, together with the demands left unmet.
+    ///
+    /// The edited node produces at most what `count` machines can make at
+    /// full load. Each consumer above it then runs at the same reduced rate,
+    /// so its amount and load shrink while its machine count is kept. The
+    /// inputs of the edited node are left as planned. A node split into
+    /// layout blocks is split again with the size of its first block.
+    ///
+    /// Nothing is changed if `path` does not lead to a resolved node.
+    pub fn with_machine_count(
+        &self,
+        path: &NodePath,
+        count: u32,
+    ) -> (ProductionNode, CapacityReport) {
+        let mut report = CapacityReport::default();
+
+        let node = match self.limit_machines(&path.0, count, &mut report) {
+            Some((node, _)) => node,
+            None => self.clone(),
+        };
+        report.achievable_output = node.amount();
+
+        (node, report)
+    }
+
+    /// Applies the edit below this node. Returns the new node and the
+    /// fraction of its planned amount it still produces.
+    fn limit_machines(
+        &self,
+        path: &[usize],
+        count: u32,
+        report: &mut CapacityReport,
+    ) -> Option<(ProductionNode, f64)> {
+        let ProductionNode::Resolved {
+            item_id,
+            amount,
+            machine_count,
+            power_usage,
+            standby_power,
+            load,
+            inputs,
+            ..
+        } = self
+        else {
+            return None;
+        };
+
+        let mut node = self.clone();
+        let ratio = match path.split_first() {
+            None => {
+                // Machines without a known throughput (e.g. instant crafting)
+                // never limit the output
+                let (ratio, new_load) = if *amount > 0 && *machine_count > 0 && *load > 0.0 {
+                    let capacity = *amount as f64 / (*machine_count as f64 * load) * count as f64;
+                    let achievable = capacity.min(*amount as f64);
+                    let new_load = if capacity > 0.0 {
+                        achievable / capacity
+                    } else {
+                        0.0
+                    };
+                    (achievable / *amount as f64, new_load)
+                } else {
+                    (1.0, *load)
+                };
+
+                if let ProductionNode::Resolved {
+                    machine_count: node_count,
+                    power_usage: node_power,
+                    standby_power: node_standby,
+                    load: node_load,
+                    blocks: node_blocks,
+                    ..
+                } = &mut node
+                {
+                    *node_blocks = split_into_blocks(count, node_blocks.first().copied());
+                    let power_per_machine = power_usage.checked_div(*machine_count).unwrap_or(0);
+                    let standby_per_machine =
+                        standby_power.checked_div(*machine_count).unwrap_or(0);
+                    *node_count = count;
+                    *node_power = power_per_machine.saturating_mul(count);
+                    *node_standby = standby_per_machine.saturating_mul(count);
+                    *node_load = new_load;
+                }
+                ratio
+            }
+            Some((&index, rest)) => {
+                let child = inputs.get(index)?;
+                let (new_child, ratio) = child.limit_machines(rest, count, report)?;
+
+                if ratio < 1.0 {
+                    report.shortfalls.push(Shortfall {
+                        item_id: child.item_id().clone(),
+                        consumer_id: item_id.clone(),
+                        required: child.amount(),
+                        available: new_child.amount(),
+                    });
+                }
+
+                if let ProductionNode::Resolved {
+                    inputs: node_inputs,
+                    load: node_load,
+                    ..
+                } = &mut node
+                {
+                    node_inputs[index] = new_child;
+                    *node_load = load * ratio;
+                }
+                ratio
+            }
+        };
+
+        if let ProductionNode::Resolved {
+            amount: node_amount,
+            ..
+        } = &mut node
+        {
+            *node_amount = (*amount as f64 * ratio).floor() as u32;
+        }
+
+        Some((node, ratio))
+    }
+
+    pub(crate) fn item_id(&self) -> &ItemId {
+        match self {
+            ProductionNode::Resolved { item_id, .. }
+            | ProductionNode::Unresolved { item_id, .. }
+            | ProductionNode::External { item_id, .. }
+            | ProductionNode::Cycle { item_id, .. }
+            | ProductionNode::Shared { item_id, .. } => item_id,
+        }
+    }
+
+    /// The node's amount: whole items per minute, or total items in
+    /// `plan_batch` trees.
+    pub fn amount(&self) -> u32 {
+        match self {
+            ProductionNode::Resolved { amount, .. }
+            | ProductionNode::Unresolved { amount, .. }
+            | ProductionNode::External { amount, .. }
+            | ProductionNode::Cycle { amount, .. }
+            | ProductionNode::Shared { amount, .. } => *amount,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::{self, on_machines};
+
+    fn resolved(
+        item_id: &str,
+        amount: u32,
+        machine_count: u32,
+        load: f64,
+        inputs: Vec<ProductionNode>,
+    ) -> ProductionNode {
+        let mut node = on_machines(
+            test_fixtures::resolved(item_id, amount, inputs),
+            "refining_unit",
+            machine_count,
+        );
+        if let ProductionNode::Resolved {
+            load: node_load, ..
+        } = &mut node
+        {
+            *node_load = load;
+        }
+        node
+    }
+
+    // amethyst_component <- amethyst_fiber <- amethyst_ore
+    fn sample_plan() -> ProductionNode {
+        resolved(
+            "amethyst_component",
+            10,
+            1,
+            1.0,
+            vec![resolved(
+                "amethyst_fiber",
+                20,
+                4,
+                1.0,
+                vec![resolved("amethyst_ore", 40, 2, 1.0, vec![])],
+            )],
+        )
+    }
+
+    fn fields(node: &ProductionNode) -> (u32, u32, u32, f64) {
+        match node {
+            ProductionNode::Resolved {
+                amount,
+                machine_count,
+                power_usage,
+                load,
+                ..
+            } => (*amount, *machine_count, *power_usage, *load),
+            _ => panic!("Expected Resolved node"),
+        }
+    }
+
+    #[test]
+    fn test_fewer_machines_propagate_shortfall() {
+        let plan = sample_plan();
+        let fiber = NodePath::root().child(0);
+
+        let (edited, report) = plan.with_machine_count(&fiber, 2);
+
+        assert_eq!(fields(edited.node_at(&fiber).unwrap()), (10, 2, 10, 1.0));
+        assert_eq!(fields(&edited), (5, 1, 5, 0.5));
+        assert_eq!(
+            report.shortfalls,
+            vec![Shortfall {
+                item_id: "amethyst_fiber".into(),
+                consumer_id: "amethyst_component".into(),
+                required: 20,
+                available: 10,
+            }]
+        );
+        assert_eq!(report.shortfalls[0].missing(), 10);
+        assert_eq!(report.achievable_output, 5);
+    }
+
+    #[test]
+    fn test_shortfall_reaches_root_through_every_consumer() {
+        let plan = sample_plan();
+        let ore = NodePath::root().child(0).child(0);
+
+        let (edited, report) = plan.with_machine_count(&ore, 1);
+
+        let consumers: Vec<(&str, u32, u32)> = report
+            .shortfalls
+            .iter()
+            .map(|s| (s.consumer_id.as_str(), s.required, s.available))
+            .collect();
+        assert_eq!(
+            consumers,
+            vec![("amethyst_fiber", 40, 20), ("amethyst_component", 20, 10)]
+        );
+        assert_eq!(report.achievable_output, 5);
+        // The fiber machines are kept but only run half the time
+        assert_eq!(
+            fields(edited.node_at(&NodePath::root().child(0)).unwrap()),
+            (10, 4, 20, 0.5)
+        );
+    }
+
+    #[test]
+    fn test_more_machines_lower_load_without_shortfall() {
+        let plan = sample_plan();
+        let fiber = NodePath::root().child(0);
+
+        let (edited, report) = plan.with_machine_count(&fiber, 8);
+
+        assert_eq!(fields(edited.node_at(&fiber).unwrap()), (20, 8, 40, 0.5));
+        assert!(report.shortfalls.is_empty());
+        assert_eq!(report.achievable_output, 10);
+    }
+
+    #[test]
+    fn test_standby_power_scales_with_machine_count() {
+        let mut plan = resolved("amethyst_fiber", 20, 4, 1.0, vec![]);
+        if let ProductionNode::Resolved { standby_power, .. } = &mut plan {
+            *standby_power = 4;
+        }
+
+        let (edited, report) = plan.with_machine_count(&NodePath::root(), 2);
+
+        assert_eq!(fields(&edited), (10, 2, 10, 1.0));
+        let ProductionNode::Resolved { standby_power, .. } = edited else {
+            panic!("Expected Resolved node");
+        };
+        assert_eq!(standby_power, 2);
+        assert_eq!(report.achievable_output, 10);
+    }
+
+    #[test]
+    fn test_layout_blocks_follow_machine_count() {
+        let mut plan = resolved("amethyst_fiber", 20, 20, 1.0, vec![]);
+        if let ProductionNode::Resolved { blocks, .. } = &mut plan {
+            *blocks = vec![8, 8, 4];
+        }
+        let blocks_of = |count| match plan.with_machine_count(&NodePath::root(), count).0 {
+            ProductionNode::Resolved { blocks, .. } => blocks,
+            _ => panic!("Expected Resolved node"),
+        };
+
+        assert_eq!(blocks_of(12), vec![8, 4]);
+        assert!(blocks_of(6).is_empty());
+    }
+
+    #[test]
+    fn test_invalid_path_leaves_plan_unchanged() {
+        let plan = sample_plan();
+
+        let (edited, report) = plan.with_machine_count(&NodePath(vec![3]), 1);
+
+        assert_eq!(edited, plan);
+        assert!(report.shortfalls.is_empty());
+        assert_eq!(report.achievable_output, 10);
+    }
+}
@@ -0,0 +1,132 @@
+//! Stable hashes of production plans, for caching and change detection.
+
+use super::production::ProductionNode;
+use crate::hash::Fnv1a;
+
+/// 64-bit FNV-1a with helpers for the fields of a plan.
+struct StableHasher(Fnv1a<u64>);
+
+impl StableHasher {
+    fn new() -> Self {
+        StableHasher(Fnv1a::new())
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.write(bytes);
+    }
+
+    fn write_u32(&mut self, value: u32) {
+        self.write(&value.to_le_bytes());
+    }
+
+    /// Writes the length first so adjacent strings cannot run together.
+    fn write_str(&mut self, value: &str) {
+        self.write(&(value.len() as u64).to_le_bytes());
+        self.write(value.as_bytes());
+    }
+}
+
+impl ProductionNode {
+    /// Returns a hash of the plan that is stable across runs, e.g. for an
+    /// ETag or to tell whether a saved plan still matches.
+    ///
+    /// Covers item IDs, recipe unique IDs, amounts and machine counts of
+    /// every node. Input order does not matter, as the plan is hashed in
+    /// `canonicalize` order. Names are not part of the plan, so the locale
+    /// never changes the hash; any amount change does.
+    pub fn fingerprint(&self) -> u64 {
+        let mut node = self.clone();
+        node.canonicalize();
+
+        let mut hasher = StableHasher::new();
+        node.write_fingerprint(&mut hasher);
+        hasher.0.finish()
+    }
+
+    fn write_fingerprint(&self, hasher: &mut StableHasher) {
+        let (tag, item_id, amount) = match self {
+            ProductionNode::Resolved {
+                item_id, amount, ..
+            } => (0, item_id, amount),
+            ProductionNode::Unresolved { item_id, amount } => (1, item_id, amount),
+            ProductionNode::External { item_id, amount } => (2, item_id, amount),
+            ProductionNode::Cycle { item_id, amount } => (3, item_id, amount),
+            ProductionNode::Shared { item_id, amount } => (4, item_id, amount),
+        };
+        hasher.write(&[tag]);
+        hasher.write_str(item_id.as_str());
+        hasher.write_u32(*amount);
+
+        if let ProductionNode::Resolved {
+            recipe_id,
+            machine_count,
+            inputs,
+            ..
+        } = self
+        {
+            hasher.write_str(recipe_id.as_str());
+            hasher.write_u32(*machine_count);
+            hasher.write_u32(inputs.len() as u32);
+            for input in inputs {
+                input.write_fingerprint(hasher);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::resolved;
+
+    fn plan(amount: u32) -> ProductionNode {
+        resolved(
+            "amethyst_component",
+            amount,
+            vec![
+                resolved("origocrust", 30, vec![]),
+                ProductionNode::Unresolved {
+                    item_id: "amethyst_fiber".into(),
+                    amount: 30,
+                },
+            ],
+        )
+    }
+
+    #[test]
+    fn test_same_plan_same_fingerprint() {
+        assert_eq!(plan(30).fingerprint(), plan(30).fingerprint());
+    }
+
+    #[test]
+    fn test_amount_changes_fingerprint() {
+        assert_ne!(plan(30).fingerprint(), plan(31).fingerprint());
+    }
+
+    #[test]
+    fn test_input_order_does_not_matter() {
+        let mut permuted = plan(30);
+        if let ProductionNode::Resolved { inputs, .. } = &mut permuted {
+            inputs.reverse();
+        }
+        assert_ne!(permuted, plan(30));
+
+        assert_eq!(permuted.fingerprint(), plan(30).fingerprint());
+        permuted.canonicalize();
+        assert_eq!(permuted.fingerprint(), plan(30).fingerprint());
+    }
+
+    #[test]
+    fn test_node_kind_changes_fingerprint() {
+        let unresolved = ProductionNode::Unresolved {
+            item_id: "origocrust".into(),
+            amount: 30,
+        };
+        let external = ProductionNode::External {
+            item_id: "origocrust".into(),
+            amount: 30,
+        };
+
+        assert_ne!(unresolved.fingerprint(), external.fingerprint());
+    }
+}
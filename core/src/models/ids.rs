@@ -0,0 +1,200 @@
+//! Typed identifiers, so that item, machine and recipe IDs cannot be
+//! passed in place of one another.
+//!
+//! ```compile_fail
+//! use endfield_planner_core::models::{ItemId, RecipeUid};
+//!
+//! fn plan_for(item_id: &ItemId) {}
+//!
+//! // A recipe unique ID is not an item ID
+//! plan_for(&RecipeUid::from("origocrust@refining_unit[]"));
+//! ```
+//!
+//! ```compile_fail
+//! use endfield_planner_core::models::{ItemId, MachineId};
+//!
+//! let machine = MachineId::from("refining_unit");
+//! let item: ItemId = machine;
+//! ```
+//!
+//! IDs loaded through `GameData` are interned, so every copy of the same
+//! ID shares one allocation and cloning only bumps a reference count.
+//! Interning also trims and lowercases them, see `canonical_id`.
+//!
+//! All of them convert from string slices and compare with them:
+//!
+//! ```
+//! use endfield_planner_core::models::ItemId;
+//! use std::collections::HashMap;
+//!
+//! let item = ItemId::from("origocrust");
+//! assert_eq!(item, "origocrust");
+//!
+//! let amounts: HashMap<ItemId, u32> = [(item, 30)].into_iter().collect();
+//! assert_eq!(amounts.get("origocrust"), Some(&30));
+//! ```
+
+#[cfg(feature = "data-files")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::borrow::{Borrow, Cow};
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::Arc;
+
+macro_rules! id_type {
+    ($(#[$meta:meta])* $name:ident) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        pub struct $name(Arc<str>);
+
+        impl $name {
+            pub fn as_str(&self) -> &str {
+                &self.0
+            }
+
+            /// Returns true if both IDs share the same allocation.
+            pub fn ptr_eq(&self, other: &Self) -> bool {
+                Arc::ptr_eq(&self.0, &other.0)
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str(&self.0)
+            }
+        }
+
+        impl Borrow<str> for $name {
+            fn borrow(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl AsRef<str> for $name {
+            fn as_ref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl From<&str> for $name {
+            fn from(id: &str) -> Self {
+                $name(Arc::from(id))
+            }
+        }
+
+        impl From<String> for $name {
+            fn from(id: String) -> Self {
+                $name(Arc::from(id))
+            }
+        }
+
+        impl From<&String> for $name {
+            fn from(id: &String) -> Self {
+                $name(Arc::from(id.as_str()))
+            }
+        }
+
+        impl From<Arc<str>> for $name {
+            fn from(id: Arc<str>) -> Self {
+                $name(id)
+            }
+        }
+
+        impl From<&$name> for $name {
+            fn from(id: &$name) -> Self {
+                id.clone()
+            }
+        }
+
+        impl PartialEq<str> for $name {
+            fn eq(&self, other: &str) -> bool {
+                &*self.0 == other
+            }
+        }
+
+        impl PartialEq<&str> for $name {
+            fn eq(&self, other: &&str) -> bool {
+                &*self.0 == *other
+            }
+        }
+
+        #[cfg(feature = "data-files")]
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_str(&self.0)
+            }
+        }
+
+        #[cfg(feature = "data-files")]
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                String::deserialize(deserializer).map($name::from)
+            }
+        }
+    };
+}
+
+id_type!(
+    /// ID of an item, e.g. `origocrust`.
+    ItemId
+);
+
+id_type!(
+    /// ID of a machine, e.g. `refining_unit`.
+    MachineId
+);
+
+id_type!(
+    /// Unique ID of a recipe variant, see `Recipe::compute_unique_id`.
+    RecipeUid
+);
+
+/// Returns `id` without surrounding whitespace and in lowercase, the form
+/// every ID in the data is stored in, so `" Refining_Unit"` matches
+/// `refining_unit`.
+pub fn canonical_id(id: &str) -> Cow<'_, str> {
+    let trimmed = id.trim();
+    if trimmed.chars().any(char::is_uppercase) {
+        Cow::Owned(trimmed.to_lowercase())
+    } else {
+        Cow::Borrowed(trimmed)
+    }
+}
+
+/// Hands out one shared allocation per distinct ID, in the form given by
+/// `canonical_id`.
+///
+/// The same string may back IDs of different types, e.g. an item and the
+/// machine sharing its name.
+#[derive(Debug, Default)]
+pub struct IdInterner {
+    ids: HashSet<Arc<str>>,
+}
+
+impl IdInterner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the shared copy of `id` in canonical form, allocating it on
+    /// first use.
+    pub fn intern<T: From<Arc<str>>>(&mut self, id: &str) -> T {
+        let id = canonical_id(id);
+        if let Some(shared) = self.ids.get(id.as_ref()) {
+            return T::from(shared.clone());
+        }
+
+        let shared: Arc<str> = Arc::from(id.as_ref());
+        self.ids.insert(shared.clone());
+        T::from(shared)
+    }
+
+    /// Number of distinct IDs allocated so far.
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+}
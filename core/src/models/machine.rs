@@ -1,8 +1,15 @@
-use serde::Deserialize;
+use super::ids::MachineId;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug)]
+#[cfg_attr(feature = "data-files", derive(serde::Deserialize))]
 pub struct Machine {
-    pub id: String,
+    pub id: MachineId,
     pub tier: u32,
     pub power: u32,
+    /// Number of distinct input items the machine accepts, if known.
+    pub input_ports: Option<u32>,
+    /// Number of distinct output items the machine produces, if known.
+    pub output_ports: Option<u32>,
+    /// Standby power drawn by each machine while not crafting, if any.
+    pub idle_power: Option<u32>,
 }
@@ -1,7 +1,19 @@
+mod aggregate;
+mod capacity;
+mod fingerprint;
+mod ids;
 mod machine;
 mod production;
+mod quantity;
 mod recipe;
 
+pub use aggregate::{AggregatedPlan, AggregatedRow, Consumer};
+pub use capacity::{CapacityReport, NodePath, Shortfall};
+pub use ids::{IdInterner, ItemId, MachineId, RecipeUid, canonical_id};
 pub use machine::Machine;
-pub use production::ProductionNode;
-pub use recipe::Recipe;
+pub use production::{
+    DEFAULT_SPARE_THRESHOLD, InputKind, MachineUtil, ProductionNode, RawOverrun, RoundingPolicy,
+    notable_spare,
+};
+pub use quantity::{AmountKind, Quantity, Rate, RateUnit};
+pub use recipe::{InputGroup, Recipe, TimeUnit};
@@ -1,25 +1,155 @@
-use serde::Serialize;
-use std::collections::HashMap;
+use super::ids::{ItemId, MachineId, RecipeUid};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::hash::Hash;
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "data-files", derive(serde::Serialize))]
 pub enum ProductionNode {
     Resolved {
-        item_id: String,
-        machine_id: String,
+        item_id: ItemId,
+        machine_id: MachineId,
+        /// Unique ID of the recipe used (see `Recipe::compute_unique_id`).
+        recipe_id: RecipeUid,
         amount: u32,
         machine_count: u32,
+        /// Power drawn with every machine crafting.
         power_usage: u32,
+        /// Power drawn with every machine idle, see `Machine::idle_power`.
+        standby_power: u32,
         load: f64,
         inputs: Vec<ProductionNode>,
         is_source: bool,
+        /// Machine counts of the layout blocks the node is split into, see
+        /// `PlanOptions::max_machines_per_node`. Empty when not split.
+        #[cfg_attr(feature = "data-files", serde(skip_serializing_if = "Vec::is_empty"))]
+        blocks: Vec<u32>,
     },
     Unresolved {
-        item_id: String,
+        item_id: ItemId,
         amount: u32,
     },
+    /// Supplied from outside the plan, so not expanded further.
+    External {
+        item_id: ItemId,
+        amount: u32,
+    },
+    /// An input that is already being produced higher up in the tree,
+    /// left unexpanded so the plan stays finite.
+    Cycle {
+        item_id: ItemId,
+        amount: u32,
+    },
+    /// An input built elsewhere in the same plan and not expanded here,
+    /// like an item `plan_targets` builds once for several targets, or a
+    /// byproduct of a recipe higher up in the branch.
+    Shared {
+        item_id: ItemId,
+        amount: u32,
+    },
+}
+
+/// How a node obtains one of its inputs, see `ProductionNode::input_classification`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "data-files", derive(serde::Serialize))]
+pub enum InputKind {
+    /// Gathered or mined, or supplied from outside the plan.
+    Source,
+    /// Made from further inputs in this plan.
+    Crafted,
+    /// No recipe was found for it.
+    Unresolved,
+}
+
+/// How fractional machine requirements are rounded into machine counts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "data-files", derive(serde::Serialize))]
+pub enum RoundingPolicy {
+    /// Round up at every node, as the planner builds the tree.
+    #[default]
+    CeilPerNode,
+    /// Sum the exact requirements per machine type, then round up once.
+    CeilPerType,
+    /// Keep the exact fractional requirements.
+    Exact,
+}
+
+impl fmt::Display for RoundingPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            RoundingPolicy::CeilPerNode => "ceil per node",
+            RoundingPolicy::CeilPerType => "ceil per type",
+            RoundingPolicy::Exact => "exact",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Utilization of one machine type across a plan.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "data-files", derive(serde::Serialize))]
+pub struct MachineUtil {
+    pub machine_id: MachineId,
+    pub count: u32,
+    /// Average load of the machines, weighted by machine count.
+    pub avg_load: f64,
+}
+
+/// A raw material the plan needs more of than is available, see
+/// `ProductionNode::raw_material_overruns`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "data-files", derive(serde::Serialize))]
+pub struct RawOverrun {
+    pub item_id: ItemId,
+    pub required: u32,
+    pub available: u32,
+    /// How much more the plan consumes than is available.
+    pub excess: u32,
+}
+
+/// Tolerance for float noise when rounding summed requirements up.
+pub(super) const ROUNDING_EPSILON: f64 = 1e-9;
+
+/// Default share of the requested amount that spare capacity must exceed
+/// to be worth pointing out, see `notable_spare`.
+pub const DEFAULT_SPARE_THRESHOLD: f64 = 0.05;
+
+/// Returns `spare` if it is more than `threshold` (a fraction, e.g. 0.05
+/// for 5%) of the `requested` amount. Rounding machines up always leaves
+/// a little spare, which is only noise in tight plans.
+pub fn notable_spare(spare: f64, requested: f64, threshold: f64) -> Option<f64> {
+    (requested > 0.0 && spare > requested * threshold).then_some(spare)
 }
 
 impl ProductionNode {
+    /// A node without a machine of its own grouping `inputs` under
+    /// `label`, like the roots of `plan_targets` and `bootstrap_plan`. Its
+    /// amount is 0, since the inputs are not one item.
+    pub fn virtual_root(label: impl Into<ItemId>, inputs: Vec<ProductionNode>) -> Self {
+        ProductionNode::Resolved {
+            item_id: label.into(),
+            machine_id: MachineId::default(),
+            recipe_id: RecipeUid::default(),
+            amount: 0,
+            machine_count: 0,
+            power_usage: 0,
+            standby_power: 0,
+            load: 1.0,
+            inputs,
+            is_source: false,
+            blocks: Vec::new(),
+        }
+    }
+
+    /// Whether this node was made by `virtual_root`, so it has no amount
+    /// or machine to show.
+    pub fn is_virtual_root(&self) -> bool {
+        matches!(
+            self,
+            ProductionNode::Resolved { machine_id, .. } if machine_id.as_str().is_empty()
+        )
+    }
+
     fn is_leaf(&self) -> bool {
         match self {
             ProductionNode::Resolved { inputs, .. } => inputs.is_empty(),
@@ -45,6 +175,9 @@ impl ProductionNode {
                         .product::<f64>()
                 }
             }
+            ProductionNode::External { .. }
+            | ProductionNode::Cycle { .. }
+            | ProductionNode::Shared { .. } => 1.0,
             _ => 0.0,
         }
     }
@@ -60,6 +193,73 @@ impl ProductionNode {
         }
     }
 
+    /// Returns the number of nodes in the tree, this one included.
+    pub fn node_count(&self) -> usize {
+        match self {
+            ProductionNode::Resolved { inputs, .. } => {
+                1 + inputs.iter().map(|child| child.node_count()).sum::<usize>()
+            }
+            _ => 1,
+        }
+    }
+
+    /// Returns the average power drawn by this node's machines, crafting
+    /// for the `load` fraction of the time and on standby otherwise:
+    /// `power_usage * load + standby_power * (1 - load)`.
+    ///
+    /// Machines without an idle power draw nothing on standby.
+    pub fn active_power(&self) -> f64 {
+        match self {
+            ProductionNode::Resolved {
+                power_usage,
+                standby_power,
+                load,
+                ..
+            } => *power_usage as f64 * load + *standby_power as f64 * (1.0 - load),
+            _ => 0.0,
+        }
+    }
+
+    /// Totals `active_power` over the plan, i.e. the average draw when
+    /// partially loaded machines idle between crafts.
+    ///
+    /// `total_power` is the peak draw with every machine running.
+    pub fn total_effective_power(&self) -> f64 {
+        match self {
+            ProductionNode::Resolved { inputs, .. } => {
+                self.active_power()
+                    + inputs
+                        .iter()
+                        .map(|child| child.total_effective_power())
+                        .sum::<f64>()
+            }
+            _ => 0.0,
+        }
+    }
+
+    /// Totals the power drawn by the unused fraction of each node's
+    /// machines, `power_usage * (1 - load)`, rounded to the nearest unit.
+    ///
+    /// This is the power wasted by rounding machine counts up.
+    pub fn idle_power(&self) -> u32 {
+        self.unused_power().max(0.0).round() as u32
+    }
+
+    fn unused_power(&self) -> f64 {
+        match self {
+            ProductionNode::Resolved {
+                power_usage,
+                load,
+                inputs,
+                ..
+            } => {
+                *power_usage as f64 * (1.0 - load)
+                    + inputs.iter().map(|child| child.unused_power()).sum::<f64>()
+            }
+            _ => 0.0,
+        }
+    }
+
     pub fn total_power_exclude_source(&self) -> u32 {
         match self {
             ProductionNode::Resolved {
@@ -74,7 +274,7 @@ impl ProductionNode {
         }
     }
 
-    pub fn total_source_materials(&self) -> HashMap<String, u32> {
+    pub fn total_source_materials(&self) -> HashMap<ItemId, u32> {
         self.collect_totals(|node| match node {
             ProductionNode::Resolved {
                 item_id, amount, ..
@@ -86,46 +286,422 @@ impl ProductionNode {
                 }
             }
             ProductionNode::Unresolved { item_id, amount } => Some((item_id.clone(), *amount)),
+            ProductionNode::External { .. }
+            | ProductionNode::Cycle { .. }
+            | ProductionNode::Shared { .. } => None,
         })
     }
 
-    pub fn total_machines(&self) -> HashMap<String, u32> {
+    /// Compares the raw material totals with the `available` supply and
+    /// lists those the plan consumes more of, sorted by item ID.
+    ///
+    /// Raw materials missing from `available` are not limited. The plan
+    /// itself is not changed.
+    pub fn raw_material_overruns(&self, available: &HashMap<ItemId, u32>) -> Vec<RawOverrun> {
+        let mut overruns: Vec<RawOverrun> = self
+            .total_source_materials()
+            .into_iter()
+            .filter_map(|(item_id, required)| {
+                let supply = *available.get(&item_id)?;
+                (required > supply).then(|| RawOverrun {
+                    item_id,
+                    required,
+                    available: supply,
+                    excess: required - supply,
+                })
+            })
+            .collect();
+        overruns.sort_by(|a, b| a.item_id.cmp(&b.item_id));
+        overruns
+    }
+
+    /// Totals the amounts of items supplied from outside the plan.
+    pub fn total_external_items(&self) -> HashMap<ItemId, u32> {
+        self.collect_totals(|node| match node {
+            ProductionNode::External { item_id, amount } => Some((item_id.clone(), *amount)),
+            _ => None,
+        })
+    }
+
+    /// Formats the machine count of this node with its layout blocks, e.g.
+    /// "20 (8+8+4)", or just "20" when the node is not split.
+    pub fn machine_count_label(&self) -> String {
+        match self {
+            ProductionNode::Resolved {
+                machine_count,
+                blocks,
+                ..
+            } if !blocks.is_empty() => {
+                let blocks: Vec<String> = blocks.iter().map(u32::to_string).collect();
+                format!("{} ({})", machine_count, blocks.join("+"))
+            }
+            ProductionNode::Resolved { machine_count, .. } => machine_count.to_string(),
+            _ => "0".to_string(),
+        }
+    }
+
+    pub fn total_machines(&self) -> HashMap<MachineId, u32> {
         self.collect_totals(|node| match node {
             ProductionNode::Resolved {
                 machine_id,
                 machine_count,
                 ..
-            } if !machine_id.is_empty() => Some((machine_id.clone(), *machine_count)),
+            } if !machine_id.as_str().is_empty() => Some((machine_id.clone(), *machine_count)),
             _ => None,
         })
     }
 
-    pub fn total_machines_exclude_source(&self) -> HashMap<String, u32> {
+    /// Sorts the inputs of every node by item ID (then amount), so equal
+    /// plans have the same layout regardless of recipe input order.
+    pub fn canonicalize(&mut self) {
+        if let ProductionNode::Resolved { inputs, .. } = self {
+            for child in inputs.iter_mut() {
+                child.canonicalize();
+            }
+            inputs.sort_by(|a, b| {
+                a.item_id()
+                    .cmp(b.item_id())
+                    .then_with(|| a.amount().cmp(&b.amount()))
+            });
+        }
+    }
+
+    /// Returns a copy of the tree with every node's amount passed through
+    /// `f`, e.g. `|amount| amount * 2` to double the plan.
+    ///
+    /// Only amounts change: machine counts, power and load are copied as
+    /// they were and are stale unless `f` keeps the amounts. Re-plan for
+    /// the matching machines.
+    pub fn map_amounts<F: Fn(u32) -> u32>(&self, f: F) -> ProductionNode {
+        let mut node = self.clone();
+        node.map_amounts_in_place(&f);
+        node
+    }
+
+    fn map_amounts_in_place(&mut self, f: &dyn Fn(u32) -> u32) {
+        match self {
+            ProductionNode::Resolved { amount, inputs, .. } => {
+                *amount = f(*amount);
+                for child in inputs {
+                    child.map_amounts_in_place(f);
+                }
+            }
+            ProductionNode::Unresolved { amount, .. }
+            | ProductionNode::External { amount, .. }
+            | ProductionNode::Cycle { amount, .. }
+            | ProductionNode::Shared { amount, .. } => *amount = f(*amount),
+        }
+    }
+
+    /// Totals machines per type using the given rounding policy.
+    ///
+    /// `total_machines` is the `CeilPerNode` total as integers.
+    pub fn total_machines_with(&self, policy: RoundingPolicy) -> HashMap<MachineId, f64> {
+        let mut totals = HashMap::new();
+        self.collect_machine_requirements(&mut totals, policy == RoundingPolicy::CeilPerNode);
+
+        if policy == RoundingPolicy::CeilPerType {
+            for total in totals.values_mut() {
+                *total = (*total - ROUNDING_EPSILON).ceil().max(0.0);
+            }
+        }
+
+        totals
+    }
+
+    /// Returns the count and average load of each machine type, sorted by
+    /// machine ID, e.g. for rendering a heatmap.
+    pub fn utilization_heatmap(&self) -> Vec<MachineUtil> {
+        let required = self.total_machines_with(RoundingPolicy::Exact);
+
+        let mut heatmap: Vec<MachineUtil> = self
+            .total_machines()
+            .into_iter()
+            .map(|(machine_id, count)| {
+                let avg_load = if count > 0 {
+                    required.get(&machine_id).copied().unwrap_or(0.0) / count as f64
+                } else {
+                    0.0
+                };
+                MachineUtil {
+                    machine_id,
+                    count,
+                    avg_load,
+                }
+            })
+            .collect();
+        heatmap.sort_by(|a, b| a.machine_id.cmp(&b.machine_id));
+        heatmap
+    }
+
+    /// Adds up machines per type, either the rounded counts of each node or
+    /// the exact requirements (`machine_count * load`).
+    fn collect_machine_requirements(&self, totals: &mut HashMap<MachineId, f64>, rounded: bool) {
+        if let ProductionNode::Resolved {
+            machine_id,
+            machine_count,
+            load,
+            inputs,
+            ..
+        } = self
+        {
+            if !machine_id.as_str().is_empty() {
+                let required = if rounded {
+                    *machine_count as f64
+                } else {
+                    *machine_count as f64 * load
+                };
+                *totals.entry(machine_id.clone()).or_insert(0.0) += required;
+            }
+
+            for child in inputs {
+                child.collect_machine_requirements(totals, rounded);
+            }
+        }
+    }
+
+    pub fn total_machines_exclude_source(&self) -> HashMap<MachineId, u32> {
         self.collect_totals(|node| match node {
             ProductionNode::Resolved {
                 machine_id,
                 machine_count,
                 is_source,
                 ..
-            } if !machine_id.is_empty() && !*is_source => {
+            } if !machine_id.as_str().is_empty() && !*is_source => {
                 Some((machine_id.clone(), *machine_count))
             }
             _ => None,
         })
     }
 
-    fn collect_totals<F>(&self, extract: F) -> HashMap<String, u32>
+    /// Aggregates machine counts by the item each node produces.
+    pub fn total_machines_by_item(&self) -> HashMap<ItemId, u32> {
+        self.collect_totals(|node| match node {
+            ProductionNode::Resolved {
+                item_id,
+                machine_id,
+                machine_count,
+                ..
+            } if !machine_id.as_str().is_empty() => Some((item_id.clone(), *machine_count)),
+            _ => None,
+        })
+    }
+
+    /// Extra items the node's machines could make running at full load,
+    /// left over from rounding the machine count up. In the node's own
+    /// unit: per minute, or in total in `plan_batch` trees.
+    pub fn spare_amount(&self) -> f64 {
+        match self {
+            ProductionNode::Resolved { load, .. } if *load > 0.0 => {
+                let amount = self.amount() as f64;
+                (amount / *load - amount).max(0.0)
+            }
+            _ => 0.0,
+        }
+    }
+
+    /// Returns the spare amount if it is notable for the node's requested
+    /// amount, see `notable_spare`.
+    pub fn notable_spare_amount(&self, threshold: f64) -> Option<f64> {
+        notable_spare(self.spare_amount(), self.amount() as f64, threshold)
+    }
+
+    /// Totals the spare amount per item over the nodes where it is notable
+    /// (see `notable_spare`), i.e. what could be fed into storage.
+    pub fn total_spare_amounts(&self, threshold: f64) -> HashMap<ItemId, f64> {
+        let mut totals = HashMap::new();
+        self.collect_spare_amounts(threshold, &mut totals);
+        totals
+    }
+
+    fn collect_spare_amounts(&self, threshold: f64, totals: &mut HashMap<ItemId, f64>) {
+        if let Some(spare) = self.notable_spare_amount(threshold) {
+            *totals.entry(self.item_id().clone()).or_default() += spare;
+        }
+
+        if let ProductionNode::Resolved { inputs, .. } = self {
+            for child in inputs {
+                child.collect_spare_amounts(threshold, totals);
+            }
+        }
+    }
+
+    /// Lists the fractional part of each node's exact machine requirement
+    /// (`machine_count * load`), one entry per node in tree order.
+    ///
+    /// A small remainder such as 0.05 for 2.05 machines means a whole
+    /// machine is added for little output, so a slightly lower target
+    /// would save it. Cleanly divisible nodes report 0.
+    pub fn rounding_report(&self) -> Vec<(ItemId, f64)> {
+        let mut report = Vec::new();
+        self.collect_rounding(&mut report);
+        report
+    }
+
+    fn collect_rounding(&self, report: &mut Vec<(ItemId, f64)>) {
+        if let ProductionNode::Resolved {
+            item_id,
+            machine_id,
+            machine_count,
+            load,
+            inputs,
+            ..
+        } = self
+        {
+            if !machine_id.as_str().is_empty() {
+                let required = *machine_count as f64 * load;
+                let remainder = if (required - required.round()).abs() < ROUNDING_EPSILON {
+                    0.0
+                } else {
+                    required - required.floor()
+                };
+                report.push((item_id.clone(), remainder));
+            }
+
+            for child in inputs {
+                child.collect_rounding(report);
+            }
+        }
+    }
+
+    /// Totals the amounts of intermediate items produced at more than one
+    /// place in the plan, i.e. what merging their branches would produce.
+    /// Items produced once and raw materials are left out.
+    pub fn repeated_intermediate_totals(&self) -> HashMap<ItemId, u32> {
+        let intermediate = |node: &ProductionNode| match node {
+            ProductionNode::Resolved {
+                item_id,
+                is_source: false,
+                ..
+            } => Some(item_id.clone()),
+            _ => None,
+        };
+        let occurrences = self.collect_totals(|node| intermediate(node).map(|id| (id, 1)));
+        let mut totals =
+            self.collect_totals(|node| intermediate(node).map(|id| (id, node.amount())));
+        totals.retain(|item_id, _| occurrences[item_id] > 1);
+        totals
+    }
+
+    /// Aggregates machine counts by the recipe each node uses.
+    ///
+    /// Unlike `total_machines`, a machine type used by several recipes is
+    /// split per recipe, since each group has to be configured separately.
+    pub fn machine_instances_by_recipe(&self) -> HashMap<RecipeUid, u32> {
+        self.collect_totals(|node| match node {
+            ProductionNode::Resolved {
+                recipe_id,
+                machine_id,
+                machine_count,
+                ..
+            } if !machine_id.as_str().is_empty() => Some((recipe_id.clone(), *machine_count)),
+            _ => None,
+        })
+    }
+
+    /// Returns the ratio of machines producing `item_a` to those producing
+    /// `item_b`, reduced to lowest terms (e.g. `(3, 2)` for 3:2).
+    ///
+    /// Returns `None` if either item is not produced by machines in the plan,
+    /// or if both counts are zero.
+    pub fn machine_ratio(&self, item_a: &str, item_b: &str) -> Option<(u32, u32)> {
+        let totals = self.total_machines_by_item();
+        let count_a = *totals.get(item_a)?;
+        let count_b = *totals.get(item_b)?;
+
+        let divisor = gcd(count_a, count_b);
+        if divisor == 0 {
+            return None;
+        }
+
+        Some((count_a / divisor, count_b / divisor))
+    }
+
+    /// Classifies each direct input of a resolved node, in input order.
+    ///
+    /// External inputs count as sources and cyclic ones as crafted, since
+    /// they are produced elsewhere in the tree. Other nodes have no inputs.
+    pub fn input_classification(&self) -> Vec<(ItemId, InputKind)> {
+        let ProductionNode::Resolved { inputs, .. } = self else {
+            return Vec::new();
+        };
+
+        inputs
+            .iter()
+            .map(|child| match child {
+                ProductionNode::Resolved {
+                    item_id, is_source, ..
+                } => {
+                    let kind = if *is_source || child.is_leaf() {
+                        InputKind::Source
+                    } else {
+                        InputKind::Crafted
+                    };
+                    (item_id.clone(), kind)
+                }
+                ProductionNode::Unresolved { item_id, .. } => {
+                    (item_id.clone(), InputKind::Unresolved)
+                }
+                ProductionNode::External { item_id, .. } => (item_id.clone(), InputKind::Source),
+                ProductionNode::Cycle { item_id, .. } | ProductionNode::Shared { item_id, .. } => {
+                    (item_id.clone(), InputKind::Crafted)
+                }
+            })
+            .collect()
+    }
+
+    /// Lists the items of the plan in the order to build them: every item
+    /// comes after the items it is made from, so raw materials come first
+    /// and the target last. Items used in several places appear once.
+    ///
+    /// A cycle or shared marker adds no dependency, so the item keeps the
+    /// place of the node actually producing it.
+    pub fn build_order(&self) -> Vec<ItemId> {
+        let mut dependencies = HashMap::new();
+        self.collect_dependencies(&mut dependencies);
+
+        let mut visited = HashSet::new();
+        let mut order = Vec::new();
+        visit_dependencies(self.item_id(), &dependencies, &mut visited, &mut order);
+        order
+    }
+
+    /// Maps each item to the items it is made from, across every node
+    /// producing it, in first-seen order.
+    fn collect_dependencies<'a>(&'a self, dependencies: &mut HashMap<&'a ItemId, Vec<&'a ItemId>>) {
+        let item_inputs = dependencies.entry(self.item_id()).or_default();
+        let ProductionNode::Resolved { inputs, .. } = self else {
+            return;
+        };
+
+        for child in inputs {
+            if !matches!(
+                child,
+                ProductionNode::Cycle { .. } | ProductionNode::Shared { .. }
+            ) && !item_inputs.contains(&child.item_id())
+            {
+                item_inputs.push(child.item_id());
+            }
+        }
+        for child in inputs {
+            child.collect_dependencies(dependencies);
+        }
+    }
+
+    fn collect_totals<K, F>(&self, extract: F) -> HashMap<K, u32>
     where
-        F: Fn(&ProductionNode) -> Option<(String, u32)> + Copy,
+        K: Eq + Hash,
+        F: Fn(&ProductionNode) -> Option<(K, u32)> + Copy,
     {
         let mut totals = HashMap::new();
         self.collect_totals_recursive(&mut totals, extract);
         totals
     }
 
-    fn collect_totals_recursive<F>(&self, totals: &mut HashMap<String, u32>, extract: F)
+    fn collect_totals_recursive<K, F>(&self, totals: &mut HashMap<K, u32>, extract: F)
     where
-        F: Fn(&ProductionNode) -> Option<(String, u32)> + Copy,
+        K: Eq + Hash,
+        F: Fn(&ProductionNode) -> Option<(K, u32)> + Copy,
     {
         if let Some((key, value)) = extract(self) {
             *totals.entry(key).or_insert(0) += value;
@@ -138,3 +714,531 @@ impl ProductionNode {
         }
     }
 }
+
+/// Depth-first post-order walk, so dependencies are pushed before the
+/// items needing them. Items are marked on entry, which skips back edges.
+fn visit_dependencies<'a>(
+    item_id: &'a ItemId,
+    dependencies: &HashMap<&'a ItemId, Vec<&'a ItemId>>,
+    visited: &mut HashSet<&'a ItemId>,
+    order: &mut Vec<ItemId>,
+) {
+    if !visited.insert(item_id) {
+        return;
+    }
+    for &input in dependencies.get(item_id).into_iter().flatten() {
+        visit_dependencies(input, dependencies, visited, order);
+    }
+    order.push(item_id.clone());
+}
+
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::{self, on_machines};
+
+    fn resolved(
+        item_id: &str,
+        machine_id: &str,
+        machine_count: u32,
+        inputs: Vec<ProductionNode>,
+    ) -> ProductionNode {
+        on_machines(
+            test_fixtures::resolved(item_id, 0, inputs),
+            machine_id,
+            machine_count,
+        )
+    }
+
+    #[test]
+    fn test_node_count_includes_leaves() {
+        let plan = resolved(
+            "amethyst_component",
+            "gearing_unit",
+            1,
+            vec![
+                resolved("origocrust", "refining_unit", 1, vec![]),
+                ProductionNode::External {
+                    item_id: "amethyst_fiber".into(),
+                    amount: 30,
+                },
+            ],
+        );
+
+        assert_eq!(plan.node_count(), 3);
+        assert_eq!(
+            resolved("origocrust", "refining_unit", 1, vec![]).node_count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_map_amounts_doubles_every_node() {
+        let mut fiber = resolved("amethyst_fiber", "refining_unit", 1, vec![]);
+        if let ProductionNode::Resolved { amount, .. } = &mut fiber {
+            *amount = 30;
+        }
+        let plan = ProductionNode::Resolved {
+            item_id: "amethyst_component".into(),
+            machine_id: "gearing_unit".into(),
+            recipe_id: Default::default(),
+            amount: 15,
+            machine_count: 1,
+            power_usage: 10,
+            standby_power: 0,
+            load: 1.0,
+            inputs: vec![
+                fiber,
+                ProductionNode::External {
+                    item_id: "origocrust".into(),
+                    amount: 15,
+                },
+            ],
+            is_source: false,
+            blocks: vec![],
+        };
+
+        let doubled = plan.map_amounts(|amount| amount * 2);
+
+        assert_eq!(doubled.amount(), 30);
+        let ProductionNode::Resolved {
+            inputs,
+            machine_count,
+            ..
+        } = &doubled
+        else {
+            panic!("root should stay resolved");
+        };
+        assert_eq!(inputs[0].amount(), 60);
+        assert_eq!(inputs[1].amount(), 30);
+        // Machine counts are not recomputed
+        assert_eq!(*machine_count, 1);
+        // The original is untouched
+        assert_eq!(plan.amount(), 15);
+    }
+
+    #[test]
+    fn test_machine_ratio_reduced() {
+        // Two origocrust branches use 2 + 4 refining units, against 4 gearing units
+        let plan = resolved(
+            "amethyst_component",
+            "gearing_unit",
+            4,
+            vec![
+                resolved("origocrust", "refining_unit", 2, vec![]),
+                resolved(
+                    "amethyst_fiber",
+                    "refining_unit",
+                    3,
+                    vec![resolved("origocrust", "refining_unit", 4, vec![])],
+                ),
+            ],
+        );
+
+        assert_eq!(
+            plan.machine_ratio("origocrust", "amethyst_component"),
+            Some((3, 2))
+        );
+        assert_eq!(
+            plan.machine_ratio("amethyst_component", "amethyst_fiber"),
+            Some((4, 3))
+        );
+    }
+
+    #[test]
+    fn test_effective_power_weighted_by_load() {
+        let plan = ProductionNode::Resolved {
+            item_id: "amethyst_fiber".into(),
+            machine_id: "refining_unit".into(),
+            amount: 0,
+            machine_count: 2,
+            power_usage: 10,
+            standby_power: 0,
+            load: 0.75,
+            inputs: vec![ProductionNode::Resolved {
+                item_id: "amethyst_ore".into(),
+                machine_id: "electric_mining_rig".into(),
+                amount: 0,
+                machine_count: 1,
+                power_usage: 5,
+                standby_power: 0,
+                load: 0.5,
+                inputs: vec![],
+                recipe_id: RecipeUid::default(),
+                is_source: true,
+                blocks: vec![],
+            }],
+            recipe_id: RecipeUid::default(),
+            is_source: false,
+            blocks: vec![],
+        };
+
+        assert_eq!(plan.total_power(), 15);
+        assert!((plan.total_effective_power() - 10.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_idle_power_from_partial_load() {
+        // Two 10-power machines running at half load
+        let plan = ProductionNode::Resolved {
+            item_id: "origocrust".into(),
+            machine_id: "refining_unit".into(),
+            amount: 0,
+            machine_count: 2,
+            power_usage: 20,
+            standby_power: 0,
+            load: 0.5,
+            inputs: vec![],
+            recipe_id: RecipeUid::default(),
+            is_source: false,
+            blocks: vec![],
+        };
+
+        assert_eq!(plan.idle_power(), 10);
+    }
+
+    #[test]
+    fn test_active_power_with_standby_draw() {
+        // Two machines drawing 10 each while crafting and 2 each on standby
+        let at_load = |load: f64| ProductionNode::Resolved {
+            item_id: "origocrust".into(),
+            machine_id: "refining_unit".into(),
+            amount: 0,
+            machine_count: 2,
+            power_usage: 20,
+            standby_power: 4,
+            load,
+            inputs: vec![],
+            recipe_id: RecipeUid::default(),
+            is_source: false,
+            blocks: vec![],
+        };
+
+        // 20 * 0 + 4 * 1
+        assert_eq!(at_load(0.0).active_power(), 4.0);
+        // 20 * 0.5 + 4 * 0.5
+        assert_eq!(at_load(0.5).active_power(), 12.0);
+        // 20 * 1 + 4 * 0
+        assert_eq!(at_load(1.0).active_power(), 20.0);
+
+        // Peak power is unaffected by the load
+        assert_eq!(at_load(0.5).total_power(), 20);
+        assert_eq!(at_load(0.5).total_effective_power(), 12.0);
+    }
+
+    #[test]
+    fn test_external_items_not_counted_as_sources() {
+        let plan = resolved(
+            "amethyst_component",
+            "gearing_unit",
+            1,
+            vec![
+                ProductionNode::External {
+                    item_id: "origocrust".into(),
+                    amount: 5,
+                },
+                resolved("amethyst_fiber", "refining_unit", 1, vec![]),
+            ],
+        );
+
+        let external = plan.total_external_items();
+        assert_eq!(external.get("origocrust"), Some(&5));
+        assert_eq!(external.len(), 1);
+        assert!(!plan.total_source_materials().contains_key("origocrust"));
+    }
+
+    #[test]
+    fn test_raw_material_overruns() {
+        let raw = |item_id: &str, amount: u32| ProductionNode::Unresolved {
+            item_id: item_id.into(),
+            amount,
+        };
+        let plan = resolved(
+            "amethyst_component",
+            "gearing_unit",
+            1,
+            vec![
+                raw("originium_ore", 30),
+                raw("amethyst_ore", 10),
+                raw("ferrium", 99),
+            ],
+        );
+        // ferrium has no limit
+        let available: HashMap<ItemId, u32> =
+            [("originium_ore".into(), 20), ("amethyst_ore".into(), 50)]
+                .into_iter()
+                .collect();
+
+        assert_eq!(
+            plan.raw_material_overruns(&available),
+            vec![RawOverrun {
+                item_id: "originium_ore".into(),
+                required: 30,
+                available: 20,
+                excess: 10,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_input_classification() {
+        let plan = resolved(
+            "amethyst_component",
+            "gearing_unit",
+            1,
+            vec![
+                resolved(
+                    "amethyst_fiber",
+                    "refining_unit",
+                    1,
+                    vec![resolved("amethyst_ore", "electric_mining_rig", 1, vec![])],
+                ),
+                resolved("originium_ore", "electric_mining_rig", 1, vec![]),
+            ],
+        );
+
+        assert_eq!(
+            plan.input_classification(),
+            vec![
+                ("amethyst_fiber".into(), InputKind::Crafted),
+                ("originium_ore".into(), InputKind::Source),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rounding_policies_differ() {
+        let partial = |item_id: &str, machine_id: &str, load: f64| ProductionNode::Resolved {
+            item_id: item_id.into(),
+            machine_id: machine_id.into(),
+            recipe_id: RecipeUid::default(),
+            amount: 0,
+            machine_count: 1,
+            power_usage: 0,
+            standby_power: 0,
+            load,
+            inputs: vec![],
+            is_source: false,
+            blocks: vec![],
+        };
+        let plan = resolved(
+            "amethyst_component",
+            "gearing_unit",
+            1,
+            vec![
+                partial("origocrust", "refining_unit", 0.5),
+                partial("amethyst_fiber", "refining_unit", 0.25),
+            ],
+        );
+
+        let refining = |policy| plan.total_machines_with(policy)["refining_unit"];
+
+        assert_eq!(refining(RoundingPolicy::CeilPerNode), 2.0);
+        assert_eq!(refining(RoundingPolicy::CeilPerType), 1.0);
+        assert!((refining(RoundingPolicy::Exact) - 0.75).abs() < 1e-9);
+        assert_eq!(
+            plan.total_machines_with(RoundingPolicy::CeilPerType)["gearing_unit"],
+            1.0
+        );
+    }
+
+    #[test]
+    fn test_utilization_heatmap_weighted_average() {
+        let node = |item_id: &str,
+                    machine_id: &str,
+                    machine_count: u32,
+                    load: f64,
+                    inputs: Vec<ProductionNode>| ProductionNode::Resolved {
+            item_id: item_id.into(),
+            machine_id: machine_id.into(),
+            recipe_id: RecipeUid::default(),
+            amount: 0,
+            machine_count,
+            power_usage: 0,
+            standby_power: 0,
+            load,
+            inputs,
+            is_source: false,
+            blocks: vec![],
+        };
+        let plan = node(
+            "amethyst_fiber",
+            "refining_unit",
+            1,
+            0.5,
+            vec![
+                node("origocrust", "refining_unit", 3, 1.0, vec![]),
+                node("amethyst_ore", "electric_mining_rig", 2, 0.25, vec![]),
+            ],
+        );
+
+        let heatmap = plan.utilization_heatmap();
+
+        let summary: Vec<(&str, u32)> = heatmap
+            .iter()
+            .map(|util| (util.machine_id.as_str(), util.count))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![("electric_mining_rig", 2), ("refining_unit", 4)]
+        );
+        assert!((heatmap[0].avg_load - 0.25).abs() < 1e-9);
+        // (1 * 0.5 + 3 * 1.0) / 4
+        assert!((heatmap[1].avg_load - 0.875).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_machine_instances_split_by_recipe() {
+        // refining_unit makes both origocrust (2 + 4) and amethyst_fiber (3)
+        let plan = resolved(
+            "amethyst_component",
+            "gearing_unit",
+            4,
+            vec![
+                resolved("origocrust", "refining_unit", 2, vec![]),
+                resolved(
+                    "amethyst_fiber",
+                    "refining_unit",
+                    3,
+                    vec![resolved("origocrust", "refining_unit", 4, vec![])],
+                ),
+            ],
+        );
+
+        assert_eq!(plan.total_machines()["refining_unit"], 9);
+
+        let instances = plan.machine_instances_by_recipe();
+        assert_eq!(instances.len(), 3);
+        assert_eq!(instances["origocrust@refining_unit[]"], 6);
+        assert_eq!(instances["amethyst_fiber@refining_unit[]"], 3);
+        assert_eq!(instances["amethyst_component@gearing_unit[]"], 4);
+    }
+
+    #[test]
+    fn test_machine_ratio_missing_item() {
+        let plan = resolved("origocrust", "refining_unit", 2, vec![]);
+
+        assert_eq!(plan.machine_ratio("origocrust", "amethyst_fiber"), None);
+    }
+
+    #[test]
+    fn test_build_order_sources_first() {
+        // origocrust is used by both branches; amethyst_fiber loops back to the target
+        let origocrust = || {
+            resolved(
+                "origocrust",
+                "refining_unit",
+                1,
+                vec![resolved("originium_ore", "electric_mining_rig", 1, vec![])],
+            )
+        };
+        let plan = resolved(
+            "amethyst_component",
+            "gearing_unit",
+            1,
+            vec![
+                origocrust(),
+                resolved(
+                    "amethyst_fiber",
+                    "refining_unit",
+                    1,
+                    vec![
+                        origocrust(),
+                        ProductionNode::Cycle {
+                            item_id: "amethyst_component".into(),
+                            amount: 1,
+                        },
+                    ],
+                ),
+            ],
+        );
+
+        let order = plan.build_order();
+        let order: Vec<&str> = order.iter().map(ItemId::as_str).collect();
+        assert_eq!(
+            order,
+            vec![
+                "originium_ore",
+                "origocrust",
+                "amethyst_fiber",
+                "amethyst_component"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_notable_spare_threshold() {
+        assert_eq!(notable_spare(1.8, 30.0, DEFAULT_SPARE_THRESHOLD), Some(1.8));
+        // Exactly 5% is not more than 5%
+        assert_eq!(notable_spare(1.5, 30.0, DEFAULT_SPARE_THRESHOLD), None);
+        assert_eq!(notable_spare(0.0, 30.0, DEFAULT_SPARE_THRESHOLD), None);
+        assert_eq!(notable_spare(1.0, 0.0, DEFAULT_SPARE_THRESHOLD), None);
+        assert_eq!(notable_spare(1.0, 30.0, 0.0), Some(1.0));
+    }
+
+    #[test]
+    fn test_total_spare_amounts_skip_tight_nodes() {
+        let node = |item_id: &str, amount, load, inputs| ProductionNode::Resolved {
+            item_id: ItemId::from(item_id),
+            machine_id: "refining_unit".into(),
+            amount,
+            machine_count: 1,
+            power_usage: 0,
+            standby_power: 0,
+            load,
+            inputs,
+            recipe_id: Default::default(),
+            is_source: false,
+            blocks: vec![],
+        };
+        // 20/min at 80% load leaves 5/min spare; 30/min at 99% leaves 0.3/min
+        let plan = node(
+            "amethyst_component",
+            20,
+            0.8,
+            vec![
+                node("origocrust", 30, 0.99, vec![]),
+                node("amethyst_fiber", 10, 0.5, vec![]),
+            ],
+        );
+
+        assert!((plan.spare_amount() - 5.0).abs() < 1e-9);
+
+        let totals = plan.total_spare_amounts(DEFAULT_SPARE_THRESHOLD);
+        assert_eq!(totals.len(), 2);
+        assert!((totals["amethyst_component"] - 5.0).abs() < 1e-9);
+        assert!((totals["amethyst_fiber"] - 10.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_rounding_report_fractional_remainder() {
+        let node = |item_id: &str, machine_count, load: f64, inputs| {
+            let mut node = resolved(item_id, "refining_unit", machine_count, inputs);
+            if let ProductionNode::Resolved {
+                load: node_load, ..
+            } = &mut node
+            {
+                *node_load = load;
+            }
+            node
+        };
+        // 2.05 machines need 3; 4 machines at full load divide cleanly
+        let plan = node(
+            "amethyst_component",
+            3,
+            2.05 / 3.0,
+            vec![node("origocrust", 4, 1.0, vec![])],
+        );
+
+        let report = plan.rounding_report();
+
+        assert_eq!(report.len(), 2);
+        assert_eq!(report[0].0, "amethyst_component");
+        assert!((report[0].1 - 0.05).abs() < 1e-9);
+        assert_eq!(report[1], ("origocrust".into(), 0.0));
+    }
+}
@@ -0,0 +1,115 @@
+//! Unit-safe rates and quantities of items.
+//!
+//! A `Rate` is items per time, a `Quantity` a number of items. The
+//! calculator takes one or the other, so a steady-rate target cannot be
+//! mistaken for a batch one. Plan amounts stay plain per minute numbers.
+
+/// The time unit a rate is expressed in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "data-files",
+    derive(serde::Serialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum RateUnit {
+    PerSecond,
+    /// The planner's standard unit.
+    #[default]
+    PerMinute,
+    PerHour,
+}
+
+impl RateUnit {
+    /// Length of the unit in seconds.
+    pub fn seconds(self) -> f64 {
+        match self {
+            RateUnit::PerSecond => 1.0,
+            RateUnit::PerMinute => 60.0,
+            RateUnit::PerHour => 3600.0,
+        }
+    }
+}
+
+/// What the amounts of a plan count, see `PlanOptions::amount_kind`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AmountKind {
+    /// Items per minute, for a steady rate.
+    #[default]
+    PerMinute,
+    /// Items in total, for a batch.
+    Total,
+}
+
+impl AmountKind {
+    /// Suffix after an amount in text output, e.g. " (per minute)".
+    pub fn suffix(self) -> &'static str {
+        match self {
+            AmountKind::PerMinute => " (per minute)",
+            AmountKind::Total => " (total)",
+        }
+    }
+
+    /// Key of the UI string heading a column of amounts.
+    pub fn label_key(self) -> &'static str {
+        match self {
+            AmountKind::PerMinute => "per_min",
+            AmountKind::Total => "total_amount",
+        }
+    }
+}
+
+/// Items per unit of time, stored per second.
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd)]
+pub struct Rate(f64);
+
+/// A number of items, possibly fractional, e.g. a batch to produce.
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd)]
+pub struct Quantity(f64);
+
+impl Rate {
+    pub fn per_minute(items: f64) -> Self {
+        Rate(items / RateUnit::PerMinute.seconds())
+    }
+
+    /// Items produced at this rate over `seconds`.
+    pub fn over(self, seconds: f64) -> Quantity {
+        Quantity(self.0 * seconds)
+    }
+}
+
+impl Quantity {
+    pub fn items(self) -> f64 {
+        self.0
+    }
+
+    /// Whole items needed to cover the quantity, rounded up.
+    pub fn whole(self) -> u32 {
+        self.0.max(0.0).ceil() as u32
+    }
+}
+
+impl From<u32> for Quantity {
+    fn from(items: u32) -> Self {
+        Quantity(items as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_over_time() {
+        let batch = Rate::per_minute(30.0).over(90.0);
+
+        assert!((batch.items() - 45.0).abs() < 1e-9);
+        assert_eq!(Rate::per_minute(12.0).over(5.0).whole(), 1);
+    }
+
+    #[test]
+    fn test_quantity_whole_rounds_up() {
+        assert_eq!(Quantity::from(3).whole(), 3);
+        assert_eq!(Rate::per_minute(4.2).over(60.0).whole(), 5);
+        assert_eq!(Rate::per_minute(-1.0).over(60.0).whole(), 0);
+    }
+}
@@ -1,63 +1,359 @@
+use super::ids::{IdInterner, ItemId, MachineId, RecipeUid};
 use crate::constants::SELF_REFERENCE_KEYWORD;
-use serde::Deserialize;
+use crate::error::ProductionError;
+#[cfg(feature = "data-files")]
+use serde::{Deserialize, Deserializer};
 use std::collections::HashMap;
 
-#[derive(Debug, Deserialize, Clone)]
+/// Unit in which a recipe `time` is written in the data files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "data-files", derive(Deserialize))]
+#[cfg_attr(feature = "data-files", serde(rename_all = "lowercase"))]
+pub enum TimeUnit {
+    #[default]
+    Seconds,
+    Ticks,
+}
+
+/// `by` accepts either a single machine ID or a list of them.
+#[cfg(feature = "data-files")]
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum MachineList {
+    One(MachineId),
+    Many(Vec<MachineId>),
+}
+
+#[cfg(feature = "data-files")]
+fn deserialize_machine_list<'de, D>(deserializer: D) -> Result<Vec<MachineId>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(match MachineList::deserialize(deserializer)? {
+        MachineList::One(machine) => vec![machine],
+        MachineList::Many(machines) => machines,
+    })
+}
+
+/// Interchangeable inputs of a recipe, of which any one is used.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "data-files", derive(Deserialize, serde::Serialize))]
+pub struct InputGroup {
+    pub items: Vec<ItemId>,
+    /// Amount of the chosen item consumed per craft.
+    pub count: u32,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "data-files", derive(Deserialize))]
 pub struct Recipe {
-    pub id: String,
-    pub by: String,
-    pub time: u32,
+    pub id: ItemId,
+    /// The machine this recipe runs on (set by `expand_machines`).
+    #[cfg_attr(feature = "data-files", serde(skip))]
+    pub by: MachineId,
+    #[cfg_attr(
+        feature = "data-files",
+        serde(rename = "by", deserialize_with = "deserialize_machine_list")
+    )]
+    machine_options: Vec<MachineId>,
+    /// Crafting time in seconds (after normalization).
+    pub time: f64,
+    #[cfg_attr(feature = "data-files", serde(default))]
+    time_unit: Option<TimeUnit>,
     out: Option<u32>,
-    #[serde(default)]
-    pub inputs: HashMap<String, u32>,
-    #[serde(default)]
-    pub outputs: HashMap<String, u32>,
-    #[serde(default)]
+    #[cfg_attr(feature = "data-files", serde(default))]
+    pub inputs: HashMap<ItemId, u32>,
+    #[cfg_attr(feature = "data-files", serde(default))]
+    pub outputs: HashMap<ItemId, u32>,
+    /// Items given back per craft, e.g. emptied canisters. They offset the
+    /// same item in `inputs`, so only the difference is supplied.
+    #[cfg_attr(feature = "data-files", serde(default))]
+    pub returns: HashMap<ItemId, u32>,
+    /// Inputs that may be any one of several items, in addition to
+    /// `inputs`. The planner picks one item per group.
+    #[cfg_attr(feature = "data-files", serde(default))]
+    pub any_of: Vec<InputGroup>,
+    #[cfg_attr(feature = "data-files", serde(default))]
     pub is_source: bool,
+    /// Most a single deposit yields per minute, for source recipes such as
+    /// ore veins. Demand above it needs several deposits.
+    #[cfg_attr(feature = "data-files", serde(default))]
+    pub max_rate: Option<u32>,
+    /// Free-form categories such as "smelting" or "farming".
+    #[cfg_attr(feature = "data-files", serde(default))]
+    pub tags: Vec<String>,
 }
 
 impl Recipe {
     #[cfg(test)]
     pub fn new_for_test(
-        id: String,
-        by: String,
-        time: u32,
-        inputs: HashMap<String, u32>,
-        outputs: HashMap<String, u32>,
+        id: ItemId,
+        by: MachineId,
+        time: f64,
+        inputs: HashMap<ItemId, u32>,
+        outputs: HashMap<ItemId, u32>,
         is_source: bool,
     ) -> Self {
         Recipe {
             id,
+            machine_options: vec![by.clone()],
             by,
             time,
+            time_unit: None,
             out: None,
             inputs,
             outputs,
+            returns: HashMap::new(),
+            any_of: Vec::new(),
             is_source,
+            max_rate: None,
+            tags: Vec::new(),
         }
     }
 
-    pub fn normalize(&mut self) {
-        if let Some(count) = self.out {
-            self.outputs.insert(self.id.clone(), count);
+    /// Creates a recipe for `id` running on `machines`, with `time` in
+    /// seconds. Inputs, outputs, tags and `is_source` are set on the result.
+    pub fn new(id: impl Into<ItemId>, machines: Vec<MachineId>, time: f64) -> Self {
+        Recipe {
+            id: id.into(),
+            by: MachineId::default(),
+            machine_options: machines,
+            time,
+            time_unit: None,
+            out: None,
+            inputs: HashMap::new(),
+            outputs: HashMap::new(),
+            returns: HashMap::new(),
+            any_of: Vec::new(),
+            is_source: false,
+            max_rate: None,
+            tags: Vec::new(),
+        }
+    }
+
+    /// Moves `out` and the `"this"` output into `outputs[id]`.
+    ///
+    /// `out`, `outputs[id]` and `outputs.this` may be combined as long as
+    /// they give the same count; different counts are an error rather
+    /// than one silently winning.
+    pub fn normalize(&mut self) -> Result<(), ProductionError> {
+        let declared = [
+            ("out", self.out),
+            (
+                SELF_REFERENCE_KEYWORD,
+                self.outputs.remove(SELF_REFERENCE_KEYWORD),
+            ),
+            (self.id.as_str(), self.outputs.get(&self.id).copied()),
+        ];
+
+        let mut self_output: Option<(&str, u32)> = None;
+        for (source, count) in declared {
+            let Some(count) = count else { continue };
+            match self_output {
+                Some((first, first_count)) if first_count != count => {
+                    return Err(ProductionError::ParseError(format!(
+                        "recipe '{}' gives its own output as {} in `{}` but {} in `{}`",
+                        self.id, first_count, first, count, source
+                    )));
+                }
+                Some(_) => {}
+                None => self_output = Some((source, count)),
+            }
         }
 
-        if let Some(count) = self.outputs.remove(SELF_REFERENCE_KEYWORD) {
+        if let Some((_, count)) = self_output {
             self.outputs.insert(self.id.clone(), count);
         }
+        Ok(())
+    }
+
+    /// Lists the items the recipe makes, sorted: the keys of its normalized
+    /// `outputs`, or its `id` when it lists none. The `id` may also just
+    /// name the recipe, e.g. "crack_origocrust" for a multi-output recipe.
+    pub fn output_items(&self) -> Vec<&ItemId> {
+        if self.outputs.is_empty() {
+            return vec![&self.id];
+        }
+        let mut items: Vec<&ItemId> = self.outputs.keys().collect();
+        items.sort();
+        items
     }
 
-    pub fn compute_unique_id(&self) -> String {
-        let mut sorted_inputs: Vec<_> = self.inputs.iter().collect();
-        sorted_inputs.sort_by_key(|(k, _)| *k);
+    /// Returns true if the recipe consumes `item_id`, as a fixed input or
+    /// as an alternative in a group.
+    pub fn uses_input(&self, item_id: &ItemId) -> bool {
+        self.inputs.contains_key(item_id)
+            || self
+                .any_of
+                .iter()
+                .any(|group| group.items.contains(item_id))
+    }
 
-        let inputs_str: String = sorted_inputs
+    /// Replaces every ID in the recipe with the interner's shared copy.
+    ///
+    /// Fails if two keys of the same count map differ only in case or
+    /// whitespace, since it is unclear whether their counts should add up.
+    pub fn intern_ids(&mut self, interner: &mut IdInterner) -> Result<(), ProductionError> {
+        self.id = interner.intern(self.id.as_str());
+        self.by = interner.intern(self.by.as_str());
+        for machine in &mut self.machine_options {
+            *machine = interner.intern(machine.as_str());
+        }
+        for counts in [&mut self.inputs, &mut self.outputs, &mut self.returns] {
+            let mut keys: Vec<ItemId> = counts.keys().cloned().collect();
+            keys.sort();
+            let mut interned: HashMap<ItemId, u32> = HashMap::with_capacity(counts.len());
+            let mut written_as: HashMap<ItemId, ItemId> = HashMap::with_capacity(counts.len());
+            for id in keys {
+                let canonical: ItemId = interner.intern(id.as_str());
+                if let Some(first) = written_as.insert(canonical.clone(), id.clone()) {
+                    return Err(ProductionError::ParseError(format!(
+                        "recipe '{}' lists both '{}' and '{}', which differ only in case or whitespace",
+                        self.id, first, id
+                    )));
+                }
+                interned.insert(canonical, counts[&id]);
+            }
+            *counts = interned;
+        }
+        for group in &mut self.any_of {
+            for item in &mut group.items {
+                *item = interner.intern(item.as_str());
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns how much of `input_id` one craft consumes after its returns
+    /// and any output of the same item are credited.
+    pub fn net_input(&self, input_id: &ItemId) -> u32 {
+        let given_back = self.returns.get(input_id).copied().unwrap_or(0)
+            + self.outputs.get(input_id).copied().unwrap_or(0);
+        self.inputs
+            .get(input_id)
+            .copied()
+            .unwrap_or(0)
+            .saturating_sub(given_back)
+    }
+
+    /// Returns how much of `item_id` one craft makes beyond what it
+    /// consumes, e.g. 2 for an enrichment recipe taking 1 and giving 3.
+    pub fn net_output(&self, item_id: &ItemId) -> u32 {
+        let made = self.outputs.get(item_id).copied().unwrap_or(0)
+            + self.returns.get(item_id).copied().unwrap_or(0);
+        made.saturating_sub(self.inputs.get(item_id).copied().unwrap_or(0))
+    }
+
+    /// Lists the items other than `item_id` that a craft makes more of than
+    /// it consumes, i.e. its byproducts when planned for `item_id`. Sorted.
+    pub fn net_byproducts(&self, item_id: &ItemId) -> Vec<&ItemId> {
+        let mut items: Vec<&ItemId> = self
+            .outputs
+            .keys()
+            .chain(self.returns.keys())
+            .filter(|id| *id != item_id && self.net_output(id) > 0)
+            .collect();
+        items.sort();
+        items.dedup();
+        items
+    }
+
+    /// Converts the recipe time into seconds.
+    ///
+    /// A per-recipe `time_unit` overrides `default_unit` from the file header.
+    /// `ticks_per_second` is required when the effective unit is ticks.
+    pub fn normalize_time(
+        &mut self,
+        default_unit: TimeUnit,
+        ticks_per_second: Option<f64>,
+    ) -> Result<(), ProductionError> {
+        match self.time_unit.take().unwrap_or(default_unit) {
+            TimeUnit::Seconds => Ok(()),
+            TimeUnit::Ticks => {
+                let rate = ticks_per_second.ok_or_else(|| {
+                    ProductionError::ParseError(format!(
+                        "recipe '{}' uses ticks but ticks_per_second is not set",
+                        self.id
+                    ))
+                })?;
+                self.time /= rate;
+                Ok(())
+            }
+        }
+    }
+
+    /// Returns the machines this recipe was declared to run on.
+    pub fn machine_options(&self) -> &[MachineId] {
+        &self.machine_options
+    }
+
+    /// Splits the recipe into one variant per machine option.
+    ///
+    /// Each variant has `by` set to its machine, so the recipe selector can
+    /// pick the best machine with the same rules used between recipes.
+    pub fn expand_machines(self) -> Vec<Recipe> {
+        self.machine_options
             .iter()
-            .map(|(k, v)| format!("{}:{}", k, v))
+            .map(|machine| Recipe {
+                by: machine.clone(),
+                ..self.clone()
+            })
+            .collect()
+    }
+
+    /// Returns true if the recipe carries `tag`.
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+
+    /// Builds an ID that tells apart recipes differing in anything the
+    /// planner uses, e.g. `origocrust@refining_unit[originium_ore:1]->[origocrust:1]/2s`.
+    /// Returns follow the outputs as `+[...]` when there are any. Expects
+    /// a normalized recipe, so equal recipes written in ticks and in
+    /// seconds get the same ID.
+    pub fn compute_unique_id(&self) -> RecipeUid {
+        fn counts_str(counts: &HashMap<ItemId, u32>) -> String {
+            let mut sorted: Vec<_> = counts.iter().collect();
+            sorted.sort_by_key(|(k, _)| *k);
+            sorted
+                .iter()
+                .map(|(k, v)| format!("{}:{}", k, v))
+                .collect::<Vec<_>>()
+                .join(",")
+        }
+
+        // Alternatives follow the fixed inputs, e.g. `a|b:1`
+        let mut groups: Vec<String> = self
+            .any_of
+            .iter()
+            .map(|group| {
+                let items: Vec<&str> = group.items.iter().map(ItemId::as_str).collect();
+                format!("{}:{}", items.join("|"), group.count)
+            })
+            .collect();
+        groups.sort();
+        let inputs_str = std::iter::once(counts_str(&self.inputs))
+            .filter(|s| !s.is_empty())
+            .chain(groups)
             .collect::<Vec<_>>()
             .join(",");
 
-        format!("{}@{}[{}]", self.id, self.by, inputs_str)
+        let returns_str = if self.returns.is_empty() {
+            String::new()
+        } else {
+            format!("+[{}]", counts_str(&self.returns))
+        };
+
+        format!(
+            "{}@{}[{}]->[{}]{}/{}s",
+            self.id,
+            self.by,
+            inputs_str,
+            counts_str(&self.outputs),
+            returns_str,
+            self.time
+        )
+        .into()
     }
 }
 
@@ -69,16 +365,22 @@ mod tests {
     fn test_normalize_out_field() {
         // carbon from jincao has out=2
         let mut recipe = Recipe {
-            id: "carbon".to_string(),
-            by: "refining_unit".to_string(),
-            time: 2,
+            id: "carbon".into(),
+            by: "refining_unit".into(),
+            machine_options: vec!["refining_unit".into()],
+            time: 2.0,
+            time_unit: None,
             out: Some(2),
             inputs: HashMap::new(),
             outputs: HashMap::new(),
+            returns: HashMap::new(),
+            any_of: Vec::new(),
             is_source: false,
+            max_rate: None,
+            tags: Vec::new(),
         };
 
-        recipe.normalize();
+        recipe.normalize().unwrap();
 
         assert_eq!(recipe.outputs.get("carbon"), Some(&2));
         assert_eq!(recipe.outputs.len(), 1);
@@ -88,62 +390,219 @@ mod tests {
     fn test_normalize_this_keyword() {
         // Test "this" keyword replacement
         let mut recipe = Recipe {
-            id: "origocrust".to_string(),
-            by: "refining_unit".to_string(),
-            time: 2,
+            id: "origocrust".into(),
+            by: "refining_unit".into(),
+            machine_options: vec!["refining_unit".into()],
+            time: 2.0,
+            time_unit: None,
             out: None,
             inputs: HashMap::new(),
-            outputs: vec![("this".to_string(), 1)]
-                .into_iter()
-                .collect(),
+            outputs: vec![("this".into(), 1)].into_iter().collect(),
+            returns: HashMap::new(),
+            any_of: Vec::new(),
             is_source: false,
+            max_rate: None,
+            tags: Vec::new(),
         };
 
-        recipe.normalize();
+        recipe.normalize().unwrap();
 
         assert_eq!(recipe.outputs.get("origocrust"), Some(&1));
         assert_eq!(recipe.outputs.get("this"), None);
         assert_eq!(recipe.outputs.len(), 1);
     }
 
+    #[test]
+    fn test_normalize_conflicting_self_output() {
+        let recipe = |out, outputs: Vec<(&str, u32)>| Recipe {
+            out,
+            outputs: outputs
+                .into_iter()
+                .map(|(id, count)| (id.into(), count))
+                .collect(),
+            ..Recipe::new("carbon", vec!["refining_unit".into()], 2.0)
+        };
+
+        // out = 2 with outputs.carbon = 3 is ambiguous
+        let err = recipe(Some(2), vec![("carbon", 3)])
+            .normalize()
+            .unwrap_err();
+        assert!(matches!(err, ProductionError::ParseError(msg) if msg.contains("carbon")));
+        assert!(
+            recipe(None, vec![("this", 1), ("carbon", 2)])
+                .normalize()
+                .is_err()
+        );
+
+        // Agreeing declarations are fine
+        let mut agreeing = recipe(Some(2), vec![("carbon", 2), ("stone", 1)]);
+        agreeing.normalize().unwrap();
+        assert_eq!(agreeing.outputs.get("carbon"), Some(&2));
+        assert_eq!(agreeing.outputs.len(), 2);
+    }
+
+    #[test]
+    fn test_net_amounts_of_enrichment_recipe() {
+        let mut recipe = Recipe::new_for_test(
+            "origocrust".into(),
+            "refining_unit".into(),
+            2.0,
+            vec![("origocrust".into(), 1), ("originium_ore".into(), 1)]
+                .into_iter()
+                .collect(),
+            vec![("origocrust".into(), 3), ("amethyst_fiber".into(), 1)]
+                .into_iter()
+                .collect(),
+            false,
+        );
+        recipe.returns.insert("canister".into(), 1);
+
+        let crust = ItemId::from("origocrust");
+        assert_eq!(recipe.net_output(&crust), 2);
+        assert_eq!(recipe.net_input(&crust), 0);
+        assert_eq!(recipe.net_input(&"originium_ore".into()), 1);
+        assert_eq!(
+            recipe.net_byproducts(&"amethyst_fiber".into()),
+            vec![&ItemId::from("canister"), &crust]
+        );
+        assert_eq!(
+            recipe.net_byproducts(&crust),
+            vec![&ItemId::from("amethyst_fiber"), &ItemId::from("canister")]
+        );
+    }
+
     #[test]
     fn test_compute_unique_id_deterministic() {
         // amethyst_component recipe with multiple inputs
         let recipe1 = Recipe {
-            id: "amethyst_component".to_string(),
-            by: "gearing_unit".to_string(),
-            time: 10,
+            id: "amethyst_component".into(),
+            by: "gearing_unit".into(),
+            machine_options: vec!["gearing_unit".into()],
+            time: 10.0,
+            time_unit: None,
             out: None,
-            inputs: vec![
-                ("origocrust".to_string(), 5),
-                ("amethyst_fiber".to_string(), 5),
-            ]
-            .into_iter()
-            .collect(),
+            inputs: vec![("origocrust".into(), 5), ("amethyst_fiber".into(), 5)]
+                .into_iter()
+                .collect(),
             outputs: HashMap::new(),
+            returns: HashMap::new(),
+            any_of: Vec::new(),
             is_source: false,
+            max_rate: None,
+            tags: Vec::new(),
         };
 
         // Same recipe with inputs in different order
         let recipe2 = Recipe {
-            id: "amethyst_component".to_string(),
-            by: "gearing_unit".to_string(),
-            time: 10,
+            id: "amethyst_component".into(),
+            by: "gearing_unit".into(),
+            machine_options: vec!["gearing_unit".into()],
+            time: 10.0,
+            time_unit: None,
             out: None,
-            inputs: vec![
-                ("amethyst_fiber".to_string(), 5),
-                ("origocrust".to_string(), 5),
-            ]
-            .into_iter()
-            .collect(),
+            inputs: vec![("amethyst_fiber".into(), 5), ("origocrust".into(), 5)]
+                .into_iter()
+                .collect(),
             outputs: HashMap::new(),
+            returns: HashMap::new(),
+            any_of: Vec::new(),
             is_source: false,
+            max_rate: None,
+            tags: Vec::new(),
         };
 
         let id1 = recipe1.compute_unique_id();
         let id2 = recipe2.compute_unique_id();
 
         assert_eq!(id1, id2);
-        assert_eq!(id1, "amethyst_component@gearing_unit[amethyst_fiber:5,origocrust:5]");
+        assert_eq!(
+            id1,
+            "amethyst_component@gearing_unit[amethyst_fiber:5,origocrust:5]->[]/10s"
+        );
+    }
+
+    #[test]
+    fn test_compute_unique_id_covers_returns_and_groups() {
+        let group = |items: &[&str]| InputGroup {
+            items: items.iter().map(|item| (*item).into()).collect(),
+            count: 1,
+        };
+        let mut recipe = Recipe::new_for_test(
+            "origocrust".into(),
+            "refining_unit".into(),
+            2.0,
+            HashMap::new(),
+            HashMap::new(),
+            false,
+        );
+        recipe.any_of = vec![group(&["originium_ore"]), group(&["amethyst_ore"])];
+        let mut reordered = recipe.clone();
+        reordered.any_of.reverse();
+        let mut returning = recipe.clone();
+        returning.returns.insert("canister".into(), 1);
+
+        assert_eq!(recipe.compute_unique_id(), reordered.compute_unique_id());
+        assert_eq!(
+            returning.compute_unique_id(),
+            "origocrust@refining_unit[amethyst_ore:1,originium_ore:1]->[]+[canister:1]/2s"
+        );
+    }
+
+    #[test]
+    fn test_normalize_time_ticks() {
+        let mut recipe = Recipe::new_for_test(
+            "origocrust".into(),
+            "refining_unit".into(),
+            40.0,
+            HashMap::new(),
+            HashMap::new(),
+            false,
+        );
+
+        recipe.normalize_time(TimeUnit::Ticks, Some(20.0)).unwrap();
+
+        assert_eq!(recipe.time, 2.0);
+    }
+
+    #[test]
+    fn test_normalize_time_ticks_without_rate() {
+        let mut recipe = Recipe::new_for_test(
+            "origocrust".into(),
+            "refining_unit".into(),
+            40.0,
+            HashMap::new(),
+            HashMap::new(),
+            false,
+        );
+
+        assert!(recipe.normalize_time(TimeUnit::Ticks, None).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "data-files")]
+    fn test_expand_machines_unique_ids() {
+        let recipes_toml = r#"
+id = "originium_ore"
+by = ["portable_originium_rig", "electric_mining_rig"]
+time = 2
+out = 1
+"#;
+
+        let recipe: Recipe = toml::from_str(recipes_toml).unwrap();
+        assert_eq!(
+            recipe.machine_options(),
+            ["portable_originium_rig", "electric_mining_rig"]
+        );
+
+        let variants = recipe.expand_machines();
+        let ids: Vec<RecipeUid> = variants.iter().map(|r| r.compute_unique_id()).collect();
+
+        assert_eq!(
+            ids,
+            vec![
+                "originium_ore@portable_originium_rig[]->[]/2s",
+                "originium_ore@electric_mining_rig[]->[]/2s",
+            ]
+        );
     }
 }
@@ -1,74 +1,834 @@
-use crate::models::ProductionNode;
+use super::format::{RATE_PRECISION, format_decimal, format_power, format_rate};
+use crate::i18n::{IdResolver, Localizer, NameResolver};
+use crate::models::{AmountKind, ItemId, ProductionNode, RoundingPolicy};
+use crate::planner::{PlanSummary, PlanWarnings};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::io::{self, Write};
 
-fn print_node_recursive(node: &ProductionNode, prefix: &str, is_last: bool) {
-    let connector = if is_last { "└── " } else { "├── " };
-    let child_prefix = if is_last { "    " } else { "│   " };
+/// Order of the raw material and machine lists in the summary.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SummarySort {
+    /// Alphabetical by displayed name.
+    #[default]
+    Name,
+    /// Largest count first, ties by name.
+    Count,
+}
+
+/// Characters used to draw the production tree.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TreeStyle {
+    /// Box-drawing characters, e.g. `├──` and `└──`.
+    #[default]
+    Unicode,
+    /// Plain ASCII, e.g. `+--` and `\--`, for terminals that render box
+    /// drawing poorly.
+    Ascii,
+}
+
+impl TreeStyle {
+    /// Drawn before a node, depending on whether it is the last input.
+    fn connector(self, is_last: bool) -> &'static str {
+        match (self, is_last) {
+            (TreeStyle::Unicode, false) => "├── ",
+            (TreeStyle::Unicode, true) => "└── ",
+            (TreeStyle::Ascii, false) => "+-- ",
+            (TreeStyle::Ascii, true) => "\\-- ",
+        }
+    }
+
+    /// Continues the prefix below a node for its inputs.
+    fn child_prefix(self, is_last: bool) -> &'static str {
+        match (self, is_last) {
+            (_, true) => "    ",
+            (TreeStyle::Unicode, false) => "│   ",
+            (TreeStyle::Ascii, false) => "|   ",
+        }
+    }
+}
+
+/// Settings for `write_summary`. The default shows IDs and English UI
+/// strings.
+#[derive(Debug, Clone, Copy)]
+pub struct SummaryOptions<'a> {
+    /// Provides the UI strings, such as the status markers.
+    pub localizer: Option<&'a Localizer>,
+    /// Names the items and machines.
+    pub names: &'a dyn NameResolver,
+    pub sort: SummarySort,
+    /// Whether amounts are rates or batch totals, for their suffix.
+    pub amounts: AmountKind,
+    /// How machine counts are rounded in the machine totals.
+    pub rounding: RoundingPolicy,
+    pub tree_style: TreeStyle,
+    /// Deposits needed per raw material, shown after its total, see
+    /// `PlanWarnings::deposits_needed`.
+    pub deposits: Option<&'a HashMap<ItemId, u32>>,
+}
+
+impl Default for SummaryOptions<'_> {
+    fn default() -> Self {
+        SummaryOptions {
+            localizer: None,
+            names: &IdResolver,
+            sort: SummarySort::default(),
+            amounts: AmountKind::default(),
+            rounding: RoundingPolicy::default(),
+            tree_style: TreeStyle::default(),
+            deposits: None,
+        }
+    }
+}
+
+impl SummaryOptions<'_> {
+    /// Localized status marker from the UI strings, or `default` without a
+    /// localizer.
+    fn status_label(&self, key: &str, default: &str) -> String {
+        let label = self
+            .localizer
+            .map_or(default.to_string(), |l| l.get_ui(key));
+        format!("[{}]", label)
+    }
+}
 
-    let node_info = match node {
+/// Formats the tree line for a single node. An item produced at several
+/// places also shows its total over all of them, from `merged_totals`.
+fn node_info(
+    node: &ProductionNode,
+    options: &SummaryOptions,
+    merged_totals: &HashMap<ItemId, u32>,
+) -> String {
+    match node {
+        ProductionNode::Resolved { item_id, .. } if node.is_virtual_root() => {
+            options.names.name_of(item_id.as_str())
+        }
+        ProductionNode::Resolved {
+            item_id,
+            machine_id,
+            machine_count: 0,
+            ..
+        } => format!(
+            "{} x{} [{}]",
+            options.names.name_of(item_id.as_str()),
+            node.display_amount(RATE_PRECISION),
+            options.names.name_of(machine_id.as_str())
+        ),
         ProductionNode::Resolved {
             item_id,
             machine_id,
-            amount,
-            machine_count,
             ..
         } => {
+            let merged = merged_totals
+                .get(item_id)
+                .map_or(String::new(), |total| format!(" of {} total", total));
             format!(
-                "{} x{} [{} x{}]",
-                item_id, amount, machine_id, machine_count
+                "{} x{}{} [{} x{}]",
+                options.names.name_of(item_id.as_str()),
+                node.display_amount(RATE_PRECISION),
+                merged,
+                options.names.name_of(machine_id.as_str()),
+                node.machine_count_label()
             )
         }
         ProductionNode::Unresolved { item_id, .. } => {
-            format!("{} [MISSING RECIPE]", item_id)
+            format!(
+                "{} {}",
+                options.names.name_of(item_id.as_str()),
+                options.status_label("missing_recipe", "MISSING RECIPE")
+            )
         }
-    };
+        ProductionNode::External { item_id, .. } => {
+            format!(
+                "{} x{} {}",
+                options.names.name_of(item_id.as_str()),
+                node.display_amount(RATE_PRECISION),
+                options.status_label("external_supply", "EXTERNAL")
+            )
+        }
+        ProductionNode::Cycle { item_id, .. } => {
+            format!(
+                "{} x{} {}",
+                options.names.name_of(item_id.as_str()),
+                node.display_amount(RATE_PRECISION),
+                options.status_label("cycle_break", "CYCLE")
+            )
+        }
+        ProductionNode::Shared { item_id, .. } => {
+            format!(
+                "{} x{} {}",
+                options.names.name_of(item_id.as_str()),
+                node.display_amount(RATE_PRECISION),
+                options.status_label("shared_in_plan", "BUILT ELSEWHERE")
+            )
+        }
+    }
+}
 
-    println!("{}{}{}", prefix, connector, node_info);
+fn write_node_recursive<W: Write>(
+    out: &mut W,
+    node: &ProductionNode,
+    options: &SummaryOptions,
+    merged_totals: &HashMap<ItemId, u32>,
+    prefix: &str,
+    is_last: bool,
+) -> io::Result<()> {
+    let connector = options.tree_style.connector(is_last);
+    let child_prefix = options.tree_style.child_prefix(is_last);
+
+    writeln!(
+        out,
+        "{}{}{}",
+        prefix,
+        connector,
+        node_info(node, options, merged_totals)
+    )?;
 
     if let ProductionNode::Resolved { inputs, .. } = node {
         let count = inputs.len();
         for (i, child) in inputs.iter().enumerate() {
             let is_last_child = i == count - 1;
-            print_node_recursive(child, &format!("{}{}", prefix, child_prefix), is_last_child);
+            write_node_recursive(
+                out,
+                child,
+                options,
+                merged_totals,
+                &format!("{}{}", prefix, child_prefix),
+                is_last_child,
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Labels the totals and orders them for display.
+fn sorted_totals<K: AsRef<str>, T: PartialOrd + Copy>(
+    totals: HashMap<K, T>,
+    label: impl Fn(&str) -> String,
+    sort: SummarySort,
+) -> Vec<(String, T)> {
+    let mut rows: Vec<(String, String, T)> = totals
+        .into_iter()
+        .map(|(id, count)| (label(id.as_ref()), id.as_ref().to_string(), count))
+        .collect();
+
+    rows.sort_by(|a, b| {
+        let by_name = a.0.cmp(&b.0).then_with(|| a.1.cmp(&b.1));
+        match sort {
+            SummarySort::Name => by_name,
+            SummarySort::Count => {
+                b.2.partial_cmp(&a.2)
+                    .unwrap_or(Ordering::Equal)
+                    .then(by_name)
+            }
         }
+    });
+
+    rows.into_iter()
+        .map(|(label, _, count)| (label, count))
+        .collect()
+}
+
+/// Approximates the terminal columns taken by `text`, counting East Asian
+/// wide characters (kana, CJK, full-width forms) as two.
+fn display_width(text: &str) -> usize {
+    text.chars()
+        .map(|c| match c as u32 {
+            0x1100..=0x115F
+            | 0x2E80..=0xA4CF
+            | 0xAC00..=0xD7A3
+            | 0xF900..=0xFAFF
+            | 0xFE30..=0xFE4F
+            | 0xFF00..=0xFF60
+            | 0xFFE0..=0xFFE6 => 2,
+            _ => 1,
+        })
+        .sum()
+}
+
+/// Writes labeled counts with the counts right-aligned in one column,
+/// each followed by `suffix` and its own note.
+fn write_totals<W: Write, T: Display>(
+    out: &mut W,
+    rows: &[(String, T, String)],
+    suffix: &str,
+) -> io::Result<()> {
+    let label_width = rows
+        .iter()
+        .map(|(label, _, _)| display_width(label))
+        .max()
+        .unwrap_or(0);
+    let count_width = rows
+        .iter()
+        .map(|(_, count, _)| count.to_string().chars().count())
+        .max()
+        .unwrap_or(0);
+
+    for (label, count, note) in rows {
+        let padding = label_width - display_width(label);
+        writeln!(
+            out,
+            " - {}{}  {:>width$}{}{}",
+            label,
+            " ".repeat(padding),
+            count,
+            suffix,
+            note,
+            width = count_width
+        )?;
     }
+
+    Ok(())
 }
 
-pub fn print_summary(node: &ProductionNode) {
-    println!("--- Production Line Tree ---");
+/// Writes the production tree and totals to `out`.
+pub fn write_summary<W: Write>(
+    out: &mut W,
+    node: &ProductionNode,
+    options: &SummaryOptions,
+) -> io::Result<()> {
+    writeln!(out, "--- Production Line Tree ---")?;
 
+    let merged_totals = node.repeated_intermediate_totals();
     match node {
-        ProductionNode::Resolved {
-            item_id,
-            machine_id,
-            amount,
-            machine_count,
-            inputs,
-            ..
-        } => {
-            println!(
-                "{} x{} [{} x{}]",
-                item_id, amount, machine_id, machine_count
-            );
+        ProductionNode::Resolved { inputs, .. } => {
+            writeln!(out, "{}", node_info(node, options, &merged_totals))?;
 
             let count = inputs.len();
             for (i, child) in inputs.iter().enumerate() {
-                print_node_recursive(child, "", i == count - 1);
+                write_node_recursive(out, child, options, &merged_totals, "", i == count - 1)?;
+            }
+        }
+        _ => writeln!(out, "Invalid root node")?,
+    }
+
+    let item_label = |id: &str| options.names.name_of(id);
+    let machine_label = |id: &str| options.names.name_of(id);
+
+    let number = |value: f64| match options.localizer {
+        Some(localizer) => format_rate(value, RATE_PRECISION, localizer),
+        None => format_decimal(value, RATE_PRECISION),
+    };
+
+    writeln!(out, "\nTotal Raw Materials Needed:")?;
+    let materials_with_deposits: HashMap<ItemId, (u32, Option<u32>)> = node
+        .total_source_materials()
+        .into_iter()
+        .map(|(item_id, amount)| {
+            let deposits = options
+                .deposits
+                .and_then(|deposits| deposits.get(&item_id).copied());
+            (item_id, (amount, deposits))
+        })
+        .collect();
+    let materials: Vec<(String, String, String)> =
+        sorted_totals(materials_with_deposits, item_label, options.sort)
+            .into_iter()
+            .map(|(label, (amount, deposits))| {
+                let note = deposits
+                    .map(|deposits| format!("  ({} deposits)", deposits))
+                    .unwrap_or_default();
+                (label, number(amount as f64), note)
+            })
+            .collect();
+    write_totals(out, &materials, options.amounts.suffix())?;
+
+    writeln!(out, "\nTotal Machines Needed ({}):", options.rounding)?;
+    let machines: Vec<(String, String, String)> = sorted_totals(
+        node.total_machines_with(options.rounding),
+        machine_label,
+        options.sort,
+    )
+    .into_iter()
+    .filter(|(_, count)| *count > 0.0)
+    .map(|(label, count)| (label, number(count), String::new()))
+    .collect();
+    write_totals(out, &machines, "")?;
+
+    writeln!(
+        out,
+        "\nTotal Power Needed: {}",
+        format_power(node.total_power() as u64)
+    )?;
+    writeln!(
+        out,
+        "Average Power Draw: {}",
+        number(node.total_effective_power())
+    )?;
+
+    writeln!(
+        out,
+        "\nOverall Line Utilization Rate: {} %",
+        node.utilization()
+    )
+}
+
+/// Writes the machine and power totals per recipe tag, if any recipe in
+/// the plan is tagged.
+pub fn write_tag_breakdown<W: Write>(out: &mut W, summary: &PlanSummary) -> io::Result<()> {
+    if summary.by_tag.is_empty() {
+        return Ok(());
+    }
+
+    writeln!(out, "\nMachines and Power by Tag:")?;
+    for (tag, totals) in &summary.by_tag {
+        writeln!(
+            out,
+            " - {}: {} machines, {} power",
+            tag,
+            totals.machines,
+            format_power(totals.power as u64)
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Prints the production tree and totals, with names from `names` and UI
+/// strings from `localizer` when given.
+pub fn print_summary(
+    node: &ProductionNode,
+    localizer: Option<&Localizer>,
+    names: &dyn NameResolver,
+) {
+    let options = SummaryOptions {
+        localizer,
+        names,
+        ..Default::default()
+    };
+    let _ = write_summary(&mut io::stdout().lock(), node, &options);
+}
+
+pub fn print_warnings(warnings: &PlanWarnings) {
+    if warnings.is_empty() {
+        return;
+    }
+
+    println!("\nWarnings:");
+    for warning in warnings {
+        println!(" - {}", warning);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "data-files")]
+    use crate::i18n::LocalizedResolver;
+    #[cfg(feature = "data-files")]
+    use crate::models::MachineId;
+    use crate::test_fixtures::{self, on_machines};
+
+    fn resolved(
+        item_id: &str,
+        machine_id: &str,
+        machine_count: u32,
+        amount: u32,
+        inputs: Vec<ProductionNode>,
+    ) -> ProductionNode {
+        let node = test_fixtures::resolved(item_id, amount, inputs);
+        on_machines(node, machine_id, machine_count)
+    }
+
+    fn sample_plan() -> ProductionNode {
+        resolved(
+            "amethyst_component",
+            "gearing_unit",
+            1,
+            10,
+            vec![
+                resolved("originium_ore", "electric_mining_rig", 1, 60, vec![]),
+                resolved("amethyst_ore", "electric_mining_rig", 3, 120, vec![]),
+                resolved("ferrium_ore", "portable_originium_rig", 3, 20, vec![]),
+            ],
+        )
+    }
+
+    fn section<'a>(output: &'a str, title: &str) -> Vec<&'a str> {
+        output
+            .split(title)
+            .nth(1)
+            .unwrap()
+            .lines()
+            .skip(1)
+            .take_while(|line| line.starts_with(" - "))
+            .collect()
+    }
+
+    fn render(options: &SummaryOptions) -> String {
+        let mut out = Vec::new();
+        write_summary(&mut out, &sample_plan(), options).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_totals_sorted_by_name() {
+        let output = render(&SummaryOptions::default());
+
+        assert_eq!(
+            section(&output, "Total Raw Materials Needed:"),
+            vec![
+                " - amethyst_ore   120 (per minute)",
+                " - ferrium_ore     20 (per minute)",
+                " - originium_ore   60 (per minute)",
+            ]
+        );
+        assert_eq!(
+            section(&output, "Total Machines Needed"),
+            vec![
+                " - electric_mining_rig     4",
+                " - gearing_unit            1",
+                " - portable_originium_rig  3",
+            ]
+        );
+        // Rendering is deterministic across runs
+        assert_eq!(output, render(&SummaryOptions::default()));
+    }
+
+    #[test]
+    fn test_totals_sorted_by_count() {
+        let output = render(&SummaryOptions {
+            sort: SummarySort::Count,
+            ..Default::default()
+        });
+
+        assert_eq!(
+            section(&output, "Total Raw Materials Needed:"),
+            vec![
+                " - amethyst_ore   120 (per minute)",
+                " - originium_ore   60 (per minute)",
+                " - ferrium_ore     20 (per minute)",
+            ]
+        );
+        assert_eq!(
+            section(&output, "Total Machines Needed"),
+            vec![
+                " - electric_mining_rig     4",
+                " - portable_originium_rig  3",
+                " - gearing_unit            1",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_names_come_from_resolver() {
+        struct Shouting;
+
+        impl NameResolver for Shouting {
+            fn name_of(&self, id: &str) -> String {
+                id.to_uppercase()
             }
         }
-        _ => println!("Invalid root node"),
+
+        let output = render(&SummaryOptions {
+            names: &Shouting,
+            ..Default::default()
+        });
+
+        assert_eq!(
+            section(&output, "Total Machines Needed"),
+            vec![
+                " - ELECTRIC_MINING_RIG     4",
+                " - GEARING_UNIT            1",
+                " - PORTABLE_ORIGINIUM_RIG  3",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_batch_totals_are_not_per_minute() {
+        let output = render(&SummaryOptions {
+            amounts: AmountKind::Total,
+            ..Default::default()
+        });
+
+        assert_eq!(
+            section(&output, "Total Raw Materials Needed:")[0],
+            " - amethyst_ore   120 (total)"
+        );
+        assert!(!output.contains("per minute"));
+    }
+
+    #[test]
+    fn test_virtual_root_shows_only_its_label() {
+        let root = ProductionNode::virtual_root("bootstrap", vec![sample_plan()]);
+        let mut out = Vec::new();
+        write_summary(&mut out, &root, &SummaryOptions::default()).unwrap();
+        let output = String::from_utf8(out).unwrap();
+
+        assert!(output.contains("\nbootstrap\n"));
+        // The root has no machine to count
+        assert_eq!(
+            section(&output, "Total Machines Needed"),
+            vec![
+                " - electric_mining_rig     4",
+                " - gearing_unit            1",
+                " - portable_originium_rig  3",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_raw_materials_show_deposits_needed() {
+        let deposits = HashMap::from([(ItemId::from("amethyst_ore"), 2)]);
+        let output = render(&SummaryOptions {
+            deposits: Some(&deposits),
+            ..Default::default()
+        });
+
+        assert_eq!(
+            section(&output, "Total Raw Materials Needed:"),
+            vec![
+                " - amethyst_ore   120 (per minute)  (2 deposits)",
+                " - ferrium_ore     20 (per minute)",
+                " - originium_ore   60 (per minute)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_machine_totals_show_rounding_policy() {
+        let output = render(&SummaryOptions {
+            rounding: RoundingPolicy::Exact,
+            ..Default::default()
+        });
+
+        assert!(output.contains("Total Machines Needed (exact):"));
+        assert_eq!(
+            section(&output, "Total Machines Needed"),
+            vec![
+                " - electric_mining_rig     4",
+                " - gearing_unit            1",
+                " - portable_originium_rig  3",
+            ]
+        );
+    }
+
+    /// Names with `localizer`, knowing the machines of `sample_plan`.
+    #[cfg(feature = "data-files")]
+    fn localized_names(localizer: &Localizer) -> LocalizedResolver<'_> {
+        LocalizedResolver {
+            localizer,
+            machine_ids: [
+                "electric_mining_rig",
+                "gearing_unit",
+                "portable_originium_rig",
+            ]
+            .into_iter()
+            .map(MachineId::from)
+            .collect(),
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "data-files")]
+    fn test_totals_use_localized_names() {
+        let localizer = Localizer::new(
+            r#"
+[items]
+amethyst_ore = "Zeta Amethyst"
+ferrium_ore = "Alpha Ferrium"
+originium_ore = "Mid Originium"
+"#,
+        )
+        .unwrap();
+
+        let output = render(&SummaryOptions {
+            localizer: Some(&localizer),
+            names: &localized_names(&localizer),
+            sort: SummarySort::Name,
+            ..Default::default()
+        });
+
+        assert_eq!(
+            section(&output, "Total Raw Materials Needed:"),
+            vec![
+                " - Alpha Ferrium   20 (per minute)",
+                " - Mid Originium   60 (per minute)",
+                " - Zeta Amethyst  120 (per minute)",
+            ]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "data-files")]
+    fn test_tree_uses_localized_names() {
+        let localizer = Localizer::new(
+            r#"
+[items]
+amethyst_component = "Amethyst Component"
+originium_ore = "Originium Ore"
+
+[machines]
+gearing_unit = "Gearing Unit"
+"#,
+        )
+        .unwrap();
+
+        let output = render(&SummaryOptions {
+            localizer: Some(&localizer),
+            names: &localized_names(&localizer),
+            ..Default::default()
+        });
+        let tree: Vec<&str> = output.lines().skip(1).take(4).collect();
+
+        assert_eq!(
+            tree,
+            vec![
+                "Amethyst Component x10 [Gearing Unit x1]",
+                "├── Originium Ore x60 [electric_mining_rig x1]",
+                "├── amethyst_ore x120 [electric_mining_rig x3]",
+                "└── ferrium_ore x20 [portable_originium_rig x3]",
+            ]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "data-files")]
+    fn test_localized_summary_matches_unlocalized_layout() {
+        let localizer = Localizer::new(
+            r#"
+[items]
+amethyst_component = "紫晶部品"
+amethyst_ore = "紫晶鉱石"
+ferrium_ore = "赤鉄鉱石"
+originium_ore = "源石鉱石"
+
+[machines]
+electric_mining_rig = "電動採掘機"
+gearing_unit = "ギア加工機"
+portable_originium_rig = "携帯源石採掘機"
+
+[ui]
+missing_recipe = "レシピなし"
+"#,
+        )
+        .unwrap();
+        let mut plan = sample_plan();
+        if let ProductionNode::Resolved { inputs, .. } = &mut plan {
+            inputs.push(ProductionNode::Unresolved {
+                item_id: "origocrust".into(),
+                amount: 5,
+            });
+        }
+        let names = localized_names(&localizer);
+        let render_plan = |localizer: Option<&Localizer>, names: &dyn NameResolver| {
+            let mut out = Vec::new();
+            let options = SummaryOptions {
+                localizer,
+                names,
+                ..Default::default()
+            };
+            write_summary(&mut out, &plan, &options).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+
+        let plain = render_plan(None, &IdResolver);
+        let localized = render_plan(Some(&localizer), &names);
+
+        assert!(plain.contains("├── originium_ore x60 [electric_mining_rig x1]"));
+        assert!(plain.contains("└── origocrust [MISSING RECIPE]"));
+        assert!(localized.contains("├── 源石鉱石 x60 [電動採掘機 x1]"));
+        // Untranslated IDs are shown as they are
+        assert!(localized.contains("└── origocrust [レシピなし]"));
+        assert_eq!(
+            section(&localized, "Total Machines Needed"),
+            vec![
+                " - ギア加工機      1",
+                " - 携帯源石採掘機  3",
+                " - 電動採掘機      4",
+            ]
+        );
+        // Same layout, only the names differ
+        assert_eq!(plain.lines().count(), localized.lines().count());
+        assert!(!localized.contains("electric_mining_rig"));
     }
 
-    println!("\nTotal Raw Materials Needed:");
-    for (item, count) in node.total_source_materials().iter() {
-        println!(" - {}: {} (per minute)", item, count);
+    #[test]
+    fn test_tree_shows_merged_total_of_repeated_item() {
+        let plan = resolved(
+            "amethyst_component",
+            "gearing_unit",
+            1,
+            10,
+            vec![
+                resolved("origocrust", "refining_unit", 1, 5, vec![]),
+                resolved(
+                    "origocrust_powder",
+                    "shredding_unit",
+                    1,
+                    7,
+                    vec![resolved("origocrust", "refining_unit", 1, 7, vec![])],
+                ),
+            ],
+        );
+
+        let mut out = Vec::new();
+        write_summary(&mut out, &plan, &SummaryOptions::default()).unwrap();
+        let output = String::from_utf8(out).unwrap();
+        let tree: Vec<&str> = output.lines().skip(1).take(4).collect();
+
+        assert_eq!(
+            tree,
+            vec![
+                "amethyst_component x10 [gearing_unit x1]",
+                "├── origocrust x5 of 12 total [refining_unit x1]",
+                "└── origocrust_powder x7 [shredding_unit x1]",
+                "    └── origocrust x7 of 12 total [refining_unit x1]",
+            ]
+        );
     }
 
-    println!("\nTotal Machines Needed:");
-    for (machine, count) in node.total_machines() {
-        println!(" - {}: {}", machine, count);
+    #[test]
+    fn test_ascii_tree_style() {
+        let mut plan = sample_plan();
+        if let ProductionNode::Resolved { inputs, .. } = &mut plan {
+            inputs[2] = resolved(
+                "ferrium_ore",
+                "portable_originium_rig",
+                3,
+                20,
+                vec![resolved("carbon", "refining_unit", 1, 10, vec![])],
+            );
+            inputs.push(resolved("origocrust", "refining_unit", 1, 5, vec![]));
+        }
+
+        let mut out = Vec::new();
+        let options = SummaryOptions {
+            tree_style: TreeStyle::Ascii,
+            ..Default::default()
+        };
+        write_summary(&mut out, &plan, &options).unwrap();
+        let output = String::from_utf8(out).unwrap();
+
+        assert!(output.is_ascii(), "{}", output);
+        let tree: Vec<&str> = output.lines().skip(1).take(6).collect();
+        assert_eq!(
+            tree,
+            vec![
+                "amethyst_component x10 [gearing_unit x1]",
+                "+-- originium_ore x60 [electric_mining_rig x1]",
+                "+-- amethyst_ore x120 [electric_mining_rig x3]",
+                "+-- ferrium_ore x20 [portable_originium_rig x3]",
+                "|   \\-- carbon x10 [refining_unit x1]",
+                "\\-- origocrust x5 [refining_unit x1]",
+            ]
+        );
     }
 
-    println!("\nTotal Power Needed: {}", node.total_power());
+    #[test]
+    fn test_tree_shows_machine_blocks() {
+        let mut node = resolved("origocrust", "refining_unit", 20, 600, vec![]);
+        if let ProductionNode::Resolved { blocks, .. } = &mut node {
+            *blocks = vec![8, 8, 4];
+        }
+
+        let mut out = Vec::new();
+        write_summary(&mut out, &node, &SummaryOptions::default()).unwrap();
+        let output = String::from_utf8(out).unwrap();
 
-    println!("\nOverall Line Utilization Rate: {} %", node.utilization());
+        assert!(output.contains("origocrust x600 [refining_unit x20 (8+8+4)]"));
+    }
 }
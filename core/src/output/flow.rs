@@ -0,0 +1,365 @@
+//! Layered layout of a production plan as a flow (Sankey-style) diagram.
+
+use crate::models::{ItemId, ProductionNode};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Width of a node bar.
+pub const FLOW_NODE_WIDTH: f64 = 16.0;
+/// Horizontal distance between the left edges of adjacent layers.
+pub const FLOW_LAYER_SPACING: f64 = 160.0;
+/// Vertical gap between nodes in the same layer.
+pub const FLOW_NODE_GAP: f64 = 12.0;
+/// Height of the tallest layer's nodes, excluding gaps.
+pub const FLOW_MAX_LAYER_HEIGHT: f64 = 360.0;
+/// Minimum drawn height of a node or ribbon.
+const MIN_THICKNESS: f64 = 2.0;
+
+/// An item in the diagram, aggregated over every place it appears in the plan.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlowNode {
+    pub item_id: ItemId,
+    /// Total amount produced per time window.
+    pub amount: u32,
+    pub machine_count: u32,
+    /// Distance from the target; the target is layer 0.
+    pub layer: usize,
+    pub x: f64,
+    pub y: f64,
+    pub height: f64,
+}
+
+/// A ribbon carrying an item from its producer to a consumer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlowLink {
+    /// Index of the producing node.
+    pub source: usize,
+    /// Index of the consuming node.
+    pub target: usize,
+    pub amount: u32,
+    pub source_y: f64,
+    pub target_y: f64,
+    pub thickness: f64,
+}
+
+/// A laid-out flow diagram. Raw materials are on the left and the target
+/// on the right.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FlowDiagram {
+    pub nodes: Vec<FlowNode>,
+    pub links: Vec<FlowLink>,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl FlowDiagram {
+    /// Returns the SVG path of a ribbon's center line, to be stroked with
+    /// the link's thickness.
+    pub fn link_path(&self, link: &FlowLink) -> String {
+        let x0 = self.nodes[link.source].x + FLOW_NODE_WIDTH;
+        let x1 = self.nodes[link.target].x;
+        let mid = (x0 + x1) / 2.0;
+        format!(
+            "M{:.1},{:.1} C{:.1},{:.1} {:.1},{:.1} {:.1},{:.1}",
+            x0, link.source_y, mid, link.source_y, mid, link.target_y, x1, link.target_y
+        )
+    }
+
+    /// Returns the node and every node feeding into it, directly or not.
+    pub fn upstream(&self, index: usize) -> HashSet<usize> {
+        let mut found = HashSet::new();
+        let mut pending = vec![index];
+        while let Some(current) = pending.pop() {
+            if found.insert(current) {
+                pending.extend(
+                    self.links
+                        .iter()
+                        .filter(|link| link.target == current)
+                        .map(|link| link.source),
+                );
+            }
+        }
+        found
+    }
+}
+
+#[derive(Default)]
+struct ItemTotals {
+    amount: u32,
+    machine_count: u32,
+}
+
+fn collect(
+    node: &ProductionNode,
+    items: &mut BTreeMap<ItemId, ItemTotals>,
+    edges: &mut BTreeMap<(ItemId, ItemId), u32>,
+) {
+    let (item_id, amount, machine_count, inputs) = match node {
+        ProductionNode::Resolved {
+            item_id,
+            amount,
+            machine_count,
+            inputs,
+            ..
+        } => (item_id, *amount, *machine_count, inputs.as_slice()),
+        ProductionNode::Unresolved { item_id, amount }
+        | ProductionNode::External { item_id, amount } => (item_id, *amount, 0, [].as_slice()),
+        // Cycle breaks point back up the tree and would form a loop;
+        // shared items are counted where they are built
+        ProductionNode::Cycle { .. } | ProductionNode::Shared { .. } => return,
+    };
+
+    let totals = items.entry(item_id.clone()).or_default();
+    totals.amount += amount;
+    totals.machine_count += machine_count;
+
+    for child in inputs {
+        if let ProductionNode::Resolved {
+            item_id: child_id,
+            amount: child_amount,
+            ..
+        }
+        | ProductionNode::Unresolved {
+            item_id: child_id,
+            amount: child_amount,
+        }
+        | ProductionNode::External {
+            item_id: child_id,
+            amount: child_amount,
+        } = child
+        {
+            *edges
+                .entry((child_id.clone(), item_id.clone()))
+                .or_insert(0) += child_amount;
+        }
+        collect(child, items, edges);
+    }
+}
+
+/// Aggregates a production tree by item and lays it out in layers.
+///
+/// Each item sits one layer further left than its furthest consumer, so
+/// every ribbon flows from left to right. Node and ribbon heights are
+/// proportional to the amounts they carry.
+pub fn layout_flow(root: &ProductionNode) -> FlowDiagram {
+    let mut items = BTreeMap::new();
+    let mut edges = BTreeMap::new();
+    collect(root, &mut items, &mut edges);
+
+    if items.is_empty() {
+        return FlowDiagram::default();
+    }
+
+    let index: HashMap<&str, usize> = items
+        .keys()
+        .enumerate()
+        .map(|(i, id)| (id.as_str(), i))
+        .collect();
+    let edge_list: Vec<(usize, usize, u32)> = edges
+        .iter()
+        .map(|((source, target), amount)| (index[source.as_str()], index[target.as_str()], *amount))
+        .collect();
+
+    // Longest distance from the target. An item made by different recipes
+    // in different branches can close a loop in the aggregated graph, so
+    // relaxing stops after one round per node; a loop's ribbons then flow
+    // right to left.
+    let mut layers = vec![0usize; items.len()];
+    for _ in 0..items.len() {
+        let mut changed = false;
+        for &(source, target, _) in &edge_list {
+            if layers[source] < layers[target] + 1 {
+                layers[source] = layers[target] + 1;
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+    let max_layer = layers.iter().copied().max().unwrap_or(0);
+
+    // Scale so the layer carrying the most moves fits the maximum height
+    let mut layer_totals = vec![0u32; max_layer + 1];
+    for (totals, &layer) in items.values().zip(&layers) {
+        layer_totals[layer] += totals.amount;
+    }
+    let busiest = layer_totals.iter().copied().max().unwrap_or(0).max(1);
+    let unit = FLOW_MAX_LAYER_HEIGHT / busiest as f64;
+
+    let mut nodes: Vec<FlowNode> = Vec::with_capacity(items.len());
+    let mut layer_cursors = vec![0.0f64; max_layer + 1];
+    for ((item_id, totals), &layer) in items.into_iter().zip(&layers) {
+        let height = (totals.amount as f64 * unit).max(MIN_THICKNESS);
+        let y = layer_cursors[layer];
+        layer_cursors[layer] += height + FLOW_NODE_GAP;
+
+        nodes.push(FlowNode {
+            item_id,
+            amount: totals.amount,
+            machine_count: totals.machine_count,
+            layer,
+            x: (max_layer - layer) as f64 * FLOW_LAYER_SPACING,
+            y,
+            height,
+        });
+    }
+
+    // Stack ribbons along each node's edges in the order they are listed.
+    // A node can take in more than it makes (e.g. 2 ore per ingot), so
+    // each side is squeezed to fit the node's height.
+    let thickness_of = |amount: u32| (amount as f64 * unit).max(MIN_THICKNESS);
+    let mut out_totals = vec![0.0f64; nodes.len()];
+    let mut in_totals = vec![0.0f64; nodes.len()];
+    for &(source, target, amount) in &edge_list {
+        out_totals[source] += thickness_of(amount);
+        in_totals[target] += thickness_of(amount);
+    }
+    let fit = |node: &FlowNode, total: f64| (node.height / total).min(1.0);
+
+    let mut out_offsets = vec![0.0f64; nodes.len()];
+    let mut in_offsets = vec![0.0f64; nodes.len()];
+    let links = edge_list
+        .into_iter()
+        .map(|(source, target, amount)| {
+            let thickness = thickness_of(amount);
+            let source_y = nodes[source].y
+                + (out_offsets[source] + thickness / 2.0) * fit(&nodes[source], out_totals[source]);
+            let target_y = nodes[target].y
+                + (in_offsets[target] + thickness / 2.0) * fit(&nodes[target], in_totals[target]);
+            out_offsets[source] += thickness;
+            in_offsets[target] += thickness;
+
+            FlowLink {
+                source,
+                target,
+                amount,
+                source_y,
+                target_y,
+                thickness,
+            }
+        })
+        .collect();
+
+    let height = layer_cursors
+        .iter()
+        .map(|cursor| cursor - FLOW_NODE_GAP)
+        .fold(0.0, f64::max);
+
+    FlowDiagram {
+        nodes,
+        links,
+        width: max_layer as f64 * FLOW_LAYER_SPACING + FLOW_NODE_WIDTH,
+        height,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::resolved;
+
+    fn node<'a>(diagram: &'a FlowDiagram, item_id: &str) -> &'a FlowNode {
+        diagram
+            .nodes
+            .iter()
+            .find(|node| node.item_id == item_id)
+            .unwrap()
+    }
+
+    // amethyst_component needs origocrust and amethyst_fiber, and
+    // amethyst_fiber needs origocrust as well
+    fn sample_plan() -> ProductionNode {
+        resolved(
+            "amethyst_component",
+            10,
+            vec![
+                resolved("origocrust", 10, vec![]),
+                resolved(
+                    "amethyst_fiber",
+                    10,
+                    vec![resolved("origocrust", 20, vec![])],
+                ),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_layers_follow_longest_dependency_chain() {
+        let diagram = layout_flow(&sample_plan());
+
+        assert_eq!(diagram.nodes.len(), 3);
+        assert_eq!(node(&diagram, "amethyst_component").layer, 0);
+        assert_eq!(node(&diagram, "amethyst_fiber").layer, 1);
+        // Consumed by both, so it sits left of its furthest consumer
+        assert_eq!(node(&diagram, "origocrust").layer, 2);
+        assert_eq!(node(&diagram, "origocrust").amount, 30);
+
+        // Every ribbon flows left to right
+        for link in &diagram.links {
+            assert!(diagram.nodes[link.source].x < diagram.nodes[link.target].x);
+        }
+    }
+
+    #[test]
+    fn test_nodes_in_a_layer_do_not_overlap() {
+        let plan = resolved(
+            "amethyst_component",
+            10,
+            vec![
+                resolved("origocrust", 10, vec![]),
+                resolved("amethyst_fiber", 10, vec![]),
+                resolved("ferrium", 5, vec![]),
+            ],
+        );
+        let diagram = layout_flow(&plan);
+
+        let mut layer: Vec<&FlowNode> = diagram.nodes.iter().filter(|n| n.layer == 1).collect();
+        assert_eq!(layer.len(), 3);
+        layer.sort_by(|a, b| a.y.total_cmp(&b.y));
+        for pair in layer.windows(2) {
+            assert!(pair[0].y + pair[0].height <= pair[1].y);
+        }
+        assert!(layer.iter().all(|n| n.y + n.height <= diagram.height));
+    }
+
+    #[test]
+    fn test_ribbons_stay_within_their_nodes() {
+        // 10 components take 40 items in
+        let plan = resolved(
+            "amethyst_component",
+            10,
+            vec![
+                resolved("origocrust", 20, vec![]),
+                resolved("amethyst_fiber", 20, vec![]),
+            ],
+        );
+        let diagram = layout_flow(&plan);
+
+        for link in &diagram.links {
+            let target = &diagram.nodes[link.target];
+            assert!(link.target_y >= target.y);
+            assert!(link.target_y <= target.y + target.height);
+        }
+    }
+
+    #[test]
+    fn test_upstream_chain() {
+        let diagram = layout_flow(&sample_plan());
+        let index_of = |item_id: &str| {
+            diagram
+                .nodes
+                .iter()
+                .position(|node| node.item_id == item_id)
+                .unwrap()
+        };
+
+        let upstream = diagram.upstream(index_of("amethyst_fiber"));
+
+        assert_eq!(
+            upstream,
+            [index_of("amethyst_fiber"), index_of("origocrust")]
+                .into_iter()
+                .collect()
+        );
+    }
+}
@@ -0,0 +1,177 @@
+//! Number formatting shared by every frontend.
+
+use crate::i18n::Localizer;
+use crate::models::ProductionNode;
+
+/// Decimals shown for amounts and rates unless a caller needs otherwise.
+pub const RATE_PRECISION: u8 = 2;
+
+/// Rounds `value` to `precision` decimals and drops trailing zeros, so
+/// `12.0` becomes `"12"` while `3.5` stays `"3.5"`. Always uses `.`.
+pub fn format_decimal(value: f64, precision: u8) -> String {
+    let mut text = format!("{:.*}", precision as usize, value);
+
+    if text.contains('.') {
+        let trimmed = text.trim_end_matches('0').trim_end_matches('.').len();
+        text.truncate(trimmed);
+    }
+
+    // Values that round to zero would otherwise show as "-0"
+    if text == "-0" {
+        text.remove(0);
+    }
+
+    text
+}
+
+/// Formats a rate like `format_decimal`, using the locale's decimal
+/// separator (the `decimal_separator` UI string, `.` when missing).
+pub fn format_rate(value: f64, precision: u8, localizer: &Localizer) -> String {
+    let text = format_decimal(value, precision);
+    let separator = localizer.decimal_separator();
+
+    if separator == "." {
+        text
+    } else {
+        text.replace('.', separator)
+    }
+}
+
+/// Formats seconds as e.g. `"2h 15m 30s"`, without zero components.
+/// Durations above a day start with days, and the seconds keep up to two
+/// decimals, e.g. `"0.25s"` or `"1m 1.5s"`. Negative and NaN durations are
+/// shown as `"0s"`, an infinite one as `"∞"`.
+pub fn format_duration(secs: f64) -> String {
+    if secs.is_nan() || secs <= 0.0 {
+        return "0s".to_string();
+    }
+    if secs.is_infinite() {
+        return "∞".to_string();
+    }
+
+    // Round first so 59.999 carries over into "1m"
+    let rounded = (secs * 100.0).round() / 100.0;
+    // Saturates for values beyond u64
+    let whole = rounded.floor() as u64;
+    let fraction = rounded - rounded.floor();
+    let seconds = (whole % 60) as f64 + fraction;
+
+    let mut parts: Vec<String> = [
+        (whole / 86_400, "d"),
+        (whole % 86_400 / 3_600, "h"),
+        (whole % 3_600 / 60, "m"),
+    ]
+    .iter()
+    .filter(|(value, _)| *value > 0)
+    .map(|(value, unit)| format!("{}{}", value, unit))
+    .collect();
+    if seconds > 0.0 || parts.is_empty() {
+        parts.push(format!("{}s", format_decimal(seconds, RATE_PRECISION)));
+    }
+
+    parts.join(" ")
+}
+
+/// Formats power with a k, M, G, T, P or E suffix for every factor of
+/// 1000, e.g. `1500` as `"1.5k"`, keeping up to two decimals.
+pub fn format_power(value: u64) -> String {
+    const UNITS: [&str; 7] = ["", "k", "M", "G", "T", "P", "E"];
+
+    let mut scaled = value as f64;
+    let mut unit = 0;
+    // Compare the rounded value so 999_999 becomes "1M", not "1000k"
+    while unit + 1 < UNITS.len() && (scaled * 100.0).round() / 100.0 >= 1000.0 {
+        scaled /= 1000.0;
+        unit += 1;
+    }
+
+    format!("{}{}", format_decimal(scaled, RATE_PRECISION), UNITS[unit])
+}
+
+impl ProductionNode {
+    /// Formats the node's amount with up to `precision` decimals.
+    pub fn display_amount(&self, precision: u8) -> String {
+        format_decimal(self.amount() as f64, precision)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_decimal_trims_trailing_zeros() {
+        assert_eq!(format_decimal(12.0, 2), "12");
+        assert_eq!(format_decimal(3.5, 2), "3.5");
+        assert_eq!(format_decimal(0.999, 1), "1");
+        assert_eq!(format_decimal(0.25, 1), "0.2");
+        assert_eq!(format_decimal(-0.001, 2), "0");
+        assert_eq!(format_decimal(100.0, 0), "100");
+        assert_eq!(format_decimal(1e12 + 0.5, 1), "1000000000000.5");
+        assert_eq!(format_decimal(u32::MAX as f64, 2), "4294967295");
+    }
+
+    #[test]
+    fn test_format_duration() {
+        let table = [
+            (0.0, "0s"),
+            (-5.0, "0s"),
+            (f64::NAN, "0s"),
+            (0.25, "0.25s"),
+            (0.001, "0s"),
+            (0.999, "1s"),
+            (1.0, "1s"),
+            (1.5, "1.5s"),
+            (59.4, "59.4s"),
+            (59.999, "1m"),
+            (60.0, "1m"),
+            (61.5, "1m 1.5s"),
+            (3_600.0, "1h"),
+            (3_630.0, "1h 30s"),
+            (8_130.0, "2h 15m 30s"),
+            (86_400.0, "1d"),
+            (90_061.0, "1d 1h 1m 1s"),
+            (1e9, "11574d 1h 46m 40s"),
+            (f64::INFINITY, "∞"),
+        ];
+
+        for (secs, expected) in table {
+            assert_eq!(format_duration(secs), expected, "{} seconds", secs);
+        }
+    }
+
+    #[test]
+    fn test_format_power() {
+        let table = [
+            (0, "0"),
+            (999, "999"),
+            (1_000, "1k"),
+            (1_500, "1.5k"),
+            (12_345, "12.35k"),
+            (999_999, "1M"),
+            (1_000_000, "1M"),
+            (2_500_000_000, "2.5G"),
+            (u64::MAX, "18.45E"),
+        ];
+
+        for (value, expected) in table {
+            assert_eq!(format_power(value), expected, "{}", value);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "data-files")]
+    fn test_format_rate_uses_locale_separator() {
+        let comma = Localizer::new(
+            r#"
+[ui]
+decimal_separator = ","
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(format_rate(3.25, 2, &comma), "3,25");
+        assert_eq!(format_rate(12.0, 2, &comma), "12");
+        assert_eq!(format_rate(3.25, 2, &Localizer::empty()), "3.25");
+    }
+}
@@ -0,0 +1,303 @@
+//! Dependency graph of the whole recipe database, for documentation.
+
+use super::mermaid::escape_mermaid;
+use crate::config::GameData;
+use crate::i18n::NameResolver;
+use crate::models::{ItemId, MachineId, Recipe, RecipeUid};
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// Which part of the recipe database `RecipeGraph::from_data` covers.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GraphFilter {
+    /// Only the items this item is made from, directly or indirectly, and
+    /// the recipes making them.
+    pub root: Option<ItemId>,
+    /// Only recipes carrying this tag.
+    pub tag: Option<String>,
+}
+
+/// A recipe turning one of its inputs into one of its outputs.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct RecipeEdge {
+    pub input: ItemId,
+    pub output: ItemId,
+    pub machine_id: MachineId,
+    pub recipe_id: RecipeUid,
+}
+
+/// Items and the recipes connecting them. An item made by several recipes
+/// gets one edge per recipe. Nodes and edges are sorted, so rendering is
+/// deterministic.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RecipeGraph {
+    pub nodes: BTreeSet<ItemId>,
+    pub edges: Vec<RecipeEdge>,
+}
+
+/// Items a recipe consumes, including every alternative of its groups.
+fn inputs_of(recipe: &Recipe) -> impl Iterator<Item = &ItemId> {
+    let group_items = recipe.any_of.iter().flat_map(|group| &group.items);
+    recipe.inputs.keys().chain(group_items)
+}
+
+impl RecipeGraph {
+    /// Builds the graph of the recipes in `data` that pass `filter`.
+    pub fn from_data(data: &GameData, filter: &GraphFilter) -> Self {
+        let recipes: Vec<&Recipe> = data
+            .recipes
+            .values()
+            .filter(|recipe| {
+                filter
+                    .tag
+                    .as_ref()
+                    .is_none_or(|tag| recipe.tags.contains(tag))
+            })
+            .collect();
+
+        let recipes = match &filter.root {
+            Some(root) => reachable_recipes(&recipes, root),
+            None => recipes,
+        };
+
+        let mut graph = RecipeGraph::default();
+        if let Some(root) = &filter.root {
+            graph.nodes.insert(root.clone());
+        }
+        for recipe in recipes {
+            for output in recipe.output_items() {
+                graph.nodes.insert(output.clone());
+                for input in inputs_of(recipe) {
+                    graph.nodes.insert(input.clone());
+                    graph.edges.push(RecipeEdge {
+                        input: input.clone(),
+                        output: output.clone(),
+                        machine_id: recipe.by.clone(),
+                        recipe_id: recipe.compute_unique_id(),
+                    });
+                }
+            }
+        }
+        graph.edges.sort();
+        graph.edges.dedup();
+
+        graph
+    }
+
+    /// Renders the graph as a Graphviz `digraph`, with edges from input to
+    /// output labeled with the machine. Nodes whose name differs from their
+    /// ID get it as a label.
+    pub fn to_dot(&self, names: &dyn NameResolver) -> String {
+        let mut dot = String::from("digraph recipes {\n    rankdir=LR;\n");
+        for item_id in &self.nodes {
+            let name = names.name_of(item_id.as_str());
+            if name == item_id.as_str() {
+                dot.push_str(&format!("    \"{}\";\n", escape_dot(item_id.as_str())));
+            } else {
+                dot.push_str(&format!(
+                    "    \"{}\" [label=\"{}\"];\n",
+                    escape_dot(item_id.as_str()),
+                    escape_dot(&name)
+                ));
+            }
+        }
+        for edge in &self.edges {
+            dot.push_str(&format!(
+                "    \"{}\" -> \"{}\" [label=\"{}\"];\n",
+                escape_dot(edge.input.as_str()),
+                escape_dot(edge.output.as_str()),
+                escape_dot(&names.name_of(edge.machine_id.as_str()))
+            ));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Renders the graph as a Mermaid `graph LR` flowchart, like `to_dot`.
+    pub fn to_mermaid(&self, names: &dyn NameResolver) -> String {
+        let mut chart = String::from("graph LR\n");
+        for item_id in &self.nodes {
+            let name = names.name_of(item_id.as_str());
+            let node_id = escape_mermaid(item_id.as_str());
+            if name == item_id.as_str() {
+                chart.push_str(&format!("    {}\n", node_id));
+            } else {
+                chart.push_str(&format!("    {}[\"{}\"]\n", node_id, escape_mermaid(&name)));
+            }
+        }
+        for edge in &self.edges {
+            chart.push_str(&format!(
+                "    {} -->|{}| {}\n",
+                escape_mermaid(edge.input.as_str()),
+                escape_mermaid(&names.name_of(edge.machine_id.as_str())),
+                escape_mermaid(edge.output.as_str())
+            ));
+        }
+        chart
+    }
+}
+
+/// Keeps the recipes making `root` or, recursively, any of their inputs.
+fn reachable_recipes<'a>(recipes: &[&'a Recipe], root: &ItemId) -> Vec<&'a Recipe> {
+    let mut producers: HashMap<&ItemId, Vec<&'a Recipe>> = HashMap::new();
+    for recipe in recipes {
+        for output in recipe.output_items() {
+            producers.entry(output).or_default().push(recipe);
+        }
+    }
+
+    let mut seen_items: HashSet<&ItemId> = HashSet::from([root]);
+    let mut seen_recipes: HashSet<RecipeUid> = HashSet::new();
+    let mut reachable = Vec::new();
+    let mut stack = vec![root];
+    while let Some(item_id) = stack.pop() {
+        for &recipe in producers.get(item_id).into_iter().flatten() {
+            if !seen_recipes.insert(recipe.compute_unique_id()) {
+                continue;
+            }
+            reachable.push(recipe);
+            for input in inputs_of(recipe) {
+                if seen_items.insert(input) {
+                    stack.push(input);
+                }
+            }
+        }
+    }
+
+    reachable
+}
+
+/// Escapes text for a double-quoted DOT ID or label.
+fn escape_dot(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(all(test, feature = "data-files"))]
+mod tests {
+    use super::*;
+    use crate::i18n::{IdResolver, LocalizedResolver, Localizer};
+
+    // origocrust has two recipes; amethyst_fiber is unrelated to it
+    const RECIPES: &str = r#"
+[[recipes]]
+id = "originium_ore"
+by = "electric_mining_rig"
+time = 2
+out = 1
+is_source = true
+
+[[recipes]]
+id = "origocrust"
+by = "refining_unit"
+time = 2
+out = 1
+tags = ["smelting"]
+[recipes.inputs]
+originium_ore = 1
+
+[[recipes]]
+id = "origocrust"
+by = "refining_unit_mk2"
+time = 1
+out = 1
+[recipes.inputs]
+originium_ore = 1
+
+[[recipes]]
+id = "origocrust_powder"
+by = "shredding_unit"
+time = 2
+out = 1
+[recipes.inputs]
+origocrust = 1
+
+[[recipes]]
+id = "amethyst_fiber"
+by = "refining_unit"
+time = 2
+out = 1
+tags = ["smelting"]
+[recipes.inputs]
+amethyst_ore = 1
+"#;
+
+    fn data() -> GameData {
+        GameData::new(RECIPES, "machines = []").unwrap()
+    }
+
+    #[test]
+    fn test_whole_dataset_with_parallel_edges() {
+        let graph = RecipeGraph::from_data(&data(), &GraphFilter::default());
+
+        assert_eq!(graph.nodes.len(), 5);
+        assert_eq!(graph.edges.len(), 4);
+
+        let dot = graph.to_dot(&IdResolver);
+        assert!(dot.contains("\"originium_ore\" -> \"origocrust\" [label=\"refining_unit\"];"));
+        assert!(dot.contains("\"originium_ore\" -> \"origocrust\" [label=\"refining_unit_mk2\"];"));
+        assert_eq!(dot, graph.to_dot(&IdResolver));
+    }
+
+    #[test]
+    fn test_root_keeps_reachable_subgraph() {
+        let filter = GraphFilter {
+            root: Some("origocrust_powder".into()),
+            ..Default::default()
+        };
+        let graph = RecipeGraph::from_data(&data(), &filter);
+
+        let nodes: Vec<&str> = graph.nodes.iter().map(ItemId::as_str).collect();
+        assert_eq!(
+            nodes,
+            vec!["originium_ore", "origocrust", "origocrust_powder"]
+        );
+        assert_eq!(graph.edges.len(), 3);
+    }
+
+    #[test]
+    fn test_tag_filter() {
+        let filter = GraphFilter {
+            tag: Some("smelting".to_string()),
+            ..Default::default()
+        };
+        let graph = RecipeGraph::from_data(&data(), &filter);
+
+        assert_eq!(graph.nodes.len(), 4);
+        assert_eq!(graph.edges.len(), 2);
+    }
+
+    #[test]
+    fn test_localized_labels() {
+        let localizer = Localizer::new(
+            r#"
+[items]
+origocrust = "Origocrust"
+
+[machines]
+refining_unit = "Refining Unit"
+"#,
+        )
+        .unwrap();
+        let data = GameData::new(
+            RECIPES,
+            "[[machines]]\nid = \"refining_unit\"\ntier = 1\npower = 5\n",
+        )
+        .unwrap();
+        let names = LocalizedResolver::new(&localizer, &data);
+        let graph = RecipeGraph::from_data(&data, &GraphFilter::default());
+
+        let dot = graph.to_dot(&names);
+        assert!(dot.contains("    \"origocrust\" [label=\"Origocrust\"];\n"));
+        assert!(dot.contains("    \"amethyst_ore\";\n"));
+        assert!(dot.contains("\"originium_ore\" -> \"origocrust\" [label=\"Refining Unit\"];"));
+
+        let chart = graph.to_mermaid(&names);
+        assert!(chart.contains("    origocrust[\"Origocrust\"]\n"));
+        assert!(chart.contains("    amethyst_ore\n"));
+        assert!(chart.contains("    originium_ore -->|Refining Unit| origocrust\n"));
+    }
+
+    #[test]
+    fn test_escape_dot() {
+        assert_eq!(escape_dot("a\"b\\c"), "a\\\"b\\\\c");
+    }
+}
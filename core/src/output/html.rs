@@ -0,0 +1,189 @@
+//! Static HTML rendering of production trees.
+
+use crate::i18n::{Localizer, NameResolver};
+use crate::models::ProductionNode;
+
+/// Escapes text for safe inclusion in HTML element content and attributes.
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn write_node(
+    node: &ProductionNode,
+    localizer: &Localizer,
+    names: &dyn NameResolver,
+    html: &mut String,
+) {
+    match node {
+        ProductionNode::Resolved {
+            item_id,
+            machine_id,
+            amount,
+            machine_count,
+            inputs,
+            ..
+        } => {
+            if node.is_virtual_root() {
+                html.push_str(&format!(
+                    "<li class=\"tree-line\"><span class=\"tree-item\"><strong>{}</strong></span>",
+                    escape_html(&names.name_of(item_id.as_str()))
+                ));
+            } else {
+                html.push_str(&format!(
+                    "<li class=\"tree-line\"><span class=\"tree-item\"><strong>{}</strong> ×{}</span> <span class=\"tree-machine\">{} ×{}</span>",
+                    escape_html(&names.name_of(item_id.as_str())),
+                    amount,
+                    escape_html(&names.name_of(machine_id.as_str())),
+                    machine_count
+                ));
+            }
+
+            if !inputs.is_empty() {
+                html.push_str("<ul>");
+                for child in inputs {
+                    write_node(child, localizer, names, html);
+                }
+                html.push_str("</ul>");
+            }
+
+            html.push_str("</li>");
+        }
+        ProductionNode::Unresolved { item_id, amount } => {
+            html.push_str(&format!(
+                "<li class=\"tree-line tree-missing\"><span class=\"tree-item\"><strong>{}</strong> ×{}</span> <span class=\"tree-machine missing\">[{}]</span></li>",
+                escape_html(&names.name_of(item_id.as_str())),
+                amount,
+                escape_html(&localizer.get_ui("missing_recipe"))
+            ));
+        }
+        ProductionNode::External { item_id, amount } => {
+            html.push_str(&format!(
+                "<li class=\"tree-line tree-external\"><span class=\"tree-item\"><strong>{}</strong> ×{}</span> <span class=\"tree-machine external\">[{}]</span></li>",
+                escape_html(&names.name_of(item_id.as_str())),
+                amount,
+                escape_html(&localizer.get_ui("external_supply"))
+            ));
+        }
+        ProductionNode::Cycle { item_id, amount } => {
+            html.push_str(&format!(
+                "<li class=\"tree-line tree-cycle\"><span class=\"tree-item\"><strong>{}</strong> ×{}</span> <span class=\"tree-machine cycle\">[{}]</span></li>",
+                escape_html(&names.name_of(item_id.as_str())),
+                amount,
+                escape_html(&localizer.get_ui("cycle_break"))
+            ));
+        }
+        ProductionNode::Shared { item_id, amount } => {
+            html.push_str(&format!(
+                "<li class=\"tree-line tree-shared\"><span class=\"tree-item\"><strong>{}</strong> ×{}</span> <span class=\"tree-machine shared\">[{}]</span></li>",
+                escape_html(&names.name_of(item_id.as_str())),
+                amount,
+                escape_html(&localizer.get_ui("shared_in_plan"))
+            ));
+        }
+    }
+}
+
+/// Renders a production tree as nested `<ul>`/`<li>` HTML.
+///
+/// Names come from `names` and the status markers from `localizer`.
+/// Class names follow the web app's tree view so its stylesheet applies.
+pub fn to_html(node: &ProductionNode, localizer: &Localizer, names: &dyn NameResolver) -> String {
+    let mut html = String::from("<ul class=\"production-tree\">");
+    write_node(node, localizer, names, &mut html);
+    html.push_str("</ul>");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "data-files")]
+    use crate::i18n::{IdResolver, LocalizedResolver};
+
+    #[test]
+    #[cfg(feature = "data-files")]
+    fn test_to_html_nested_and_localized() {
+        let localizer = Localizer::new(
+            r#"
+[items]
+origocrust = "Origocrust"
+originium_ore = "Originium Ore"
+
+[machines]
+refining_unit = "Refining Unit"
+
+[ui]
+missing_recipe = "MISSING RECIPE"
+"#,
+        )
+        .unwrap();
+
+        let node = ProductionNode::Resolved {
+            item_id: "origocrust".into(),
+            machine_id: "refining_unit".into(),
+            amount: 30,
+            machine_count: 1,
+            power_usage: 5,
+            standby_power: 0,
+            load: 1.0,
+            inputs: vec![ProductionNode::Unresolved {
+                item_id: "originium_ore".into(),
+                amount: 30,
+            }],
+            recipe_id: Default::default(),
+            is_source: false,
+            blocks: vec![],
+        };
+
+        let data = crate::config::GameData::new(
+            "recipes = []",
+            "[[machines]]\nid = \"refining_unit\"\ntier = 1\npower = 5\n",
+        )
+        .unwrap();
+        let html = to_html(
+            &node,
+            &localizer,
+            &LocalizedResolver::new(&localizer, &data),
+        );
+
+        assert!(html.starts_with("<ul class=\"production-tree\"><li"));
+        assert!(html.contains("</span><ul><li class=\"tree-line tree-missing\">"));
+        assert!(html.contains("<strong>Origocrust</strong> ×30"));
+        assert!(html.contains("Refining Unit ×1"));
+        assert!(html.contains("<strong>Originium Ore</strong>"));
+        assert!(html.contains("[MISSING RECIPE]"));
+    }
+
+    #[test]
+    #[cfg(feature = "data-files")]
+    fn test_to_html_with_ids() {
+        let localizer = Localizer::new("[items]\norigocrust = \"Origocrust\"\n").unwrap();
+        let node = ProductionNode::Unresolved {
+            item_id: "origocrust".into(),
+            amount: 30,
+        };
+
+        let html = to_html(&node, &localizer, &IdResolver);
+
+        assert!(html.contains("<strong>origocrust</strong> ×30"));
+    }
+
+    #[test]
+    fn test_escape_html() {
+        assert_eq!(
+            escape_html("<b>\"A&B\"</b>"),
+            "&lt;b&gt;&quot;A&amp;B&quot;&lt;/b&gt;"
+        );
+    }
+}
@@ -0,0 +1,217 @@
+//! JSON export of production plans.
+
+use crate::config::GameData;
+use crate::models::{InputGroup, ItemId, MachineId, ProductionNode, RateUnit, RecipeUid};
+use crate::planner::{PlanOptions, PlanResult, PlanSummary, PlanWarnings, ranked_recipes};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+#[derive(Serialize)]
+struct PlanExport<'a> {
+    /// Unit of every amount in the plan, which are plain numbers.
+    rate_unit: RateUnit,
+    node: &'a ProductionNode,
+    summary: PlanSummary,
+    warnings: &'a PlanWarnings,
+}
+
+/// Serializes a plan, its totals and its warnings as pretty-printed JSON.
+///
+/// Totals are always written in key order. With `canonical`, the inputs of
+/// every node are also sorted by item ID; planned with
+/// `PlanOptions::sorted_inputs`, so its warnings come in a fixed order too,
+/// the same plan always exports byte for byte identically.
+pub fn to_json(result: &PlanResult, data: &GameData, canonical: bool) -> String {
+    let mut node = result.node.clone();
+    if canonical {
+        node.canonicalize();
+    }
+
+    let export = PlanExport {
+        rate_unit: RateUnit::PerMinute,
+        summary: PlanSummary::new(&node, data),
+        node: &node,
+        warnings: &result.warnings,
+    };
+
+    serde_json::to_string_pretty(&export).expect("plan export only contains string keys")
+}
+
+#[derive(Serialize)]
+struct RecipeExport<'a> {
+    recipe_id: RecipeUid,
+    machine_id: &'a MachineId,
+    tier: Option<u32>,
+    power: Option<u32>,
+    time: f64,
+    inputs: BTreeMap<&'a ItemId, u32>,
+    outputs: BTreeMap<&'a ItemId, u32>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    returns: BTreeMap<&'a ItemId, u32>,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    any_of: &'a [InputGroup],
+    is_source: bool,
+    tags: &'a [String],
+    selected: bool,
+}
+
+/// Serializes the recipes for an item as pretty-printed JSON, in the
+/// order of `ranked_recipes`. The first one is marked as selected.
+pub fn recipes_to_json(data: &GameData, item_id: &ItemId) -> String {
+    let export: Vec<RecipeExport> = ranked_recipes(data, item_id, &PlanOptions::default())
+        .into_iter()
+        .enumerate()
+        .map(|(rank, recipe)| {
+            let machine = data.machines.get(&recipe.by);
+            RecipeExport {
+                recipe_id: recipe.compute_unique_id(),
+                machine_id: &recipe.by,
+                tier: machine.map(|m| m.tier),
+                power: machine.map(|m| m.power),
+                time: recipe.time,
+                inputs: recipe
+                    .inputs
+                    .iter()
+                    .map(|(id, &count)| (id, count))
+                    .collect(),
+                outputs: recipe
+                    .outputs
+                    .iter()
+                    .map(|(id, &count)| (id, count))
+                    .collect(),
+                returns: recipe
+                    .returns
+                    .iter()
+                    .map(|(id, &count)| (id, count))
+                    .collect(),
+                any_of: &recipe.any_of,
+                is_source: recipe.is_source,
+                tags: &recipe.tags,
+                selected: rank == 0,
+            }
+        })
+        .collect();
+
+    serde_json::to_string_pretty(&export).expect("recipe export only contains string keys")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::planner::plan_production_with_options;
+
+    const RECIPES_TOML: &str = r#"
+[[recipes]]
+id = "amethyst_component"
+by = "gearing_unit"
+time = 2
+out = 1
+[recipes.inputs]
+origocrust = 1
+amethyst_fiber = 1
+ferrium = 1
+
+[[recipes]]
+id = "origocrust"
+by = "refining_unit"
+time = 2
+out = 1
+"#;
+
+    const MACHINES_TOML: &str = r#"
+[[machines]]
+id = "gearing_unit"
+tier = 1
+power = 10
+
+[[machines]]
+id = "refining_unit"
+tier = 1
+power = 5
+"#;
+
+    const GOLDEN_JSON: &str = include_str!("testdata/plan.json");
+
+    fn export() -> String {
+        // Fresh maps each time, so hash order differs between runs
+        let data = GameData::new(RECIPES_TOML, MACHINES_TOML).unwrap();
+        let options = PlanOptions {
+            sorted_inputs: true,
+            ..Default::default()
+        };
+        let result =
+            plan_production_with_options(&data, "amethyst_component", 30, &options).unwrap();
+        to_json(&result, &data, true)
+    }
+
+    #[test]
+    fn test_canonicalize_sorts_inputs() {
+        let leaf = |item_id: &str| ProductionNode::Unresolved {
+            item_id: item_id.into(),
+            amount: 1,
+        };
+        let mut node = ProductionNode::Resolved {
+            item_id: "amethyst_component".into(),
+            machine_id: "gearing_unit".into(),
+            recipe_id: RecipeUid::default(),
+            amount: 1,
+            machine_count: 1,
+            power_usage: 0,
+            standby_power: 0,
+            load: 1.0,
+            inputs: vec![leaf("origocrust"), leaf("amethyst_fiber")],
+            is_source: false,
+            blocks: vec![],
+        };
+
+        node.canonicalize();
+
+        let ProductionNode::Resolved { inputs, .. } = &node else {
+            panic!("Expected Resolved node");
+        };
+        assert_eq!(inputs, &vec![leaf("amethyst_fiber"), leaf("origocrust")]);
+    }
+
+    #[test]
+    fn test_canonical_export_is_stable() {
+        let first = export();
+
+        for _ in 0..8 {
+            assert_eq!(export(), first);
+        }
+        assert_eq!(first, GOLDEN_JSON.trim_end());
+    }
+
+    #[test]
+    fn test_recipes_export_ranks_selected_first() {
+        let recipes = format!(
+            "{}{}",
+            RECIPES_TOML,
+            r#"
+[[recipes]]
+id = "origocrust"
+by = "gearing_unit"
+time = 4
+out = 1
+[recipes.inputs]
+originium_ore = 2
+"#
+        );
+        let data = GameData::new(&recipes, MACHINES_TOML).unwrap();
+
+        let json = recipes_to_json(&data, &"origocrust".into());
+        let export: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+        // Both machines are tier 1, so the lower power refining unit wins
+        let entries = export.as_array().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            entries[0]["recipe_id"],
+            "origocrust@refining_unit[]->[origocrust:1]/2s"
+        );
+        assert_eq!(entries[0]["selected"], true);
+        assert_eq!(entries[1]["machine_id"], "gearing_unit");
+        assert_eq!(entries[1]["inputs"]["originium_ore"], 2);
+        assert_eq!(entries[1]["selected"], false);
+    }
+}
@@ -0,0 +1,319 @@
+//! Markdown rendering of production plans, for pasting into wikis.
+
+use super::format::{RATE_PRECISION, format_power, format_rate};
+use crate::i18n::{Localizer, NameResolver};
+use crate::models::{AmountKind, ProductionNode};
+use std::collections::HashMap;
+
+/// Escapes text for a Markdown table cell or list item.
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '|' | '*' | '_' | '`' | '[' | ']') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn write_node(
+    node: &ProductionNode,
+    localizer: &Localizer,
+    names: &dyn NameResolver,
+    depth: usize,
+    markdown: &mut String,
+) {
+    let item = |id: &str| escape_markdown(&names.name_of(id));
+    let amount = format_rate(node.amount() as f64, RATE_PRECISION, localizer);
+    let status = |key: &str| format!("[{}]", escape_markdown(&localizer.get_ui(key)));
+
+    let line = match node {
+        ProductionNode::Resolved { item_id, .. } if node.is_virtual_root() => {
+            format!("**{}**", item(item_id.as_str()))
+        }
+        ProductionNode::Resolved {
+            item_id,
+            machine_id,
+            ..
+        } => format!(
+            "**{}** ×{} — {} ×{}",
+            item(item_id.as_str()),
+            amount,
+            escape_markdown(&names.name_of(machine_id.as_str())),
+            node.machine_count_label()
+        ),
+        ProductionNode::Unresolved { item_id, .. } => format!(
+            "**{}** ×{} — {}",
+            item(item_id.as_str()),
+            amount,
+            status("missing_recipe")
+        ),
+        ProductionNode::External { item_id, .. } => format!(
+            "**{}** ×{} — {}",
+            item(item_id.as_str()),
+            amount,
+            status("external_supply")
+        ),
+        ProductionNode::Cycle { item_id, .. } => format!(
+            "**{}** ×{} — {}",
+            item(item_id.as_str()),
+            amount,
+            status("cycle_break")
+        ),
+        ProductionNode::Shared { item_id, .. } => format!(
+            "**{}** ×{} — {}",
+            item(item_id.as_str()),
+            amount,
+            status("shared_in_plan")
+        ),
+    };
+    markdown.push_str(&format!("{}- {}\n", "  ".repeat(depth), line));
+
+    if let ProductionNode::Resolved { inputs, .. } = node {
+        for child in inputs {
+            write_node(child, localizer, names, depth + 1, markdown);
+        }
+    }
+}
+
+/// Writes a two-column table of labeled totals, sorted by label.
+fn write_table(markdown: &mut String, header: (&str, &str), totals: HashMap<String, String>) {
+    let mut rows: Vec<(String, String)> = totals.into_iter().collect();
+    rows.sort();
+
+    markdown.push_str(&format!(
+        "| {} | {} |\n| --- | ---: |\n",
+        escape_markdown(header.0),
+        escape_markdown(header.1)
+    ));
+    for (label, value) in rows {
+        markdown.push_str(&format!("| {} | {} |\n", label, value));
+    }
+}
+
+/// Renders a production plan as Markdown: the tree as a nested list, then
+/// tables of the raw materials and machines, then the total power.
+///
+/// Item and machine names come from `names`, headings and the decimal
+/// separator from `localizer`. `amounts` picks the raw material column
+/// heading.
+pub fn to_markdown(
+    node: &ProductionNode,
+    localizer: &Localizer,
+    names: &dyn NameResolver,
+    amounts: AmountKind,
+) -> String {
+    let mut markdown = format!("## {}\n\n", localizer.get_ui("production_plan"));
+    write_node(node, localizer, names, 0, &mut markdown);
+
+    let rate = |amount: u32| format_rate(amount as f64, RATE_PRECISION, localizer);
+
+    markdown.push_str(&format!(
+        "\n## {}\n\n",
+        localizer.get_ui("total_raw_materials")
+    ));
+    let materials = node
+        .total_source_materials()
+        .into_iter()
+        .map(|(id, amount)| (escape_markdown(&names.name_of(id.as_str())), rate(amount)))
+        .collect();
+    write_table(
+        &mut markdown,
+        (
+            &localizer.get_ui("total_raw_materials"),
+            &localizer.get_ui(amounts.label_key()),
+        ),
+        materials,
+    );
+
+    markdown.push_str(&format!("\n## {}\n\n", localizer.get_ui("total_machines")));
+    let machines = node
+        .total_machines()
+        .into_iter()
+        .map(|(id, count)| {
+            (
+                escape_markdown(&names.name_of(id.as_str())),
+                count.to_string(),
+            )
+        })
+        .collect();
+    write_table(
+        &mut markdown,
+        (
+            &localizer.get_ui("total_machines"),
+            &localizer.get_ui("total_machine_count"),
+        ),
+        machines,
+    );
+
+    markdown.push_str(&format!(
+        "\n**{}:** {}\n",
+        localizer.get_ui("total_power"),
+        format_power(node.total_power() as u64)
+    ));
+
+    markdown
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "data-files")]
+    use crate::i18n::{IdResolver, LocalizedResolver};
+    #[cfg(feature = "data-files")]
+    use crate::test_fixtures::{self, on_machines};
+
+    #[cfg(feature = "data-files")]
+    fn resolved(
+        item_id: &str,
+        machine_id: &str,
+        amount: u32,
+        is_source: bool,
+        inputs: Vec<ProductionNode>,
+    ) -> ProductionNode {
+        let mut node = on_machines(
+            test_fixtures::resolved(item_id, amount, inputs),
+            machine_id,
+            1,
+        );
+        if let ProductionNode::Resolved {
+            is_source: source, ..
+        } = &mut node
+        {
+            *source = is_source;
+        }
+        node
+    }
+
+    #[test]
+    #[cfg(feature = "data-files")]
+    fn test_to_markdown_localized() {
+        let localizer = Localizer::new(
+            r#"
+[items]
+origocrust = "Origocrust"
+originium_ore = "Originium Ore"
+
+[machines]
+refining_unit = "Refining Unit"
+
+[ui]
+production_plan = "Production Plan"
+total_raw_materials = "Total Raw Materials"
+total_machines = "Total Machines"
+total_machine_count = "Count"
+total_power = "Total Power"
+per_min = "/min"
+missing_recipe = "MISSING RECIPE"
+"#,
+        )
+        .unwrap();
+
+        let node = resolved(
+            "origocrust",
+            "refining_unit",
+            30,
+            false,
+            vec![
+                resolved("originium_ore", "electric_mining_rig", 30, true, vec![]),
+                ProductionNode::Unresolved {
+                    item_id: "amethyst_fiber".into(),
+                    amount: 15,
+                },
+            ],
+        );
+
+        assert_eq!(
+            to_markdown(
+                &node,
+                &localizer,
+                &LocalizedResolver::new(&localizer, &refining_unit()),
+                AmountKind::PerMinute
+            ),
+            "## Production Plan\n\
+             \n\
+             - **Origocrust** ×30 — Refining Unit ×1\n\
+            \x20 - **Originium Ore** ×30 — electric\\_mining\\_rig ×1\n\
+            \x20 - **amethyst\\_fiber** ×15 — [MISSING RECIPE]\n\
+             \n\
+             ## Total Raw Materials\n\
+             \n\
+             | Total Raw Materials | /min |\n\
+             | --- | ---: |\n\
+             | Originium Ore | 30 |\n\
+             | amethyst\\_fiber | 15 |\n\
+             \n\
+             ## Total Machines\n\
+             \n\
+             | Total Machines | Count |\n\
+             | --- | ---: |\n\
+             | Refining Unit | 1 |\n\
+             | electric\\_mining\\_rig | 1 |\n\
+             \n\
+             **Total Power:** 10\n"
+        );
+    }
+
+    #[cfg(feature = "data-files")]
+    fn refining_unit() -> crate::config::GameData {
+        crate::config::GameData::new(
+            "recipes = []",
+            "[[machines]]\nid = \"refining_unit\"\ntier = 1\npower = 5\n",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    #[cfg(feature = "data-files")]
+    fn test_to_markdown_uses_name_resolver() {
+        struct Shouting;
+
+        impl NameResolver for Shouting {
+            fn name_of(&self, id: &str) -> String {
+                id.to_uppercase()
+            }
+        }
+
+        let localizer = Localizer::new("[items]\norigocrust = \"Origocrust\"\n").unwrap();
+        let node = resolved("origocrust", "refining_unit", 30, false, vec![]);
+
+        let markdown = to_markdown(&node, &localizer, &Shouting, AmountKind::PerMinute);
+
+        assert!(markdown.contains("- **ORIGOCRUST** ×30 — REFINING\\_UNIT ×1"));
+        assert!(markdown.contains("| REFINING\\_UNIT | 1 |"));
+        assert!(!markdown.contains("Origocrust"));
+    }
+
+    #[test]
+    #[cfg(feature = "data-files")]
+    fn test_batch_amounts_are_not_per_minute() {
+        let localizer = Localizer::new(
+            "[ui]\ntotal_raw_materials = \"Raw\"\nper_min = \"/min\"\ntotal_amount = \"Total\"\n",
+        )
+        .unwrap();
+        let node = resolved(
+            "origocrust",
+            "refining_unit",
+            30,
+            false,
+            vec![resolved(
+                "originium_ore",
+                "electric_mining_rig",
+                30,
+                true,
+                vec![],
+            )],
+        );
+
+        let markdown = to_markdown(&node, &localizer, &IdResolver, AmountKind::Total);
+
+        assert!(markdown.contains("| Raw | Total |"), "{}", markdown);
+        assert!(!markdown.contains("/min"));
+    }
+
+    #[test]
+    fn test_escape_markdown() {
+        assert_eq!(escape_markdown("a|b_c*"), "a\\|b\\_c\\*");
+    }
+}
@@ -0,0 +1,165 @@
+//! Mermaid flowchart export of production plans.
+
+use crate::i18n::NameResolver;
+use crate::models::{ItemId, ProductionNode};
+use std::collections::BTreeMap;
+
+/// An item in the chart, merged over every place it appears in the plan.
+#[derive(Default)]
+struct ChartNode {
+    amount: u32,
+    /// Shown after the amount for items the plan does not produce.
+    note: Option<&'static str>,
+    /// The root groups several items, so it is shown without an amount.
+    virtual_root: bool,
+}
+
+fn collect<'a>(
+    node: &'a ProductionNode,
+    nodes: &mut BTreeMap<&'a ItemId, ChartNode>,
+    edges: &mut BTreeMap<(&'a ItemId, &'a ItemId), u32>,
+) {
+    let entry = nodes.entry(node.item_id()).or_default();
+    match node {
+        ProductionNode::Resolved { amount, inputs, .. } => {
+            entry.amount += amount;
+            entry.virtual_root = node.is_virtual_root();
+            for input in inputs {
+                *edges.entry((input.item_id(), node.item_id())).or_insert(0) += input.amount();
+                collect(input, nodes, edges);
+            }
+        }
+        ProductionNode::Unresolved { amount, .. } => {
+            entry.amount += amount;
+            entry.note = Some("missing recipe");
+        }
+        ProductionNode::External { amount, .. } => {
+            entry.amount += amount;
+            entry.note = Some("external");
+        }
+        // Produced elsewhere in the plan, so only the edge is drawn
+        ProductionNode::Cycle { .. } | ProductionNode::Shared { .. } => {}
+    }
+}
+
+/// Renders the plan as a Mermaid `graph TD` flowchart.
+///
+/// Each item is a single node with its total amount, so intermediates
+/// shared by several consumers appear once. Edges point from an input to
+/// the item consuming it and are labeled with the amount carried. Labels
+/// name items through `names`; node IDs are the escaped item IDs.
+pub fn to_mermaid(node: &ProductionNode, names: &dyn NameResolver) -> String {
+    let mut nodes = BTreeMap::new();
+    let mut edges = BTreeMap::new();
+    collect(node, &mut nodes, &mut edges);
+
+    let mut chart = String::from("graph TD\n");
+    for (item_id, chart_node) in &nodes {
+        let name = escape_mermaid(&names.name_of(item_id.as_str()));
+        let label = match chart_node.note {
+            _ if chart_node.virtual_root => name,
+            Some(note) => format!("{} x{} ({})", name, chart_node.amount, note),
+            None => format!("{} x{}", name, chart_node.amount),
+        };
+        chart.push_str(&format!(
+            "    {}[\"{}\"]\n",
+            escape_mermaid(item_id.as_str()),
+            label
+        ));
+    }
+    for ((source, target), amount) in &edges {
+        chart.push_str(&format!(
+            "    {} -->|{}| {}\n",
+            escape_mermaid(source.as_str()),
+            amount,
+            escape_mermaid(target.as_str())
+        ));
+    }
+
+    chart
+}
+
+/// Escapes the characters that would end a quoted Mermaid label or an
+/// edge label next to a node ID.
+pub(crate) fn escape_mermaid(text: &str) -> String {
+    text.replace('"', "#quot;").replace('|', "#124;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::i18n::IdResolver;
+    use crate::test_fixtures::resolved;
+
+    #[test]
+    fn test_shared_intermediate_is_one_node() {
+        // origocrust feeds both the component and the fiber
+        let plan = resolved(
+            "amethyst_component",
+            30,
+            vec![
+                resolved("origocrust", 30, vec![]),
+                resolved(
+                    "amethyst_fiber",
+                    30,
+                    vec![resolved("origocrust", 60, vec![])],
+                ),
+            ],
+        );
+
+        let chart = to_mermaid(&plan, &IdResolver);
+
+        assert!(chart.starts_with("graph TD\n"));
+        assert_eq!(chart.matches("origocrust[").count(), 1);
+        assert!(chart.contains("    origocrust[\"origocrust x90\"]\n"));
+        assert!(chart.contains("    origocrust -->|60| amethyst_fiber\n"));
+        assert!(chart.contains("    origocrust -->|30| amethyst_component\n"));
+    }
+
+    #[test]
+    fn test_unproduced_items_are_labeled() {
+        let plan = resolved(
+            "origocrust",
+            30,
+            vec![ProductionNode::Unresolved {
+                item_id: "originium_ore".into(),
+                amount: 30,
+            }],
+        );
+
+        let chart = to_mermaid(&plan, &IdResolver);
+
+        assert!(chart.contains("originium_ore[\"originium_ore x30 (missing recipe)\"]"));
+    }
+
+    struct Quoted;
+
+    impl NameResolver for Quoted {
+        fn name_of(&self, id: &str) -> String {
+            format!("\"{}\"", id)
+        }
+    }
+
+    #[test]
+    fn test_labels_use_name_resolver() {
+        let plan = resolved("origocrust", 30, vec![]);
+
+        let chart = to_mermaid(&plan, &Quoted);
+
+        assert!(chart.contains("    origocrust[\"#quot;origocrust#quot; x30\"]\n"));
+    }
+
+    #[test]
+    fn test_node_ids_are_escaped() {
+        let plan = resolved(
+            "origo|crust",
+            30,
+            vec![resolved("originium_ore", 30, vec![])],
+        );
+
+        let chart = to_mermaid(&plan, &IdResolver);
+
+        assert!(chart.contains("    origo#124;crust[\"origo#124;crust x30\"]\n"));
+        assert!(chart.contains("    originium_ore -->|30| origo#124;crust\n"));
+    }
+}
@@ -1,3 +1,25 @@
 mod display;
+mod flow;
+mod format;
+mod graph;
+mod html;
+#[cfg(feature = "data-files")]
+mod json;
+mod markdown;
+mod mermaid;
 
-pub use display::print_summary;
+pub use display::{
+    SummaryOptions, SummarySort, TreeStyle, print_summary, print_warnings, write_summary,
+    write_tag_breakdown,
+};
+pub use flow::{
+    FLOW_LAYER_SPACING, FLOW_MAX_LAYER_HEIGHT, FLOW_NODE_GAP, FLOW_NODE_WIDTH, FlowDiagram,
+    FlowLink, FlowNode, layout_flow,
+};
+pub use format::{RATE_PRECISION, format_decimal, format_duration, format_power, format_rate};
+pub use graph::{GraphFilter, RecipeEdge, RecipeGraph};
+pub use html::to_html;
+#[cfg(feature = "data-files")]
+pub use json::{recipes_to_json, to_json};
+pub use markdown::to_markdown;
+pub use mermaid::to_mermaid;
@@ -0,0 +1,210 @@
+//! Planning the construction of the machines a plan needs.
+
+use super::{
+    PlanOptions, PlanResult, PlanWarnings, batch_options, check_policies, finish_plan, resolve_tree,
+};
+use crate::config::GameData;
+use crate::error::ProductionError;
+use crate::models::{ItemId, MachineId, ProductionNode};
+
+/// Item ID of the root node of a bootstrap plan, see `bootstrap_plan`.
+pub const BOOTSTRAP_ROOT: &str = "bootstrap";
+
+/// Plans building every machine of `plan` within `within_secs`, from the
+/// recipes making each machine as an item, to show the materials and
+/// stations needed before the plan can run.
+///
+/// Each machine count is planned with `options` as a quantity, like
+/// `plan_batch`, so amounts in the result are totals, not rates. The
+/// root is a `BOOTSTRAP_ROOT` virtual root with one input per machine type
+/// in ID order. Machines without a construction recipe are `Unresolved`
+/// inputs. The bootstrap's own machines are not planned further. Fails
+/// like `plan_production_with_options`.
+pub fn bootstrap_plan(
+    data: &GameData,
+    plan: &ProductionNode,
+    within_secs: f64,
+    options: &PlanOptions,
+) -> Result<PlanResult, ProductionError> {
+    let options = batch_options(options, within_secs);
+    let mut machines: Vec<(MachineId, u32)> = plan
+        .total_machines()
+        .into_iter()
+        .filter(|(_, count)| *count > 0)
+        .collect();
+    machines.sort();
+
+    let mut warnings = PlanWarnings::new();
+    let inputs: Vec<ProductionNode> = machines
+        .iter()
+        .map(|(machine_id, count)| {
+            let item_id = ItemId::from(machine_id.as_str());
+            if data.recipes_by_output.contains_key(&item_id) {
+                resolve_tree(data, &item_id, *count, &options, &mut warnings)
+            } else {
+                ProductionNode::Unresolved {
+                    item_id,
+                    amount: *count,
+                }
+            }
+        })
+        .collect();
+
+    let root = ProductionNode::virtual_root(BOOTSTRAP_ROOT, inputs);
+    check_policies(finish_plan(data, root, warnings, &options), &options)
+}
+
+/// Lists the machines of a bootstrap plan that have no construction
+/// recipe, in ID order.
+pub fn unbuildable_machines(bootstrap: &ProductionNode) -> Vec<ItemId> {
+    match bootstrap {
+        ProductionNode::Resolved { inputs, .. } => inputs
+            .iter()
+            .filter_map(|input| match input {
+                ProductionNode::Unresolved { item_id, .. } => Some(item_id.clone()),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(all(test, feature = "data-files"))]
+mod tests {
+    use super::*;
+    use crate::planner::plan_production_with;
+
+    // Each refining unit takes 2 gearing components; the gearing unit
+    // itself has no construction recipe
+    const RECIPES: &str = r#"
+[[recipes]]
+id = "origocrust"
+by = "refining_unit"
+time = 6
+out = 1
+[recipes.inputs]
+originium_ore = 1
+
+[[recipes]]
+id = "originium_ore"
+by = "electric_mining_rig"
+time = 2
+out = 1
+is_source = true
+
+[[recipes]]
+id = "refining_unit"
+by = "gearing_unit"
+time = 10
+out = 1
+[recipes.inputs]
+gearing_component = 2
+
+[[recipes]]
+id = "gearing_component"
+by = "gearing_unit"
+time = 2
+out = 1
+[recipes.inputs]
+originium_ore = 3
+"#;
+
+    const MACHINES: &str = r#"
+[[machines]]
+id = "refining_unit"
+tier = 1
+power = 5
+
+[[machines]]
+id = "electric_mining_rig"
+tier = 1
+power = 5
+
+[[machines]]
+id = "gearing_unit"
+tier = 1
+power = 10
+"#;
+
+    fn bootstrap(within_secs: f64) -> ProductionNode {
+        let data = GameData::new(RECIPES, MACHINES).unwrap();
+        // 30 origocrust per minute at 6 seconds each need 3 refining units
+        let plan = plan_production_with(&data, "origocrust", 30).node;
+        bootstrap_plan(&data, &plan, within_secs, &PlanOptions::default())
+            .unwrap()
+            .node
+    }
+
+    #[test]
+    fn test_machine_counts_become_construction_quantities() {
+        let bootstrap = bootstrap(60.0);
+
+        assert!(bootstrap.is_virtual_root());
+        let ProductionNode::Resolved { inputs, .. } = &bootstrap else {
+            panic!("bootstrap root is not resolved");
+        };
+        assert_eq!(inputs.len(), 2);
+        assert_eq!(
+            inputs[0],
+            ProductionNode::Unresolved {
+                item_id: "electric_mining_rig".into(),
+                amount: 1,
+            }
+        );
+
+        let ProductionNode::Resolved {
+            item_id,
+            amount,
+            machine_count,
+            inputs: components,
+            ..
+        } = &inputs[1]
+        else {
+            panic!("refining unit is not resolved");
+        };
+        assert_eq!(item_id, "refining_unit");
+        assert_eq!(*amount, 3);
+        // 3 crafts of 10 seconds fit in one gearing unit within a minute
+        assert_eq!(*machine_count, 1);
+        // 2 components for each of the 3 units
+        assert_eq!(components[0].item_id(), "gearing_component");
+        assert_eq!(components[0].amount(), 6);
+        assert_eq!(
+            bootstrap.total_source_materials()[&ItemId::from("originium_ore")],
+            18
+        );
+    }
+
+    #[test]
+    fn test_time_limit_sizes_construction_machines() {
+        let bootstrap = bootstrap(10.0);
+
+        let ProductionNode::Resolved { inputs, .. } = &bootstrap else {
+            panic!("bootstrap root is not resolved");
+        };
+        // Each 10 second craft needs its own gearing unit
+        assert!(matches!(
+            inputs[1],
+            ProductionNode::Resolved {
+                machine_count: 3,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_machines_without_recipe_are_listed() {
+        let bootstrap = bootstrap(60.0);
+
+        assert_eq!(
+            unbuildable_machines(&bootstrap),
+            vec![ItemId::from("electric_mining_rig")]
+        );
+        // The bootstrap root adds no machines of its own
+        assert!(
+            !bootstrap
+                .total_machines()
+                .contains_key(&MachineId::default())
+        );
+    }
+}
@@ -1,7 +1,12 @@
 //! Production calculation utilities.
 
 use crate::constants::PRODUCTION_TIME_WINDOW;
-use crate::models::{Machine, Recipe};
+use crate::models::{ItemId, Machine, Quantity, Rate, Recipe};
+use std::fmt;
+
+/// Tolerance for float noise from rate conversions when rounding the
+/// machine count up.
+const MACHINE_EPSILON: f64 = 1e-9;
 
 /// Result of production calculations for a single recipe.
 #[derive(Debug, Clone, PartialEq)]
@@ -14,6 +19,46 @@ pub struct ProductionCalculation {
     pub load: f64,
     /// Total power consumption for all machines.
     pub power_usage: u32,
+    /// Total standby power of all machines while not crafting.
+    pub standby_power: u32,
+}
+
+/// A fixed quantity to produce within a time limit, for sizing machines for
+/// batch production instead of a steady rate.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BatchTarget {
+    /// Number of items to produce.
+    pub quantity: Quantity,
+    /// Time allowed to produce them, in seconds.
+    pub within_secs: f64,
+}
+
+/// Formats as e.g. "needs 3 machines at 83% load, 15 power", with the
+/// load rounded to a whole percent.
+impl fmt::Display for ProductionCalculation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.machine_count {
+            0 => write!(f, "needs no machines")?,
+            1 => write!(f, "needs 1 machine")?,
+            count => write!(f, "needs {} machines", count)?,
+        }
+        if self.machine_count > 0 {
+            write!(f, " at {}% load", (self.load * 100.0).round() as u32)?;
+        }
+        write!(f, ", {} power", self.power_usage)
+    }
+}
+
+/// Net amount of `item_id` one craft adds, see `Recipe::net_output`. An
+/// unlisted output counts as one, like the planner's `OutputDefaulted`, and
+/// a recipe giving back no more than it takes counts as making one, which
+/// the planner reports as `NoNetOutput`.
+fn output_per_craft(recipe: &Recipe, item_id: &ItemId) -> u32 {
+    if recipe.outputs.contains_key(item_id) {
+        recipe.net_output(item_id).max(1)
+    } else {
+        1
+    }
 }
 
 /// Calculates production requirements for a recipe.
@@ -21,21 +66,22 @@ pub struct ProductionCalculation {
 /// # Arguments
 /// * `recipe` - The recipe to calculate for
 /// * `machine` - The machine used (None for manual crafting)
-/// * `target_amount` - Desired output per time window
+/// * `target_rate` - Desired output rate
 /// * `item_id` - The target item ID to look up output count
 pub fn calculate(
     recipe: &Recipe,
     machine: Option<&Machine>,
-    target_amount: u32,
-    item_id: &str,
+    target_rate: Rate,
+    item_id: &ItemId,
 ) -> ProductionCalculation {
     let power = machine.map(|m| m.power).unwrap_or(0);
-    let output_per_craft = *recipe.outputs.get(item_id).unwrap_or(&1) as f64;
-    let recipe_time = recipe.time as f64;
+    let idle_power = machine.and_then(|m| m.idle_power).unwrap_or(0);
+    let output_per_craft = output_per_craft(recipe, item_id) as f64;
+    let recipe_time = recipe.time;
 
-    let required_crafts = target_amount as f64 / output_per_craft;
+    let required_crafts = target_rate.over(PRODUCTION_TIME_WINDOW).items() / output_per_craft;
     let required_machines = recipe_time * required_crafts / PRODUCTION_TIME_WINDOW;
-    let machine_count = required_machines.ceil() as u32;
+    let machine_count = (required_machines - MACHINE_EPSILON).ceil().max(0.0) as u32;
 
     let load = if machine_count > 0 {
         required_machines / machine_count as f64
@@ -44,12 +90,82 @@ pub fn calculate(
     };
 
     let power_usage = (power as u64 * machine_count as u64).min(u32::MAX as u64) as u32;
+    let standby_power = (idle_power as u64 * machine_count as u64).min(u32::MAX as u64) as u32;
 
     ProductionCalculation {
         required_crafts,
         machine_count,
         load,
         power_usage,
+        standby_power,
+    }
+}
+
+/// Calculates the machines needed to finish a batch in time.
+///
+/// Unlike `calculate`, crafts are whole: a machine only counts the crafts
+/// it can finish within the time limit, so e.g. 5 crafts of 4 seconds in
+/// 10 seconds need 3 machines, where the same average rate needs 2. If a
+/// single craft takes longer than the limit, every craft gets its own
+/// machine, which is the fastest possible.
+pub fn calculate_batch(
+    recipe: &Recipe,
+    machine: Option<&Machine>,
+    target: &BatchTarget,
+    item_id: &ItemId,
+) -> ProductionCalculation {
+    let power = machine.map(|m| m.power).unwrap_or(0);
+    let idle_power = machine.and_then(|m| m.idle_power).unwrap_or(0);
+    let output_per_craft = output_per_craft(recipe, item_id);
+
+    let crafts = target.quantity.whole().div_ceil(output_per_craft);
+    let machine_count = if crafts == 0 {
+        0
+    } else if recipe.time <= 0.0 {
+        1
+    } else {
+        let crafts_per_machine = (target.within_secs / recipe.time).floor() as u32;
+        if crafts_per_machine == 0 {
+            crafts
+        } else {
+            crafts.div_ceil(crafts_per_machine)
+        }
+    };
+
+    let busy_time = crafts as f64 * recipe.time;
+    let available_time = machine_count as f64 * target.within_secs;
+    let load = if machine_count > 0 && available_time > 0.0 {
+        (busy_time / available_time).min(1.0)
+    } else {
+        1.0
+    };
+
+    let power_usage = (power as u64 * machine_count as u64).min(u32::MAX as u64) as u32;
+    let standby_power = (idle_power as u64 * machine_count as u64).min(u32::MAX as u64) as u32;
+
+    ProductionCalculation {
+        required_crafts: crafts as f64,
+        machine_count,
+        load,
+        power_usage,
+        standby_power,
+    }
+}
+
+/// Splits `machine_count` machines into blocks of at most `max_per_block`,
+/// e.g. 20 into 8+8+4. Returns no blocks when no split is needed, i.e.
+/// without a limit, with a limit of 0 or when the machines fit one block.
+pub fn split_into_blocks(machine_count: u32, max_per_block: Option<u32>) -> Vec<u32> {
+    match max_per_block {
+        Some(max) if max > 0 && machine_count > max => {
+            let mut blocks = vec![max; (machine_count / max) as usize];
+            let remainder = machine_count % max;
+            if remainder > 0 {
+                blocks.push(remainder);
+            }
+            blocks
+        }
+        _ => Vec::new(),
     }
 }
 
@@ -58,36 +174,41 @@ mod tests {
     use super::*;
     use std::collections::HashMap;
 
-    fn create_recipe(id: &str, by: &str, time: u32, outputs: Vec<(&str, u32)>) -> Recipe {
+    fn create_recipe(id: &str, by: &str, time: f64, outputs: Vec<(&str, u32)>) -> Recipe {
         Recipe::new_for_test(
-            id.to_string(),
-            by.to_string(),
+            id.into(),
+            by.into(),
             time,
             HashMap::new(),
-            outputs
-                .into_iter()
-                .map(|(k, v)| (k.to_string(), v))
-                .collect(),
+            outputs.into_iter().map(|(k, v)| (k.into(), v)).collect(),
             false,
         )
     }
 
     fn create_machine(id: &str, tier: u32, power: u32) -> Machine {
         Machine {
-            id: id.to_string(),
+            id: id.into(),
             tier,
             power,
+            input_ports: None,
+            output_ports: None,
+            idle_power: None,
         }
     }
 
     #[test]
     fn test_machine_count_rounds_up() {
         // origocrust: time=2, out=1
-        let recipe = create_recipe("origocrust", "refining_unit", 2, vec![("origocrust", 1)]);
+        let recipe = create_recipe("origocrust", "refining_unit", 2.0, vec![("origocrust", 1)]);
         let machine = create_machine("refining_unit", 1, 5);
 
         // Required machines = (2 * 31) / 60 = 1.033..., should round up to 2
-        let calc = calculate(&recipe, Some(&machine), 31, "origocrust");
+        let calc = calculate(
+            &recipe,
+            Some(&machine),
+            Rate::per_minute(31.0),
+            &"origocrust".into(),
+        );
 
         assert_eq!(calc.machine_count, 2);
     }
@@ -95,13 +216,23 @@ mod tests {
     #[test]
     fn test_load_calculation() {
         // amethyst_fiber: time=2, out=1
-        let recipe = create_recipe("amethyst_fiber", "refining_unit", 2, vec![("amethyst_fiber", 1)]);
+        let recipe = create_recipe(
+            "amethyst_fiber",
+            "refining_unit",
+            2.0,
+            vec![("amethyst_fiber", 1)],
+        );
         let machine = create_machine("refining_unit", 1, 5);
 
         // Required machines = (2 * 25) / 60 = 0.8333...
         // Machine count = 1 (rounded up)
         // Load = 0.8333... / 1 = 0.8333...
-        let calc = calculate(&recipe, Some(&machine), 25, "amethyst_fiber");
+        let calc = calculate(
+            &recipe,
+            Some(&machine),
+            Rate::per_minute(25.0),
+            &"amethyst_fiber".into(),
+        );
 
         assert_eq!(calc.machine_count, 1);
         assert!((calc.load - 0.8333333).abs() < 0.0001);
@@ -110,43 +241,193 @@ mod tests {
     #[test]
     fn test_power_usage() {
         // ferrium: time=2, out=1, uses grinding_unit with power=20
-        let recipe = create_recipe("ferrium", "refining_unit", 2, vec![("ferrium", 1)]);
+        let recipe = create_recipe("ferrium", "refining_unit", 2.0, vec![("ferrium", 1)]);
         let machine = create_machine("refining_unit", 1, 5);
 
         // Required machines = (2 * 90) / 60 = 3
         // Machine count = 3, power = 5
         // Power usage = 3 * 5 = 15
-        let calc = calculate(&recipe, Some(&machine), 90, "ferrium");
+        let calc = calculate(
+            &recipe,
+            Some(&machine),
+            Rate::per_minute(90.0),
+            &"ferrium".into(),
+        );
 
         assert_eq!(calc.machine_count, 3);
         assert_eq!(calc.power_usage, 15);
+        assert_eq!(calc.standby_power, 0);
+    }
+
+    #[test]
+    fn test_standby_power_from_idle_power() {
+        let recipe = create_recipe("ferrium", "refining_unit", 2.0, vec![("ferrium", 1)]);
+        let mut machine = create_machine("refining_unit", 1, 5);
+        machine.idle_power = Some(2);
+
+        // 3 machines on standby at 2 each
+        let calc = calculate(
+            &recipe,
+            Some(&machine),
+            Rate::per_minute(90.0),
+            &"ferrium".into(),
+        );
+
+        assert_eq!(calc.standby_power, 6);
     }
 
     #[test]
     fn test_required_crafts_with_multiple_output() {
         // carbon from jincao: time=2, out=2
-        let recipe = create_recipe("carbon", "refining_unit", 2, vec![("carbon", 2)]);
+        let recipe = create_recipe("carbon", "refining_unit", 2.0, vec![("carbon", 2)]);
         let machine = create_machine("refining_unit", 1, 5);
 
         // Required crafts = 10 / 2 = 5.0
-        let calc = calculate(&recipe, Some(&machine), 10, "carbon");
+        let calc = calculate(
+            &recipe,
+            Some(&machine),
+            Rate::per_minute(10.0),
+            &"carbon".into(),
+        );
 
         assert_eq!(calc.required_crafts, 5.0);
     }
 
+    #[test]
+    fn test_fractional_time() {
+        // time=1.5, out=1
+        let recipe = create_recipe("origocrust", "refining_unit", 1.5, vec![("origocrust", 1)]);
+        let machine = create_machine("refining_unit", 1, 5);
+
+        // Required machines = (1.5 * 50) / 60 = 1.25, rounded up to 2
+        // A whole-second time of 1 would give 0.83 (1 machine), 2 would give 1.67 (2 machines)
+        let calc = calculate(
+            &recipe,
+            Some(&machine),
+            Rate::per_minute(50.0),
+            &"origocrust".into(),
+        );
+
+        assert_eq!(calc.machine_count, 2);
+        assert!((calc.load - 0.625).abs() < 0.0001);
+    }
+
     #[test]
     fn test_zero_time_recipe() {
         // Machine construction recipes have time=0
-        let recipe = create_recipe("refining_unit", "hand", 0, vec![("refining_unit", 1)]);
+        let recipe = create_recipe("refining_unit", "hand", 0.0, vec![("refining_unit", 1)]);
         let machine = create_machine("hand", 0, 0);
 
         // Required machines = (0 * 10) / 60 = 0
         // Machine count = 0 (rounded up from 0)
         // Load should be 1.0 when machine_count is 0
-        let calc = calculate(&recipe, Some(&machine), 10, "refining_unit");
+        let calc = calculate(
+            &recipe,
+            Some(&machine),
+            Rate::per_minute(10.0),
+            &"refining_unit".into(),
+        );
 
         assert_eq!(calc.machine_count, 0);
         assert_eq!(calc.load, 1.0);
         assert_eq!(calc.power_usage, 0);
     }
+
+    #[test]
+    fn test_display() {
+        let recipe = create_recipe(
+            "amethyst_fiber",
+            "refining_unit",
+            2.0,
+            vec![("amethyst_fiber", 1)],
+        );
+        let machine = create_machine("refining_unit", 1, 5);
+
+        // 2.5 machines needed: 3 machines at 83% load
+        let calc = calculate(
+            &recipe,
+            Some(&machine),
+            Rate::per_minute(75.0),
+            &"amethyst_fiber".into(),
+        );
+        assert_eq!(calc.to_string(), "needs 3 machines at 83% load, 15 power");
+
+        let full = calculate(
+            &recipe,
+            Some(&machine),
+            Rate::per_minute(30.0),
+            &"amethyst_fiber".into(),
+        );
+        assert_eq!(full.to_string(), "needs 1 machine at 100% load, 5 power");
+
+        let none = calculate(
+            &recipe,
+            Some(&machine),
+            Rate::per_minute(0.0),
+            &"amethyst_fiber".into(),
+        );
+        assert_eq!(none.to_string(), "needs no machines, 0 power");
+    }
+
+    #[test]
+    fn test_batch_sizing_against_rate_sizing() {
+        let recipe = create_recipe("origocrust", "refining_unit", 4.0, vec![("origocrust", 1)]);
+        let machine = create_machine("refining_unit", 1, 5);
+        let item_id = "origocrust".into();
+
+        // 5 items in 10 seconds is 30 per minute on average, which 2
+        // machines sustain, but 2 machines only finish 4 crafts in time
+        let rate = calculate(&recipe, Some(&machine), Rate::per_minute(30.0), &item_id);
+        let batch = BatchTarget {
+            quantity: Quantity::from(5),
+            within_secs: 10.0,
+        };
+        let batched = calculate_batch(&recipe, Some(&machine), &batch, &item_id);
+        assert_eq!(rate.machine_count, 2);
+        assert_eq!(batched.machine_count, 3);
+        assert_eq!(batched.required_crafts, 5.0);
+        assert!((batched.load - 20.0 / 30.0).abs() < 1e-9);
+        assert_eq!(batched.power_usage, 15);
+
+        // With whole crafts fitting the limit, both agree
+        let batch = BatchTarget {
+            quantity: Quantity::from(15),
+            within_secs: 60.0,
+        };
+        let batched = calculate_batch(&recipe, Some(&machine), &batch, &item_id);
+        let rate = calculate(&recipe, Some(&machine), Rate::per_minute(15.0), &item_id);
+        assert_eq!(batched.machine_count, rate.machine_count);
+        assert_eq!(batched.machine_count, 1);
+    }
+
+    #[test]
+    fn test_batch_craft_longer_than_limit() {
+        let recipe = create_recipe("origocrust", "refining_unit", 4.0, vec![("origocrust", 2)]);
+        let batch = BatchTarget {
+            quantity: Quantity::from(5),
+            within_secs: 2.0,
+        };
+
+        // 3 crafts of 2 items, each on its own machine
+        let batched = calculate_batch(&recipe, None, &batch, &"origocrust".into());
+        assert_eq!(batched.machine_count, 3);
+        assert_eq!(batched.load, 1.0);
+    }
+
+    #[test]
+    fn test_split_into_blocks_with_remainder() {
+        assert_eq!(split_into_blocks(20, Some(8)), vec![8, 8, 4]);
+    }
+
+    #[test]
+    fn test_split_into_blocks_exact_multiple() {
+        assert_eq!(split_into_blocks(16, Some(8)), vec![8, 8]);
+    }
+
+    #[test]
+    fn test_no_blocks_when_within_limit() {
+        assert!(split_into_blocks(8, Some(8)).is_empty());
+        assert!(split_into_blocks(20, None).is_empty());
+        assert!(split_into_blocks(20, Some(0)).is_empty());
+    }
 }
@@ -0,0 +1,243 @@
+//! Side-by-side comparison of the recipes that produce an item.
+
+use crate::config::GameData;
+use crate::models::{ItemId, ProductionNode, RecipeUid};
+use std::collections::HashSet;
+
+use super::dependency_resolver;
+use super::deposits::check_deposits;
+use super::machine_limits::check_machine_limits;
+use super::options::PlanOptions;
+use super::source_rounding::round_sources;
+use super::warnings::{PlanResult, PlanWarnings};
+
+/// Relative importance of each metric in `RecipeComparison::score`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScoreWeights {
+    pub power: f64,
+    pub machines: f64,
+    pub raw_materials: f64,
+}
+
+impl Default for ScoreWeights {
+    fn default() -> Self {
+        ScoreWeights {
+            power: 1.0,
+            machines: 1.0,
+            raw_materials: 1.0,
+        }
+    }
+}
+
+/// The plan obtained by producing an item with one particular recipe.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecipeComparison {
+    /// Unique ID of the recipe used for the target item.
+    pub recipe_id: RecipeUid,
+    pub result: PlanResult,
+    /// Total power of the plan.
+    pub power: u32,
+    /// Total number of machines in the plan.
+    pub machines: u32,
+    /// Total raw material amount consumed per minute.
+    pub raw_materials: u32,
+    /// Largest `(power, machines, raw_materials)` among the compared recipes.
+    maxima: (u32, u32, u32),
+}
+
+impl RecipeComparison {
+    /// The production tree of this alternative.
+    pub fn node(&self) -> &ProductionNode {
+        &self.result.node
+    }
+
+    /// Combines the metrics into a single cost. Lower is better.
+    ///
+    /// Each metric is divided by the largest value among the compared
+    /// recipes, so it lies in `0.0..=1.0` before weighting.
+    pub fn score(&self, weights: &ScoreWeights) -> f64 {
+        let normalized = |value: u32, max: u32| {
+            if max == 0 {
+                0.0
+            } else {
+                value as f64 / max as f64
+            }
+        };
+
+        weights.power * normalized(self.power, self.maxima.0)
+            + weights.machines * normalized(self.machines, self.maxima.1)
+            + weights.raw_materials * normalized(self.raw_materials, self.maxima.2)
+    }
+}
+
+/// Plans `item_id` once per recipe that produces it.
+///
+/// Only the target item's recipe is varied; inputs use the usual recipe
+/// selection. Recipes rejected by the tag filters in `options` are left
+/// out. Results follow the order of `recipes_by_output`.
+pub fn compare_recipes(
+    data: &GameData,
+    item_id: impl Into<ItemId>,
+    amount: u32,
+    options: &PlanOptions,
+) -> Vec<RecipeComparison> {
+    let item_id = item_id.into();
+    let Some(candidates) = data.recipes_by_output.get(&item_id) else {
+        return Vec::new();
+    };
+
+    let mut comparisons: Vec<RecipeComparison> = candidates
+        .iter()
+        .filter_map(|recipe_id| data.recipes.get(recipe_id).map(|r| (recipe_id, r)))
+        .filter(|(_, recipe)| options.allows_recipe(recipe))
+        .map(|(recipe_id, recipe)| {
+            let mut warnings = PlanWarnings::new();
+            let node = dependency_resolver::resolve_with_recipe(
+                recipe,
+                &data.recipes,
+                &data.recipes_by_output,
+                &data.machines,
+                &item_id,
+                amount,
+                options,
+                &mut HashSet::new(),
+                &mut warnings,
+            );
+            for warning in check_deposits(&node, &data.recipes, options.batch_within_secs) {
+                warnings.push(warning);
+            }
+
+            RecipeComparison {
+                recipe_id: recipe_id.clone(),
+                power: node.total_power(),
+                machines: node.total_machines().values().sum(),
+                raw_materials: node.total_source_materials().values().sum(),
+                result: PlanResult {
+                    source_rounding: options
+                        .round_sources_to
+                        .map(|multiple| round_sources(&node, multiple))
+                        .unwrap_or_default(),
+                    machine_limit_violations: check_machine_limits(&node, &options.machine_limits),
+                    node,
+                    warnings,
+                },
+                maxima: (0, 0, 0),
+            }
+        })
+        .collect();
+
+    let maxima = comparisons.iter().fold((0, 0, 0), |max, c| {
+        (
+            max.0.max(c.power),
+            max.1.max(c.machines),
+            max.2.max(c.raw_materials),
+        )
+    });
+    for comparison in &mut comparisons {
+        comparison.maxima = maxima;
+    }
+
+    comparisons
+}
+
+/// Returns the comparison with the lowest score.
+pub fn best_recipe<'a>(
+    comparisons: &'a [RecipeComparison],
+    weights: &ScoreWeights,
+) -> Option<&'a RecipeComparison> {
+    comparisons
+        .iter()
+        .min_by(|a, b| a.score(weights).total_cmp(&b.score(weights)))
+}
+
+#[cfg(all(test, feature = "data-files"))]
+mod tests {
+    use super::*;
+    use crate::models::MachineId;
+
+    const MACHINES_TOML: &str = r#"
+[[machines]]
+id = "electric_mining_rig"
+tier = 1
+power = 5
+
+[[machines]]
+id = "refining_unit"
+tier = 1
+power = 50
+
+[[machines]]
+id = "shredding_unit"
+tier = 1
+power = 5
+"#;
+
+    // The refining recipe draws more power, the shredding one uses more ore
+    const RECIPES_TOML: &str = r#"
+[[recipes]]
+id = "originium_ore"
+by = "electric_mining_rig"
+time = 2
+out = 1
+is_source = true
+
+[[recipes]]
+id = "origocrust"
+by = "refining_unit"
+time = 2
+out = 1
+[recipes.inputs]
+originium_ore = 1
+
+[[recipes]]
+id = "origocrust"
+by = "shredding_unit"
+time = 2
+out = 1
+[recipes.inputs]
+originium_ore = 4
+"#;
+
+    fn best_machine(comparisons: &[RecipeComparison], weights: &ScoreWeights) -> MachineId {
+        match best_recipe(comparisons, weights).unwrap().node() {
+            ProductionNode::Resolved { machine_id, .. } => machine_id.clone(),
+            _ => panic!("Expected Resolved node"),
+        }
+    }
+
+    #[test]
+    fn test_weights_change_best_recipe() {
+        let data = GameData::new(RECIPES_TOML, MACHINES_TOML).unwrap();
+        let comparisons = compare_recipes(&data, "origocrust", 30, &PlanOptions::default());
+        assert_eq!(comparisons.len(), 2);
+
+        let power_first = ScoreWeights {
+            power: 1.0,
+            machines: 0.0,
+            raw_materials: 0.0,
+        };
+        let materials_first = ScoreWeights {
+            power: 0.0,
+            machines: 0.0,
+            raw_materials: 1.0,
+        };
+
+        assert_eq!(best_machine(&comparisons, &power_first), "shredding_unit");
+        assert_eq!(
+            best_machine(&comparisons, &materials_first),
+            "refining_unit"
+        );
+    }
+
+    #[test]
+    fn test_scores_are_normalized() {
+        let data = GameData::new(RECIPES_TOML, MACHINES_TOML).unwrap();
+        let comparisons = compare_recipes(&data, "origocrust", 30, &PlanOptions::default());
+
+        for comparison in &comparisons {
+            let score = comparison.score(&ScoreWeights::default());
+            assert!((0.0..=3.0).contains(&score));
+        }
+        assert!(compare_recipes(&data, "unknown", 30, &PlanOptions::default()).is_empty());
+    }
+}
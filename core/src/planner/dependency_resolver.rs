@@ -1,10 +1,13 @@
 //! Dependency resolution for production planning.
 
-use crate::models::{Machine, ProductionNode, Recipe};
+use crate::models::{ItemId, Machine, MachineId, ProductionNode, Rate, Recipe, RecipeUid};
 use std::collections::{HashMap, HashSet};
 
 use super::calculator;
+use super::options::{CyclePolicy, PlanOptions};
 use super::recipe_selector;
+use super::trace::TraceEvent;
+use super::warnings::{PlanWarning, PlanWarnings};
 
 /// Recursively resolves production dependencies for an item.
 ///
@@ -14,20 +17,243 @@ use super::recipe_selector;
 /// * `machines` - All available machines indexed by ID
 /// * `item_id` - The item to produce
 /// * `amount` - Desired output amount per time window
+/// * `options` - Settings that adjust how the plan is built
 /// * `visiting` - Set of items currently being resolved (for cycle detection)
+/// * `warnings` - Accumulator for conditions worked around while resolving
 ///
 /// # Returns
 /// A `ProductionNode` representing the production tree for the item.
+#[allow(clippy::too_many_arguments)]
 pub fn resolve(
-    recipes: &HashMap<String, Recipe>,
-    recipes_by_output: &HashMap<String, Vec<String>>,
-    machines: &HashMap<String, Machine>,
-    item_id: &str,
+    recipes: &HashMap<RecipeUid, Recipe>,
+    recipes_by_output: &HashMap<ItemId, Vec<RecipeUid>>,
+    machines: &HashMap<MachineId, Machine>,
+    item_id: &ItemId,
     amount: u32,
-    visiting: &mut HashSet<String>,
+    options: &PlanOptions,
+    visiting: &mut HashSet<ItemId>,
+    warnings: &mut PlanWarnings,
 ) -> ProductionNode {
+    resolve_in_branch(
+        recipes,
+        recipes_by_output,
+        machines,
+        item_id,
+        amount,
+        options,
+        visiting,
+        &mut Vec::new(),
+        warnings,
+        None,
+    )
+}
+
+/// Subtrees already resolved, reused when the same amount of an item is
+/// needed again, e.g. across the plans of `GameData::plan_all`.
+///
+/// Only items whose dependencies never lead back to themselves are cached:
+/// no candidate recipe below them has a cyclic input, so their recipe
+/// choices do not depend on the items being resolved above them. A cache
+/// is only valid for one `PlanOptions`, without a tracer or progress
+/// callback.
+#[derive(Debug, Default)]
+pub(crate) struct SubtreeCache {
+    acyclic: HashMap<ItemId, bool>,
+    nodes: HashMap<(ItemId, u32), (ProductionNode, PlanWarnings)>,
+}
+
+impl SubtreeCache {
+    /// Returns true if no recipe for the item, or for anything it may
+    /// depend on, has an input leading back to an item on the way there.
+    fn is_acyclic(
+        &mut self,
+        item_id: &ItemId,
+        recipes: &HashMap<RecipeUid, Recipe>,
+        recipes_by_output: &HashMap<ItemId, Vec<RecipeUid>>,
+        path: &mut HashSet<ItemId>,
+    ) -> bool {
+        if let Some(acyclic) = self.acyclic.get(item_id) {
+            return *acyclic;
+        }
+        if !path.insert(item_id.clone()) {
+            return false;
+        }
+
+        let inputs: Vec<&ItemId> = recipes_by_output
+            .get(item_id)
+            .into_iter()
+            .flatten()
+            .filter_map(|recipe_id| recipes.get(recipe_id))
+            .flat_map(|recipe| {
+                let alternatives = recipe.any_of.iter().flat_map(|group| &group.items);
+                recipe.inputs.keys().chain(alternatives)
+            })
+            .collect();
+        let acyclic = inputs
+            .into_iter()
+            .all(|input_id| self.is_acyclic(input_id, recipes, recipes_by_output, path));
+
+        path.remove(item_id);
+        self.acyclic.insert(item_id.clone(), acyclic);
+        acyclic
+    }
+}
+
+/// Like `resolve`, reusing and filling `cache`.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn resolve_cached(
+    recipes: &HashMap<RecipeUid, Recipe>,
+    recipes_by_output: &HashMap<ItemId, Vec<RecipeUid>>,
+    machines: &HashMap<MachineId, Machine>,
+    item_id: &ItemId,
+    amount: u32,
+    options: &PlanOptions,
+    warnings: &mut PlanWarnings,
+    cache: &mut SubtreeCache,
+) -> ProductionNode {
+    resolve_in_branch(
+        recipes,
+        recipes_by_output,
+        machines,
+        item_id,
+        amount,
+        options,
+        &mut HashSet::new(),
+        &mut Vec::new(),
+        warnings,
+        Some(cache),
+    )
+}
+
+/// Resolves an item below the recipes that produced `byproducts`, taking
+/// the subtree from `cache` when it has one that fits.
+///
+/// `byproducts` holds the byproducts of the recipes higher up in the
+/// branch, with the amounts not consumed yet, when
+/// `PlanOptions::prefer_byproducts` is set. Entries added below are removed
+/// before returning; amounts consumed below stay consumed.
+#[allow(clippy::too_many_arguments)]
+fn resolve_in_branch(
+    recipes: &HashMap<RecipeUid, Recipe>,
+    recipes_by_output: &HashMap<ItemId, Vec<RecipeUid>>,
+    machines: &HashMap<MachineId, Machine>,
+    item_id: &ItemId,
+    amount: u32,
+    options: &PlanOptions,
+    visiting: &mut HashSet<ItemId>,
+    byproducts: &mut Vec<(ItemId, u32)>,
+    warnings: &mut PlanWarnings,
+    mut cache: Option<&mut SubtreeCache>,
+) -> ProductionNode {
+    // Byproducts from above change what the subtree needs
+    let cacheable = byproducts.is_empty()
+        && cache.as_deref_mut().is_some_and(|cache| {
+            cache.is_acyclic(item_id, recipes, recipes_by_output, &mut HashSet::new())
+        });
+    let cache = match cache {
+        Some(cache) if cacheable => cache,
+        cache => {
+            return resolve_item(
+                recipes,
+                recipes_by_output,
+                machines,
+                item_id,
+                amount,
+                options,
+                visiting,
+                byproducts,
+                warnings,
+                cache,
+            );
+        }
+    };
+
+    let key = (item_id.clone(), amount);
+    let (node, subtree_warnings) = match cache.nodes.get(&key) {
+        Some(cached) => cached.clone(),
+        None => {
+            let mut subtree_warnings = PlanWarnings::new();
+            let node = resolve_item(
+                recipes,
+                recipes_by_output,
+                machines,
+                item_id,
+                amount,
+                options,
+                visiting,
+                byproducts,
+                &mut subtree_warnings,
+                Some(&mut *cache),
+            );
+            cache
+                .nodes
+                .insert(key, (node.clone(), subtree_warnings.clone()));
+            (node, subtree_warnings)
+        }
+    };
+    for warning in subtree_warnings.into_vec() {
+        warnings.push(warning);
+    }
+    node
+}
+
+/// Resolves an item with its best recipe, see `resolve_in_branch`.
+#[allow(clippy::too_many_arguments)]
+fn resolve_item(
+    recipes: &HashMap<RecipeUid, Recipe>,
+    recipes_by_output: &HashMap<ItemId, Vec<RecipeUid>>,
+    machines: &HashMap<MachineId, Machine>,
+    item_id: &ItemId,
+    amount: u32,
+    options: &PlanOptions,
+    visiting: &mut HashSet<ItemId>,
+    byproducts: &mut Vec<(ItemId, u32)>,
+    warnings: &mut PlanWarnings,
+    cache: Option<&mut SubtreeCache>,
+) -> ProductionNode {
+    // Prefer the game data's shared copy of the ID over the caller's
+    let item_id = recipes_by_output
+        .get_key_value(item_id)
+        .map_or(item_id, |(id, _)| id);
+
+    let depth = visiting.len();
+    options.trace(|| TraceEvent::Requested {
+        depth,
+        item_id: item_id.clone(),
+        amount,
+    });
+
+    // Externally supplied items are not produced by this plan
+    if options.external_items.contains(item_id) {
+        options.trace(|| TraceEvent::External {
+            depth,
+            item_id: item_id.clone(),
+        });
+        return ProductionNode::External {
+            item_id: item_id.clone(),
+            amount,
+        };
+    }
+
     // Mark item as being visited (cycle detection)
-    visiting.insert(item_id.to_string());
+    visiting.insert(item_id.clone());
+
+    options.trace(|| TraceEvent::Candidates {
+        depth,
+        item_id: item_id.clone(),
+        ranked: recipe_selector::rank_recipes(
+            item_id,
+            recipes,
+            recipes_by_output,
+            machines,
+            visiting,
+            byproducts,
+            options,
+        )
+        .into_iter()
+        .map(Recipe::compute_unique_id)
+        .collect(),
+    });
 
     let result = match recipe_selector::select_best_recipe(
         item_id,
@@ -35,6 +261,8 @@ pub fn resolve(
         recipes_by_output,
         machines,
         visiting,
+        byproducts,
+        options,
     ) {
         Some(recipe) => build_resolved_node(
             recipe,
@@ -43,12 +271,26 @@ pub fn resolve(
             machines,
             item_id,
             amount,
+            options,
             visiting,
+            byproducts,
+            warnings,
+            cache,
         ),
-        None => ProductionNode::Unresolved {
-            item_id: item_id.to_string(),
-            amount,
-        },
+        None => {
+            options.trace(|| TraceEvent::Unresolved {
+                depth,
+                item_id: item_id.clone(),
+            });
+            warnings.push(PlanWarning::MissingRecipe {
+                item_id: item_id.clone(),
+            });
+
+            ProductionNode::Unresolved {
+                item_id: item_id.clone(),
+                amount,
+            }
+        }
     };
 
     // Backtrack
@@ -57,60 +299,252 @@ pub fn resolve(
     result
 }
 
+/// Resolves an item using the given recipe instead of the selected one.
+///
+/// Inputs are still resolved with the usual recipe selection.
+#[allow(clippy::too_many_arguments)]
+pub fn resolve_with_recipe(
+    recipe: &Recipe,
+    recipes: &HashMap<RecipeUid, Recipe>,
+    recipes_by_output: &HashMap<ItemId, Vec<RecipeUid>>,
+    machines: &HashMap<MachineId, Machine>,
+    item_id: &ItemId,
+    amount: u32,
+    options: &PlanOptions,
+    visiting: &mut HashSet<ItemId>,
+    warnings: &mut PlanWarnings,
+) -> ProductionNode {
+    visiting.insert(item_id.clone());
+
+    let result = build_resolved_node(
+        recipe,
+        recipes,
+        recipes_by_output,
+        machines,
+        item_id,
+        amount,
+        options,
+        visiting,
+        &mut Vec::new(),
+        warnings,
+        None,
+    );
+
+    visiting.remove(item_id);
+
+    result
+}
+
+/// Takes up to `amount` of `item_id` from the byproducts left in the
+/// branch and returns how much was taken.
+fn take_byproduct(byproducts: &mut [(ItemId, u32)], item_id: &ItemId, amount: u32) -> u32 {
+    let mut taken = 0;
+    for (_, left) in byproducts.iter_mut().filter(|(id, _)| id == item_id) {
+        let take = (*left).min(amount - taken);
+        *left -= take;
+        taken += take;
+    }
+    taken
+}
+
 /// Builds a resolved production node with its children.
+#[allow(clippy::too_many_arguments)]
 fn build_resolved_node(
     recipe: &Recipe,
-    recipes: &HashMap<String, Recipe>,
-    recipes_by_output: &HashMap<String, Vec<String>>,
-    machines: &HashMap<String, Machine>,
-    item_id: &str,
+    recipes: &HashMap<RecipeUid, Recipe>,
+    recipes_by_output: &HashMap<ItemId, Vec<RecipeUid>>,
+    machines: &HashMap<MachineId, Machine>,
+    item_id: &ItemId,
     amount: u32,
-    visiting: &mut HashSet<String>,
+    options: &PlanOptions,
+    visiting: &mut HashSet<ItemId>,
+    byproducts: &mut Vec<(ItemId, u32)>,
+    warnings: &mut PlanWarnings,
+    mut cache: Option<&mut SubtreeCache>,
 ) -> ProductionNode {
     let machine = machines.get(&recipe.by);
-    let machine_id = machine
-        .map(|m| m.id.clone())
-        .unwrap_or_else(|| "missing_machine".to_string());
+    let machine_id = match machine {
+        Some(m) => m.id.clone(),
+        None => {
+            warnings.push(PlanWarning::MissingMachine {
+                item_id: item_id.clone(),
+                recipe_id: recipe.compute_unique_id(),
+                machine_id: recipe.by.clone(),
+            });
+            MachineId::from("missing_machine")
+        }
+    };
+
+    if !recipe.outputs.contains_key(item_id) {
+        warnings.push(PlanWarning::OutputDefaulted {
+            item_id: item_id.clone(),
+            recipe_id: recipe.compute_unique_id(),
+        });
+    } else if recipe.net_output(item_id) == 0 {
+        warnings.push(PlanWarning::NoNetOutput {
+            item_id: item_id.clone(),
+            recipe_id: recipe.compute_unique_id(),
+        });
+    }
 
-    let calc = calculator::calculate(recipe, machine, amount, item_id);
+    let calc = match options.batch_within_secs {
+        Some(within_secs) => {
+            let target = calculator::BatchTarget {
+                quantity: amount.into(),
+                within_secs,
+            };
+            calculator::calculate_batch(recipe, machine, &target, item_id)
+        }
+        None => calculator::calculate(recipe, machine, Rate::per_minute(amount as f64), item_id),
+    };
+
+    // The item itself is already in `visiting`
+    let depth = visiting.len().saturating_sub(1);
+    options.trace(|| TraceEvent::Chosen {
+        depth,
+        item_id: item_id.clone(),
+        recipe_id: recipe.compute_unique_id(),
+    });
+    options.trace(|| TraceEvent::Computed {
+        depth,
+        item_id: item_id.clone(),
+        crafts: calc.required_crafts,
+        machines: calc.machine_count,
+    });
 
-    let children: Vec<ProductionNode> = recipe
+    // Source recipes are only leaves when asked to skip their inputs
+    let expand_inputs = !(recipe.is_source && options.leaf_sources);
+
+    // Returned items recirculate, so only the make-up amount is planned
+    // and fully returned inputs are left out
+    let mut recipe_inputs: Vec<(&ItemId, u32)> = recipe
         .inputs
-        .iter()
-        .filter_map(|(input_id, input_count)| {
-            // Skip if already visiting (cycle prevention)
-            if visiting.contains(input_id) {
-                return None;
+        .keys()
+        .map(|input_id| (input_id, recipe.net_input(input_id)))
+        .filter(|(_, count)| *count > 0)
+        .collect();
+
+    // Each group of interchangeable inputs contributes its best item
+    for group in &recipe.any_of {
+        let Some(chosen) = recipe_selector::select_alternative(
+            &group.items,
+            recipes,
+            recipes_by_output,
+            machines,
+            visiting,
+            byproducts,
+            options,
+        ) else {
+            continue;
+        };
+        match recipe_inputs
+            .iter_mut()
+            .find(|(input_id, _)| *input_id == chosen)
+        {
+            Some((_, count)) => *count += group.count,
+            None => recipe_inputs.push((chosen, group.count)),
+        }
+    }
+    if options.sorted_inputs {
+        recipe_inputs.sort();
+    }
+
+    // The recipe's byproducts are available to everything below it
+    let branch_len = byproducts.len();
+    if options.prefer_byproducts {
+        byproducts.extend(recipe.net_byproducts(item_id).into_iter().map(|byproduct| {
+            let made = recipe.net_output(byproduct) as f64 * calc.required_crafts;
+            (byproduct.clone(), made.floor() as u32)
+        }));
+    }
+
+    let mut children = Vec::new();
+    for (input_id, input_count) in recipe_inputs.into_iter().filter(|_| expand_inputs) {
+        let needed = (input_count as f64 * calc.required_crafts).ceil() as u32;
+
+        // Byproducts made higher up cover part of the input
+        let covered = take_byproduct(byproducts, input_id, needed);
+        if covered > 0 {
+            children.push(ProductionNode::Shared {
+                item_id: input_id.clone(),
+                amount: covered,
+            });
+        }
+        let sub_amount = needed - covered;
+        if sub_amount == 0 {
+            continue;
+        }
+
+        // Already being resolved higher up (cycle prevention)
+        if visiting.contains(input_id) {
+            options.trace(|| TraceEvent::Cycle {
+                depth,
+                parent: item_id.clone(),
+                item_id: input_id.clone(),
+            });
+            if options.cycle_policy == CyclePolicy::BreakWithCycleNode {
+                children.push(ProductionNode::Cycle {
+                    item_id: input_id.clone(),
+                    amount: sub_amount,
+                });
+                continue;
             }
 
-            let sub_amount = (*input_count as f64 * calc.required_crafts).ceil() as u32;
+            warnings.push(PlanWarning::CyclicInputSkipped {
+                item_id: item_id.clone(),
+                recipe_id: recipe.compute_unique_id(),
+                input_id: input_id.clone(),
+            });
+            continue;
+        }
 
-            Some(resolve(
-                recipes,
-                recipes_by_output,
-                machines,
-                input_id,
-                sub_amount,
-                visiting,
-            ))
-        })
-        .collect();
+        options.trace(|| TraceEvent::Child {
+            depth,
+            parent: item_id.clone(),
+            item_id: input_id.clone(),
+            amount: sub_amount,
+        });
+        children.push(resolve_in_branch(
+            recipes,
+            recipes_by_output,
+            machines,
+            input_id,
+            sub_amount,
+            options,
+            visiting,
+            byproducts,
+            warnings,
+            cache.as_deref_mut(),
+        ));
+    }
+
+    byproducts.truncate(branch_len);
+
+    if let Some(progress) = &options.progress {
+        progress.node_resolved();
+    }
 
     ProductionNode::Resolved {
-        item_id: item_id.to_string(),
+        item_id: item_id.clone(),
         machine_id,
+        recipe_id: recipe.compute_unique_id(),
         amount,
         machine_count: calc.machine_count,
         load: calc.load,
         power_usage: calc.power_usage,
+        standby_power: calc.standby_power,
         inputs: children,
         is_source: recipe.is_source,
+        blocks: calculator::split_into_blocks(calc.machine_count, options.max_machines_per_node),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::GameData;
+    use crate::planner::Progress;
+    use std::sync::{Arc, Mutex};
 
     fn create_recipe(
         id: &str,
@@ -119,26 +553,23 @@ mod tests {
         outputs: Vec<(&str, u32)>,
     ) -> Recipe {
         Recipe::new_for_test(
-            id.to_string(),
-            by.to_string(),
-            60,
-            inputs
-                .into_iter()
-                .map(|(k, v)| (k.to_string(), v))
-                .collect(),
-            outputs
-                .into_iter()
-                .map(|(k, v)| (k.to_string(), v))
-                .collect(),
+            id.into(),
+            by.into(),
+            60.0,
+            inputs.into_iter().map(|(k, v)| (k.into(), v)).collect(),
+            outputs.into_iter().map(|(k, v)| (k.into(), v)).collect(),
             false,
         )
     }
 
     fn create_machine(id: &str, tier: u32, power: u32) -> Machine {
         Machine {
-            id: id.to_string(),
+            id: id.into(),
             tier,
             power,
+            input_ports: None,
+            output_ports: None,
+            idle_power: None,
         }
     }
 
@@ -164,56 +595,26 @@ mod tests {
             vec![("origocrust_powder", 1)],
         );
 
-        let mut recipes = HashMap::new();
-        recipes.insert(
-            "originium_ore@electric_mining_rig[]".to_string(),
-            recipe_ore,
-        );
-        recipes.insert(
-            "originium_powder@shredding_unit[originium_ore:1]".to_string(),
-            recipe_powder,
-        );
-        recipes.insert(
-            "origocrust_powder@refining_unit[originium_powder:1]".to_string(),
-            recipe_crust_powder,
-        );
-
-        let mut recipes_by_output = HashMap::new();
-        recipes_by_output.insert(
-            "originium_ore".to_string(),
-            vec!["originium_ore@electric_mining_rig[]".to_string()],
-        );
-        recipes_by_output.insert(
-            "originium_powder".to_string(),
-            vec!["originium_powder@shredding_unit[originium_ore:1]".to_string()],
-        );
-        recipes_by_output.insert(
-            "origocrust_powder".to_string(),
-            vec!["origocrust_powder@refining_unit[originium_powder:1]".to_string()],
-        );
-
-        let mut machines = HashMap::new();
-        machines.insert(
-            "electric_mining_rig".to_string(),
-            create_machine("electric_mining_rig", 2, 5),
-        );
-        machines.insert(
-            "shredding_unit".to_string(),
-            create_machine("shredding_unit", 1, 10),
-        );
-        machines.insert(
-            "refining_unit".to_string(),
-            create_machine("refining_unit", 1, 5),
-        );
+        let data = GameData::builder()
+            .add_recipe(recipe_ore)
+            .add_recipe(recipe_powder)
+            .add_recipe(recipe_crust_powder)
+            .add_machine(create_machine("electric_mining_rig", 2, 5))
+            .add_machine(create_machine("shredding_unit", 1, 10))
+            .add_machine(create_machine("refining_unit", 1, 5))
+            .build()
+            .unwrap();
 
         let mut visiting = HashSet::new();
         let result = resolve(
-            &recipes,
-            &recipes_by_output,
-            &machines,
-            "origocrust_powder",
+            &data.recipes,
+            &data.recipes_by_output,
+            &data.machines,
+            &"origocrust_powder".into(),
             1,
+            &PlanOptions::default(),
             &mut visiting,
+            &mut PlanWarnings::new(),
         );
 
         match result {
@@ -270,46 +671,25 @@ mod tests {
             vec![("amethyst_component", 1)],
         );
 
-        let mut recipes = HashMap::new();
-        recipes.insert("amethyst_fiber@refining_unit[]".to_string(), recipe_fiber);
-        recipes.insert("origocrust@refining_unit[]".to_string(), recipe_crust);
-        recipes.insert(
-            "amethyst_component@gearing_unit[amethyst_fiber:5,origocrust:5]".to_string(),
-            recipe_component,
-        );
-
-        let mut recipes_by_output = HashMap::new();
-        recipes_by_output.insert(
-            "amethyst_fiber".to_string(),
-            vec!["amethyst_fiber@refining_unit[]".to_string()],
-        );
-        recipes_by_output.insert(
-            "origocrust".to_string(),
-            vec!["origocrust@refining_unit[]".to_string()],
-        );
-        recipes_by_output.insert(
-            "amethyst_component".to_string(),
-            vec!["amethyst_component@gearing_unit[amethyst_fiber:5,origocrust:5]".to_string()],
-        );
-
-        let mut machines = HashMap::new();
-        machines.insert(
-            "refining_unit".to_string(),
-            create_machine("refining_unit", 1, 5),
-        );
-        machines.insert(
-            "gearing_unit".to_string(),
-            create_machine("gearing_unit", 1, 10),
-        );
+        let data = GameData::builder()
+            .add_recipe(recipe_fiber)
+            .add_recipe(recipe_crust)
+            .add_recipe(recipe_component)
+            .add_machine(create_machine("refining_unit", 1, 5))
+            .add_machine(create_machine("gearing_unit", 1, 10))
+            .build()
+            .unwrap();
 
         let mut visiting = HashSet::new();
         let result = resolve(
-            &recipes,
-            &recipes_by_output,
-            &machines,
-            "amethyst_component",
+            &data.recipes,
+            &data.recipes_by_output,
+            &data.machines,
+            &"amethyst_component".into(),
             1,
+            &PlanOptions::default(),
             &mut visiting,
+            &mut PlanWarnings::new(),
         );
 
         match result {
@@ -319,7 +699,7 @@ mod tests {
                 assert_eq!(item_id, "amethyst_component");
                 assert_eq!(inputs.len(), 2);
 
-                let item_ids: Vec<String> = inputs
+                let item_ids: Vec<ItemId> = inputs
                     .iter()
                     .filter_map(|node| match node {
                         ProductionNode::Resolved { item_id, .. } => Some(item_id.clone()),
@@ -327,8 +707,8 @@ mod tests {
                     })
                     .collect();
 
-                assert!(item_ids.contains(&"amethyst_fiber".to_string()));
-                assert!(item_ids.contains(&"origocrust".to_string()));
+                assert!(item_ids.contains(&"amethyst_fiber".into()));
+                assert!(item_ids.contains(&"origocrust".into()));
             }
             _ => panic!("Expected Resolved node for amethyst_component"),
         }
@@ -350,39 +730,23 @@ mod tests {
             vec![("origocrust", 1)],
         );
 
-        let mut recipes = HashMap::new();
-        recipes.insert(
-            "origocrust@refining_unit[originium_ore:1]".to_string(),
-            recipe_normal,
-        );
-        recipes.insert(
-            "origocrust@refining_unit[origocrust_powder:1]".to_string(),
-            recipe_powder,
-        );
-
-        let mut recipes_by_output = HashMap::new();
-        recipes_by_output.insert(
-            "origocrust".to_string(),
-            vec![
-                "origocrust@refining_unit[originium_ore:1]".to_string(),
-                "origocrust@refining_unit[origocrust_powder:1]".to_string(),
-            ],
-        );
-
-        let mut machines = HashMap::new();
-        machines.insert(
-            "refining_unit".to_string(),
-            create_machine("refining_unit", 1, 5),
-        );
+        let data = GameData::builder()
+            .add_recipe(recipe_normal)
+            .add_recipe(recipe_powder)
+            .add_machine(create_machine("refining_unit", 1, 5))
+            .build()
+            .unwrap();
 
         let mut visiting = HashSet::new();
         let result = resolve(
-            &recipes,
-            &recipes_by_output,
-            &machines,
-            "origocrust",
+            &data.recipes,
+            &data.recipes_by_output,
+            &data.machines,
+            &"origocrust".into(),
             1,
+            &PlanOptions::default(),
             &mut visiting,
+            &mut PlanWarnings::new(),
         );
 
         // Should select the originium_ore recipe to avoid potential cycle
@@ -401,18 +765,18 @@ mod tests {
 
     #[test]
     fn test_unresolved_when_no_recipe() {
-        let recipes = HashMap::new();
-        let recipes_by_output = HashMap::new();
-        let machines = HashMap::new();
+        let data = GameData::builder().build().unwrap();
 
         let mut visiting = HashSet::new();
         let result = resolve(
-            &recipes,
-            &recipes_by_output,
-            &machines,
-            "unknown_material",
+            &data.recipes,
+            &data.recipes_by_output,
+            &data.machines,
+            &"unknown_material".into(),
             10,
+            &PlanOptions::default(),
             &mut visiting,
+            &mut PlanWarnings::new(),
         );
 
         match result {
@@ -423,4 +787,394 @@ mod tests {
             _ => panic!("Expected Unresolved node"),
         }
     }
+
+    #[test]
+    fn test_warns_on_missing_recipe() {
+        let data = GameData::builder().build().unwrap();
+
+        let mut visiting = HashSet::new();
+        let mut warnings = PlanWarnings::new();
+        resolve(
+            &data.recipes,
+            &data.recipes_by_output,
+            &data.machines,
+            &"unknown_material".into(),
+            10,
+            &PlanOptions::default(),
+            &mut visiting,
+            &mut warnings,
+        );
+
+        assert_eq!(
+            warnings.into_vec(),
+            vec![PlanWarning::MissingRecipe {
+                item_id: "unknown_material".into()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_warns_on_missing_machine() {
+        let recipe = create_recipe(
+            "origocrust",
+            "unknown_machine",
+            vec![],
+            vec![("origocrust", 1)],
+        );
+
+        let data = GameData::builder().add_recipe(recipe).build().unwrap();
+
+        let mut visiting = HashSet::new();
+        let mut warnings = PlanWarnings::new();
+        resolve(
+            &data.recipes,
+            &data.recipes_by_output,
+            &data.machines,
+            &"origocrust".into(),
+            1,
+            &PlanOptions::default(),
+            &mut visiting,
+            &mut warnings,
+        );
+
+        assert_eq!(
+            warnings.into_vec(),
+            vec![PlanWarning::MissingMachine {
+                item_id: "origocrust".into(),
+                recipe_id: "origocrust@unknown_machine[]->[origocrust:1]/60s".into(),
+                machine_id: "unknown_machine".into(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_warns_on_skipped_cyclic_input() {
+        // origocrust and origocrust_powder can only be made from each other
+        let recipe_crust = create_recipe(
+            "origocrust",
+            "refining_unit",
+            vec![("origocrust_powder", 1)],
+            vec![("origocrust", 1)],
+        );
+        let recipe_powder = create_recipe(
+            "origocrust_powder",
+            "shredding_unit",
+            vec![("origocrust", 1)],
+            vec![("origocrust_powder", 1)],
+        );
+
+        let data = GameData::builder()
+            .add_recipe(recipe_crust)
+            .add_recipe(recipe_powder)
+            .add_machine(create_machine("refining_unit", 1, 5))
+            .add_machine(create_machine("shredding_unit", 1, 10))
+            .build()
+            .unwrap();
+
+        let mut visiting = HashSet::new();
+        let mut warnings = PlanWarnings::new();
+        resolve(
+            &data.recipes,
+            &data.recipes_by_output,
+            &data.machines,
+            &"origocrust".into(),
+            1,
+            &PlanOptions::default(),
+            &mut visiting,
+            &mut warnings,
+        );
+
+        assert_eq!(
+            warnings.into_vec(),
+            vec![PlanWarning::CyclicInputSkipped {
+                item_id: "origocrust_powder".into(),
+                recipe_id:
+                    "origocrust_powder@shredding_unit[origocrust:1]->[origocrust_powder:1]/60s"
+                        .into(),
+                input_id: "origocrust".into(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_warns_on_defaulted_output() {
+        let recipe = create_recipe("origocrust", "refining_unit", vec![], vec![]);
+
+        let data = GameData::builder()
+            .add_recipe(recipe)
+            .add_machine(create_machine("refining_unit", 1, 5))
+            .build()
+            .unwrap();
+
+        let mut visiting = HashSet::new();
+        let mut warnings = PlanWarnings::new();
+        resolve(
+            &data.recipes,
+            &data.recipes_by_output,
+            &data.machines,
+            &"origocrust".into(),
+            1,
+            &PlanOptions::default(),
+            &mut visiting,
+            &mut warnings,
+        );
+
+        assert_eq!(
+            warnings.into_vec(),
+            vec![PlanWarning::OutputDefaulted {
+                item_id: "origocrust".into(),
+                recipe_id: "origocrust@refining_unit[]->[]/60s".into(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_warns_on_no_net_output() {
+        // Filling gives back the bottle it takes
+        let recipe = create_recipe(
+            "amethyst_bottle",
+            "filling_unit",
+            vec![("amethyst_bottle", 1), ("clean_water", 1)],
+            vec![("amethyst_bottle", 1)],
+        );
+
+        let data = GameData::builder()
+            .add_recipe(recipe)
+            .add_machine(create_machine("filling_unit", 1, 5))
+            .build()
+            .unwrap();
+
+        let mut visiting = HashSet::new();
+        let mut warnings = PlanWarnings::new();
+        resolve(
+            &data.recipes,
+            &data.recipes_by_output,
+            &data.machines,
+            &"amethyst_bottle".into(),
+            1,
+            &PlanOptions::default(),
+            &mut visiting,
+            &mut warnings,
+        );
+
+        assert!(warnings.into_vec().contains(&PlanWarning::NoNetOutput {
+            item_id: "amethyst_bottle".into(),
+            recipe_id: "amethyst_bottle@filling_unit[amethyst_bottle:1,clean_water:1]->[amethyst_bottle:1]/60s".into(),
+        }));
+    }
+
+    #[test]
+    fn test_external_item_not_expanded() {
+        let recipe_powder = create_recipe(
+            "originium_powder",
+            "shredding_unit",
+            vec![("originium_ore", 1)],
+            vec![("originium_powder", 1)],
+        );
+        let recipe_crust_powder = create_recipe(
+            "origocrust_powder",
+            "refining_unit",
+            vec![("originium_powder", 2)],
+            vec![("origocrust_powder", 1)],
+        );
+
+        let data = GameData::builder()
+            .add_recipe(recipe_powder)
+            .add_recipe(recipe_crust_powder)
+            .add_machine(create_machine("shredding_unit", 1, 10))
+            .add_machine(create_machine("refining_unit", 1, 5))
+            .build()
+            .unwrap();
+
+        let options = PlanOptions {
+            external_items: ["originium_powder".into()].into_iter().collect(),
+            ..Default::default()
+        };
+
+        let mut visiting = HashSet::new();
+        let result = resolve(
+            &data.recipes,
+            &data.recipes_by_output,
+            &data.machines,
+            &"origocrust_powder".into(),
+            3,
+            &options,
+            &mut visiting,
+            &mut PlanWarnings::new(),
+        );
+
+        match result {
+            ProductionNode::Resolved { inputs, .. } => {
+                assert_eq!(
+                    inputs,
+                    vec![ProductionNode::External {
+                        item_id: "originium_powder".into(),
+                        amount: 6,
+                    }]
+                );
+            }
+            _ => panic!("Expected Resolved node for origocrust_powder"),
+        }
+    }
+
+    #[test]
+    fn test_max_machines_per_node_splits_into_blocks() {
+        let data = GameData::builder()
+            .add_recipe(create_recipe(
+                "origocrust",
+                "refining_unit",
+                vec![],
+                vec![("origocrust", 1)],
+            ))
+            .add_machine(create_machine("refining_unit", 1, 5))
+            .build()
+            .unwrap();
+
+        let options = PlanOptions {
+            max_machines_per_node: Some(8),
+            ..Default::default()
+        };
+        let plan = |amount| {
+            resolve(
+                &data.recipes,
+                &data.recipes_by_output,
+                &data.machines,
+                &"origocrust".into(),
+                amount,
+                &options,
+                &mut HashSet::new(),
+                &mut PlanWarnings::new(),
+            )
+        };
+
+        // One machine per item per minute, so the amount is the machine count
+        for (amount, expected) in [(20, vec![8, 8, 4]), (16, vec![8, 8]), (8, vec![])] {
+            match plan(amount) {
+                ProductionNode::Resolved {
+                    machine_count,
+                    blocks,
+                    ..
+                } => {
+                    assert_eq!(machine_count, amount);
+                    assert_eq!(blocks, expected);
+                }
+                _ => panic!("Expected Resolved node for origocrust"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_progress_called_once_per_resolved_node() {
+        let data = GameData::builder()
+            .add_recipe(create_recipe(
+                "originium_ore",
+                "electric_mining_rig",
+                vec![],
+                vec![("originium_ore", 1)],
+            ))
+            .add_recipe(create_recipe(
+                "origocrust",
+                "refining_unit",
+                vec![("originium_ore", 1)],
+                vec![("origocrust", 1)],
+            ))
+            .add_recipe(create_recipe(
+                "amethyst_component",
+                "gearing_unit",
+                vec![("origocrust", 1), ("amethyst_fiber", 1)],
+                vec![("amethyst_component", 1)],
+            ))
+            .build()
+            .unwrap();
+
+        let reported = Arc::new(Mutex::new(Vec::new()));
+        let options = PlanOptions {
+            progress: Some(Progress::new({
+                let reported = Arc::clone(&reported);
+                move |resolved| reported.lock().unwrap().push(resolved)
+            })),
+            ..Default::default()
+        };
+        let plan = resolve(
+            &data.recipes,
+            &data.recipes_by_output,
+            &data.machines,
+            &"amethyst_component".into(),
+            1,
+            &options,
+            &mut HashSet::new(),
+            &mut PlanWarnings::new(),
+        );
+
+        // amethyst_fiber has no recipe, so three of the four nodes are resolved
+        let resolved_nodes = plan.total_machines_by_item().len();
+        assert_eq!(resolved_nodes, 3);
+        assert_eq!(*reported.lock().unwrap(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_leaf_sources_skips_consumable_input() {
+        // Mining originium_ore consumes a drill bit crafted from ferrium
+        let recipe_ore = Recipe::new_for_test(
+            "originium_ore".into(),
+            "electric_mining_rig".into(),
+            60.0,
+            [("drill_bit".into(), 1)].into_iter().collect(),
+            [("originium_ore".into(), 1)].into_iter().collect(),
+            true,
+        );
+        let recipe_bit = create_recipe(
+            "drill_bit",
+            "gearing_unit",
+            vec![("ferrium", 1)],
+            vec![("drill_bit", 1)],
+        );
+
+        let data = GameData::builder()
+            .add_recipe(recipe_ore)
+            .add_recipe(recipe_bit)
+            .add_machine(create_machine("electric_mining_rig", 2, 5))
+            .add_machine(create_machine("gearing_unit", 1, 10))
+            .build()
+            .unwrap();
+
+        let plan = |options: &PlanOptions| {
+            resolve(
+                &data.recipes,
+                &data.recipes_by_output,
+                &data.machines,
+                &"originium_ore".into(),
+                2,
+                options,
+                &mut HashSet::new(),
+                &mut PlanWarnings::new(),
+            )
+        };
+
+        let options = PlanOptions {
+            leaf_sources: true,
+            ..Default::default()
+        };
+        match plan(&options) {
+            ProductionNode::Resolved { inputs, .. } => assert!(inputs.is_empty()),
+            _ => panic!("Expected Resolved node"),
+        }
+
+        // By default the consumable is planned too
+        match plan(&PlanOptions::default()) {
+            ProductionNode::Resolved { inputs, .. } => {
+                assert_eq!(inputs.len(), 1);
+                match &inputs[0] {
+                    ProductionNode::Resolved {
+                        item_id, amount, ..
+                    } => {
+                        assert_eq!(item_id, "drill_bit");
+                        assert_eq!(*amount, 2);
+                    }
+                    _ => panic!("Expected Resolved drill_bit node"),
+                }
+            }
+            _ => panic!("Expected Resolved node"),
+        }
+    }
 }
@@ -0,0 +1,266 @@
+//! Checking raw material demand against what one deposit yields.
+
+use super::PlanWarning;
+use crate::models::{ItemId, ProductionNode, Recipe, RecipeUid};
+use std::collections::{BTreeMap, HashMap};
+
+struct DepositDemand {
+    max_rate: u32,
+    amount: u32,
+}
+
+/// Sums the demand per source recipe, so each kind of deposit for an item
+/// is checked against its own `max_rate`.
+fn collect_demand(
+    node: &ProductionNode,
+    recipes: &HashMap<RecipeUid, Recipe>,
+    demand: &mut BTreeMap<(ItemId, RecipeUid), DepositDemand>,
+) {
+    let ProductionNode::Resolved {
+        item_id,
+        recipe_id,
+        amount,
+        is_source,
+        inputs,
+        ..
+    } = node
+    else {
+        return;
+    };
+
+    let max_rate = recipes.get(recipe_id).and_then(|recipe| recipe.max_rate);
+    if let (true, Some(max_rate)) = (*is_source, max_rate) {
+        demand
+            .entry((item_id.clone(), recipe_id.clone()))
+            .or_insert(DepositDemand {
+                max_rate,
+                amount: 0,
+            })
+            .amount += amount;
+    }
+
+    for child in inputs {
+        collect_demand(child, recipes, demand);
+    }
+}
+
+/// Warns about every source recipe whose total demand exceeds its
+/// `max_rate`, with the number of deposits needed. Recipes without a
+/// `max_rate` are unlimited. Sorted by item ID, then recipe.
+///
+/// In batch mode, see `PlanOptions::batch_within_secs`, the totals are
+/// spread over the time limit to compare them as per minute demand.
+pub fn check_deposits(
+    node: &ProductionNode,
+    recipes: &HashMap<RecipeUid, Recipe>,
+    batch_within_secs: Option<f64>,
+) -> Vec<PlanWarning> {
+    let mut demand = BTreeMap::new();
+    collect_demand(node, recipes, &mut demand);
+
+    if let Some(within_secs) = batch_within_secs {
+        // Finishing instantly needs no steady supply to compare
+        if within_secs <= 0.0 {
+            return Vec::new();
+        }
+        for demand in demand.values_mut() {
+            demand.amount = (demand.amount as f64 * 60.0 / within_secs).ceil() as u32;
+        }
+    }
+
+    demand
+        .into_iter()
+        .filter(|(_, demand)| demand.max_rate > 0 && demand.amount > demand.max_rate)
+        .map(
+            |((item_id, recipe_id), demand)| PlanWarning::DepositsExceeded {
+                item_id,
+                recipe_id,
+                amount: demand.amount,
+                max_rate: demand.max_rate,
+                deposits: demand.amount.div_ceil(demand.max_rate),
+            },
+        )
+        .collect()
+}
+
+#[cfg(all(test, feature = "data-files"))]
+mod tests {
+    use super::*;
+    use crate::config::GameData;
+    use crate::planner::{BatchTarget, PlanOptions, plan_batch, plan_production_with};
+    use crate::test_fixtures::{on_machines, resolved};
+
+    const RECIPES: &str = r#"
+[[recipes]]
+id = "originium_ore"
+by = "electric_mining_rig"
+time = 2
+out = 1
+is_source = true
+max_rate = 60
+
+[[recipes]]
+id = "amethyst_ore"
+by = "electric_mining_rig"
+time = 2
+out = 1
+is_source = true
+
+[[recipes]]
+id = "origocrust"
+by = "refining_unit"
+time = 2
+out = 1
+[recipes.inputs]
+originium_ore = 1
+amethyst_ore = 1
+"#;
+
+    const MACHINES: &str = r#"
+[[machines]]
+id = "electric_mining_rig"
+tier = 1
+power = 5
+
+[[machines]]
+id = "refining_unit"
+tier = 1
+power = 5
+"#;
+
+    #[test]
+    fn test_demand_above_cap_needs_more_deposits() {
+        let data = GameData::new(RECIPES, MACHINES).unwrap();
+        let result = plan_production_with(&data, "origocrust", 200);
+
+        let warnings = check_deposits(&result.node, &data.recipes, None);
+
+        assert_eq!(
+            warnings,
+            vec![PlanWarning::DepositsExceeded {
+                item_id: "originium_ore".into(),
+                recipe_id: "originium_ore@electric_mining_rig[]->[originium_ore:1]/2s".into(),
+                amount: 200,
+                max_rate: 60,
+                deposits: 4,
+            }]
+        );
+        assert!(
+            result
+                .warnings
+                .iter()
+                .any(|warning| *warning == warnings[0])
+        );
+        assert_eq!(result.warnings.deposits_needed()["originium_ore"], 4);
+    }
+
+    #[test]
+    fn test_demand_within_cap_or_uncapped_is_fine() {
+        let data = GameData::new(RECIPES, MACHINES).unwrap();
+        let result = plan_production_with(&data, "origocrust", 60);
+
+        assert!(check_deposits(&result.node, &data.recipes, None).is_empty());
+        assert!(result.warnings.deposits_needed().is_empty());
+    }
+
+    #[test]
+    fn test_each_deposit_kind_checked_against_its_own_cap() {
+        let recipes = r#"
+[[recipes]]
+id = "originium_ore"
+by = "electric_mining_rig"
+time = 2
+out = 1
+is_source = true
+max_rate = 60
+
+[[recipes]]
+id = "originium_ore"
+by = "portable_mining_rig"
+time = 2
+out = 1
+is_source = true
+max_rate = 20
+"#;
+        let machines = r#"
+[[machines]]
+id = "electric_mining_rig"
+tier = 1
+power = 5
+
+[[machines]]
+id = "portable_mining_rig"
+tier = 1
+power = 5
+"#;
+        let data = GameData::new(recipes, machines).unwrap();
+        let source = |machine_id: &str, amount: u32| {
+            let mut node = on_machines(resolved("originium_ore", amount, vec![]), machine_id, 1);
+            if let ProductionNode::Resolved {
+                recipe_id,
+                is_source,
+                ..
+            } = &mut node
+            {
+                *recipe_id = data
+                    .recipes
+                    .iter()
+                    .find(|(_, recipe)| recipe.by.as_str() == machine_id)
+                    .map(|(uid, _)| uid.clone())
+                    .unwrap();
+                *is_source = true;
+            }
+            node
+        };
+        // 50 per minute from both: within the 60 cap, but not the 20 one
+        let node = resolved(
+            "origocrust",
+            100,
+            vec![
+                source("electric_mining_rig", 50),
+                source("portable_mining_rig", 50),
+            ],
+        );
+
+        let warnings = check_deposits(&node, &data.recipes, None);
+
+        assert_eq!(
+            warnings,
+            vec![PlanWarning::DepositsExceeded {
+                item_id: "originium_ore".into(),
+                recipe_id: "originium_ore@portable_mining_rig[]->[originium_ore:1]/2s".into(),
+                amount: 50,
+                max_rate: 20,
+                deposits: 3,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_batch_demand_compared_per_minute() {
+        let data = GameData::new(RECIPES, MACHINES).unwrap();
+        let result = plan_batch(
+            &data,
+            "origocrust",
+            BatchTarget {
+                quantity: 100.into(),
+                within_secs: 120.0,
+            },
+            &PlanOptions::default(),
+        )
+        .unwrap();
+
+        // 100 ores in two minutes is 50 per minute, within one deposit
+        assert!(result.warnings.deposits_needed().is_empty());
+        assert_eq!(
+            check_deposits(&result.node, &data.recipes, Some(30.0)),
+            vec![PlanWarning::DepositsExceeded {
+                item_id: "originium_ore".into(),
+                recipe_id: "originium_ore@electric_mining_rig[]->[originium_ore:1]/2s".into(),
+                amount: 200,
+                max_rate: 60,
+                deposits: 4,
+            }]
+        );
+    }
+}
@@ -0,0 +1,202 @@
+//! Plan problems grouped by kind, for UIs that list them in one place.
+
+use crate::config::{DataWarning, GameData};
+use crate::error::ProductionError;
+use crate::models::{ItemId, MachineId, ProductionNode, RecipeUid};
+use std::collections::HashSet;
+
+use super::options::PlanOptions;
+use super::warnings::{PlanResult, PlanWarning};
+
+/// Everything worth flagging about a plan, one list per kind.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "data-files", derive(serde::Serialize))]
+pub struct PlanDiagnostics {
+    /// Items no allowed recipe produces.
+    pub unresolved: Vec<ItemId>,
+    /// `(item_id, machine_id)` for recipes whose machine is not defined.
+    pub missing_machines: Vec<(ItemId, MachineId)>,
+    /// `(item_id, input_id)` for inputs skipped to avoid a cycle.
+    pub cyclic_inputs: Vec<(ItemId, ItemId)>,
+    /// `(item_id, recipe_id)` for recipes assumed to make one per craft.
+    pub defaulted_outputs: Vec<(ItemId, RecipeUid)>,
+    /// Items produced without any machine, e.g. by instant recipes.
+    pub zero_machine: Vec<ItemId>,
+    /// Data file warnings about recipes used in the plan.
+    pub suspicious_data: Vec<DataWarning>,
+}
+
+impl PlanDiagnostics {
+    /// Collects the diagnostics of a finished plan.
+    pub fn new(result: &PlanResult, data: &GameData) -> Self {
+        let mut diagnostics = PlanDiagnostics::default();
+
+        for warning in &result.warnings {
+            match warning {
+                PlanWarning::MissingRecipe { item_id } => {
+                    diagnostics.unresolved.push(item_id.clone());
+                }
+                PlanWarning::MissingMachine {
+                    item_id,
+                    machine_id,
+                    ..
+                } => {
+                    diagnostics
+                        .missing_machines
+                        .push((item_id.clone(), machine_id.clone()));
+                }
+                PlanWarning::CyclicInputSkipped {
+                    item_id, input_id, ..
+                } => {
+                    diagnostics
+                        .cyclic_inputs
+                        .push((item_id.clone(), input_id.clone()));
+                }
+                PlanWarning::OutputDefaulted { item_id, recipe_id }
+                | PlanWarning::NoNetOutput { item_id, recipe_id } => {
+                    diagnostics
+                        .defaulted_outputs
+                        .push((item_id.clone(), recipe_id.clone()));
+                }
+                // A limit of the game world rather than a data problem
+                PlanWarning::DepositsExceeded { .. } => {}
+                // A limit of the planner rather than a data problem
+                PlanWarning::SharedAmountUnsettled { .. } => {}
+            }
+        }
+
+        let mut recipe_ids = HashSet::new();
+        collect_plan_details(&result.node, &mut recipe_ids, &mut diagnostics.zero_machine);
+
+        diagnostics.suspicious_data = data
+            .warnings
+            .iter()
+            .filter(|warning| recipe_ids.contains(warning.recipe_id()))
+            .cloned()
+            .collect();
+
+        diagnostics
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.unresolved.is_empty()
+            && self.missing_machines.is_empty()
+            && self.cyclic_inputs.is_empty()
+            && self.defaulted_outputs.is_empty()
+            && self.zero_machine.is_empty()
+            && self.suspicious_data.is_empty()
+    }
+}
+
+/// Gathers the recipes used in the tree and the items made without machines.
+fn collect_plan_details<'a>(
+    node: &'a ProductionNode,
+    recipe_ids: &mut HashSet<&'a RecipeUid>,
+    zero_machine: &mut Vec<ItemId>,
+) {
+    if let ProductionNode::Resolved {
+        item_id,
+        recipe_id,
+        amount,
+        machine_count,
+        inputs,
+        ..
+    } = node
+    {
+        recipe_ids.insert(recipe_id);
+        if *machine_count == 0 && *amount > 0 && !zero_machine.contains(item_id) {
+            zero_machine.push(item_id.clone());
+        }
+
+        for child in inputs {
+            collect_plan_details(child, recipe_ids, zero_machine);
+        }
+    }
+}
+
+/// Plans an item and collects its diagnostics in one pass. Fails like
+/// `plan_production_with_options`.
+pub fn plan_with_diagnostics(
+    data: &GameData,
+    item_id: impl Into<ItemId>,
+    amount: u32,
+    options: &PlanOptions,
+) -> Result<(ProductionNode, PlanDiagnostics), ProductionError> {
+    let result = super::plan_production_with_options(data, item_id, amount, options)?;
+    let diagnostics = PlanDiagnostics::new(&result, data);
+
+    Ok((result.node, diagnostics))
+}
+
+#[cfg(all(test, feature = "data-files"))]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::REFINING_UNIT_TOML;
+
+    #[test]
+    fn test_missing_recipe_is_unresolved() {
+        let recipes = r#"
+[[recipes]]
+id = "origocrust"
+by = "refining_unit"
+time = 2
+out = 1
+[recipes.inputs]
+originium_ore = 1
+"#;
+        let data = GameData::new(recipes, REFINING_UNIT_TOML).unwrap();
+
+        let (node, diagnostics) =
+            plan_with_diagnostics(&data, "origocrust", 30, &PlanOptions::default()).unwrap();
+
+        assert!(matches!(node, ProductionNode::Resolved { .. }));
+        assert_eq!(diagnostics.unresolved, vec!["originium_ore"]);
+        assert!(diagnostics.missing_machines.is_empty());
+        assert!(diagnostics.zero_machine.is_empty());
+    }
+
+    #[test]
+    fn test_instant_recipe_and_data_warning_reported() {
+        // Crafted instantly, and the outputs omit the recipe's own item
+        let recipes = r#"
+[[recipes]]
+id = "origocrust"
+by = "refining_unit"
+time = 0
+[recipes.outputs]
+origocrust_powder = 1
+"#;
+        let data = GameData::new(recipes, REFINING_UNIT_TOML).unwrap();
+
+        let (_, diagnostics) =
+            plan_with_diagnostics(&data, "origocrust", 30, &PlanOptions::default()).unwrap();
+
+        assert_eq!(diagnostics.zero_machine, vec!["origocrust"]);
+        assert_eq!(diagnostics.suspicious_data.len(), 1);
+        assert_eq!(diagnostics.defaulted_outputs.len(), 1);
+        assert!(!diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_strict_plan_fails() {
+        let recipes = r#"
+[[recipes]]
+id = "origocrust"
+by = "refining_unit"
+time = 2
+out = 1
+[recipes.inputs]
+originium_ore = 1
+"#;
+        let data = GameData::new(recipes, REFINING_UNIT_TOML).unwrap();
+        let options = PlanOptions {
+            strict: true,
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            plan_with_diagnostics(&data, "origocrust", 30, &options),
+            Err(ProductionError::UnresolvedItems(items)) if items == vec!["originium_ore"]
+        ));
+    }
+}
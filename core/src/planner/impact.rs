@@ -0,0 +1,318 @@
+//! How a change of game data affects a saved plan.
+
+use super::warnings::PlanResult;
+use super::{PlanOptions, resolve_with};
+use crate::config::GameData;
+#[cfg(feature = "data-files")]
+use crate::error::ProductionError;
+use crate::models::{ItemId, MachineId, ProductionNode, Recipe, RecipeUid};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// The target of a plan and the options it was planned with, kept so it
+/// can be planned again later.
+///
+/// ```toml
+/// item = "origocrust"
+/// amount = 30
+///
+/// [options]
+/// external_items = ["originium_ore"]
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "data-files", derive(serde::Deserialize, serde::Serialize))]
+pub struct SavedPlan {
+    pub item: ItemId,
+    /// Output per minute.
+    pub amount: u32,
+    /// Planned with the defaults when left out.
+    #[cfg_attr(feature = "data-files", serde(default))]
+    pub options: PlanOptions,
+}
+
+/// What changed about a recipe used by the plan.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "data-files", derive(serde::Serialize))]
+pub enum RecipeChangeKind {
+    Time { old: f64, new: f64 },
+    Inputs,
+    Outputs,
+}
+
+/// A recipe used by the plan that the new data defines differently.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "data-files", derive(serde::Serialize))]
+pub struct RecipeChange {
+    /// Unique ID of the recipe in the old data.
+    pub recipe_id: RecipeUid,
+    pub changes: Vec<RecipeChangeKind>,
+}
+
+/// Effect of new game data on a saved plan, see `SavedPlan::impact`.
+///
+/// Deltas are new minus old and leave out totals that did not change.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "data-files", derive(serde::Serialize))]
+pub struct PlanImpact {
+    pub changed_recipes: Vec<RecipeChange>,
+    /// Recipes used by the plan that the new data no longer has.
+    pub removed_recipes: Vec<RecipeUid>,
+    pub machine_deltas: BTreeMap<MachineId, i64>,
+    pub power_delta: i64,
+    pub raw_material_deltas: BTreeMap<ItemId, i64>,
+    /// The plan built from the new data.
+    pub new_plan: PlanResult,
+}
+
+impl PlanImpact {
+    /// Returns true if the new data changes nothing about the plan.
+    pub fn is_unchanged(&self) -> bool {
+        self.changed_recipes.is_empty()
+            && self.removed_recipes.is_empty()
+            && self.machine_deltas.is_empty()
+            && self.power_delta == 0
+            && self.raw_material_deltas.is_empty()
+    }
+}
+
+impl SavedPlan {
+    pub fn new(item: impl Into<ItemId>, amount: u32) -> Self {
+        SavedPlan {
+            item: item.into(),
+            amount,
+            options: PlanOptions::default(),
+        }
+    }
+
+    /// Sets the options the plan is built with.
+    pub fn with_options(mut self, options: PlanOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    #[cfg(feature = "data-files")]
+    pub fn from_toml(content: &str) -> Result<Self, ProductionError> {
+        toml::from_str(content).map_err(|e| ProductionError::ParseError(e.to_string()))
+    }
+
+    /// Plans the target with `data` and the saved options.
+    pub fn plan(&self, data: &GameData) -> PlanResult {
+        resolve_with(data, &self.item, self.amount, &self.options)
+    }
+
+    /// Plans the target with both `old` and `new` data and reports the
+    /// recipes of the old plan that changed or were removed, and how the
+    /// totals moved.
+    ///
+    /// A recipe whose unique ID is gone counts as changed if the new data
+    /// still makes the item on the same machine, and as removed otherwise.
+    pub fn impact(&self, old: &GameData, new: &GameData) -> PlanImpact {
+        let old_plan = self.plan(old);
+        let new_plan = self.plan(new);
+
+        let mut used: BTreeSet<&RecipeUid> = BTreeSet::new();
+        collect_recipe_ids(&old_plan.node, &mut used);
+
+        let mut changed_recipes = Vec::new();
+        let mut removed_recipes = Vec::new();
+        for recipe_id in used {
+            let Some(old_recipe) = old.recipes.get(recipe_id) else {
+                continue;
+            };
+            match find_counterpart(old_recipe, recipe_id, new) {
+                Some(new_recipe) => {
+                    let changes = recipe_changes(old_recipe, new_recipe);
+                    if !changes.is_empty() {
+                        changed_recipes.push(RecipeChange {
+                            recipe_id: recipe_id.clone(),
+                            changes,
+                        });
+                    }
+                }
+                None => removed_recipes.push(recipe_id.clone()),
+            }
+        }
+
+        let old_node = &old_plan.node;
+        let new_node = &new_plan.node;
+        PlanImpact {
+            changed_recipes,
+            removed_recipes,
+            machine_deltas: deltas(old_node.total_machines(), new_node.total_machines()),
+            power_delta: new_node.total_power() as i64 - old_node.total_power() as i64,
+            raw_material_deltas: deltas(
+                old_node.total_source_materials(),
+                new_node.total_source_materials(),
+            ),
+            new_plan,
+        }
+    }
+}
+
+fn collect_recipe_ids<'a>(node: &'a ProductionNode, ids: &mut BTreeSet<&'a RecipeUid>) {
+    if let ProductionNode::Resolved {
+        recipe_id, inputs, ..
+    } = node
+    {
+        ids.insert(recipe_id);
+        for input in inputs {
+            collect_recipe_ids(input, ids);
+        }
+    }
+}
+
+/// Finds the new data's version of a recipe, by unique ID or else by
+/// output item and machine.
+fn find_counterpart<'a>(
+    old_recipe: &Recipe,
+    recipe_id: &RecipeUid,
+    new: &'a GameData,
+) -> Option<&'a Recipe> {
+    new.recipes.get(recipe_id).or_else(|| {
+        new.recipes_by_output
+            .get(old_recipe.output_items()[0])?
+            .iter()
+            .filter_map(|id| new.recipes.get(id))
+            .find(|recipe| recipe.id == old_recipe.id && recipe.by == old_recipe.by)
+    })
+}
+
+fn recipe_changes(old: &Recipe, new: &Recipe) -> Vec<RecipeChangeKind> {
+    let mut changes = Vec::new();
+    if (old.time - new.time).abs() > f64::EPSILON {
+        changes.push(RecipeChangeKind::Time {
+            old: old.time,
+            new: new.time,
+        });
+    }
+    if old.inputs != new.inputs || old.any_of != new.any_of || old.returns != new.returns {
+        changes.push(RecipeChangeKind::Inputs);
+    }
+    if old.outputs != new.outputs {
+        changes.push(RecipeChangeKind::Outputs);
+    }
+    changes
+}
+
+fn deltas<K: Ord + std::hash::Hash>(
+    old: HashMap<K, u32>,
+    mut new: HashMap<K, u32>,
+) -> BTreeMap<K, i64> {
+    let mut deltas = BTreeMap::new();
+    for (key, old_value) in old {
+        let new_value = new.remove(&key).unwrap_or(0);
+        deltas.insert(key, new_value as i64 - old_value as i64);
+    }
+    deltas.extend(new.into_iter().map(|(key, value)| (key, value as i64)));
+    deltas.retain(|_, delta| *delta != 0);
+    deltas
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Machine;
+
+    fn data(origocrust_time: f64, ore_machine: &str) -> GameData {
+        let mut ore = Recipe::new("originium_ore", vec![ore_machine.into()], 2.0);
+        ore.outputs.insert("originium_ore".into(), 1);
+        ore.is_source = true;
+
+        let mut crust = Recipe::new("origocrust", vec!["refining_unit".into()], origocrust_time);
+        crust.inputs.insert("originium_ore".into(), 1);
+        crust.outputs.insert("origocrust".into(), 1);
+
+        let machine = |id: &str| Machine {
+            id: id.into(),
+            tier: 1,
+            power: 5,
+            input_ports: None,
+            output_ports: None,
+            idle_power: None,
+        };
+
+        GameData::builder()
+            .add_machine(machine("refining_unit"))
+            .add_machine(machine(ore_machine))
+            .add_recipe(ore)
+            .add_recipe(crust)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_time_change_adds_machines() {
+        let plan = SavedPlan::new("origocrust", 30);
+
+        // Refining twice as slow needs a second refining unit
+        let impact = plan.impact(
+            &data(2.0, "electric_mining_rig"),
+            &data(4.0, "electric_mining_rig"),
+        );
+
+        assert_eq!(
+            impact.changed_recipes,
+            vec![RecipeChange {
+                recipe_id: "origocrust@refining_unit[originium_ore:1]->[origocrust:1]/2s".into(),
+                changes: vec![RecipeChangeKind::Time { old: 2.0, new: 4.0 }],
+            }]
+        );
+        assert!(impact.removed_recipes.is_empty());
+        assert_eq!(
+            impact.machine_deltas,
+            [("refining_unit".into(), 1)].into_iter().collect()
+        );
+        assert_eq!(impact.power_delta, 5);
+        assert!(impact.raw_material_deltas.is_empty());
+    }
+
+    #[test]
+    fn test_removed_recipe_and_unchanged_data() {
+        let plan = SavedPlan::new("origocrust", 30);
+        let old = data(2.0, "electric_mining_rig");
+
+        assert!(plan.impact(&old, &old).is_unchanged());
+
+        let impact = plan.impact(&old, &data(2.0, "portable_originium_rig"));
+        assert_eq!(
+            impact.removed_recipes,
+            vec![RecipeUid::from(
+                "originium_ore@electric_mining_rig[]->[originium_ore:1]/2s"
+            )]
+        );
+        assert_eq!(
+            impact.machine_deltas,
+            [
+                ("electric_mining_rig".into(), -1),
+                ("portable_originium_rig".into(), 1)
+            ]
+            .into_iter()
+            .collect()
+        );
+    }
+
+    #[cfg(feature = "data-files")]
+    #[test]
+    fn test_saved_plan_from_toml() {
+        let plan = SavedPlan::from_toml("item = \"origocrust\"\namount = 30\n").unwrap();
+
+        assert_eq!(plan, SavedPlan::new("origocrust", 30));
+        assert!(SavedPlan::from_toml("amount = 30").is_err());
+    }
+
+    #[cfg(feature = "data-files")]
+    #[test]
+    fn test_saved_options_are_used_when_replanning() {
+        let plan = SavedPlan::from_toml(
+            "item = \"origocrust\"\namount = 30\n[options]\nexternal_items = [\"originium_ore\"]\n",
+        )
+        .unwrap();
+        let old = data(2.0, "electric_mining_rig");
+
+        // The ore is supplied, so changing its rig changes nothing
+        assert!(plan.options.external_items.contains("originium_ore"));
+        assert!(
+            plan.impact(&old, &data(2.0, "portable_originium_rig"))
+                .is_unchanged()
+        );
+    }
+}
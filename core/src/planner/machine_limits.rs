@@ -0,0 +1,92 @@
+//! Checking a plan against the machines available to build it.
+
+use crate::models::{MachineId, ProductionNode};
+use std::collections::HashMap;
+
+/// A machine type the plan needs more of than are available, see
+/// `PlanOptions::machine_limits`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "data-files", derive(serde::Serialize))]
+pub struct MachineLimitViolation {
+    pub machine_id: MachineId,
+    pub needed: u32,
+    pub available: u32,
+}
+
+impl MachineLimitViolation {
+    /// Machines missing to build the plan.
+    pub fn shortfall(&self) -> u32 {
+        self.needed.saturating_sub(self.available)
+    }
+}
+
+/// Compares the machine totals of the plan with `limits`. Machines without
+/// a limit are unrestricted. Sorted by machine ID.
+pub fn check_machine_limits(
+    node: &ProductionNode,
+    limits: &HashMap<MachineId, u32>,
+) -> Vec<MachineLimitViolation> {
+    if limits.is_empty() {
+        return Vec::new();
+    }
+
+    let mut violations: Vec<MachineLimitViolation> = node
+        .total_machines()
+        .into_iter()
+        .filter_map(|(machine_id, needed)| {
+            let available = *limits.get(&machine_id)?;
+            (needed > available).then_some(MachineLimitViolation {
+                machine_id,
+                needed,
+                available,
+            })
+        })
+        .collect();
+    violations.sort_by(|a, b| a.machine_id.cmp(&b.machine_id));
+    violations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::{self, on_machines};
+
+    fn resolved(
+        machine_id: &str,
+        machine_count: u32,
+        inputs: Vec<ProductionNode>,
+    ) -> ProductionNode {
+        let node = test_fixtures::resolved("amethyst_component", 30, inputs);
+        on_machines(node, machine_id, machine_count)
+    }
+
+    #[test]
+    fn test_violations_against_totals() {
+        let plan = resolved(
+            "gearing_unit",
+            2,
+            vec![
+                resolved("gearing_unit", 3, vec![]),
+                resolved("refining_unit", 4, vec![]),
+                resolved("shredding_unit", 9, vec![]),
+            ],
+        );
+        let limits = HashMap::from([
+            (MachineId::from("gearing_unit"), 3),
+            (MachineId::from("refining_unit"), 4),
+        ]);
+
+        let violations = check_machine_limits(&plan, &limits);
+
+        assert_eq!(
+            violations,
+            vec![MachineLimitViolation {
+                machine_id: "gearing_unit".into(),
+                needed: 5,
+                available: 3,
+            }]
+        );
+        assert_eq!(violations[0].shortfall(), 2);
+        assert!(check_machine_limits(&plan, &HashMap::new()).is_empty());
+    }
+}
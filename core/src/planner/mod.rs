@@ -1,12 +1,45 @@
 //! Production planning module for Endfield Production Planner.
 
+mod bootstrap;
 mod calculator;
+mod comparison;
 mod dependency_resolver;
+mod deposits;
+mod diagnostics;
+mod impact;
+mod machine_limits;
+mod options;
+mod progress;
 mod recipe_selector;
+mod share;
+mod source_rounding;
+mod summary;
+mod targets;
+mod trace;
+mod warnings;
 
-pub use calculator::ProductionCalculation;
+pub use bootstrap::{BOOTSTRAP_ROOT, bootstrap_plan, unbuildable_machines};
+pub(crate) use calculator::split_into_blocks;
+pub use calculator::{BatchTarget, ProductionCalculation};
+pub use comparison::{RecipeComparison, ScoreWeights, best_recipe, compare_recipes};
+pub use deposits::check_deposits;
+pub use diagnostics::{PlanDiagnostics, plan_with_diagnostics};
+pub use impact::{PlanImpact, RecipeChange, RecipeChangeKind, SavedPlan};
+pub use machine_limits::{MachineLimitViolation, check_machine_limits};
+pub use options::{CyclePolicy, PlanOptions};
+pub use progress::Progress;
+pub(crate) use recipe_selector::compare_for_selection;
+pub use share::{ShareState, decode_node_paths, encode_node_paths, resolvable_paths};
+pub use source_rounding::{SourceRounding, round_sources};
+pub use summary::{PlanSummary, TagTotals};
+pub use targets::{TARGETS_ROOT, plan_targets};
+pub use trace::{PlanTracer, TraceEvent, TraceFormat, Tracer, VecTracer, WriterTracer};
+pub use warnings::{PlanResult, PlanWarning, PlanWarnings};
 
-use crate::models::{Machine, ProductionNode, Recipe};
+use crate::config::GameData;
+use crate::error::ProductionError;
+use crate::models::{ItemId, Machine, MachineId, ProductionNode, Recipe, RecipeUid};
+use dependency_resolver::SubtreeCache;
 use std::collections::{HashMap, HashSet};
 
 /// Plans the production tree for a target item.
@@ -14,19 +47,1067 @@ use std::collections::{HashMap, HashSet};
 /// This is the main entry point for production planning.
 /// See `dependency_resolver::resolve` for implementation details.
 pub fn plan_production(
-    recipes: &HashMap<String, Recipe>,
-    recipes_by_output: &HashMap<String, Vec<String>>,
-    machines: &HashMap<String, Machine>,
-    item_id: &str,
+    recipes: &HashMap<RecipeUid, Recipe>,
+    recipes_by_output: &HashMap<ItemId, Vec<RecipeUid>>,
+    machines: &HashMap<MachineId, Machine>,
+    item_id: impl Into<ItemId>,
     amount: u32,
-    visiting: &mut HashSet<String>,
+    visiting: &mut HashSet<ItemId>,
 ) -> ProductionNode {
     dependency_resolver::resolve(
         recipes,
         recipes_by_output,
         machines,
-        item_id,
+        &item_id.into(),
         amount,
+        &PlanOptions::default(),
         visiting,
+        &mut PlanWarnings::new(),
+    )
+}
+
+/// Plans the production tree for a target item using borrowed game data.
+///
+/// Unlike `plan_production`, this also returns the warnings raised for
+/// anything the planner had to work around.
+pub fn plan_production_with(
+    data: &GameData,
+    item_id: impl Into<ItemId>,
+    amount: u32,
+) -> PlanResult {
+    resolve_with(data, &item_id.into(), amount, &PlanOptions::default())
+}
+
+/// Plans the production tree for a target item with custom options.
+///
+/// Fails under `CyclePolicy::Error`, when a cyclic input could not be
+/// avoided by choosing another recipe, and in strict mode, when any item
+/// has no usable recipe.
+pub fn plan_production_with_options(
+    data: &GameData,
+    item_id: impl Into<ItemId>,
+    amount: u32,
+    options: &PlanOptions,
+) -> Result<PlanResult, ProductionError> {
+    check_policies(
+        resolve_with(data, &item_id.into(), amount, options),
+        options,
     )
 }
+
+/// Fails a finished plan under `CyclePolicy::Error` or in strict mode, see
+/// `plan_production_with_options`.
+fn check_policies(
+    result: PlanResult,
+    options: &PlanOptions,
+) -> Result<PlanResult, ProductionError> {
+    if options.cycle_policy == CyclePolicy::Error {
+        let cyclic_input = result.warnings.iter().find_map(|warning| match warning {
+            PlanWarning::CyclicInputSkipped { input_id, .. } => Some(input_id.to_string()),
+            _ => None,
+        });
+        if let Some(input_id) = cyclic_input {
+            return Err(ProductionError::CyclicDependency(input_id));
+        }
+    }
+
+    if options.strict {
+        let mut unresolved: Vec<String> = result
+            .warnings
+            .iter()
+            .filter_map(|warning| match warning {
+                PlanWarning::MissingRecipe { item_id } => Some(item_id.to_string()),
+                _ => None,
+            })
+            .collect();
+        unresolved.sort();
+        unresolved.dedup();
+
+        if !unresolved.is_empty() {
+            return Err(ProductionError::UnresolvedItems(unresolved));
+        }
+    }
+
+    Ok(result)
+}
+
+/// Plans a fixed quantity of an item to be produced within a time limit.
+///
+/// Amounts in the tree are total quantities instead of rates, and each
+/// node has the machines to finish its quantity within the limit, see
+/// `BatchTarget`. Fails like `plan_production_with_options`.
+pub fn plan_batch(
+    data: &GameData,
+    item_id: impl Into<ItemId>,
+    target: BatchTarget,
+    options: &PlanOptions,
+) -> Result<PlanResult, ProductionError> {
+    let options = batch_options(options, target.within_secs);
+    plan_production_with_options(data, item_id, target.quantity.whole(), &options)
+}
+
+/// `options` for planning quantities to finish within `within_secs`.
+fn batch_options(options: &PlanOptions, within_secs: f64) -> PlanOptions {
+    PlanOptions {
+        batch_within_secs: Some(within_secs),
+        ..options.clone()
+    }
+}
+
+/// Plans the largest whole amount per minute, up to `amount`, that the
+/// machines in `PlanOptions::machine_limits` can build. The root node's
+/// amount is the rate reached.
+///
+/// Machine counts only grow with the amount, so the amount is found by
+/// bisection. If not even one item per minute fits, the plan for `amount`
+/// is returned with its violations. Fails like `plan_production_with_options`.
+pub fn plan_within_machine_limits(
+    data: &GameData,
+    item_id: impl Into<ItemId>,
+    amount: u32,
+    options: &PlanOptions,
+) -> Result<PlanResult, ProductionError> {
+    let item_id = item_id.into();
+    let full = plan_production_with_options(data, &item_id, amount, options)?;
+    if full.machine_limit_violations.is_empty() {
+        return Ok(full);
+    }
+
+    // Nothing is needed for `low`, and `high` does not fit
+    let (mut low, mut high) = (0, amount);
+    let mut best = None;
+    while high - low > 1 {
+        let mid = low + (high - low) / 2;
+        let result = plan_production_with_options(data, &item_id, mid, options)?;
+        if result.machine_limit_violations.is_empty() {
+            low = mid;
+            best = Some(result);
+        } else {
+            high = mid;
+        }
+    }
+
+    Ok(best.unwrap_or(full))
+}
+
+impl GameData {
+    /// Plans `amount` per minute of every craftable item with the default
+    /// options, e.g. to precompute plans for a static site.
+    ///
+    /// The recipe picked for an input may depend on the items above it, as
+    /// cyclic inputs are avoided, so only subtrees that cannot cycle are
+    /// shared between the plans; the rest are resolved again for each.
+    /// Every plan is the same as planning its item on its own.
+    pub fn plan_all(&self, amount: u32) -> HashMap<ItemId, ProductionNode> {
+        let options = PlanOptions::default();
+        let mut cache = SubtreeCache::default();
+        self.recipes_by_output
+            .keys()
+            .map(|item_id| {
+                let node = dependency_resolver::resolve_cached(
+                    &self.recipes,
+                    &self.recipes_by_output,
+                    &self.machines,
+                    item_id,
+                    amount,
+                    &options,
+                    &mut PlanWarnings::new(),
+                    &mut cache,
+                );
+                (item_id.clone(), node)
+            })
+            .collect()
+    }
+}
+
+/// Lists the recipes allowed by `options` for an item, best first.
+///
+/// The order is the planner's recipe selection ranking for the target
+/// item, so the first recipe is the one a plan would use.
+pub fn ranked_recipes<'a>(
+    data: &'a GameData,
+    item_id: impl Into<ItemId>,
+    options: &PlanOptions,
+) -> Vec<&'a Recipe> {
+    recipe_selector::rank_recipes(
+        &item_id.into(),
+        &data.recipes,
+        &data.recipes_by_output,
+        &data.machines,
+        &HashSet::new(),
+        &[],
+        options,
+    )
+}
+
+fn resolve_with(
+    data: &GameData,
+    item_id: &ItemId,
+    amount: u32,
+    options: &PlanOptions,
+) -> PlanResult {
+    let mut warnings = PlanWarnings::new();
+    let node = resolve_tree(data, item_id, amount, options, &mut warnings);
+    finish_plan(data, node, warnings, options)
+}
+
+/// Resolves the production tree of one item, without the checks of
+/// `finish_plan`.
+fn resolve_tree(
+    data: &GameData,
+    item_id: &ItemId,
+    amount: u32,
+    options: &PlanOptions,
+    warnings: &mut PlanWarnings,
+) -> ProductionNode {
+    dependency_resolver::resolve(
+        &data.recipes,
+        &data.recipes_by_output,
+        &data.machines,
+        item_id,
+        amount,
+        options,
+        &mut HashSet::new(),
+        warnings,
+    )
+}
+
+/// Adds the reports that look at the whole tree: source rounding, machine
+/// limits and deposit caps.
+fn finish_plan(
+    data: &GameData,
+    node: ProductionNode,
+    mut warnings: PlanWarnings,
+    options: &PlanOptions,
+) -> PlanResult {
+    let source_rounding = options
+        .round_sources_to
+        .map(|multiple| round_sources(&node, multiple))
+        .unwrap_or_default();
+    let machine_limit_violations = check_machine_limits(&node, &options.machine_limits);
+    for warning in check_deposits(&node, &data.recipes, options.batch_within_secs) {
+        warnings.push(warning);
+    }
+
+    PlanResult {
+        node,
+        warnings,
+        source_rounding,
+        machine_limit_violations,
+    }
+}
+
+#[cfg(all(test, feature = "data-files"))]
+mod tests {
+    use super::*;
+
+    const MACHINES_TOML: &str = r#"
+[[machines]]
+id = "refining_unit"
+tier = 1
+power = 5
+
+[[machines]]
+id = "shredding_unit"
+tier = 1
+power = 10
+"#;
+
+    // origocrust and origocrust_powder can only be made from each other
+    const CYCLIC_RECIPES_TOML: &str = r#"
+[[recipes]]
+id = "origocrust"
+by = "refining_unit"
+time = 2
+out = 1
+[recipes.inputs]
+origocrust_powder = 1
+
+[[recipes]]
+id = "origocrust_powder"
+by = "shredding_unit"
+time = 2
+out = 1
+[recipes.inputs]
+origocrust = 1
+"#;
+
+    fn cyclic_data() -> GameData {
+        GameData::new(CYCLIC_RECIPES_TOML, MACHINES_TOML).unwrap()
+    }
+
+    fn options_with(cycle_policy: CyclePolicy) -> PlanOptions {
+        PlanOptions {
+            cycle_policy,
+            ..Default::default()
+        }
+    }
+
+    fn inputs_of(node: &ProductionNode) -> &[ProductionNode] {
+        match node {
+            ProductionNode::Resolved { inputs, .. } => inputs,
+            _ => panic!("Expected Resolved node"),
+        }
+    }
+
+    #[test]
+    fn test_cycle_policy_skip() {
+        let data = cyclic_data();
+
+        let result =
+            plan_production_with_options(&data, "origocrust", 30, &options_with(CyclePolicy::Skip))
+                .unwrap();
+
+        let powder = &inputs_of(&result.node)[0];
+        assert!(inputs_of(powder).is_empty());
+        assert!(matches!(
+            result.warnings.iter().next(),
+            Some(PlanWarning::CyclicInputSkipped { input_id, .. }) if input_id == "origocrust"
+        ));
+    }
+
+    #[test]
+    fn test_cycle_policy_error() {
+        let data = cyclic_data();
+
+        let result = plan_production_with_options(
+            &data,
+            "origocrust",
+            30,
+            &options_with(CyclePolicy::Error),
+        );
+
+        assert!(matches!(
+            result,
+            Err(ProductionError::CyclicDependency(ref id)) if id == "origocrust"
+        ));
+    }
+
+    #[test]
+    fn test_cycle_policy_break_with_cycle_node() {
+        let data = cyclic_data();
+
+        let result = plan_production_with_options(
+            &data,
+            "origocrust",
+            30,
+            &options_with(CyclePolicy::BreakWithCycleNode),
+        )
+        .unwrap();
+
+        let powder = &inputs_of(&result.node)[0];
+        assert_eq!(
+            inputs_of(powder),
+            &[ProductionNode::Cycle {
+                item_id: "origocrust".into(),
+                amount: 30,
+            }]
+        );
+        assert!(result.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_error_policy_allows_acyclic_alternative() {
+        // A second origocrust recipe breaks the cycle, so the error policy
+        // picks it instead of failing
+        let recipes = format!(
+            "{}{}",
+            CYCLIC_RECIPES_TOML,
+            r#"
+[[recipes]]
+id = "origocrust"
+by = "refining_unit"
+time = 2
+out = 1
+[recipes.inputs]
+originium_ore = 1
+"#
+        );
+        let data = GameData::new(&recipes, MACHINES_TOML).unwrap();
+
+        let result = plan_production_with_options(
+            &data,
+            "origocrust_powder",
+            30,
+            &options_with(CyclePolicy::Error),
+        );
+
+        assert!(result.is_ok());
+    }
+
+    // carbon prefers the higher tier farming recipe; buckflower can only be farmed
+    const TAGGED_RECIPES_TOML: &str = r#"
+[[recipes]]
+id = "carbon"
+by = "planting_unit"
+tags = ["farming"]
+time = 2
+out = 1
+
+[[recipes]]
+id = "carbon"
+by = "refining_unit"
+tags = ["smelting"]
+time = 2
+out = 1
+[recipes.inputs]
+buckflower = 1
+
+[[recipes]]
+id = "buckflower"
+by = "planting_unit"
+tags = ["farming"]
+time = 2
+out = 1
+"#;
+
+    fn tagged_data() -> GameData {
+        let machines = format!(
+            "{}{}",
+            MACHINES_TOML,
+            r#"
+[[machines]]
+id = "planting_unit"
+tier = 2
+power = 20
+"#
+        );
+        GameData::new(TAGGED_RECIPES_TOML, &machines).unwrap()
+    }
+
+    fn excluding(tag: &str) -> PlanOptions {
+        PlanOptions {
+            excluded_tags: [tag.to_string()].into_iter().collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_round_sources_to_multiple() {
+        let recipes = r#"
+[[recipes]]
+id = "originium_ore"
+by = "refining_unit"
+time = 2
+out = 1
+is_source = true
+
+[[recipes]]
+id = "origocrust"
+by = "refining_unit"
+time = 2
+out = 1
+[recipes.inputs]
+originium_ore = 1
+"#;
+        let data = GameData::new(recipes, MACHINES_TOML).unwrap();
+        let options = PlanOptions {
+            round_sources_to: Some(10),
+            ..Default::default()
+        };
+
+        let result = plan_production_with_options(&data, "origocrust", 37, &options).unwrap();
+
+        assert_eq!(
+            result.source_rounding,
+            vec![SourceRounding {
+                item_id: "originium_ore".into(),
+                required: 37,
+                rounded: 40,
+                surplus: 3,
+            }]
+        );
+        assert!(
+            plan_production_with(&data, "origocrust", 37)
+                .source_rounding
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_excluded_tag_falls_back_to_alternative() {
+        let data = tagged_data();
+        assert_eq!(data.recipes_by_tag["farming"].len(), 2);
+
+        let default = plan_production_with(&data, "carbon", 30);
+        assert!(matches!(
+            &default.node,
+            ProductionNode::Resolved { machine_id, .. } if machine_id == "planting_unit"
+        ));
+
+        let result =
+            plan_production_with_options(&data, "carbon", 30, &excluding("farming")).unwrap();
+        assert!(matches!(
+            &result.node,
+            ProductionNode::Resolved { machine_id, .. } if machine_id == "refining_unit"
+        ));
+        // The only buckflower recipe is excluded as well
+        assert_eq!(
+            inputs_of(&result.node),
+            &[ProductionNode::Unresolved {
+                item_id: "buckflower".into(),
+                amount: 30,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_strict_mode_lists_unresolved_items() {
+        let recipes = r#"
+[[recipes]]
+id = "origocrust"
+by = "refining_unit"
+time = 2
+out = 1
+[recipes.inputs]
+originium_ore = 1
+carbon = 1
+
+[[recipes]]
+id = "origocrust_powder"
+by = "shredding_unit"
+time = 2
+out = 1
+[recipes.inputs]
+origocrust = 1
+originium_ore = 1
+"#;
+        let data = GameData::new(recipes, MACHINES_TOML).unwrap();
+
+        let lenient =
+            plan_production_with_options(&data, "origocrust_powder", 30, &PlanOptions::default());
+        assert!(lenient.is_ok());
+
+        let strict = PlanOptions {
+            strict: true,
+            ..Default::default()
+        };
+        let result = plan_production_with_options(&data, "origocrust_powder", 30, &strict);
+
+        assert!(matches!(
+            result,
+            Err(ProductionError::UnresolvedItems(ref ids)) if ids == &["carbon", "originium_ore"]
+        ));
+    }
+
+    #[test]
+    fn test_strict_mode_passes_complete_plan() {
+        let strict = PlanOptions {
+            strict: true,
+            ..Default::default()
+        };
+
+        let result = plan_production_with_options(&tagged_data(), "carbon", 30, &strict);
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_plan_all_resolves_every_item() {
+        let data = tagged_data();
+
+        let plans = data.plan_all(1);
+
+        assert_eq!(plans.len(), data.recipes_by_output.len());
+        for (item_id, node) in &plans {
+            assert_eq!(node.item_id(), item_id);
+            assert!(fully_resolved(node), "{} has unresolved nodes", item_id);
+        }
+        assert_eq!(plans["carbon"].amount(), 1);
+    }
+
+    #[test]
+    fn test_plan_all_matches_planning_each_item() {
+        // origocrust and its powder cycle, with an ore-based way out; the
+        // ore and amethyst subtrees can be shared between plans
+        let recipes = format!(
+            "{}{}",
+            CYCLIC_RECIPES_TOML,
+            r#"
+[[recipes]]
+id = "origocrust"
+by = "refining_unit"
+time = 2
+out = 1
+[recipes.inputs]
+originium_ore = 1
+
+[[recipes]]
+id = "originium_ore"
+by = "shredding_unit"
+time = 2
+out = 1
+is_source = true
+
+[[recipes]]
+id = "amethyst_fiber"
+by = "refining_unit"
+time = 2
+out = 1
+[recipes.inputs]
+originium_ore = 1
+origocrust_powder = 1
+"#
+        );
+        let data = GameData::new(&recipes, MACHINES_TOML).unwrap();
+
+        let plans = data.plan_all(30);
+
+        assert_eq!(plans.len(), data.recipes_by_output.len());
+        for (item_id, node) in &plans {
+            let alone = plan_production_with(&data, item_id, 30);
+            assert_eq!(*node, alone.node, "plan for {} differs", item_id);
+        }
+    }
+
+    fn fully_resolved(node: &ProductionNode) -> bool {
+        match node {
+            ProductionNode::Resolved { inputs, .. } => inputs.iter().all(fully_resolved),
+            _ => false,
+        }
+    }
+
+    /// Collects the item IDs of every node in the tree.
+    fn node_item_ids(node: &ProductionNode, ids: &mut Vec<ItemId>) {
+        ids.push(node.item_id().clone());
+        if let ProductionNode::Resolved { inputs, .. } = node {
+            for input in inputs {
+                node_item_ids(input, ids);
+            }
+        }
+    }
+
+    #[test]
+    fn test_nodes_for_same_item_share_id() {
+        // originium_ore is reached through two different recipes
+        let recipes = r#"
+[[recipes]]
+id = "origocrust"
+by = "refining_unit"
+time = 2
+out = 1
+[recipes.inputs]
+originium_ore = 1
+
+[[recipes]]
+id = "origocrust_powder"
+by = "shredding_unit"
+time = 2
+out = 1
+[recipes.inputs]
+origocrust = 1
+originium_ore = 1
+"#;
+        let data = GameData::new(recipes, MACHINES_TOML).unwrap();
+
+        let result = plan_production_with(&data, String::from("origocrust_powder"), 30);
+
+        let mut ids = Vec::new();
+        node_item_ids(&result.node, &mut ids);
+        let ores: Vec<&ItemId> = ids.iter().filter(|id| *id == "originium_ore").collect();
+        assert_eq!(ores.len(), 2);
+        assert!(ores[0].ptr_eq(ores[1]));
+
+        // The target passed in by the caller is swapped for the shared copy
+        let (target, _) = data
+            .recipes_by_output
+            .get_key_value("origocrust_powder")
+            .unwrap();
+        assert!(result.node.item_id().ptr_eq(target));
+    }
+
+    #[test]
+    fn test_wide_plan_allocates_each_id_once() {
+        // A target with many intermediates that all consume the same ore
+        let width = 200;
+        let mut recipes = String::from(
+            "[[recipes]]\nid = \"target\"\nby = \"refining_unit\"\ntime = 2\nout = 1\n[recipes.inputs]\n",
+        );
+        for i in 0..width {
+            recipes.push_str(&format!("part_{} = 1\n", i));
+        }
+        for i in 0..width {
+            recipes.push_str(&format!(
+                "[[recipes]]\nid = \"part_{}\"\nby = \"shredding_unit\"\ntime = 2\nout = 1\n[recipes.inputs]\noriginium_ore = 1\n",
+                i
+            ));
+        }
+        let data = GameData::new(&recipes, MACHINES_TOML).unwrap();
+
+        let result = plan_production_with(&data, "target", 30);
+
+        let mut ids = Vec::new();
+        node_item_ids(&result.node, &mut ids);
+        let mut allocations: Vec<&ItemId> = Vec::new();
+        for id in &ids {
+            if !allocations.iter().any(|seen| seen.ptr_eq(id)) {
+                allocations.push(id);
+            }
+        }
+
+        // One node per part plus one ore node under each, but only one
+        // allocation per distinct item
+        assert_eq!(ids.len(), 1 + 2 * width);
+        assert_eq!(allocations.len(), 1 + width + 1);
+    }
+
+    #[test]
+    fn test_summary_breaks_down_by_tag() {
+        let data = tagged_data();
+        let options = PlanOptions {
+            required_tags: ["smelting".to_string()].into_iter().collect(),
+            ..Default::default()
+        };
+
+        let result = plan_production_with_options(&data, "carbon", 30, &options).unwrap();
+        let summary = PlanSummary::new(&result.node, &data);
+
+        assert_eq!(
+            summary.by_tag.into_iter().collect::<Vec<_>>(),
+            vec![(
+                "smelting".to_string(),
+                TagTotals {
+                    machines: 1,
+                    power: 5,
+                }
+            )]
+        );
+    }
+
+    #[test]
+    fn test_plan_batch_sizes_every_node_for_the_limit() {
+        let recipes = r#"
+[[recipes]]
+id = "origocrust"
+by = "refining_unit"
+time = 2
+out = 1
+
+[[recipes]]
+id = "origocrust_powder"
+by = "shredding_unit"
+time = 2
+out = 1
+[recipes.inputs]
+origocrust = 1
+"#;
+        let data = GameData::new(recipes, MACHINES_TOML).unwrap();
+        let machine_count = |node: &ProductionNode| match node {
+            ProductionNode::Resolved { machine_count, .. } => *machine_count,
+            _ => panic!("Expected Resolved node"),
+        };
+
+        // 5 items in 5 seconds averages 60 per minute, but a machine only
+        // finishes 2 crafts in time
+        let target = BatchTarget {
+            quantity: 5.into(),
+            within_secs: 5.0,
+        };
+        let batch =
+            plan_batch(&data, "origocrust_powder", target, &PlanOptions::default()).unwrap();
+        let rate = plan_production_with(&data, "origocrust_powder", 60);
+
+        assert_eq!(machine_count(&batch.node), 3);
+        assert_eq!(machine_count(&rate.node), 2);
+        let crust = &inputs_of(&batch.node)[0];
+        assert_eq!(crust.amount(), 5);
+        assert_eq!(machine_count(crust), 3);
+    }
+
+    const CHAIN_RECIPES_TOML: &str = r#"
+[[recipes]]
+id = "origocrust"
+by = "refining_unit"
+time = 2
+out = 1
+
+[[recipes]]
+id = "origocrust_powder"
+by = "shredding_unit"
+time = 2
+out = 1
+[recipes.inputs]
+origocrust = 1
+"#;
+
+    fn limited_to(shredding_units: u32) -> PlanOptions {
+        PlanOptions {
+            machine_limits: HashMap::from([("shredding_unit".into(), shredding_units)]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_machine_limits_report_violations() {
+        let data = GameData::new(CHAIN_RECIPES_TOML, MACHINES_TOML).unwrap();
+
+        // One machine makes 30 per minute, so 100 per minute needs 4
+        let result =
+            plan_production_with_options(&data, "origocrust_powder", 100, &limited_to(3)).unwrap();
+
+        assert_eq!(
+            result.machine_limit_violations,
+            vec![MachineLimitViolation {
+                machine_id: "shredding_unit".into(),
+                needed: 4,
+                available: 3,
+            }]
+        );
+        assert_eq!(result.node.amount(), 100);
+
+        let within =
+            plan_production_with_options(&data, "origocrust_powder", 90, &limited_to(3)).unwrap();
+        assert!(within.machine_limit_violations.is_empty());
+    }
+
+    #[test]
+    fn test_plan_within_machine_limits_scales_target_down() {
+        let data = GameData::new(CHAIN_RECIPES_TOML, MACHINES_TOML).unwrap();
+
+        let fitted =
+            plan_within_machine_limits(&data, "origocrust_powder", 100, &limited_to(3)).unwrap();
+        assert_eq!(fitted.node.amount(), 90);
+        assert!(fitted.machine_limit_violations.is_empty());
+
+        // A plan that already fits is kept
+        let kept =
+            plan_within_machine_limits(&data, "origocrust_powder", 60, &limited_to(3)).unwrap();
+        assert_eq!(kept.node.amount(), 60);
+
+        // Without any machine nothing fits, so the violations are reported
+        let impossible =
+            plan_within_machine_limits(&data, "origocrust_powder", 100, &limited_to(0)).unwrap();
+        assert_eq!(impossible.node.amount(), 100);
+        assert_eq!(impossible.machine_limit_violations[0].shortfall(), 4);
+    }
+
+    #[test]
+    fn test_prefer_byproducts_consumes_branch_byproduct() {
+        // Shredding origocrust leaves amethyst_scrap, which can replace the
+        // ore in one of two otherwise equal origocrust recipes
+        let recipes = r#"
+[[recipes]]
+id = "origocrust_powder"
+by = "shredding_unit"
+time = 2
+[recipes.inputs]
+origocrust = 1
+[recipes.outputs]
+origocrust_powder = 1
+amethyst_scrap = 1
+
+[[recipes]]
+id = "origocrust"
+by = "refining_unit"
+time = 2
+out = 1
+[recipes.inputs]
+originium_ore = 1
+
+[[recipes]]
+id = "origocrust"
+by = "refining_unit"
+time = 2
+out = 1
+[recipes.inputs]
+amethyst_scrap = 1
+"#;
+        let data = GameData::new(recipes, MACHINES_TOML).unwrap();
+        let crust_recipe = |result: &PlanResult| match &inputs_of(&result.node)[0] {
+            ProductionNode::Resolved { recipe_id, .. } => recipe_id.to_string(),
+            _ => panic!("Expected Resolved origocrust node"),
+        };
+
+        let default = plan_production_with(&data, "origocrust_powder", 30);
+        assert_eq!(
+            crust_recipe(&default),
+            "origocrust@refining_unit[originium_ore:1]->[origocrust:1]/2s"
+        );
+
+        let options = PlanOptions {
+            prefer_byproducts: true,
+            ..Default::default()
+        };
+        let result =
+            plan_production_with_options(&data, "origocrust_powder", 30, &options).unwrap();
+        assert_eq!(
+            crust_recipe(&result),
+            "origocrust@refining_unit[amethyst_scrap:1]->[origocrust:1]/2s"
+        );
+        // The 30 scrap made while shredding cover all 30 origocrust need
+        let crust = &inputs_of(&result.node)[0];
+        assert_eq!(crust.amount(), 30);
+        assert_eq!(
+            inputs_of(crust),
+            &[ProductionNode::Shared {
+                item_id: "amethyst_scrap".into(),
+                amount: 30,
+            }]
+        );
+        assert!(result.warnings.is_empty());
+        assert!(result.node.total_source_materials().is_empty());
+
+        // Without the byproduct above it, the raw recipe is kept
+        let top_level = plan_production_with_options(&data, "origocrust", 30, &options).unwrap();
+        assert!(matches!(
+            &top_level.node,
+            ProductionNode::Resolved { recipe_id, .. }
+                if recipe_id == "origocrust@refining_unit[originium_ore:1]->[origocrust:1]/2s"
+        ));
+    }
+
+    fn liquid_fertilizer_data(canisters_used: u32, canisters_returned: u32) -> GameData {
+        let recipes = format!(
+            r#"
+[[recipes]]
+id = "liquid_fertilizer"
+by = "refining_unit"
+time = 2
+out = 1
+[recipes.inputs]
+buckflower = 1
+canister = {}
+[recipes.returns]
+canister = {}
+"#,
+            canisters_used, canisters_returned
+        );
+        GameData::new(&recipes, MACHINES_TOML).unwrap()
+    }
+
+    #[test]
+    fn test_fully_returned_input_is_not_planned() {
+        let result = plan_production_with(&liquid_fertilizer_data(1, 1), "liquid_fertilizer", 30);
+
+        assert_eq!(
+            inputs_of(&result.node),
+            &[ProductionNode::Unresolved {
+                item_id: "buckflower".into(),
+                amount: 30,
+            }]
+        );
+        assert!(!result.warnings.iter().any(|warning| matches!(
+            warning,
+            PlanWarning::MissingRecipe { item_id } if item_id == "canister"
+        )));
+    }
+
+    #[test]
+    fn test_partly_returned_input_plans_make_up_amount() {
+        // 4 canisters in and 3 back leave 1 lost per craft
+        let result = plan_production_with(&liquid_fertilizer_data(4, 3), "liquid_fertilizer", 30);
+
+        assert!(
+            inputs_of(&result.node).contains(&ProductionNode::Unresolved {
+                item_id: "canister".into(),
+                amount: 30,
+            })
+        );
+    }
+
+    #[test]
+    fn test_enrichment_recipe_counts_net_output() {
+        // Each craft takes 1 origocrust and gives 3, netting 2
+        let recipes = r#"
+[[recipes]]
+id = "origocrust"
+by = "refining_unit"
+time = 2
+[recipes.inputs]
+origocrust = 1
+originium_ore = 1
+[recipes.outputs]
+origocrust = 3
+"#;
+        let data = GameData::new(recipes, MACHINES_TOML).unwrap();
+
+        let result = plan_production_with(&data, "origocrust", 60);
+
+        // 30 crafts per minute rather than 20, and no cyclic input
+        assert_eq!(
+            inputs_of(&result.node),
+            &[ProductionNode::Unresolved {
+                item_id: "originium_ore".into(),
+                amount: 30,
+            }]
+        );
+        assert!(
+            !result
+                .warnings
+                .iter()
+                .any(|warning| matches!(warning, PlanWarning::CyclicInputSkipped { .. }))
+        );
+    }
+
+    #[test]
+    fn test_any_of_input_picks_lower_power_source() {
+        let recipes = r#"
+[[recipes]]
+id = "originium_ore"
+by = "electric_mining_rig"
+time = 2
+out = 1
+is_source = true
+
+[[recipes]]
+id = "amethyst_ore"
+by = "hydraulic_mining_rig"
+time = 2
+out = 1
+is_source = true
+
+[[recipes]]
+id = "origocrust"
+by = "refining_unit"
+time = 2
+out = 1
+[[recipes.any_of]]
+items = ["amethyst_ore", "originium_ore"]
+count = 2
+"#;
+        let machines = format!(
+            "{}{}",
+            MACHINES_TOML,
+            r#"
+[[machines]]
+id = "electric_mining_rig"
+tier = 2
+power = 5
+
+[[machines]]
+id = "hydraulic_mining_rig"
+tier = 2
+power = 20
+"#
+        );
+        let data = GameData::new(recipes, &machines).unwrap();
+
+        let result = plan_production_with(&data, "origocrust", 30);
+
+        assert!(matches!(
+            &result.node,
+            ProductionNode::Resolved { recipe_id, .. }
+                if recipe_id == "origocrust@refining_unit[amethyst_ore|originium_ore:2]->[origocrust:1]/2s"
+        ));
+        let inputs = inputs_of(&result.node);
+        assert_eq!(inputs.len(), 1);
+        assert!(matches!(
+            &inputs[0],
+            ProductionNode::Resolved { item_id, amount: 60, .. } if item_id == "originium_ore"
+        ));
+
+        // A supplied alternative is used as is
+        let options = PlanOptions {
+            external_items: ["amethyst_ore".into()].into_iter().collect(),
+            ..Default::default()
+        };
+        let supplied = plan_production_with_options(&data, "origocrust", 30, &options).unwrap();
+        assert_eq!(
+            inputs_of(&supplied.node),
+            &[ProductionNode::External {
+                item_id: "amethyst_ore".into(),
+                amount: 60,
+            }]
+        );
+    }
+}
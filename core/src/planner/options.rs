@@ -0,0 +1,120 @@
+//! Options that adjust how a production plan is built.
+
+use super::progress::Progress;
+use super::trace::{TraceEvent, Tracer};
+use crate::models::{AmountKind, ItemId, MachineId, Recipe};
+use std::collections::{HashMap, HashSet};
+
+/// What the planner does when a cyclic input cannot be avoided.
+///
+/// Under every policy, recipes without cyclic inputs are preferred, so
+/// the policy only applies when no acyclic alternative exists. Under
+/// `Error` they are preferred even over recipes on an unknown machine.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "data-files", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "data-files", serde(rename_all = "snake_case"))]
+pub enum CyclePolicy {
+    /// Leave the cyclic input out of the tree and record a warning.
+    #[default]
+    Skip,
+    /// Fail the plan with `ProductionError::CyclicDependency`.
+    Error,
+    /// Keep the input in the tree as a `Cycle` node that is not expanded.
+    BreakWithCycleNode,
+}
+
+/// Settings for a single planning run.
+///
+/// The defaults reproduce the planner's standard behavior. Everything but
+/// the tracer and progress callback can be saved, see `SavedPlan`.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "data-files", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "data-files", serde(default))]
+pub struct PlanOptions {
+    /// Items supplied from outside the plan (e.g. taken from storage).
+    /// They appear as `External` nodes and are not expanded further.
+    pub external_items: HashSet<ItemId>,
+    /// How unavoidable cyclic inputs are handled.
+    pub cycle_policy: CyclePolicy,
+    /// Treat source items as leaves, leaving out the inputs of source
+    /// recipes (e.g. consumables used while mining). By default those
+    /// inputs are planned like any other.
+    pub leaf_sources: bool,
+    /// Recipes carrying any of these tags are never used.
+    pub excluded_tags: HashSet<String>,
+    /// When not empty, only recipes carrying at least one of these tags
+    /// are used.
+    pub required_tags: HashSet<String>,
+    /// Reports raw material totals rounded up to this multiple in
+    /// `PlanResult::source_rounding`. The plan itself is unchanged.
+    pub round_sources_to: Option<u32>,
+    /// Fail the plan with `ProductionError::UnresolvedItems` instead of
+    /// leaving `Unresolved` nodes in the tree.
+    pub strict: bool,
+    /// Prefer recipes consuming byproducts of the recipes higher up in the
+    /// same branch, including items they return in surplus, so those
+    /// byproducts replace raw inputs. The amounts made are taken off the
+    /// inputs consuming them, which keep a `Shared` node for the part
+    /// covered. Only breaks ties left after the cyclic and source checks;
+    /// see `select_best_recipe` for the full order.
+    pub prefer_byproducts: bool,
+    /// Receives every resolution step when set, see `TraceEvent`.
+    #[cfg_attr(feature = "data-files", serde(skip))]
+    pub tracer: Option<Tracer>,
+    /// Called once per resolved node, see `Progress`.
+    #[cfg_attr(feature = "data-files", serde(skip))]
+    pub progress: Option<Progress>,
+    /// Largest number of machines one node is laid out with. Larger nodes
+    /// are reported in `blocks` of at most this many machines; the totals
+    /// are unchanged.
+    pub max_machines_per_node: Option<u32>,
+    /// Sizes machines to finish the requested amount within this many
+    /// seconds, as a batch, instead of for a steady rate per minute. Set
+    /// by `plan_batch`.
+    pub batch_within_secs: Option<f64>,
+    /// Most machines of each type available, e.g. from the blueprints
+    /// owned. Plans needing more report `PlanResult::machine_limit_violations`;
+    /// `plan_within_machine_limits` scales the target down to fit instead.
+    pub machine_limits: HashMap<MachineId, u32>,
+    /// In `plan_targets`, build each item that several targets need once,
+    /// as its own input of the root, instead of once in every target's
+    /// tree. Targets of the same item are merged too.
+    pub merge_shared_items: bool,
+    /// Resolve each recipe's inputs in item ID order, so the same plan
+    /// always has the same layout and warnings. Otherwise the order the
+    /// recipe's inputs happen to be stored in is kept.
+    pub sorted_inputs: bool,
+}
+
+impl PlanOptions {
+    /// Whether the plan's amounts are rates or, in batch mode, totals.
+    pub fn amount_kind(&self) -> AmountKind {
+        match self.batch_within_secs {
+            Some(_) => AmountKind::Total,
+            None => AmountKind::PerMinute,
+        }
+    }
+
+    /// Returns true if the tag filters allow `recipe` to be used.
+    pub fn allows_recipe(&self, recipe: &Recipe) -> bool {
+        let excluded = recipe
+            .tags
+            .iter()
+            .any(|tag| self.excluded_tags.contains(tag));
+        let required = self.required_tags.is_empty()
+            || recipe
+                .tags
+                .iter()
+                .any(|tag| self.required_tags.contains(tag));
+
+        !excluded && required
+    }
+
+    /// Sends an event to the tracer, if any. The event is only built when
+    /// a tracer is set.
+    pub(crate) fn trace(&self, event: impl FnOnce() -> TraceEvent) {
+        if let Some(Tracer(tracer)) = &self.tracer {
+            tracer.event(&event());
+        }
+    }
+}
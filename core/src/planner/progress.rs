@@ -0,0 +1,49 @@
+//! Progress reporting while a plan is resolved.
+
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+struct ProgressState {
+    resolved: usize,
+    callback: Box<dyn FnMut(usize) + Send>,
+}
+
+/// A callback shared with `PlanOptions`, called once per resolved node with
+/// the number of nodes resolved so far, e.g. to show progress or yield to
+/// the UI during a large plan.
+///
+/// The count runs across every plan made with the same `Progress`, so
+/// create a new one per run. Options are equal when they hold the same
+/// callback.
+#[derive(Clone)]
+pub struct Progress(Arc<Mutex<ProgressState>>);
+
+impl Progress {
+    pub fn new(callback: impl FnMut(usize) + Send + 'static) -> Self {
+        Progress(Arc::new(Mutex::new(ProgressState {
+            resolved: 0,
+            callback: Box::new(callback),
+        })))
+    }
+
+    /// Counts one more resolved node and reports the new total.
+    pub(crate) fn node_resolved(&self) {
+        if let Ok(mut state) = self.0.lock() {
+            state.resolved += 1;
+            let resolved = state.resolved;
+            (state.callback)(resolved);
+        }
+    }
+}
+
+impl fmt::Debug for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Progress")
+    }
+}
+
+impl PartialEq for Progress {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
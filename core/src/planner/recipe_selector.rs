@@ -1,57 +1,219 @@
 //! Recipe selection logic for production planning.
 
-use crate::models::{Machine, Recipe};
+use crate::models::{ItemId, Machine, MachineId, Recipe, RecipeUid};
+use std::cmp::Ordering;
 use std::collections::{HashMap, HashSet};
 
-fn has_cyclic_inputs(recipe: &Recipe, visiting: &HashSet<String>) -> bool {
+use super::options::{CyclePolicy, PlanOptions};
+
+fn has_cyclic_inputs(recipe: &Recipe, visiting: &HashSet<ItemId>) -> bool {
+    // Fully returned inputs are never planned, so they cannot cycle
+    let cyclic_input = recipe
+        .inputs
+        .keys()
+        .any(|input_id| visiting.contains(input_id) && recipe.net_input(input_id) > 0);
+    // A group only cycles if every alternative does
+    let cyclic_group = recipe
+        .any_of
+        .iter()
+        .any(|group| group.items.iter().all(|item| visiting.contains(item)));
+
+    cyclic_input || cyclic_group
+}
+
+/// Counts the inputs of a recipe that are among `byproducts` with some
+/// amount left.
+fn consumed_byproducts(recipe: &Recipe, byproducts: &[(ItemId, u32)]) -> usize {
     recipe
         .inputs
         .keys()
-        .any(|input_id| visiting.contains(input_id))
+        .filter(|input_id| {
+            byproducts
+                .iter()
+                .any(|(item_id, left)| item_id == *input_id && *left > 0)
+        })
+        .count()
+}
+
+/// Orders two recipes for the same item; the greater one is preferred.
+pub(crate) fn compare_for_selection(
+    recipe_a: &Recipe,
+    recipe_b: &Recipe,
+    machines: &HashMap<MachineId, Machine>,
+    visiting: &HashSet<ItemId>,
+    byproducts: &[(ItemId, u32)],
+    cycle_policy: CyclePolicy,
+) -> Ordering {
+    let machine_a = machines.get(&recipe_a.by);
+    let machine_b = machines.get(&recipe_b.by);
+
+    let tier_a = machine_a.map(|m| m.tier).unwrap_or(0);
+    let tier_b = machine_b.map(|m| m.tier).unwrap_or(0);
+
+    let power_a = machine_a.map(|m| m.power).unwrap_or(0);
+    let power_b = machine_b.map(|m| m.power).unwrap_or(0);
+
+    let cyclic_a = has_cyclic_inputs(recipe_a, visiting);
+    let cyclic_b = has_cyclic_inputs(recipe_b, visiting);
+
+    // The 0 defaults above must not let an unknown machine win on power
+    let defined = machine_a.is_some().cmp(&machine_b.is_some());
+    let acyclic = cyclic_b.cmp(&cyclic_a);
+    // A cyclic input fails the whole plan under `CyclePolicy::Error`,
+    // which is worse than a missing machine
+    let first = match cycle_policy {
+        CyclePolicy::Error => acyclic.then(defined),
+        CyclePolicy::Skip | CyclePolicy::BreakWithCycleNode => defined.then(acyclic),
+    };
+
+    first
+        .then_with(|| recipe_a.is_source.cmp(&recipe_b.is_source))
+        .then_with(|| {
+            consumed_byproducts(recipe_a, byproducts)
+                .cmp(&consumed_byproducts(recipe_b, byproducts))
+        })
+        .then_with(|| tier_a.cmp(&tier_b))
+        .then_with(|| power_b.cmp(&power_a))
+        .then_with(|| recipe_a.id.cmp(&recipe_b.id))
 }
 
 /// Selects the best recipe for a given item based on priority rules.
 ///
 /// Priority (highest to lowest):
-/// 1. Cyclic check
-/// 2. Is source
-/// 3. Higher machine tier
-/// 4. Lower power consumption
-/// 5. Alphabetical recipe ID (for determinism)
+/// 1. Machine is defined
+/// 2. Cyclic check, first under `CyclePolicy::Error`
+/// 3. Is source
+/// 4. More inputs among `byproducts`
+/// 5. Higher machine tier
+/// 6. Lower power consumption
+/// 7. Alphabetical recipe ID (for determinism)
 ///
-/// Returns `None` if no recipe exists for the item.
+/// `byproducts` are items already produced as byproducts in the current
+/// branch, see `PlanOptions::prefer_byproducts`. Recipes rejected by the
+/// tag filters in `options` are not considered.
+///
+/// Returns `None` if no allowed recipe exists for the item.
 pub fn select_best_recipe<'a>(
-    item_id: &str,
-    recipes: &'a HashMap<String, Recipe>,
-    recipes_by_output: &HashMap<String, Vec<String>>,
-    machines: &HashMap<String, Machine>,
-    visiting: &HashSet<String>,
+    item_id: &ItemId,
+    recipes: &'a HashMap<RecipeUid, Recipe>,
+    recipes_by_output: &HashMap<ItemId, Vec<RecipeUid>>,
+    machines: &HashMap<MachineId, Machine>,
+    visiting: &HashSet<ItemId>,
+    byproducts: &[(ItemId, u32)],
+    options: &PlanOptions,
 ) -> Option<&'a Recipe> {
     recipes_by_output.get(item_id).and_then(|candidates| {
         candidates
             .iter()
             .filter_map(|id| recipes.get(id))
+            .filter(|recipe| options.allows_recipe(recipe))
             .max_by(|recipe_a, recipe_b| {
-                let machine_a = machines.get(&recipe_a.by);
-                let machine_b = machines.get(&recipe_b.by);
+                compare_for_selection(
+                    recipe_a,
+                    recipe_b,
+                    machines,
+                    visiting,
+                    byproducts,
+                    options.cycle_policy,
+                )
+            })
+    })
+}
 
-                let tier_a = machine_a.map(|m| m.tier).unwrap_or(0);
-                let tier_b = machine_b.map(|m| m.tier).unwrap_or(0);
+/// Picks the item to use from a group of interchangeable inputs.
+///
+/// Externally supplied items come first. Otherwise each alternative is
+/// judged by the recipe `select_best_recipe` would use for it, by the same
+/// rules; alternatives without a recipe come last, and ties keep the
+/// earlier item. Returns `None` for an empty group.
+pub fn select_alternative<'a>(
+    alternatives: &'a [ItemId],
+    recipes: &HashMap<RecipeUid, Recipe>,
+    recipes_by_output: &HashMap<ItemId, Vec<RecipeUid>>,
+    machines: &HashMap<MachineId, Machine>,
+    visiting: &HashSet<ItemId>,
+    byproducts: &[(ItemId, u32)],
+    options: &PlanOptions,
+) -> Option<&'a ItemId> {
+    if let Some(external) = alternatives
+        .iter()
+        .find(|item_id| options.external_items.contains(*item_id))
+    {
+        return Some(external);
+    }
 
-                let power_a = machine_a.map(|m| m.power).unwrap_or(0);
-                let power_b = machine_b.map(|m| m.power).unwrap_or(0);
+    let mut best: Option<(&ItemId, Option<&Recipe>)> = None;
+    for item_id in alternatives
+        .iter()
+        .filter(|item_id| !visiting.contains(*item_id))
+    {
+        let recipe = select_best_recipe(
+            item_id,
+            recipes,
+            recipes_by_output,
+            machines,
+            visiting,
+            byproducts,
+            options,
+        );
+        let better = match (&best, recipe) {
+            (None, _) => true,
+            (Some((_, None)), Some(_)) => true,
+            (Some((_, Some(best_recipe))), Some(recipe)) => {
+                compare_for_selection(
+                    recipe,
+                    best_recipe,
+                    machines,
+                    visiting,
+                    byproducts,
+                    options.cycle_policy,
+                ) == Ordering::Greater
+            }
+            _ => false,
+        };
+        if better {
+            best = Some((item_id, recipe));
+        }
+    }
 
-                let cyclic_a = has_cyclic_inputs(recipe_a, visiting);
-                let cyclic_b = has_cyclic_inputs(recipe_b, visiting);
+    best.map(|(item_id, _)| item_id)
+        .or_else(|| alternatives.first())
+}
 
-                cyclic_b
-                    .cmp(&cyclic_a)
-                    .then_with(|| recipe_a.is_source.cmp(&recipe_b.is_source))
-                    .then_with(|| tier_a.cmp(&tier_b))
-                    .then_with(|| power_b.cmp(&power_a))
-                    .then_with(|| recipe_a.id.cmp(&recipe_b.id))
-            })
-    })
+/// Lists the allowed recipes for an item from best to worst, by the same
+/// rules as `select_best_recipe`. The first one is the recipe it selects.
+pub fn rank_recipes<'a>(
+    item_id: &ItemId,
+    recipes: &'a HashMap<RecipeUid, Recipe>,
+    recipes_by_output: &HashMap<ItemId, Vec<RecipeUid>>,
+    machines: &HashMap<MachineId, Machine>,
+    visiting: &HashSet<ItemId>,
+    byproducts: &[(ItemId, u32)],
+    options: &PlanOptions,
+) -> Vec<&'a Recipe> {
+    let Some(candidates) = recipes_by_output.get(item_id) else {
+        return Vec::new();
+    };
+
+    // `max_by` keeps the last of equal recipes, so equal ones are ranked
+    // in reverse candidate order
+    let mut ranked: Vec<&Recipe> = candidates
+        .iter()
+        .rev()
+        .filter_map(|id| recipes.get(id))
+        .filter(|recipe| options.allows_recipe(recipe))
+        .collect();
+    ranked.sort_by(|recipe_a, recipe_b| {
+        compare_for_selection(
+            recipe_b,
+            recipe_a,
+            machines,
+            visiting,
+            byproducts,
+            options.cycle_policy,
+        )
+    });
+    ranked
 }
 
 #[cfg(test)]
@@ -60,16 +222,13 @@ mod tests {
 
     fn create_recipe(id: &str, by: &str, inputs: Vec<(&str, u32)>, is_source: bool) -> Recipe {
         Recipe::new_for_test(
-            id.to_string(),
-            by.to_string(),
-            60,
-            inputs
-                .into_iter()
-                .map(|(k, v)| (k.to_string(), v))
-                .collect(),
+            id.into(),
+            by.into(),
+            60.0,
+            inputs.into_iter().map(|(k, v)| (k.into(), v)).collect(),
             vec![(id.to_string(), 1)]
                 .into_iter()
-                .map(|(k, v)| (k.to_string(), v))
+                .map(|(k, v)| (k.into(), v))
                 .collect(),
             is_source,
         )
@@ -77,20 +236,23 @@ mod tests {
 
     fn create_machine(id: &str, tier: u32, power: u32) -> Machine {
         Machine {
-            id: id.to_string(),
+            id: id.into(),
             tier,
             power,
+            input_ports: None,
+            output_ports: None,
+            idle_power: None,
         }
     }
 
     fn setup_recipes_by_output(
         item_id: &str,
         recipe_ids: Vec<&str>,
-    ) -> HashMap<String, Vec<String>> {
+    ) -> HashMap<ItemId, Vec<RecipeUid>> {
         let mut map = HashMap::new();
         map.insert(
-            item_id.to_string(),
-            recipe_ids.iter().map(|s| s.to_string()).collect(),
+            item_id.into(),
+            recipe_ids.iter().map(|&s| s.into()).collect(),
         );
         map
     }
@@ -112,27 +274,29 @@ mod tests {
         );
 
         let mut recipes = HashMap::new();
-        recipes.insert("recipe_cyclic".to_string(), recipe_cyclic);
-        recipes.insert("recipe_acyclic".to_string(), recipe_acyclic);
+        recipes.insert("recipe_cyclic".into(), recipe_cyclic);
+        recipes.insert("recipe_acyclic".into(), recipe_acyclic);
 
         let recipes_by_output =
             setup_recipes_by_output("origocrust", vec!["recipe_cyclic", "recipe_acyclic"]);
 
         let mut machines = HashMap::new();
         machines.insert(
-            "refining_unit".to_string(),
+            "refining_unit".into(),
             create_machine("refining_unit", 1, 5),
         );
 
         let mut visiting = HashSet::new();
-        visiting.insert("origocrust_powder".to_string());
+        visiting.insert("origocrust_powder".into());
 
         let selected = select_best_recipe(
-            "origocrust",
+            &"origocrust".into(),
             &recipes,
             &recipes_by_output,
             &machines,
             &visiting,
+            &[],
+            &PlanOptions::default(),
         );
 
         assert!(selected.is_some());
@@ -142,6 +306,61 @@ mod tests {
         assert!(selected.unwrap().inputs.contains_key("originium_ore"));
     }
 
+    #[test]
+    fn test_error_policy_avoids_cycles_before_missing_machines() {
+        let recipe_cyclic = create_recipe(
+            "origocrust",
+            "refining_unit",
+            vec![("origocrust_powder", 1)],
+            false,
+        );
+        let recipe_unknown_machine = create_recipe(
+            "origocrust",
+            "unknown_machine",
+            vec![("originium_ore", 1)],
+            false,
+        );
+
+        let mut recipes = HashMap::new();
+        recipes.insert("recipe_cyclic".into(), recipe_cyclic);
+        recipes.insert("recipe_unknown_machine".into(), recipe_unknown_machine);
+
+        let recipes_by_output = setup_recipes_by_output(
+            "origocrust",
+            vec!["recipe_cyclic", "recipe_unknown_machine"],
+        );
+
+        let mut machines = HashMap::new();
+        machines.insert(
+            "refining_unit".into(),
+            create_machine("refining_unit", 1, 5),
+        );
+
+        let mut visiting = HashSet::new();
+        visiting.insert("origocrust_powder".into());
+
+        let select = |cycle_policy| {
+            select_best_recipe(
+                &"origocrust".into(),
+                &recipes,
+                &recipes_by_output,
+                &machines,
+                &visiting,
+                &[],
+                &PlanOptions {
+                    cycle_policy,
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+            .by
+            .clone()
+        };
+
+        assert_eq!(select(CyclePolicy::Skip), "refining_unit");
+        assert_eq!(select(CyclePolicy::Error), "unknown_machine");
+    }
+
     #[test]
     fn test_prefers_is_source() {
         // buckflower_seed can be picked (is_source=true) or could hypothetically be crafted
@@ -159,30 +378,29 @@ mod tests {
         );
 
         let mut recipes = HashMap::new();
-        recipes.insert("recipe_source".to_string(), recipe_source);
-        recipes.insert("recipe_regular".to_string(), recipe_regular);
+        recipes.insert("recipe_source".into(), recipe_source);
+        recipes.insert("recipe_regular".into(), recipe_regular);
 
         let recipes_by_output =
             setup_recipes_by_output("buckflower_seed", vec!["recipe_source", "recipe_regular"]);
 
         let mut machines = HashMap::new();
         machines.insert(
-            "seed_picking_unit".to_string(),
+            "seed_picking_unit".into(),
             create_machine("seed_picking_unit", 3, 10),
         );
-        machines.insert(
-            "gearing_unit".to_string(),
-            create_machine("gearing_unit", 1, 10),
-        );
+        machines.insert("gearing_unit".into(), create_machine("gearing_unit", 1, 10));
 
         let visiting = HashSet::new();
 
         let selected = select_best_recipe(
-            "buckflower_seed",
+            &"buckflower_seed".into(),
             &recipes,
             &recipes_by_output,
             &machines,
             &visiting,
+            &[],
+            &PlanOptions::default(),
         );
 
         assert!(selected.is_some());
@@ -198,9 +416,9 @@ mod tests {
         let recipe_tier3 = create_recipe("originium_ore", "electric_mining_rig_mk2", vec![], true);
 
         let mut recipes = HashMap::new();
-        recipes.insert("recipe_tier1".to_string(), recipe_tier1);
-        recipes.insert("recipe_tier2".to_string(), recipe_tier2);
-        recipes.insert("recipe_tier3".to_string(), recipe_tier3);
+        recipes.insert("recipe_tier1".into(), recipe_tier1);
+        recipes.insert("recipe_tier2".into(), recipe_tier2);
+        recipes.insert("recipe_tier3".into(), recipe_tier3);
 
         let recipes_by_output = setup_recipes_by_output(
             "originium_ore",
@@ -209,26 +427,28 @@ mod tests {
 
         let mut machines = HashMap::new();
         machines.insert(
-            "portable_originium_rig".to_string(),
+            "portable_originium_rig".into(),
             create_machine("portable_originium_rig", 1, 0),
         );
         machines.insert(
-            "electric_mining_rig".to_string(),
+            "electric_mining_rig".into(),
             create_machine("electric_mining_rig", 2, 5),
         );
         machines.insert(
-            "electric_mining_rig_mk2".to_string(),
+            "electric_mining_rig_mk2".into(),
             create_machine("electric_mining_rig_mk2", 3, 10),
         );
 
         let visiting = HashSet::new();
 
         let selected = select_best_recipe(
-            "originium_ore",
+            &"originium_ore".into(),
             &recipes,
             &recipes_by_output,
             &machines,
             &visiting,
+            &[],
+            &PlanOptions::default(),
         );
 
         assert!(selected.is_some());
@@ -243,8 +463,8 @@ mod tests {
         let recipe_low_power = create_recipe("amethyst_ore", "fluid_pump", vec![], true);
 
         let mut recipes = HashMap::new();
-        recipes.insert("recipe_high_power".to_string(), recipe_high_power);
-        recipes.insert("recipe_low_power".to_string(), recipe_low_power);
+        recipes.insert("recipe_high_power".into(), recipe_high_power);
+        recipes.insert("recipe_low_power".into(), recipe_low_power);
 
         let recipes_by_output = setup_recipes_by_output(
             "amethyst_ore",
@@ -254,25 +474,69 @@ mod tests {
         let mut machines = HashMap::new();
         // Same tier, different power
         machines.insert(
-            "electric_mining_rig".to_string(),
+            "electric_mining_rig".into(),
             create_machine("electric_mining_rig", 2, 10),
         );
-        machines.insert("fluid_pump".to_string(), create_machine("fluid_pump", 2, 5));
+        machines.insert("fluid_pump".into(), create_machine("fluid_pump", 2, 5));
 
         let visiting = HashSet::new();
 
         let selected = select_best_recipe(
-            "amethyst_ore",
+            &"amethyst_ore".into(),
             &recipes,
             &recipes_by_output,
             &machines,
             &visiting,
+            &[],
+            &PlanOptions::default(),
         );
 
         assert!(selected.is_some());
         assert_eq!(selected.unwrap().by, "fluid_pump");
     }
 
+    #[test]
+    fn test_unknown_machine_ranks_last() {
+        let recipe_valid = create_recipe("originium_ore", "portable_originium_rig", vec![], true);
+        let recipe_unknown = create_recipe("originium_ore", "missing_rig", vec![], true);
+
+        let mut recipes = HashMap::new();
+        recipes.insert("recipe_valid".into(), recipe_valid);
+        recipes.insert("recipe_unknown".into(), recipe_unknown);
+
+        let recipes_by_output =
+            setup_recipes_by_output("originium_ore", vec!["recipe_valid", "recipe_unknown"]);
+        let visiting = HashSet::new();
+
+        let select = |machines: &HashMap<MachineId, Machine>| {
+            select_best_recipe(
+                &"originium_ore".into(),
+                &recipes,
+                &recipes_by_output,
+                machines,
+                &visiting,
+                &[],
+                &PlanOptions::default(),
+            )
+            .map(|recipe| recipe.by.clone())
+        };
+
+        let mut machines = HashMap::new();
+        machines.insert(
+            "portable_originium_rig".into(),
+            create_machine("portable_originium_rig", 1, 10),
+        );
+        assert_eq!(select(&machines), Some("portable_originium_rig".into()));
+
+        // A tier 0 machine ties the unknown machine's default tier and
+        // loses on power, yet still wins
+        machines.insert(
+            "portable_originium_rig".into(),
+            create_machine("portable_originium_rig", 0, 10),
+        );
+        assert_eq!(select(&machines), Some("portable_originium_rig".into()));
+    }
+
     #[test]
     fn test_returns_none_when_no_candidates() {
         let recipes = HashMap::new();
@@ -281,13 +545,84 @@ mod tests {
         let visiting = HashSet::new();
 
         let selected = select_best_recipe(
-            "nonexistent_item",
+            &"nonexistent_item".into(),
             &recipes,
             &recipes_by_output,
             &machines,
             &visiting,
+            &[],
+            &PlanOptions::default(),
         );
 
         assert!(selected.is_none());
     }
+
+    #[test]
+    fn test_rank_recipes_starts_with_selected() {
+        let mut recipes = HashMap::new();
+        recipes.insert(
+            "tier1".into(),
+            create_recipe("originium_ore", "portable_originium_rig", vec![], true),
+        );
+        recipes.insert(
+            "tier2".into(),
+            create_recipe("originium_ore", "electric_mining_rig", vec![], true),
+        );
+        recipes.insert(
+            "crafted".into(),
+            create_recipe(
+                "originium_ore",
+                "gearing_unit",
+                vec![("origocrust", 1)],
+                false,
+            ),
+        );
+
+        let recipes_by_output =
+            setup_recipes_by_output("originium_ore", vec!["crafted", "tier1", "tier2"]);
+
+        let mut machines = HashMap::new();
+        machines.insert(
+            "portable_originium_rig".into(),
+            create_machine("portable_originium_rig", 1, 0),
+        );
+        machines.insert(
+            "electric_mining_rig".into(),
+            create_machine("electric_mining_rig", 2, 5),
+        );
+        machines.insert("gearing_unit".into(), create_machine("gearing_unit", 3, 10));
+
+        let item_id = "originium_ore".into();
+        let visiting = HashSet::new();
+        let options = PlanOptions::default();
+        let ranked = rank_recipes(
+            &item_id,
+            &recipes,
+            &recipes_by_output,
+            &machines,
+            &visiting,
+            &[],
+            &options,
+        );
+        let selected = select_best_recipe(
+            &item_id,
+            &recipes,
+            &recipes_by_output,
+            &machines,
+            &visiting,
+            &[],
+            &options,
+        );
+
+        let machines_in_order: Vec<&MachineId> = ranked.iter().map(|recipe| &recipe.by).collect();
+        assert_eq!(
+            machines_in_order,
+            vec![
+                "electric_mining_rig",
+                "portable_originium_rig",
+                "gearing_unit"
+            ]
+        );
+        assert!(std::ptr::eq(ranked[0], selected.unwrap()));
+    }
 }
@@ -0,0 +1,312 @@
+//! Compact "factory codes" for sharing a plan by copy and paste.
+
+use super::PlanOptions;
+use crate::error::ProductionError;
+use crate::hash::fnv1a;
+use crate::models::{ItemId, NodePath, ProductionNode};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+
+/// Format version written as the first byte of a code.
+const CODE_VERSION: u8 = 1;
+
+/// Bytes of the checksum at the end of a code.
+const CHECKSUM_LEN: usize = 4;
+
+/// What a shared plan needs to be rebuilt: the target, the items supplied
+/// from outside the plan and the tree nodes shown collapsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShareState {
+    pub item: ItemId,
+    /// Output per minute.
+    pub amount: u32,
+    /// Items supplied externally, see `PlanOptions::external_items`.
+    pub excluded: Vec<ItemId>,
+    /// Collapsed nodes of the production tree, see `resolvable_paths`.
+    pub collapsed: Vec<NodePath>,
+}
+
+/// Encodes node paths as their indices joined with dots, separated by
+/// commas, e.g. `0,1.2`. Sorted so equal sets encode the same; the root
+/// path is left out.
+pub fn encode_node_paths<'a>(paths: impl IntoIterator<Item = &'a NodePath>) -> String {
+    let mut encoded: Vec<String> = paths
+        .into_iter()
+        .filter(|path| !path.0.is_empty())
+        .map(|path| {
+            let indices: Vec<String> = path.0.iter().map(usize::to_string).collect();
+            indices.join(".")
+        })
+        .collect();
+    encoded.sort();
+    encoded.dedup();
+    encoded.join(",")
+}
+
+/// Decodes paths written by `encode_node_paths`. Malformed entries are
+/// skipped, since the text may come from a hand-edited URL.
+pub fn decode_node_paths(text: &str) -> Vec<NodePath> {
+    text.split(',')
+        .filter_map(|entry| {
+            let indices = entry
+                .split('.')
+                .map(|index| index.parse().ok())
+                .collect::<Option<Vec<usize>>>()?;
+            Some(NodePath(indices))
+        })
+        .collect()
+}
+
+/// Keeps the paths that still lead to a node with inputs in `plan`, so a
+/// collapsed set saved for another plan layout drops what no longer fits.
+pub fn resolvable_paths<'a>(
+    paths: impl IntoIterator<Item = &'a NodePath>,
+    plan: &ProductionNode,
+) -> Vec<NodePath> {
+    paths
+        .into_iter()
+        .filter(|path| {
+            matches!(
+                plan.node_at(path),
+                Some(ProductionNode::Resolved { inputs, .. }) if !inputs.is_empty()
+            )
+        })
+        .cloned()
+        .collect()
+}
+
+/// 32-bit FNV-1a, enough to catch truncated or mistyped codes.
+fn checksum(bytes: &[u8]) -> [u8; CHECKSUM_LEN] {
+    fnv1a::<u32>(bytes).to_be_bytes()
+}
+
+fn invalid(reason: &str) -> ProductionError {
+    ProductionError::ParseError(format!("invalid share code: {}", reason))
+}
+
+impl ShareState {
+    pub fn new(item: impl Into<ItemId>, amount: u32) -> Self {
+        ShareState {
+            item: item.into(),
+            amount,
+            excluded: Vec::new(),
+            collapsed: Vec::new(),
+        }
+    }
+
+    /// Encodes the state as URL-safe base64 of a version byte, the item,
+    /// amount and excluded items as lines of text, and a checksum. The
+    /// collapsed nodes follow on a fourth line when there are any, so codes
+    /// without them are unchanged.
+    pub fn to_code(&self) -> String {
+        let excluded: Vec<&str> = self.excluded.iter().map(ItemId::as_str).collect();
+        let mut text = format!("{}\n{}\n{}", self.item, self.amount, excluded.join(","));
+        if !self.collapsed.is_empty() {
+            text.push('\n');
+            text.push_str(&encode_node_paths(&self.collapsed));
+        }
+        let mut bytes = vec![CODE_VERSION];
+        bytes.extend_from_slice(text.as_bytes());
+        bytes.extend_from_slice(&checksum(&bytes));
+
+        URL_SAFE_NO_PAD.encode(bytes)
+    }
+
+    /// Decodes a code made by `to_code`. Surrounding whitespace is ignored;
+    /// a corrupted or truncated code is a `ParseError`.
+    pub fn from_code(code: &str) -> Result<Self, ProductionError> {
+        let bytes = URL_SAFE_NO_PAD
+            .decode(code.trim())
+            .map_err(|_| invalid("not a valid code"))?;
+        if bytes.len() <= CHECKSUM_LEN {
+            return Err(invalid("too short"));
+        }
+
+        let (content, expected) = bytes.split_at(bytes.len() - CHECKSUM_LEN);
+        if checksum(content) != expected {
+            return Err(invalid("checksum mismatch, the code may be incomplete"));
+        }
+        if content[0] != CODE_VERSION {
+            return Err(invalid(&format!("unsupported version {}", content[0])));
+        }
+
+        let text = std::str::from_utf8(&content[1..]).map_err(|_| invalid("not valid text"))?;
+        let mut lines = text.split('\n');
+        let (Some(item), Some(amount), Some(excluded), collapsed, None) = (
+            lines.next(),
+            lines.next(),
+            lines.next(),
+            lines.next(),
+            lines.next(),
+        ) else {
+            return Err(invalid("wrong number of fields"));
+        };
+        if item.is_empty() {
+            return Err(invalid("missing item"));
+        }
+        let amount = amount
+            .parse()
+            .ok()
+            .filter(|amount| *amount > 0)
+            .ok_or_else(|| invalid("amount must be a positive number"))?;
+
+        Ok(ShareState {
+            item: item.into(),
+            amount,
+            excluded: excluded
+                .split(',')
+                .filter(|item| !item.is_empty())
+                .map(ItemId::from)
+                .collect(),
+            collapsed: collapsed.map(decode_node_paths).unwrap_or_default(),
+        })
+    }
+
+    /// Options that supply the excluded items externally. The target itself
+    /// is always planned. Inputs are sorted, so the collapsed paths find
+    /// the same nodes on every run.
+    pub fn plan_options(&self) -> PlanOptions {
+        PlanOptions {
+            sorted_inputs: true,
+            external_items: self
+                .excluded
+                .iter()
+                .filter(|item| **item != self.item)
+                .cloned()
+                .collect(),
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state() -> ShareState {
+        ShareState {
+            excluded: vec!["origocrust".into(), "amethyst_fiber".into()],
+            ..ShareState::new("lc_wuling_battery", 12)
+        }
+    }
+
+    #[test]
+    fn test_code_round_trip() {
+        let code = state().to_code();
+
+        assert!(
+            code.chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        );
+        assert_eq!(ShareState::from_code(&code).unwrap(), state());
+        assert_eq!(
+            ShareState::from_code(&format!(" {}\n", code)).unwrap(),
+            state()
+        );
+
+        let plain = ShareState::new("origocrust", 30);
+        assert_eq!(ShareState::from_code(&plain.to_code()).unwrap(), plain);
+    }
+
+    #[test]
+    fn test_collapsed_paths_round_trip() {
+        let with_collapsed = ShareState {
+            collapsed: vec![NodePath(vec![0]), NodePath(vec![1, 2])],
+            ..state()
+        };
+
+        assert_eq!(
+            ShareState::from_code(&with_collapsed.to_code()).unwrap(),
+            with_collapsed
+        );
+        // Nothing collapsed keeps the three-line format
+        let bytes = URL_SAFE_NO_PAD.decode(state().to_code()).unwrap();
+        assert_eq!(bytes.iter().filter(|byte| **byte == b'\n').count(), 2);
+    }
+
+    #[test]
+    fn test_node_path_encoding() {
+        let paths = [NodePath(vec![1, 2]), NodePath::root(), NodePath(vec![0])];
+
+        assert_eq!(encode_node_paths(&paths), "0,1.2");
+        assert_eq!(
+            decode_node_paths("0,1.2"),
+            vec![NodePath(vec![0]), NodePath(vec![1, 2])]
+        );
+        assert_eq!(decode_node_paths("0,x.1,,3."), vec![NodePath(vec![0])]);
+        assert_eq!(decode_node_paths(""), vec![]);
+    }
+
+    #[test]
+    fn test_resolvable_paths_drop_missing_nodes() {
+        let leaf = |item_id: &str| ProductionNode::Unresolved {
+            item_id: item_id.into(),
+            amount: 30,
+        };
+        let resolved = |item_id: &str, inputs| ProductionNode::Resolved {
+            item_id: item_id.into(),
+            machine_id: "refining_unit".into(),
+            recipe_id: Default::default(),
+            amount: 30,
+            machine_count: 1,
+            power_usage: 5,
+            standby_power: 0,
+            load: 1.0,
+            inputs,
+            is_source: false,
+            blocks: vec![],
+        };
+        let plan = resolved(
+            "amethyst_component",
+            vec![resolved("amethyst_fiber", vec![leaf("amethyst_ore")])],
+        );
+
+        let paths = [
+            NodePath(vec![0]),
+            // A leaf has nothing to collapse
+            NodePath(vec![0, 0]),
+            NodePath(vec![1]),
+            NodePath(vec![0, 0, 0]),
+        ];
+        assert_eq!(resolvable_paths(&paths, &plan), vec![NodePath(vec![0])]);
+    }
+
+    #[test]
+    fn test_corrupted_code_is_rejected() {
+        let code = state().to_code();
+
+        let truncated = &code[..code.len() - 3];
+        assert!(ShareState::from_code(truncated).is_err());
+
+        // Change one character in the middle
+        let mut changed: Vec<char> = code.chars().collect();
+        let middle = changed.len() / 2;
+        changed[middle] = if changed[middle] == 'A' { 'B' } else { 'A' };
+        let changed: String = changed.into_iter().collect();
+        let err = ShareState::from_code(&changed).unwrap_err().to_string();
+        assert!(err.contains("invalid share code"), "{}", err);
+
+        assert!(ShareState::from_code("").is_err());
+        assert!(ShareState::from_code("not a code!").is_err());
+    }
+
+    #[test]
+    fn test_unsupported_version() {
+        let mut bytes = vec![CODE_VERSION + 1];
+        bytes.extend_from_slice(b"origocrust\n30\n");
+        bytes.extend_from_slice(&checksum(&bytes));
+
+        let err = ShareState::from_code(&URL_SAFE_NO_PAD.encode(bytes)).unwrap_err();
+        assert!(err.to_string().contains("unsupported version 2"));
+    }
+
+    #[test]
+    fn test_plan_options_keep_target() {
+        let mut state = state();
+        state.excluded.push("lc_wuling_battery".into());
+
+        let options = state.plan_options();
+        assert_eq!(options.external_items.len(), 2);
+        assert!(!options.external_items.contains("lc_wuling_battery"));
+    }
+}
@@ -0,0 +1,39 @@
+//! Rounding raw material totals up to procurement-friendly amounts.
+
+use crate::models::{ItemId, ProductionNode};
+
+/// A raw material total rounded up to a multiple, e.g. a full belt load.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "data-files", derive(serde::Serialize))]
+pub struct SourceRounding {
+    pub item_id: ItemId,
+    /// Amount the plan actually consumes.
+    pub required: u32,
+    /// `required` rounded up to the multiple.
+    pub rounded: u32,
+    /// Extra amount supplied by rounding up, left unused by the plan.
+    pub surplus: u32,
+}
+
+/// Rounds every raw material total of the plan up to `multiple`.
+///
+/// Informational only: the plan itself is not changed. Sorted by item ID.
+pub fn round_sources(node: &ProductionNode, multiple: u32) -> Vec<SourceRounding> {
+    let multiple = multiple.max(1);
+
+    let mut roundings: Vec<SourceRounding> = node
+        .total_source_materials()
+        .into_iter()
+        .map(|(item_id, required)| {
+            let rounded = required.div_ceil(multiple).saturating_mul(multiple);
+            SourceRounding {
+                item_id,
+                required,
+                rounded,
+                surplus: rounded - required,
+            }
+        })
+        .collect();
+    roundings.sort_by(|a, b| a.item_id.cmp(&b.item_id));
+    roundings
+}
@@ -0,0 +1,73 @@
+//! Aggregated figures for a finished production plan.
+
+use crate::config::GameData;
+use crate::models::{ItemId, MachineId, ProductionNode};
+use std::collections::BTreeMap;
+
+/// Machine and power totals for the recipes sharing one tag.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "data-files", derive(serde::Serialize))]
+pub struct TagTotals {
+    pub machines: u32,
+    pub power: u32,
+}
+
+/// Totals of a production plan.
+///
+/// Maps are ordered so serialized summaries are stable.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "data-files", derive(serde::Serialize))]
+pub struct PlanSummary {
+    /// Peak power, with every machine crafting.
+    pub power: u32,
+    /// Average power, see `ProductionNode::total_effective_power`.
+    pub active_power: f64,
+    pub machines: BTreeMap<MachineId, u32>,
+    pub raw_materials: BTreeMap<ItemId, u32>,
+    /// Totals per recipe tag. A recipe with several tags counts toward each
+    /// of them; untagged recipes are not listed.
+    pub by_tag: BTreeMap<String, TagTotals>,
+}
+
+impl PlanSummary {
+    /// Summarizes `node`, looking up recipe tags in `data`.
+    pub fn new(node: &ProductionNode, data: &GameData) -> Self {
+        let mut by_tag = BTreeMap::new();
+        collect_tag_totals(node, data, &mut by_tag);
+
+        PlanSummary {
+            power: node.total_power(),
+            active_power: node.total_effective_power(),
+            machines: node.total_machines().into_iter().collect(),
+            raw_materials: node.total_source_materials().into_iter().collect(),
+            by_tag,
+        }
+    }
+}
+
+fn collect_tag_totals(
+    node: &ProductionNode,
+    data: &GameData,
+    by_tag: &mut BTreeMap<String, TagTotals>,
+) {
+    if let ProductionNode::Resolved {
+        recipe_id,
+        machine_count,
+        power_usage,
+        inputs,
+        ..
+    } = node
+    {
+        if let Some(recipe) = data.recipes.get(recipe_id) {
+            for tag in &recipe.tags {
+                let totals = by_tag.entry(tag.clone()).or_default();
+                totals.machines += machine_count;
+                totals.power += power_usage;
+            }
+        }
+
+        for child in inputs {
+            collect_tag_totals(child, data, by_tag);
+        }
+    }
+}
@@ -0,0 +1,406 @@
+//! Planning several targets side by side.
+
+use super::{
+    PlanOptions, PlanResult, PlanWarning, PlanWarnings, check_policies, finish_plan, resolve_tree,
+};
+use crate::config::GameData;
+use crate::error::ProductionError;
+use crate::models::{ItemId, ProductionNode};
+use std::collections::{HashMap, HashSet};
+
+/// Item ID of the root node of a multi-target plan, see `plan_targets`.
+pub const TARGETS_ROOT: &str = "targets";
+
+/// Plans several `(item, amount per minute)` targets as the inputs of one
+/// `TARGETS_ROOT` virtual root, so their totals are summed into one
+/// summary.
+///
+/// With `PlanOptions::merge_shared_items`, items crafted in the trees of
+/// several targets are planned once, at their summed amount, as inputs of
+/// the root after the targets in ID order; the trees consuming them keep a
+/// `Shared` node in their place. Raw materials stay in every tree. Fails
+/// like `plan_production_with_options`.
+pub fn plan_targets(
+    data: &GameData,
+    targets: &[(ItemId, u32)],
+    options: &PlanOptions,
+) -> Result<PlanResult, ProductionError> {
+    let mut warnings = PlanWarnings::new();
+    let inputs = if options.merge_shared_items {
+        merged_trees(data, targets, options, &mut warnings)
+    } else {
+        targets
+            .iter()
+            .map(|(item_id, amount)| resolve_tree(data, item_id, *amount, options, &mut warnings))
+            .collect()
+    };
+
+    let root = ProductionNode::virtual_root(TARGETS_ROOT, inputs);
+
+    check_policies(finish_plan(data, root, warnings, options), options)
+}
+
+/// Plans the targets with every shared item hoisted to the top level.
+///
+/// A shared item's amount is what the other trees take of it, which is
+/// only known once they are planned, so all trees are planned again until
+/// the amounts settle. That takes one round per level of shared items;
+/// items still changing after the last round get a
+/// `PlanWarning::SharedAmountUnsettled`.
+fn merged_trees(
+    data: &GameData,
+    targets: &[(ItemId, u32)],
+    options: &PlanOptions,
+    warnings: &mut PlanWarnings,
+) -> Vec<ProductionNode> {
+    let mut requested: Vec<(ItemId, u32)> = Vec::new();
+    for (item_id, amount) in targets {
+        match requested.iter_mut().find(|(id, _)| id == item_id) {
+            Some((_, total)) => *total += amount,
+            None => requested.push((item_id.clone(), *amount)),
+        }
+    }
+
+    let separate: Vec<ProductionNode> = requested
+        .iter()
+        .map(|(item_id, amount)| {
+            resolve_tree(data, item_id, *amount, options, &mut PlanWarnings::new())
+        })
+        .collect();
+    let mut shared: Vec<ItemId> = shared_items(&separate)
+        .into_iter()
+        .filter(|item_id| !requested.iter().any(|(id, _)| id == item_id))
+        .collect();
+    shared.sort();
+
+    let mut top_level = requested.clone();
+    top_level.extend(shared.iter().map(|item_id| (item_id.clone(), 0)));
+    let hoisted: HashSet<ItemId> = top_level.iter().map(|(id, _)| id.clone()).collect();
+
+    let mut demand: HashMap<ItemId, u32> = HashMap::new();
+    let mut trees = Vec::new();
+    let mut settled = false;
+    for _ in 0..=hoisted.len() {
+        let mut round_warnings = PlanWarnings::new();
+        trees = top_level
+            .iter()
+            .map(|(item_id, amount)| {
+                let amount = amount + demand.get(item_id).copied().unwrap_or(0);
+                let mut options = options.clone();
+                options
+                    .external_items
+                    .extend(hoisted.iter().filter(|id| *id != item_id).cloned());
+                (amount > 0)
+                    .then(|| resolve_tree(data, item_id, amount, &options, &mut round_warnings))
+            })
+            .collect::<Vec<_>>();
+
+        let mut next_demand = HashMap::new();
+        for tree in trees.iter().flatten() {
+            collect_demand(tree, &hoisted, &options.external_items, &mut next_demand);
+        }
+        *warnings = round_warnings;
+        settled = next_demand == demand;
+        if settled {
+            break;
+        }
+        demand = next_demand;
+    }
+
+    if !settled {
+        for warning in unsettled_amounts(&top_level, &trees, &demand) {
+            warnings.push(warning);
+        }
+    }
+
+    trees
+        .into_iter()
+        .flatten()
+        .map(|mut tree| {
+            mark_shared(&mut tree, &hoisted, &options.external_items);
+            tree
+        })
+        .collect()
+}
+
+/// Compares the amount each top-level tree was planned for with what the
+/// trees took of it, `demand`, after the last round.
+fn unsettled_amounts(
+    top_level: &[(ItemId, u32)],
+    trees: &[Option<ProductionNode>],
+    demand: &HashMap<ItemId, u32>,
+) -> Vec<PlanWarning> {
+    top_level
+        .iter()
+        .zip(trees)
+        .filter_map(|((item_id, requested), tree)| {
+            let planned = tree.as_ref().map_or(0, ProductionNode::amount);
+            let needed = requested + demand.get(item_id).copied().unwrap_or(0);
+            (planned != needed).then(|| PlanWarning::SharedAmountUnsettled {
+                item_id: item_id.clone(),
+                planned,
+                needed,
+            })
+        })
+        .collect()
+}
+
+/// Items crafted (not mined) in more than one of `trees`.
+fn shared_items(trees: &[ProductionNode]) -> HashSet<ItemId> {
+    fn crafted(node: &ProductionNode, items: &mut HashSet<ItemId>) {
+        if let ProductionNode::Resolved {
+            item_id,
+            inputs,
+            is_source: false,
+            ..
+        } = node
+        {
+            items.insert(item_id.clone());
+            for child in inputs {
+                crafted(child, items);
+            }
+        }
+    }
+
+    let mut seen_in: HashMap<ItemId, usize> = HashMap::new();
+    for tree in trees {
+        let mut items = HashSet::new();
+        crafted(tree, &mut items);
+        for item_id in items {
+            *seen_in.entry(item_id).or_insert(0) += 1;
+        }
+    }
+    seen_in
+        .into_iter()
+        .filter(|(_, trees)| *trees > 1)
+        .map(|(item_id, _)| item_id)
+        .collect()
+}
+
+/// Adds up what `node` takes of the hoisted items, which the planner left
+/// as `External` nodes. Items the caller supplies externally are skipped.
+fn collect_demand(
+    node: &ProductionNode,
+    hoisted: &HashSet<ItemId>,
+    external: &HashSet<ItemId>,
+    demand: &mut HashMap<ItemId, u32>,
+) {
+    match node {
+        ProductionNode::Resolved { inputs, .. } => {
+            for child in inputs {
+                collect_demand(child, hoisted, external, demand);
+            }
+        }
+        ProductionNode::External { item_id, amount }
+            if hoisted.contains(item_id) && !external.contains(item_id) =>
+        {
+            *demand.entry(item_id.clone()).or_insert(0) += amount;
+        }
+        _ => {}
+    }
+}
+
+/// Turns the `External` nodes of hoisted items into `Shared` nodes, since
+/// the plan itself builds them.
+fn mark_shared(node: &mut ProductionNode, hoisted: &HashSet<ItemId>, external: &HashSet<ItemId>) {
+    match node {
+        ProductionNode::Resolved { inputs, .. } => {
+            for child in inputs {
+                mark_shared(child, hoisted, external);
+            }
+        }
+        ProductionNode::External { item_id, amount }
+            if hoisted.contains(item_id) && !external.contains(item_id) =>
+        {
+            *node = ProductionNode::Shared {
+                item_id: item_id.clone(),
+                amount: *amount,
+            };
+        }
+        _ => {}
+    }
+}
+
+#[cfg(all(test, feature = "data-files"))]
+mod tests {
+    use super::*;
+    use crate::models::MachineId;
+
+    // Both targets are made from origocrust
+    const RECIPES: &str = r#"
+[[recipes]]
+id = "originium_ore"
+by = "electric_mining_rig"
+time = 2
+out = 1
+is_source = true
+
+[[recipes]]
+id = "origocrust"
+by = "refining_unit"
+time = 2
+out = 1
+[recipes.inputs]
+originium_ore = 1
+
+[[recipes]]
+id = "origocrust_powder"
+by = "shredding_unit"
+time = 2
+out = 1
+[recipes.inputs]
+origocrust = 1
+
+[[recipes]]
+id = "packed_origocrust"
+by = "packaging_unit"
+time = 2
+out = 1
+[recipes.inputs]
+origocrust = 1
+"#;
+
+    const MACHINES: &str = r#"
+[[machines]]
+id = "refining_unit"
+tier = 1
+power = 5
+
+[[machines]]
+id = "shredding_unit"
+tier = 1
+power = 5
+
+[[machines]]
+id = "packaging_unit"
+tier = 1
+power = 5
+
+[[machines]]
+id = "electric_mining_rig"
+tier = 1
+power = 5
+"#;
+
+    fn targets() -> Vec<(ItemId, u32)> {
+        vec![
+            ("origocrust_powder".into(), 10),
+            ("packed_origocrust".into(), 10),
+        ]
+    }
+
+    fn root_inputs(node: &ProductionNode) -> &[ProductionNode] {
+        match node {
+            ProductionNode::Resolved { inputs, .. } => inputs,
+            _ => panic!("the root should be resolved"),
+        }
+    }
+
+    #[test]
+    fn test_shared_intermediate_is_planned_once() {
+        let data = GameData::new(RECIPES, MACHINES).unwrap();
+        let options = PlanOptions {
+            merge_shared_items: true,
+            ..Default::default()
+        };
+
+        let result = plan_targets(&data, &targets(), &options).unwrap();
+
+        let inputs = root_inputs(&result.node);
+        let items: Vec<&str> = inputs
+            .iter()
+            .map(|input| input.item_id().as_str())
+            .collect();
+        assert_eq!(
+            items,
+            vec!["origocrust_powder", "packed_origocrust", "origocrust"]
+        );
+        assert_eq!(inputs[2].amount(), 20);
+        assert_eq!(
+            root_inputs(&inputs[0]),
+            &[ProductionNode::Shared {
+                item_id: "origocrust".into(),
+                amount: 10,
+            }]
+        );
+        // One refining unit covers both targets
+        assert_eq!(
+            result.node.total_machines()[&MachineId::from("refining_unit")],
+            1
+        );
+        assert_eq!(
+            result.node.total_source_materials()[&ItemId::from("originium_ore")],
+            20
+        );
+    }
+
+    #[test]
+    fn test_targets_are_planned_separately_by_default() {
+        let data = GameData::new(RECIPES, MACHINES).unwrap();
+
+        let result = plan_targets(&data, &targets(), &PlanOptions::default()).unwrap();
+
+        assert_eq!(root_inputs(&result.node).len(), 2);
+        assert_eq!(
+            result.node.total_machines()[&MachineId::from("refining_unit")],
+            2
+        );
+    }
+
+    #[test]
+    fn test_repeated_target_is_merged() {
+        let data = GameData::new(RECIPES, MACHINES).unwrap();
+        let options = PlanOptions {
+            merge_shared_items: true,
+            ..Default::default()
+        };
+        let targets = vec![("origocrust".into(), 10), ("origocrust".into(), 5)];
+
+        let result = plan_targets(&data, &targets, &options).unwrap();
+
+        let inputs = root_inputs(&result.node);
+        assert_eq!(inputs.len(), 1);
+        assert_eq!(inputs[0].amount(), 15);
+        assert!(result.node.is_virtual_root());
+    }
+
+    #[test]
+    fn test_unsettled_shared_amount_is_reported() {
+        let data = GameData::new(RECIPES, MACHINES).unwrap();
+        let top_level = vec![("origocrust_powder".into(), 10), ("origocrust".into(), 0)];
+        let plan = |item_id: &str, amount| {
+            Some(resolve_tree(
+                &data,
+                &item_id.into(),
+                amount,
+                &PlanOptions::default(),
+                &mut PlanWarnings::new(),
+            ))
+        };
+        // Origocrust was planned for 5 but the trees took 10
+        let trees = vec![plan("origocrust_powder", 10), plan("origocrust", 5)];
+        let demand = HashMap::from([(ItemId::from("origocrust"), 10)]);
+
+        assert_eq!(
+            unsettled_amounts(&top_level, &trees, &demand),
+            vec![PlanWarning::SharedAmountUnsettled {
+                item_id: "origocrust".into(),
+                planned: 5,
+                needed: 10,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_settled_plan_has_no_warnings() {
+        let data = GameData::new(RECIPES, MACHINES).unwrap();
+        let options = PlanOptions {
+            merge_shared_items: true,
+            ..Default::default()
+        };
+
+        let result = plan_targets(&data, &targets(), &options).unwrap();
+
+        assert!(result.warnings.is_empty());
+    }
+}
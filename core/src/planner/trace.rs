@@ -0,0 +1,340 @@
+//! Step-by-step trace of the planner's decisions, for debugging a plan.
+
+use crate::models::{ItemId, RecipeUid};
+use std::fmt;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+/// One resolution step. `depth` is the number of items being resolved
+/// above the step's item, so the target is at depth 0.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "data-files", derive(serde::Serialize))]
+pub enum TraceEvent {
+    /// An item and amount are about to be resolved.
+    Requested {
+        depth: usize,
+        item_id: ItemId,
+        amount: u32,
+    },
+    /// The allowed recipes for the item, best first.
+    Candidates {
+        depth: usize,
+        item_id: ItemId,
+        ranked: Vec<RecipeUid>,
+    },
+    /// The recipe used for the item.
+    Chosen {
+        depth: usize,
+        item_id: ItemId,
+        recipe_id: RecipeUid,
+    },
+    /// Crafts and machines needed for the requested amount.
+    Computed {
+        depth: usize,
+        item_id: ItemId,
+        crafts: f64,
+        machines: u32,
+    },
+    /// An input of the item is resolved next.
+    Child {
+        depth: usize,
+        parent: ItemId,
+        item_id: ItemId,
+        amount: u32,
+    },
+    /// The item is supplied from outside the plan.
+    External { depth: usize, item_id: ItemId },
+    /// No allowed recipe produces the item.
+    Unresolved { depth: usize, item_id: ItemId },
+    /// The input is already being resolved higher up and is not expanded.
+    Cycle {
+        depth: usize,
+        parent: ItemId,
+        item_id: ItemId,
+    },
+}
+
+impl TraceEvent {
+    pub fn depth(&self) -> usize {
+        match self {
+            TraceEvent::Requested { depth, .. }
+            | TraceEvent::Candidates { depth, .. }
+            | TraceEvent::Chosen { depth, .. }
+            | TraceEvent::Computed { depth, .. }
+            | TraceEvent::Child { depth, .. }
+            | TraceEvent::External { depth, .. }
+            | TraceEvent::Unresolved { depth, .. }
+            | TraceEvent::Cycle { depth, .. } => *depth,
+        }
+    }
+}
+
+impl fmt::Display for TraceEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TraceEvent::Requested {
+                item_id, amount, ..
+            } => write!(f, "request {} x{}", item_id, amount),
+            TraceEvent::Candidates { ranked, .. } => {
+                let ranked: Vec<String> = ranked
+                    .iter()
+                    .enumerate()
+                    .map(|(rank, id)| format!("#{} {}", rank + 1, id))
+                    .collect();
+                write!(f, "candidates: {}", ranked.join(", "))
+            }
+            TraceEvent::Chosen { recipe_id, .. } => write!(f, "chose {}", recipe_id),
+            TraceEvent::Computed {
+                crafts, machines, ..
+            } => write!(f, "{:.2} crafts on {} machine(s)", crafts, machines),
+            TraceEvent::Child {
+                item_id, amount, ..
+            } => write!(f, "input {} x{}", item_id, amount),
+            TraceEvent::External { item_id, .. } => write!(f, "{} is supplied externally", item_id),
+            TraceEvent::Unresolved { item_id, .. } => write!(f, "no recipe for {}", item_id),
+            TraceEvent::Cycle { item_id, .. } => {
+                write!(f, "input {} is already being resolved", item_id)
+            }
+        }
+    }
+}
+
+/// Receives the planner's resolution steps, in order.
+pub trait PlanTracer: Send + Sync {
+    fn event(&self, event: &TraceEvent);
+}
+
+/// A tracer shared with `PlanOptions`. Options are equal when they hold
+/// the same tracer.
+#[derive(Clone)]
+pub struct Tracer(pub Arc<dyn PlanTracer>);
+
+impl Tracer {
+    pub fn new(tracer: impl PlanTracer + 'static) -> Self {
+        Tracer(Arc::new(tracer))
+    }
+}
+
+impl fmt::Debug for Tracer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Tracer")
+    }
+}
+
+impl PartialEq for Tracer {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// Collects the events, e.g. for tests.
+#[derive(Debug, Default)]
+pub struct VecTracer {
+    events: Mutex<Vec<TraceEvent>>,
+}
+
+impl VecTracer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the events received so far.
+    pub fn events(&self) -> Vec<TraceEvent> {
+        self.events
+            .lock()
+            .map(|events| events.clone())
+            .unwrap_or_default()
+    }
+}
+
+impl PlanTracer for VecTracer {
+    fn event(&self, event: &TraceEvent) {
+        if let Ok(mut events) = self.events.lock() {
+            events.push(event.clone());
+        }
+    }
+}
+
+/// How `WriterTracer` writes each event.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TraceFormat {
+    /// One line per event, indented by depth.
+    #[default]
+    Text,
+    /// One JSON object per line.
+    #[cfg(feature = "data-files")]
+    JsonLines,
+}
+
+/// Writes each event to `out` as it happens. Write errors are ignored so
+/// tracing never changes the plan.
+pub struct WriterTracer<W> {
+    out: Mutex<W>,
+    format: TraceFormat,
+}
+
+impl<W: Write + Send> WriterTracer<W> {
+    pub fn new(out: W, format: TraceFormat) -> Self {
+        WriterTracer {
+            out: Mutex::new(out),
+            format,
+        }
+    }
+
+    /// Returns the writer, e.g. to inspect a buffer.
+    pub fn into_inner(self) -> W {
+        match self.out.into_inner() {
+            Ok(out) => out,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+impl<W: Write + Send> PlanTracer for WriterTracer<W> {
+    fn event(&self, event: &TraceEvent) {
+        let Ok(mut out) = self.out.lock() else {
+            return;
+        };
+        let _ = match self.format {
+            TraceFormat::Text => writeln!(out, "{}{}", "  ".repeat(event.depth()), event),
+            #[cfg(feature = "data-files")]
+            TraceFormat::JsonLines => match serde_json::to_string(event) {
+                Ok(line) => writeln!(out, "{}", line),
+                Err(_) => Ok(()),
+            },
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::GameData;
+    use crate::models::{Machine, Recipe};
+    use crate::planner::{PlanOptions, plan_production_with_options};
+
+    fn machine(id: &str) -> Machine {
+        Machine {
+            id: id.into(),
+            tier: 1,
+            power: 5,
+            input_ports: None,
+            output_ports: None,
+            idle_power: None,
+        }
+    }
+
+    // origocrust is refined from mined originium_ore
+    fn two_level_data() -> GameData {
+        let mut ore = Recipe::new("originium_ore", vec!["electric_mining_rig".into()], 2.0);
+        ore.outputs.insert("originium_ore".into(), 1);
+        ore.is_source = true;
+
+        let mut crust = Recipe::new("origocrust", vec!["refining_unit".into()], 2.0);
+        crust.inputs.insert("originium_ore".into(), 1);
+        crust.outputs.insert("origocrust".into(), 1);
+
+        GameData::builder()
+            .add_machine(machine("electric_mining_rig"))
+            .add_machine(machine("refining_unit"))
+            .add_recipe(ore)
+            .add_recipe(crust)
+            .build()
+            .unwrap()
+    }
+
+    fn traced(tracer: Tracer) -> PlanOptions {
+        PlanOptions {
+            tracer: Some(tracer),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_two_level_event_sequence() {
+        let tracer = Arc::new(VecTracer::new());
+        let options = traced(Tracer(tracer.clone()));
+
+        plan_production_with_options(&two_level_data(), "origocrust", 30, &options).unwrap();
+
+        let crust_recipe: RecipeUid =
+            "origocrust@refining_unit[originium_ore:1]->[origocrust:1]/2s".into();
+        let ore_recipe: RecipeUid =
+            "originium_ore@electric_mining_rig[]->[originium_ore:1]/2s".into();
+        assert_eq!(
+            tracer.events(),
+            vec![
+                TraceEvent::Requested {
+                    depth: 0,
+                    item_id: "origocrust".into(),
+                    amount: 30,
+                },
+                TraceEvent::Candidates {
+                    depth: 0,
+                    item_id: "origocrust".into(),
+                    ranked: vec![crust_recipe.clone()],
+                },
+                TraceEvent::Chosen {
+                    depth: 0,
+                    item_id: "origocrust".into(),
+                    recipe_id: crust_recipe,
+                },
+                TraceEvent::Computed {
+                    depth: 0,
+                    item_id: "origocrust".into(),
+                    crafts: 30.0,
+                    machines: 1,
+                },
+                TraceEvent::Child {
+                    depth: 0,
+                    parent: "origocrust".into(),
+                    item_id: "originium_ore".into(),
+                    amount: 30,
+                },
+                TraceEvent::Requested {
+                    depth: 1,
+                    item_id: "originium_ore".into(),
+                    amount: 30,
+                },
+                TraceEvent::Candidates {
+                    depth: 1,
+                    item_id: "originium_ore".into(),
+                    ranked: vec![ore_recipe.clone()],
+                },
+                TraceEvent::Chosen {
+                    depth: 1,
+                    item_id: "originium_ore".into(),
+                    recipe_id: ore_recipe,
+                },
+                TraceEvent::Computed {
+                    depth: 1,
+                    item_id: "originium_ore".into(),
+                    crafts: 30.0,
+                    machines: 1,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_writer_tracer_indents_by_depth() {
+        let tracer = Arc::new(WriterTracer::new(Vec::new(), TraceFormat::Text));
+        let options = traced(Tracer(tracer.clone()));
+
+        plan_production_with_options(&two_level_data(), "origocrust", 30, &options).unwrap();
+        drop(options);
+
+        let tracer = Arc::into_inner(tracer).unwrap();
+        let text = String::from_utf8(tracer.into_inner()).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "request origocrust x30");
+        assert_eq!(lines[4], "input originium_ore x30");
+        assert_eq!(lines[5], "  request originium_ore x30");
+        assert_eq!(lines[8], "  30.00 crafts on 1 machine(s)");
+    }
+
+    #[test]
+    fn test_no_tracer_by_default() {
+        assert!(PlanOptions::default().tracer.is_none());
+    }
+}
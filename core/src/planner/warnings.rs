@@ -0,0 +1,207 @@
+//! Warnings collected while planning instead of silently degrading.
+
+use super::machine_limits::MachineLimitViolation;
+use super::source_rounding::SourceRounding;
+use crate::models::{ItemId, MachineId, ProductionNode, RecipeUid};
+use std::collections::HashMap;
+use std::fmt;
+
+/// A condition the planner worked around while building a plan.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "data-files", derive(serde::Serialize))]
+pub enum PlanWarning {
+    /// No recipe produces the item, so it was left unresolved.
+    MissingRecipe { item_id: ItemId },
+    /// The recipe's machine is not defined in the machine data.
+    MissingMachine {
+        item_id: ItemId,
+        recipe_id: RecipeUid,
+        machine_id: MachineId,
+    },
+    /// An input was skipped because it is already being resolved higher up.
+    CyclicInputSkipped {
+        item_id: ItemId,
+        recipe_id: RecipeUid,
+        input_id: ItemId,
+    },
+    /// The recipe does not list the item as an output; one per craft was assumed.
+    OutputDefaulted {
+        item_id: ItemId,
+        recipe_id: RecipeUid,
+    },
+    /// The recipe makes no more of the item than it consumes; one per
+    /// craft was assumed.
+    NoNetOutput {
+        item_id: ItemId,
+        recipe_id: RecipeUid,
+    },
+    /// More of a source item is needed per minute from one source recipe
+    /// than one of its deposits yields, see `Recipe::max_rate`.
+    DepositsExceeded {
+        item_id: ItemId,
+        recipe_id: RecipeUid,
+        /// Total demand per minute.
+        amount: u32,
+        max_rate: u32,
+        /// Deposits needed to cover the demand.
+        deposits: u32,
+    },
+    /// The amount of an item `plan_targets` builds once for several
+    /// targets did not settle, so it may not match what the trees take.
+    SharedAmountUnsettled {
+        item_id: ItemId,
+        /// Amount planned for the item.
+        planned: u32,
+        /// Amount the trees took of it in the last round.
+        needed: u32,
+    },
+}
+
+impl PlanWarning {
+    /// Returns the item the warning was raised for.
+    pub fn item_id(&self) -> &ItemId {
+        match self {
+            PlanWarning::MissingRecipe { item_id }
+            | PlanWarning::MissingMachine { item_id, .. }
+            | PlanWarning::CyclicInputSkipped { item_id, .. }
+            | PlanWarning::OutputDefaulted { item_id, .. }
+            | PlanWarning::NoNetOutput { item_id, .. }
+            | PlanWarning::DepositsExceeded { item_id, .. }
+            | PlanWarning::SharedAmountUnsettled { item_id, .. } => item_id,
+        }
+    }
+}
+
+impl fmt::Display for PlanWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PlanWarning::MissingRecipe { item_id } => {
+                write!(f, "No recipe found for {}", item_id)
+            }
+            PlanWarning::MissingMachine {
+                item_id,
+                recipe_id,
+                machine_id,
+            } => write!(
+                f,
+                "Machine {} not found for {} (recipe {})",
+                machine_id, item_id, recipe_id
+            ),
+            PlanWarning::CyclicInputSkipped {
+                item_id,
+                recipe_id,
+                input_id,
+            } => write!(
+                f,
+                "Skipped cyclic input {} of {} (recipe {})",
+                input_id, item_id, recipe_id
+            ),
+            PlanWarning::OutputDefaulted { item_id, recipe_id } => write!(
+                f,
+                "Recipe {} does not list {} as an output; assuming 1 per craft",
+                recipe_id, item_id
+            ),
+            PlanWarning::NoNetOutput { item_id, recipe_id } => write!(
+                f,
+                "Recipe {} makes no more {} than it uses; assuming 1 per craft",
+                recipe_id, item_id
+            ),
+            PlanWarning::DepositsExceeded {
+                item_id,
+                recipe_id,
+                amount,
+                max_rate,
+                deposits,
+            } => write!(
+                f,
+                "{} needs {} per minute but one deposit yields at most {} (recipe {}); {} deposits needed",
+                item_id, amount, max_rate, recipe_id, deposits
+            ),
+            PlanWarning::SharedAmountUnsettled {
+                item_id,
+                planned,
+                needed,
+            } => write!(
+                f,
+                "Shared item {} did not settle: {} planned but {} needed",
+                item_id, planned, needed
+            ),
+        }
+    }
+}
+
+/// Accumulates warnings during planning, ignoring exact duplicates.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "data-files", derive(serde::Serialize))]
+pub struct PlanWarnings {
+    warnings: Vec<PlanWarning>,
+}
+
+impl PlanWarnings {
+    /// Creates an empty accumulator.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a warning unless the same one was already recorded.
+    pub fn push(&mut self, warning: PlanWarning) {
+        if !self.warnings.contains(&warning) {
+            self.warnings.push(warning);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.warnings.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.warnings.len()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, PlanWarning> {
+        self.warnings.iter()
+    }
+
+    pub fn into_vec(self) -> Vec<PlanWarning> {
+        self.warnings
+    }
+
+    /// Deposits needed per source item whose demand exceeds one deposit,
+    /// from the `DepositsExceeded` warnings. An item mined from several
+    /// kinds of deposit gets their sum.
+    pub fn deposits_needed(&self) -> HashMap<ItemId, u32> {
+        let mut needed = HashMap::new();
+        for warning in &self.warnings {
+            if let PlanWarning::DepositsExceeded {
+                item_id, deposits, ..
+            } = warning
+            {
+                *needed.entry(item_id.clone()).or_default() += deposits;
+            }
+        }
+        needed
+    }
+}
+
+impl<'a> IntoIterator for &'a PlanWarnings {
+    type Item = &'a PlanWarning;
+    type IntoIter = std::slice::Iter<'a, PlanWarning>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.warnings.iter()
+    }
+}
+
+/// A production plan together with the warnings raised while building it.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "data-files", derive(serde::Serialize))]
+pub struct PlanResult {
+    pub node: ProductionNode,
+    pub warnings: PlanWarnings,
+    /// Rounded raw material totals, set when `PlanOptions::round_sources_to` is.
+    #[cfg_attr(feature = "data-files", serde(skip_serializing_if = "Vec::is_empty"))]
+    pub source_rounding: Vec<SourceRounding>,
+    /// Machines needed beyond `PlanOptions::machine_limits`.
+    #[cfg_attr(feature = "data-files", serde(skip_serializing_if = "Vec::is_empty"))]
+    pub machine_limit_violations: Vec<MachineLimitViolation>,
+}
@@ -0,0 +1,54 @@
+//! Fixtures shared by the unit tests.
+
+use crate::models::ProductionNode;
+
+/// Machine file with a single tier 1 refining unit.
+#[cfg(feature = "data-files")]
+pub(crate) const REFINING_UNIT_TOML: &str = r#"
+[[machines]]
+id = "refining_unit"
+tier = 1
+power = 5
+"#;
+
+/// A node made by one refining unit at full load.
+pub(crate) fn resolved(item_id: &str, amount: u32, inputs: Vec<ProductionNode>) -> ProductionNode {
+    let node = ProductionNode::Resolved {
+        item_id: item_id.into(),
+        machine_id: Default::default(),
+        recipe_id: Default::default(),
+        amount,
+        machine_count: 0,
+        power_usage: 0,
+        standby_power: 0,
+        load: 1.0,
+        inputs,
+        is_source: false,
+        blocks: vec![],
+    };
+    on_machines(node, "refining_unit", 1)
+}
+
+/// Moves a resolved node to `machine_count` machines of `machine_id`,
+/// drawing 5 power each.
+pub(crate) fn on_machines(
+    mut node: ProductionNode,
+    machine_id: &str,
+    machine_count: u32,
+) -> ProductionNode {
+    if let ProductionNode::Resolved {
+        item_id,
+        machine_id: node_machine,
+        recipe_id,
+        machine_count: node_count,
+        power_usage,
+        ..
+    } = &mut node
+    {
+        *recipe_id = format!("{}@{}[]", item_id, machine_id).into();
+        *node_machine = machine_id.into();
+        *node_count = machine_count;
+        *power_usage = machine_count * 5;
+    }
+    node
+}
@@ -1,63 +1,144 @@
 use endfield_planner_core::config::GameData;
-use endfield_planner_core::i18n::{Locale, Localizer};
-use endfield_planner_core::models::ProductionNode;
-use endfield_planner_core::planner::plan_production;
+use endfield_planner_core::error::ProductionError;
+use endfield_planner_core::i18n::{Locale, LocalizedResolver, Localizer, SearchIndex, pick_locale};
+use endfield_planner_core::models::{
+    AmountKind, DEFAULT_SPARE_THRESHOLD, ItemId, NodePath, ProductionNode,
+};
+use endfield_planner_core::output::{RATE_PRECISION, format_rate, to_markdown};
+use endfield_planner_core::planner::{
+    PlanOptions, PlanResult, PlanWarnings, ShareState, plan_production_with_options,
+    resolvable_paths,
+};
 use leptos::prelude::*;
 use std::collections::{HashMap, HashSet};
+use std::time::Duration;
 
-use crate::components::tree_view::TreeView;
-use crate::utils::localization::get_localized_name;
-use crate::utils::url::{generate_share_url, parse_url_params, update_url_params};
+use crate::components::compare_view::CompareView;
+use crate::components::data_warnings::DataWarnings;
+use crate::components::flow_view::FlowView;
+use crate::components::load_error::LoadError;
+use crate::components::tree_view::{AlternativesBadge, SpareNote, TreeView};
+use crate::utils::alternatives::count_alternatives;
+use crate::utils::amount::resolve_amount_input;
+use crate::utils::clipboard::copy_to_clipboard;
+use crate::utils::complexity::{ItemSort, compare_complexity, item_complexity};
+use crate::utils::download::{
+    DownloadFormat, download_file, download_filename, plan_file_contents,
+};
+use crate::utils::error::describe_load_error;
+use crate::utils::item_list::{ItemListEntry, shows_section_headers, with_section_headers};
+use crate::utils::localization::{localize_amount, localize_warning};
+use crate::utils::number::format_number;
+use crate::utils::power::sample_power_scaling;
+use crate::utils::scroll::use_scroll_memory;
+use crate::utils::storage::{
+    RECENT_ITEMS_LIMIT, browser_languages, load_locale, load_recent_items, push_recent_item,
+    save_locale, save_recent_items,
+};
+use crate::utils::url::{
+    CompareUrlState, PlanUrlState, URL_UPDATE_DELAY_MS, generate_share_url, known_excluded,
+    parse_url_params, should_update_url, update_url_params,
+};
+
+/// Options for planning `item_id` with the `excluded` items supplied
+/// externally. The target itself is always planned, even if it was excluded
+/// elsewhere. Inputs are sorted so collapsed node paths survive reloads.
+pub(crate) fn target_options(excluded: &[String], item_id: &str) -> PlanOptions {
+    PlanOptions {
+        sorted_inputs: true,
+        external_items: excluded
+            .iter()
+            .filter(|item| *item != item_id)
+            .map(ItemId::from)
+            .collect(),
+        ..Default::default()
+    }
+}
 
 #[component]
 pub fn app() -> impl IntoView {
     // Load static data which is executed once on launch
     let recipes_str = include_str!("../../../res/recipes.toml");
     let machines_str = include_str!("../../../res/machines.toml");
-    let game_data = GameData::new(recipes_str, machines_str).expect("Failed to load data");
 
     // Load locales
-    let en_locale = include_str!("../../../res/locales/en.toml");
-    let ja_locale = include_str!("../../../res/locales/ja.toml");
-
-    let localizers: HashMap<Locale, Localizer> = [
-        (
-            Locale::English,
-            Localizer::new(en_locale).expect("Failed to load English locale"),
-        ),
-        (
-            Locale::Japanese,
-            Localizer::new(ja_locale).expect("Failed to load Japanese locale"),
-        ),
+    let en_locale = [
+        include_str!("../../../res/locales/en/items.toml"),
+        include_str!("../../../res/locales/en/machines.toml"),
+        include_str!("../../../res/locales/en/ui.toml"),
+    ];
+    let ja_locale = [
+        include_str!("../../../res/locales/ja/items.toml"),
+        include_str!("../../../res/locales/ja/machines.toml"),
+        include_str!("../../../res/locales/ja/ui.toml"),
+    ];
+    // Item and machine names fall back to English until translated
+    let zh_locale = [
+        include_str!("../../../res/locales/en/items.toml"),
+        include_str!("../../../res/locales/en/machines.toml"),
+        include_str!("../../../res/locales/zh/items.toml"),
+        include_str!("../../../res/locales/zh/ui.toml"),
+    ];
+
+    let localizers: Result<HashMap<Locale, Localizer>, ProductionError> = [
+        (Locale::English, &en_locale[..]),
+        (Locale::Japanese, &ja_locale[..]),
+        (Locale::ChineseSimplified, &zh_locale[..]),
     ]
     .into_iter()
+    .map(|(locale, parts)| {
+        Localizer::from_parts(parts)
+            .map(|localizer| (locale, localizer))
+            .map_err(ProductionError::ParseError)
+    })
     .collect();
+    // Without a locale to show it in, the error page falls back to the keys
+    let localizers = match localizers {
+        Ok(localizers) => localizers,
+        Err(error) => {
+            return view! { <LoadError error=error localizer=Localizer::empty() /> }.into_any();
+        }
+    };
 
-    let mut all_items: Vec<String> = game_data.recipes_by_output.keys().cloned().collect();
-    all_items.sort();
+    // A language chosen earlier wins over the browser's preferences
+    let initial_locale =
+        load_locale().unwrap_or_else(|| pick_locale(&browser_languages(), &Locale::ALL));
 
-    let machine_ids: HashSet<String> = game_data.machines.keys().cloned().collect();
-    let machine_ids_store = StoredValue::new(machine_ids);
-
-    // Deternime user's language setting to decide initial locale
-    let initial_locale = {
-        if let Some(window) = web_sys::window() {
-            let navigator = window.navigator();
-
-            if let Some(lang) = navigator.language() {
-                if lang.starts_with("ja") {
-                    Locale::Japanese
-                } else {
-                    Locale::English
-                }
-            } else {
-                Locale::English
-            }
-        } else {
-            Locale::English
+    // Show the error page instead of panicking when the data is malformed
+    let game_data = match GameData::new(recipes_str, machines_str) {
+        Ok(data) => data,
+        Err(error) => {
+            let localizer = localizers
+                .get(&initial_locale)
+                .cloned()
+                .unwrap_or_else(Localizer::empty);
+            return view! { <LoadError error=error localizer=localizer /> }.into_any();
         }
     };
 
+    let mut all_items: Vec<String> = game_data
+        .recipes_by_output
+        .keys()
+        .map(ItemId::to_string)
+        .collect();
+    all_items.sort();
+    let compare_items = all_items.clone();
+
+    // Search keys are normalized once per locale rather than per keystroke
+    let search_indexes: HashMap<Locale, SearchIndex> = localizers
+        .iter()
+        .map(|(locale, localizer)| {
+            let index = SearchIndex::new(all_items.iter().map(String::as_str), localizer);
+            (*locale, index)
+        })
+        .collect();
+    let search_indexes = StoredValue::new(search_indexes);
+
+    let data_warnings = game_data.warnings.clone();
+    let alternatives = StoredValue::new(count_alternatives(&game_data));
+    let complexity = StoredValue::new(item_complexity(&game_data));
+    let game_data = StoredValue::new(game_data);
+
     // Parse URL parameters for initial state
     let url_params = parse_url_params();
 
@@ -65,8 +146,13 @@ pub fn app() -> impl IntoView {
     let (current_locale, set_current_locale) = signal(initial_locale);
     let (target_amount, set_target_amount) = signal(url_params.amount.unwrap_or(1));
     let (search_query, set_search_query) = signal(String::new());
+    let (item_sort, set_item_sort) = signal(ItemSort::default());
 
     let default_item = all_items.first().cloned().unwrap_or_else(|| "".to_string());
+    let shared_item_selected = url_params
+        .item
+        .as_ref()
+        .is_some_and(|item| all_items.contains(item));
 
     let (selected_item, set_selected_item) = signal(
         url_params
@@ -75,71 +161,245 @@ pub fn app() -> impl IntoView {
             .unwrap_or(default_item),
     );
 
+    // Items supplied from outside the plan, kept sorted for stable URLs
+    let (excluded_items, set_excluded_items) =
+        signal(known_excluded(url_params.excluded, &all_items));
+
+    // Second plan of compare mode, opened by a shared URL that has one
+    let compare_from_url = url_params
+        .compare_item
+        .filter(|item| all_items.contains(item));
+    let (compare_open, set_compare_open) = signal(compare_from_url.is_some());
+    let (compare_item, set_compare_item) =
+        signal(compare_from_url.unwrap_or_else(|| selected_item.get_untracked()));
+    let (compare_amount, set_compare_amount) = signal(url_params.compare_amount.unwrap_or(1));
+
+    // Tree nodes shown collapsed, restored from a shared URL
+    let collapsed_nodes = RwSignal::new(
+        url_params
+            .collapsed
+            .into_iter()
+            .collect::<HashSet<NodePath>>(),
+    );
+
+    let toggle_excluded = Callback::new(move |item_id: String| {
+        set_excluded_items.update(|excluded| match excluded.binary_search(&item_id) {
+            Ok(index) => {
+                excluded.remove(index);
+            }
+            Err(index) => excluded.insert(index, item_id),
+        });
+    });
+
+    // What the URL and factory codes share, with the collapsed nodes sorted
+    // so equal states compare equal
+    let current_share_state = move || ShareState {
+        excluded: excluded_items.with(|excluded| excluded.iter().map(ItemId::from).collect()),
+        collapsed: collapsed_nodes.with(|collapsed| {
+            let mut collapsed: Vec<NodePath> = collapsed.iter().cloned().collect();
+            collapsed.sort();
+            collapsed
+        }),
+        ..ShareState::new(selected_item.get(), target_amount.get())
+    };
+    let current_url_state = move || PlanUrlState {
+        plan: current_share_state(),
+        compare: compare_open.get().then(|| CompareUrlState {
+            item: compare_item.get(),
+            amount: compare_amount.get(),
+        }),
+    };
+
+    // Only touch the history when the mirrored state actually changed, and
+    // only once a burst of changes (e.g. collapsing several nodes) settles
+    let pending_url_update = StoredValue::new(None::<TimeoutHandle>);
+    Effect::new(move |last_written: Option<PlanUrlState>| {
+        let next = current_url_state();
+
+        if should_update_url(last_written.as_ref(), &next) {
+            if let Some(pending) = pending_url_update.get_value() {
+                pending.clear();
+            }
+            let state = next.clone();
+            let handle = set_timeout_with_handle(
+                move || update_url_params(&state),
+                Duration::from_millis(URL_UPDATE_DELAY_MS),
+            );
+            pending_url_update.set_value(handle.ok());
+        }
+        next
+    });
+
+    // Factory code pasted into the import field, applied on Enter
+    let (import_code, set_import_code) = signal(String::new());
+    let (import_failed, set_import_failed) = signal(false);
+    let known_items = StoredValue::new(all_items.clone());
+    let apply_import_code = move || {
+        let shared = import_code
+            .with_untracked(|code| ShareState::from_code(code))
+            .ok()
+            .filter(|shared| {
+                game_data.with_value(|data| data.recipes_by_output.contains_key(&shared.item))
+            });
+        set_import_failed.set(shared.is_none());
+
+        if let Some(shared) = shared {
+            set_selected_item.set(shared.item.to_string());
+            set_target_amount.set(shared.amount);
+            let excluded = shared.excluded.iter().map(ItemId::to_string);
+            set_excluded_items
+                .set(known_items.with_value(|all_items| known_excluded(excluded, all_items)));
+            collapsed_nodes.set(shared.collapsed.iter().cloned().collect());
+            set_import_code.set(String::new());
+        }
+    };
+
+    // Recently selected items, restored from localStorage
+    let (recent_items, set_recent_items) = signal(
+        load_recent_items()
+            .into_iter()
+            .filter(|item| all_items.contains(item))
+            .collect::<Vec<String>>(),
+    );
+
     Effect::new(move |_| {
         let item = selected_item.get();
-        let amount = target_amount.get();
-        update_url_params(&item, amount);
+        if item.is_empty() {
+            return;
+        }
+
+        set_recent_items.update(|items| push_recent_item(items, &item, RECENT_ITEMS_LIMIT));
+        recent_items.with_untracked(|items| save_recent_items(items));
     });
 
     // UI state signals
     let (sidebar_open, set_sidebar_open) = signal(false);
     let (summary_collapsed, set_summary_collapsed) = signal(false);
+    let (show_spare, set_show_spare) = signal(true);
+    // Share of the requested amount a spare must reach to be shown
+    let (spare_threshold, set_spare_threshold) = signal(DEFAULT_SPARE_THRESHOLD);
 
     // Create a memo for the current localizer
     let current_localizer =
         Memo::new(move |_| localizers.get(&current_locale.get()).unwrap().clone());
 
-    // Filter item list by a query (search both ID and localized name)
+    // Filter item list by a query (search ID, localized name and reading)
     let filtered_items = move || {
-        let query = search_query.get().to_lowercase();
+        let query = search_query.get();
         let localizer = current_localizer.get();
 
-        let mut items: Vec<String> = if query.is_empty() {
+        let mut items: Vec<String> = if query.trim().is_empty() {
             all_items.clone()
         } else {
-            all_items
-                .iter()
-                .filter(|item| {
-                    // Search by item ID
-                    let id_match = item.to_lowercase().contains(&query);
-                    // Search by localized name
-                    let localized_name = localizer.get_item(item).to_lowercase();
-                    let name_match = localized_name.contains(&query);
-
-                    id_match || name_match
-                })
-                .cloned()
-                .collect()
+            search_indexes.with_value(|indexes| {
+                indexes
+                    .get(&current_locale.get())
+                    .map(|index| {
+                        index
+                            .search(&query)
+                            .into_iter()
+                            .map(str::to_string)
+                            .collect()
+                    })
+                    .unwrap_or_default()
+            })
         };
 
-        items.sort_by(|a, b| {
-            let reading_a = localizer.get_reading(a);
-            let reading_b = localizer.get_reading(b);
-            reading_a.cmp(&reading_b)
-        });
+        items.sort_by(|a, b| localizer.compare_by_reading(a, b));
+        let sort = item_sort.get();
+        if sort == ItemSort::Complexity {
+            // Stable, so equally complex items stay in reading order
+            complexity.with_value(|complexity| {
+                items.sort_by(|a, b| compare_complexity(complexity, a, b))
+            });
+        }
 
-        items
+        // Headers only help when scanning a long list, and split it by name
+        if sort != ItemSort::Name || !shows_section_headers(!query.is_empty(), items.len()) {
+            items.into_iter().map(ItemListEntry::Item).collect()
+        } else {
+            with_section_headers(items, current_locale.get(), &localizer)
+        }
     };
 
+    let plan_options = Memo::new(move |_| {
+        let item_id = selected_item.get();
+        excluded_items.with(|excluded| target_options(excluded, &item_id))
+    });
+
     // Re-calculate the production plan everytime when the input value change
-    let production_plan = Memo::new(move |_| {
+    let plan_outcome = Memo::new(move |_| {
         let item_id = selected_item.get();
         let amount = target_amount.get();
-        let mut visiting = HashSet::new();
-
-        plan_production(
-            &game_data.recipes,
-            &game_data.recipes_by_output,
-            &game_data.machines,
-            &item_id,
-            amount, // u32
-            &mut visiting,
-        )
+
+        plan_options.with(|options| {
+            game_data.with_value(|data| {
+                plan_production_with_options(data, &item_id, amount, options)
+                    .map_err(|error| describe_load_error(&error))
+            })
+        })
+    });
+    let plan_error = Memo::new(move |_| plan_outcome.with(|outcome| outcome.clone().err()));
+    // A failed plan shows the target as unresolved, next to the error
+    let plan_result = Memo::new(move |_| {
+        plan_outcome.with(|outcome| match outcome {
+            Ok(result) => result.clone(),
+            Err(_) => PlanResult {
+                node: ProductionNode::Unresolved {
+                    item_id: ItemId::from(selected_item.get()),
+                    amount: target_amount.get(),
+                },
+                warnings: PlanWarnings::new(),
+                source_rounding: Vec::new(),
+                machine_limit_violations: Vec::new(),
+            },
+        })
     });
+    let production_plan = Memo::new(move |_| plan_result.with(|result| result.node.clone()));
+
+    // Collapsed paths are applied once the plan is rendered; those the plan
+    // no longer has, e.g. after choosing another item, are dropped
+    Effect::new(move |_| {
+        let kept = production_plan.with(|plan| {
+            collapsed_nodes.with_untracked(|collapsed| resolvable_paths(collapsed, plan))
+        });
+        if kept.len() != collapsed_nodes.with_untracked(HashSet::len) {
+            collapsed_nodes.set(kept.into_iter().collect());
+        }
+    });
+
+    // Plans at other amounts are only computed while the power details are open
+    let (power_details_open, set_power_details_open) = signal(false);
+    let power_samples = Memo::new(move |_| {
+        if !power_details_open.get() {
+            return Vec::new();
+        }
+
+        let item_id = selected_item.get();
+        let amount = target_amount.get();
+        plan_options.with(|options| {
+            game_data.with_value(|data| sample_power_scaling(data, &item_id, amount, options))
+        })
+    });
+
+    let (plan_view, set_plan_view) = signal(PlanView::Tree);
 
     // Handler to close sidebar (for overlay click and item selection)
     let close_sidebar = move |_| set_sidebar_open.set(false);
 
+    // Keep the item list's place while the sidebar is closed or the search changes
+    let item_list_scroll = use_scroll_memory();
+    Effect::new(move |_| {
+        // Re-run when the sidebar is reopened as well as when the query changes
+        sidebar_open.track();
+        search_query.with(|query| item_list_scroll.restore(query));
+    });
+
+    // Bring an item selected through a shared URL into view
+    if shared_item_selected {
+        item_list_scroll.reveal(".item-list-entry.selected");
+    }
+
     //  Construct view
     view! {
         <header class="app-header">
@@ -159,6 +419,20 @@ pub fn app() -> impl IntoView {
                 </span>
             </button>
             <div class="app-logo">"ENDFIELD PRODUCTION PLANNER"</div>
+            // Problems found in the loaded data (hidden when there are none)
+            <DataWarnings
+                warnings=data_warnings
+                localizer=current_localizer
+                game_data=game_data
+            />
+            // Switch between the single plan and two plans side by side
+            <button
+                class="compare-toggle"
+                class:active=move || compare_open.get()
+                on:click=move |_| set_compare_open.update(|open| *open = !*open)
+            >
+                {move || current_localizer.get().get_ui("compare_plans")}
+            </button>
             // Spacer to balance the layout (hidden on desktop)
             <div class="header-spacer"></div>
         </header>
@@ -187,16 +461,16 @@ pub fn app() -> impl IntoView {
                             on:change=move |ev| {
                                 let value = event_target_value(&ev);
                                 if let Some(locale) = Locale::from_code(&value) {
+                                    save_locale(locale);
                                     set_current_locale.set(locale);
                                 }
                             }
                         >
-                            <option value="en" selected=move || current_locale.get() == Locale::English>
-                                "English"
-                            </option>
-                            <option value="ja" selected=move || current_locale.get() == Locale::Japanese>
-                                "日本語"
-                            </option>
+                            {Locale::ALL.into_iter().map(|locale| view! {
+                                <option value=locale.code() selected=move || current_locale.get() == locale>
+                                    {locale.native_name()}
+                                </option>
+                            }).collect_view()}
                         </select>
                     </div>
 
@@ -212,8 +486,65 @@ pub fn app() -> impl IntoView {
                                     set_target_amount.set(val);
                                 }
                             }
+                            // Restore the displayed value when the field is left empty or invalid
+                            on:blur=move |ev| {
+                                let current = target_amount.get_untracked();
+                                set_target_amount.set(resolve_amount_input(&event_target_value(&ev), current));
+                            }
+                            class="form-input"
+                        />
+                    </div>
+
+                    // Spare capacity notes in the tree and summary
+                    <div class="form-group">
+                        <label class="form-checkbox">
+                            <input
+                                type="checkbox"
+                                prop:checked=move || show_spare.get()
+                                on:change=move |ev| set_show_spare.set(event_target_checked(&ev))
+                            />
+                            {move || current_localizer.get().get_ui("show_spare")}
+                        </label>
+                        <Show when=move || show_spare.get()>
+                            <label class="form-label">{move || current_localizer.get().get_ui("spare_threshold")}</label>
+                            <input
+                                type="number"
+                                min="0"
+                                max="100"
+                                prop:value=move || (spare_threshold.get() * 100.0).round()
+                                on:input=move |ev| {
+                                    if let Ok(percent) = event_target_value(&ev).parse::<f64>()
+                                        && (0.0..=100.0).contains(&percent)
+                                    {
+                                        set_spare_threshold.set(percent / 100.0);
+                                    }
+                                }
+                                class="form-input"
+                            />
+                        </Show>
+                    </div>
+
+                    // Factory code import
+                    <div class="form-group">
+                        <label class="form-label">{move || current_localizer.get().get_ui("import_code")}</label>
+                        <input
+                            type="text"
+                            placeholder=move || current_localizer.get().get_ui("import_code_placeholder")
+                            prop:value=move || import_code.get()
+                            on:input=move |ev| {
+                                set_import_code.set(event_target_value(&ev));
+                                set_import_failed.set(false);
+                            }
+                            on:keydown=move |ev| {
+                                if ev.key() == "Enter" {
+                                    apply_import_code();
+                                }
+                            }
                             class="form-input"
                         />
+                        <Show when=move || import_failed.get()>
+                            <div class="form-error">{move || current_localizer.get().get_ui("import_code_invalid")}</div>
+                        </Show>
                     </div>
 
                     // Item search
@@ -224,30 +555,117 @@ pub fn app() -> impl IntoView {
                             placeholder=move || current_localizer.get().get_ui("search_placeholder")
                             prop:value=move || search_query.get()
                             on:input=move |ev| set_search_query.set(event_target_value(&ev))
+                            // Select the item directly when its full name was typed
+                            on:keydown=move |ev| {
+                                if ev.key() != "Enter" {
+                                    return;
+                                }
+                                let localizer = current_localizer.get_untracked();
+                                let resolved = search_query.with_untracked(|query| {
+                                    game_data.with_value(|data| data.resolve_item_name(query, &localizer))
+                                });
+                                if let Some(item_id) = resolved {
+                                    set_selected_item.set(item_id.to_string());
+                                    set_sidebar_open.set(false);
+                                }
+                            }
                             class="form-input"
                         />
                     </div>
+
+                    // Item list order
+                    <div>
+                        <label class="form-label">{move || current_localizer.get().get_ui("sort_items")}</label>
+                        <select
+                            class="form-input"
+                            on:change=move |ev| {
+                                if let Some(sort) = ItemSort::from_code(&event_target_value(&ev)) {
+                                    set_item_sort.set(sort);
+                                }
+                            }
+                        >
+                            {ItemSort::ALL.into_iter().map(|sort| view! {
+                                <option value=sort.code() selected=move || item_sort.get() == sort>
+                                    {move || current_localizer.get().get_ui(sort.label_key())}
+                                </option>
+                            }).collect_view()}
+                        </select>
+                    </div>
                 </div>
 
+                // Recently selected items
+                <Show when=move || !recent_items.with(|items| items.is_empty())>
+                    <div class="recent-items">
+                        <label class="form-label">{move || current_localizer.get().get_ui("recent_items")}</label>
+                        <For
+                            each=move || recent_items.get()
+                            key=|item| item.clone()
+                            children=move |item| {
+                                let item_for_click = item.clone();
+                                let item_for_class = item.clone();
+                                let item_id_for_display = item.clone();
+
+                                let on_click = move |_| {
+                                    set_selected_item.set(item_for_click.clone());
+                                    set_sidebar_open.set(false);
+                                };
+
+                                view! {
+                                    <button
+                                        on:click=on_click
+                                        class=move || {
+                                            if selected_item.get() == item_for_class {
+                                                "recent-item selected"
+                                            } else {
+                                                "recent-item"
+                                            }
+                                        }
+                                    >
+                                        {move || {
+                                            let localizer = current_localizer.get();
+                                            game_data.with_value(|data| localizer.get_name(&item_id_for_display, data))
+                                        }}
+                                    </button>
+                                }
+                            }
+                        />
+                    </div>
+                </Show>
+
                 // Item list
-                <div class="item-list">
+                <div
+                    class="item-list"
+                    node_ref=item_list_scroll.node_ref
+                    on:scroll=move |_| search_query.with_untracked(|query| item_list_scroll.on_scroll(query))
+                >
                      <For
                         each=filtered_items
-                        key=|item| item.clone()
-                        children=move |item| {
+                        key=|entry| entry.key()
+                        children=move |entry| {
+                            let item = match entry {
+                                ItemListEntry::Header(section) => {
+                                    return view! { <div class="item-list-header">{section}</div> }.into_any();
+                                }
+                                ItemListEntry::Item(item) => item,
+                            };
+
                             let item_for_click = item.clone();
                             let item_for_class = item.clone();
                             let item_id_for_display = item.clone();
 
                             let on_click = move |_| {
+                                search_query.with_untracked(|query| item_list_scroll.save(query));
                                 set_selected_item.set(item_for_click.clone());
                                 // Close sidebar on mobile after selection
                                 set_sidebar_open.set(false);
                             };
 
+                            let item_for_title = item.clone();
+
                             view! {
                                 <div
                                     on:click=on_click
+                                    title=move || current_localizer.get().get_description(&item_for_title)
                                     class=move || {
                                         let is_selected = selected_item.get() == item_for_class;
                                         if is_selected {
@@ -259,12 +677,11 @@ pub fn app() -> impl IntoView {
                                 >
                                     {move || {
                                         let localizer = current_localizer.get();
-                                        machine_ids_store.with_value(|machine_ids| {
-                                            get_localized_name(&item_id_for_display, &localizer, machine_ids)
-                                        })
+                                        game_data.with_value(|data| localizer.get_name(&item_id_for_display, data))
                                     }}
                                 </div>
                             }
+                            .into_any()
                         }
                     />
                    </div>
@@ -272,13 +689,33 @@ pub fn app() -> impl IntoView {
 
             // Main content
             <div class="main-content">
+                <Show
+                    when=move || !compare_open.get()
+                    fallback=move || view! {
+                        <CompareView
+                            items=compare_items.clone()
+                            left_item=(selected_item, set_selected_item)
+                            left_amount=(target_amount, set_target_amount)
+                            right_item=(compare_item, set_compare_item)
+                            right_amount=(compare_amount, set_compare_amount)
+                            localizer=current_localizer
+                            locale=current_locale
+                            excluded_items=excluded_items
+                            game_data=game_data
+                            alternatives=alternatives
+                        />
+                    }
+                >
                 // Header with collapse toggle
                 <div class="collapsible-header">
                     <h1>{move || current_localizer.get().get_ui("production_plan")}</h1>
                     <button
                         class=move || if summary_collapsed.get() { "collapse-toggle collapsed" } else { "collapse-toggle" }
                         on:click=move |_| set_summary_collapsed.update(|c| *c = !*c)
-                        title=move || if summary_collapsed.get() { "Expand" } else { "Collapse" }
+                        title=move || {
+                            let localizer = current_localizer.get();
+                            localizer.get_ui(if summary_collapsed.get() { "expand" } else { "collapse" })
+                        }
                     >
                         <span class="collapse-toggle-icon"></span>
                     </button>
@@ -296,17 +733,32 @@ pub fn app() -> impl IntoView {
                                 {move || {
                                     let localizer = current_localizer.get();
                                     let node = production_plan.get();
-                                    let mut materials: Vec<_> = node.total_source_materials().into_iter().collect();
-                                    materials.sort_by(|a, b| a.0.cmp(&b.0));
+                                    // Only items needing more than one deposit are listed
+                                    let deposits = plan_result.with(|result| result.warnings.deposits_needed());
+                                    let materials: HashMap<_, _> = node
+                                        .total_source_materials()
+                                        .into_iter()
+                                        .map(|(item_id, amount)| {
+                                            let needed = deposits.get(&item_id).copied();
+                                            (item_id, (amount, needed))
+                                        })
+                                        .collect();
+                                    let materials: Vec<_> = game_data.with_value(|data| {
+                                        localizer.localize_amounts(materials, data).collect()
+                                    });
 
                                     if materials.is_empty() {
                                         view! { <div class="empty">{localizer.get_ui("none")}</div> }.into_any()
                                     } else {
                                         view! {
                                             <ul>
-                                                {materials.into_iter().map(|(name, count)| {
-                                                    let display_name = localizer.get_item(&name);
-                                                    view! { <li>{display_name} ": " <strong>{count}</strong></li> }
+                                                {materials.into_iter().map(|(display_name, (count, deposits))| {
+                                                    let count = format_rate(count as f64, RATE_PRECISION, &localizer);
+                                                    let deposits = deposits.map(|deposits| {
+                                                        let deposits = localizer.get_ui_plural("deposit_count", deposits as u64);
+                                                        view! { <span class="deposits">{deposits}</span> }
+                                                    });
+                                                    view! { <li>{display_name} ": " <strong>{count}</strong> {deposits}</li> }
                                                 }).collect_view()}
                                             </ul>
                                         }.into_any()
@@ -315,6 +767,77 @@ pub fn app() -> impl IntoView {
                             </div>
                         </div>
 
+                        // Externally supplied items
+                        <Show when=move || !excluded_items.with(|excluded| excluded.is_empty())>
+                            <div class="summary-card external">
+                                <h4>
+                                    {move || current_localizer.get().get_ui("externally_supplied")}
+                                    <button
+                                        class="summary-card-action"
+                                        on:click=move |_| set_excluded_items.set(Vec::new())
+                                    >
+                                        {move || current_localizer.get().get_ui("reset_exclusions")}
+                                    </button>
+                                </h4>
+                                <div class="summary-card-content">
+                                    {move || {
+                                        let localizer = current_localizer.get();
+                                        let node = production_plan.get();
+                                        let external: Vec<_> = game_data.with_value(|data| {
+                                            localizer.localize_amounts(node.total_external_items(), data).collect()
+                                        });
+
+                                        if external.is_empty() {
+                                            view! { <div class="empty">{localizer.get_ui("none")}</div> }.into_any()
+                                        } else {
+                                            view! {
+                                                <ul>
+                                                    {external.into_iter().map(|(display_name, count)| {
+                                                        let count = format_rate(count as f64, RATE_PRECISION, &localizer);
+                                                        view! { <li>{display_name} ": " <strong>{count}</strong></li> }
+                                                    }).collect_view()}
+                                                </ul>
+                                            }.into_any()
+                                        }
+                                    }}
+                                </div>
+                            </div>
+                        </Show>
+
+                        // Spare output that could be fed into storage
+                        {move || {
+                            if !show_spare.get() {
+                                return None;
+                            }
+                            let localizer = current_localizer.get();
+                            let threshold = spare_threshold.get();
+                            let mut spare: Vec<_> = production_plan
+                                .with(|node| node.total_spare_amounts(threshold))
+                                .into_iter()
+                                .collect();
+                            if spare.is_empty() {
+                                return None;
+                            }
+                            spare.sort_by(|(a, _), (b, _)| {
+                                localizer.compare_by_reading(a.as_str(), b.as_str()).then_with(|| a.cmp(b))
+                            });
+
+                            Some(view! {
+                                <div class="summary-card">
+                                    <h4>{localizer.get_ui("total_overproduction")}</h4>
+                                    <div class="summary-card-content">
+                                        <ul>
+                                            {spare.into_iter().map(|(item_id, amount)| {
+                                                let display_name = game_data.with_value(|data| localizer.get_name(item_id.as_str(), data));
+                                                let rate = format_rate(amount, RATE_PRECISION, &localizer);
+                                                view! { <li>{display_name} ": " <strong>"+" {rate}</strong></li> }
+                                            }).collect_view()}
+                                        </ul>
+                                    </div>
+                                </div>
+                            })
+                        }}
+
                         // Machines
                         <div class="summary-card">
                             <h4>{move || current_localizer.get().get_ui("total_machines")}</h4>
@@ -331,7 +854,7 @@ pub fn app() -> impl IntoView {
                                         view! {
                                             <ul>
                                                 {machines.into_iter().map(|(name, count)| {
-                                                    let display_name = localizer.get_machine(&name);
+                                                    let display_name = localizer.get_machine(name.as_str());
                                                     view! { <li>{display_name} ": " <strong>{count}</strong></li> }
                                                 }).collect_view()}
                                             </ul>
@@ -343,24 +866,41 @@ pub fn app() -> impl IntoView {
 
                         // Power
                         <div class="summary-card power">
-                            <h4>{move || current_localizer.get().get_ui("total_power")}</h4>
+                            <h4>
+                                {move || current_localizer.get().get_ui("total_power")}
+                                <button
+                                    class="summary-card-action"
+                                    on:click=move |_| set_power_details_open.update(|open| *open = !*open)
+                                >
+                                    {move || {
+                                        let key = if power_details_open.get() { "hide_details" } else { "show_details" };
+                                        current_localizer.get().get_ui(key)
+                                    }}
+                                </button>
+                            </h4>
                             <div class="summary-card-content">
                                 {move || {
                                     let localizer = current_localizer.get();
+                                    let locale = current_locale.get();
                                     let node = production_plan.get();
                                     let total_power = node.total_power();
+                                    let effective_power = (node.total_effective_power() * 10.0).round() / 10.0;
                                     let total_machines: u32 = node.total_machines().values().sum();
                                     let utilization_rate = node.utilization();
 
                                     view! {
                                         <ul>
                                             <li>
-                                                <span>{localizer.get_ui("power_usage")}</span>
-                                                <strong>{total_power}</strong>
+                                                <span>{localizer.get_ui("peak_power")}</span>
+                                                <strong>{localize_amount(&localizer, "power_amount", total_power as f64, &format_number(total_power as f64, locale))}</strong>
+                                            </li>
+                                            <li>
+                                                <span>{localizer.get_ui("effective_power")}</span>
+                                                <strong>{localize_amount(&localizer, "power_amount", effective_power, &format_number(effective_power, locale))}</strong>
                                             </li>
                                             <li>
                                                 <span>{localizer.get_ui("total_machine_count")}</span>
-                                                <strong>{total_machines} " " {localizer.get_ui("machine_unit")}</strong>
+                                                <strong>{localizer.get_ui_plural("machine_count", total_machines as u64)}</strong>
                                             </li>
                                             <li>
                                                 <span>{localizer.get_ui("utilization_rate")}</span>
@@ -369,11 +909,76 @@ pub fn app() -> impl IntoView {
                                         </ul>
                                     }
                                 }}
+                                // Peak power at 0.5x to 2x the target amount
+                                <Show when=move || power_details_open.get()>
+                                    {move || {
+                                        let localizer = current_localizer.get();
+                                        let locale = current_locale.get();
+                                        let samples = power_samples.get();
+                                        let max_power = samples.iter().map(|sample| sample.power).max().unwrap_or(0).max(1);
+
+                                        view! {
+                                            <svg class="power-chart" viewBox="0 0 160 48" role="img">
+                                                {samples.into_iter().enumerate().map(|(index, sample)| {
+                                                    let height = (sample.power as f64 / max_power as f64 * 40.0).max(1.0);
+                                                    let x = (index as f64 * 40.0 + 4.0).to_string();
+                                                    let y = (44.0 - height).to_string();
+                                                    let tooltip = format!(
+                                                        "{} · {}: {} · {}: {}",
+                                                        localize_amount(&localizer, "rate_per_min", sample.amount as f64, &format_number(sample.amount as f64, locale)),
+                                                        localizer.get_ui("peak_power"),
+                                                        localize_amount(&localizer, "power_amount", sample.power as f64, &format_number(sample.power as f64, locale)),
+                                                        localizer.get_ui("total_machine_count"),
+                                                        localize_amount(&localizer, "machine_count", sample.machines as f64, &format_number(sample.machines as f64, locale)),
+                                                    );
+                                                    view! {
+                                                        <rect x=x y=y width="32" height=height.to_string()>
+                                                            <title>{tooltip}</title>
+                                                        </rect>
+                                                    }
+                                                }).collect_view()}
+                                            </svg>
+                                        }
+                                    }}
+                                </Show>
                             </div>
                         </div>
                     </div>
                 </div>
 
+                // Why the plan could not be built
+                {move || {
+                    plan_error.get().map(|(message_key, detail)| {
+                        let localizer = current_localizer.get();
+                        view! {
+                            <div class="warning-banner plan-error">
+                                <h4>{localizer.get_ui(message_key)}</h4>
+                                <pre class="load-error-detail">{detail}</pre>
+                            </div>
+                        }
+                    })
+                }}
+
+                // Warnings raised while planning
+                {move || {
+                    let localizer = current_localizer.get();
+                    let warnings = plan_result.with(|result| result.warnings.clone());
+
+                    (!warnings.is_empty()).then(|| {
+                        view! {
+                            <div class="warning-banner">
+                                <h4>{localizer.get_ui("warnings")}</h4>
+                                <ul>
+                                    {warnings.iter().map(|warning| {
+                                        let message = game_data.with_value(|data| localize_warning(warning, &localizer, data));
+                                        view! { <li>{message}</li> }
+                                    }).collect_view()}
+                                </ul>
+                            </div>
+                        }
+                    })
+                }}
+
                 // Tree view
                 <div class="production-group">
                     <div class="target-info">
@@ -381,60 +986,140 @@ pub fn app() -> impl IntoView {
                             {move || current_localizer.get().get_ui("target")} ": " <strong>{move || {
                                 let localizer = current_localizer.get();
                                 let item_id = selected_item.get();
-                                machine_ids_store.with_value(|machine_ids| {
-                                    get_localized_name(&item_id, &localizer, machine_ids)
-                                })
+                                game_data.with_value(|data| localizer.get_name(&item_id, data))
                             }}</strong>
-                            " x" {move || target_amount.get()} {move || current_localizer.get().get_ui("per_min")}
+                            " x" {move || current_localizer.get().get_ui_plural("rate_per_min", target_amount.get() as u64)}
                         </p>
+                        {move || {
+                            current_localizer
+                                .get()
+                                .get_description(&selected_item.get())
+                                .map(|description| view! { <p class="target-description">{description}</p> })
+                        }}
+                        <div class="view-switch">
+                            <button
+                                class:active=move || plan_view.get() == PlanView::Tree
+                                on:click=move |_| set_plan_view.set(PlanView::Tree)
+                            >
+                                {move || current_localizer.get().get_ui("view_tree")}
+                            </button>
+                            <button
+                                class:active=move || plan_view.get() == PlanView::Flow
+                                on:click=move |_| set_plan_view.set(PlanView::Flow)
+                            >
+                                {move || current_localizer.get().get_ui("view_flow")}
+                            </button>
+                        </div>
                         <button
                             class="share-button"
                             on:click=move |_| {
-                                if let Some(url) = generate_share_url(&selected_item.get(), target_amount.get()) {
-                                    if let Some(window) = web_sys::window() {
-                                        let clipboard = window.navigator().clipboard();
-                                        let promise = clipboard.write_text(&url);
-
-                                        wasm_bindgen_futures::spawn_local(async move {
-                                            match wasm_bindgen_futures::JsFuture::from(promise).await {
-                                                Ok(_) => {
-                                                    web_sys::console::log_1(&"Copied to clipboard successfully!".into());
-                                                },
-                                                Err(err) => {
-                                                    web_sys::console::error_2(&"Failed to copy to clipboard: ".into(), &err);
-                                                }
-                                            }
-                                        });
-                                    };
+                                if let Some(url) = generate_share_url(&current_url_state()) {
+                                    copy_to_clipboard(&url);
                                 }
                             }
                             title="Copy link to Clipboard"
                         >
                             {move || current_localizer.get().get_ui("share")}
                         </button>
+                        <button
+                            class="share-button"
+                            on:click=move |_| {
+                                copy_to_clipboard(&untrack(current_share_state).to_code());
+                            }
+                            title="Copy factory code to Clipboard"
+                        >
+                            {move || current_localizer.get().get_ui("copy_code")}
+                        </button>
+                        <button
+                            class="share-button"
+                            on:click=move |_| {
+                                let localizer = current_localizer.get();
+                                let markdown = production_plan.with(|node| {
+                                    game_data.with_value(|data| {
+                                        to_markdown(node, &localizer, &LocalizedResolver::new(&localizer, data), AmountKind::PerMinute)
+                                    })
+                                });
+                                copy_to_clipboard(&markdown);
+                            }
+                            title="Copy plan as Markdown"
+                        >
+                            {move || current_localizer.get().get_ui("copy_markdown")}
+                        </button>
+                        <details class="download-menu">
+                            <summary class="share-button">{move || current_localizer.get().get_ui("download")}</summary>
+                            <div class="download-options">
+                                {DownloadFormat::ALL.into_iter().map(|format| view! {
+                                    <button
+                                        on:click=move |ev| {
+                                            // Rendered only on click, so large plans cost nothing until downloaded
+                                            let localizer = current_localizer.get_untracked();
+                                            let contents = plan_result.with_untracked(|result| {
+                                                game_data.with_value(|data| plan_file_contents(format, result, data, &localizer))
+                                            });
+                                            let filename = download_filename(
+                                                &selected_item.get_untracked(),
+                                                target_amount.get_untracked(),
+                                                format,
+                                            );
+                                            download_file(&filename, &contents, format.mime_type());
+                                            let menu = event_target::<web_sys::Element>(&ev).closest("details");
+                                            if let Ok(Some(menu)) = menu {
+                                                let _ = menu.remove_attribute("open");
+                                            }
+                                        }
+                                    >
+                                        {move || current_localizer.get().get_ui(format.label_key())}
+                                    </button>
+                                }).collect_view()}
+                            </div>
+                        </details>
                     </div>
 
+                    <Show
+                        when=move || plan_view.get() == PlanView::Tree
+                        fallback=move || view! {
+                            <div class="production-tree">
+                                <FlowView
+                                    node=production_plan
+                                    localizer=current_localizer
+                                    game_data=game_data
+                                />
+                            </div>
+                        }
+                    >
                     <div class="production-tree">
                         {move || {
                             let node = production_plan.get();
                             let localizer = current_localizer.get();
+                            let spare_threshold = show_spare.get().then(|| spare_threshold.get());
                             match &node {
-                                ProductionNode::Resolved { item_id, machine_id, amount, machine_count, inputs, .. } => {
-                                    let item_name = machine_ids_store.with_value(|machine_ids| {
-                                        get_localized_name(item_id, &localizer, machine_ids)
-                                    });
-                                    let machine_name = localizer.get_machine(machine_id);
+                                ProductionNode::Resolved { item_id, machine_id, amount, inputs, .. } => {
+                                    let machine_count = node.machine_count_label();
+                                    let spare_node = node.clone();
+                                    let item_name = game_data.with_value(|data| localizer.get_name(item_id.as_str(), data));
+                                    let machine_name = localizer.get_machine(machine_id.as_str());
                                     let child_count = inputs.len();
                                     view! {
                                         <div class="tree-root">
                                             <div class="tree-line tree-root-line">
                                                 <span class="tree-item">
                                                     <strong>{item_name}</strong>
-                                                    " ×"{*amount}
+                                                    " ×" {format_rate(*amount as f64, RATE_PRECISION, &localizer)}
                                                 </span>
                                                 <span class="tree-machine">
-                                                    {machine_name} " ×" {*machine_count}
+                                                    {machine_name} " ×" {machine_count}
                                                 </span>
+                                                <AlternativesBadge
+                                                    item_id=item_id.to_string()
+                                                    localizer=localizer.clone()
+                                                    game_data=game_data
+                                                    alternatives=alternatives
+                                                />
+                                                <SpareNote
+                                                    node=spare_node
+                                                    localizer=localizer.clone()
+                                                    threshold=spare_threshold
+                                                />
                                             </div>
                                             {
                                                 inputs.clone().into_iter().enumerate().map(move |(i, child)| {
@@ -443,7 +1128,12 @@ pub fn app() -> impl IntoView {
                                                         <TreeView
                                                             node=child
                                                             localizer=localizer.clone()
-                                                            machine_ids=machine_ids_store
+                                                            game_data=game_data
+                                                            alternatives=alternatives
+                                                            on_toggle_exclude=toggle_excluded
+                                                            spare_threshold=spare_threshold
+                                                            path=NodePath::root().child(i)
+                                                            collapsed=Some(collapsed_nodes)
                                                             is_last=is_last
                                                             prefix=vec![]
                                                         />
@@ -454,21 +1144,56 @@ pub fn app() -> impl IntoView {
                                     }.into_any()
                                 }
                                 ProductionNode::Unresolved { item_id, amount } => {
-                                    let item_name = machine_ids_store.with_value(|machine_ids| {
-                                        get_localized_name(item_id, &localizer, machine_ids)
-                                    });
+                                    let item_name = game_data.with_value(|data| localizer.get_name(item_id.as_str(), data));
                                     view! {
                                         <div class="tree-line tree-missing">
-                                            <span class="tree-item">{item_name} " ×" {*amount}</span>
+                                            <span class="tree-item">{item_name} " ×" {format_rate(*amount as f64, RATE_PRECISION, &localizer)}</span>
                                             <span class="tree-machine missing">"[" {localizer.get_ui("missing_recipe")} "]"</span>
                                         </div>
                                     }.into_any()
                                 }
+                                ProductionNode::External { item_id, amount } => {
+                                    let item_name = game_data.with_value(|data| localizer.get_name(item_id.as_str(), data));
+                                    view! {
+                                        <div class="tree-line tree-external">
+                                            <span class="tree-item">{item_name} " ×" {format_rate(*amount as f64, RATE_PRECISION, &localizer)}</span>
+                                            <span class="tree-machine external">"[" {localizer.get_ui("external_supply")} "]"</span>
+                                        </div>
+                                    }.into_any()
+                                }
+                                ProductionNode::Cycle { item_id, amount } => {
+                                    let item_name = game_data.with_value(|data| localizer.get_name(item_id.as_str(), data));
+                                    view! {
+                                        <div class="tree-line tree-cycle">
+                                            <span class="tree-item">{item_name} " ×" {format_rate(*amount as f64, RATE_PRECISION, &localizer)}</span>
+                                            <span class="tree-machine cycle">"[" {localizer.get_ui("cycle_break")} "]"</span>
+                                        </div>
+                                    }.into_any()
+                                }
+                                ProductionNode::Shared { item_id, amount } => {
+                                    let item_name = game_data.with_value(|data| localizer.get_name(item_id.as_str(), data));
+                                    view! {
+                                        <div class="tree-line tree-shared">
+                                            <span class="tree-item">{item_name} " ×" {format_rate(*amount as f64, RATE_PRECISION, &localizer)}</span>
+                                            <span class="tree-machine shared">"[" {localizer.get_ui("shared_in_plan")} "]"</span>
+                                        </div>
+                                    }.into_any()
+                                }
                             }
                         }}
                     </div>
+                    </Show>
                 </div>
+                </Show>
             </div>
         </div>
     }
+    .into_any()
+}
+
+/// How the production plan is displayed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PlanView {
+    Tree,
+    Flow,
 }
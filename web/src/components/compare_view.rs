@@ -0,0 +1,238 @@
+use endfield_planner_core::config::GameData;
+use endfield_planner_core::i18n::{Locale, Localizer};
+use endfield_planner_core::models::{ItemId, ProductionNode};
+use endfield_planner_core::output::{RATE_PRECISION, format_rate};
+use endfield_planner_core::planner::plan_production_with_options;
+use leptos::prelude::*;
+use std::collections::HashMap;
+
+use crate::components::app::target_options;
+use crate::components::tree_view::TreeView;
+use crate::utils::amount::resolve_amount_input;
+use crate::utils::compare::compare_plans;
+use crate::utils::number::format_number;
+
+/// Plans `item` at `amount` with the main planner's excluded items, for
+/// one compare side. A failed plan shows the item as unresolved.
+fn side_plan(
+    game_data: StoredValue<GameData>,
+    item: ReadSignal<String>,
+    amount: ReadSignal<u32>,
+    excluded_items: ReadSignal<Vec<String>>,
+) -> Memo<ProductionNode> {
+    Memo::new(move |_| {
+        let item_id = item.get();
+        let amount = amount.get();
+        let options = excluded_items.with(|excluded| target_options(excluded, &item_id));
+        game_data.with_value(|data| {
+            plan_production_with_options(data, &item_id, amount, &options)
+                .map(|result| result.node)
+                .unwrap_or_else(|_| ProductionNode::Unresolved {
+                    item_id: ItemId::from(&item_id),
+                    amount,
+                })
+        })
+    })
+}
+
+/// Two independent planners side by side with a comparison of their totals.
+///
+/// Each side keeps its own item and amount; the left side is the main
+/// planner's selection.
+#[component]
+pub fn compare_view(
+    items: Vec<String>,
+    left_item: (ReadSignal<String>, WriteSignal<String>),
+    left_amount: (ReadSignal<u32>, WriteSignal<u32>),
+    right_item: (ReadSignal<String>, WriteSignal<String>),
+    right_amount: (ReadSignal<u32>, WriteSignal<u32>),
+    localizer: Memo<Localizer>,
+    locale: ReadSignal<Locale>,
+    /// Items supplied externally, shared with the main planner.
+    excluded_items: ReadSignal<Vec<String>>,
+    game_data: StoredValue<GameData>,
+    alternatives: StoredValue<HashMap<String, usize>>,
+) -> impl IntoView {
+    let left_plan = side_plan(game_data, left_item.0, left_amount.0, excluded_items);
+    let right_plan = side_plan(game_data, right_item.0, right_amount.0, excluded_items);
+
+    let items = StoredValue::new(items);
+
+    view! {
+        <div class="compare-view">
+            // Shared comparison of the headline totals
+            <div class="summary-card compare-summary">
+                <h4>{move || localizer.get().get_ui("comparison")}</h4>
+                {move || {
+                    let localizer = localizer.get();
+                    let locale = locale.get();
+                    let rows = left_plan.with(|left| right_plan.with(|right| compare_plans(left, right)));
+
+                    view! {
+                        <table>
+                            <thead>
+                                <tr>
+                                    <th></th>
+                                    <th>{localizer.get_ui("plan_a")}</th>
+                                    <th>{localizer.get_ui("plan_b")}</th>
+                                    <th>{localizer.get_ui("difference")}</th>
+                                </tr>
+                            </thead>
+                            <tbody>
+                                {rows.into_iter().map(|row| {
+                                    let difference = row.difference();
+                                    let sign = if difference > 0.0 { "+" } else { "" };
+                                    let class = if difference > 0.0 {
+                                        "compare-increase"
+                                    } else if difference < 0.0 {
+                                        "compare-decrease"
+                                    } else {
+                                        ""
+                                    };
+                                    view! {
+                                        <tr>
+                                            <td>{localizer.get_ui(row.label_key)}</td>
+                                            <td>{format_number(row.left, locale)}</td>
+                                            <td>{format_number(row.right, locale)}</td>
+                                            <td class=class>{sign} {format_number(difference, locale)}</td>
+                                        </tr>
+                                    }
+                                }).collect_view()}
+                            </tbody>
+                        </table>
+                    }
+                }}
+            </div>
+
+            <div class="compare-panels">
+                <ComparePanel
+                    label_key="plan_a"
+                    items=items
+                    item=left_item
+                    amount=left_amount
+                    plan=left_plan
+                    localizer=localizer
+                    game_data=game_data
+                    alternatives=alternatives
+                />
+                <ComparePanel
+                    label_key="plan_b"
+                    items=items
+                    item=right_item
+                    amount=right_amount
+                    plan=right_plan
+                    localizer=localizer
+                    game_data=game_data
+                    alternatives=alternatives
+                />
+            </div>
+        </div>
+    }
+}
+
+/// One side of the compare view: item and amount inputs, raw materials
+/// and the production tree.
+#[component]
+fn compare_panel(
+    label_key: &'static str,
+    items: StoredValue<Vec<String>>,
+    item: (ReadSignal<String>, WriteSignal<String>),
+    amount: (ReadSignal<u32>, WriteSignal<u32>),
+    plan: Memo<ProductionNode>,
+    localizer: Memo<Localizer>,
+    game_data: StoredValue<GameData>,
+    alternatives: StoredValue<HashMap<String, usize>>,
+) -> impl IntoView {
+    let (item, set_item) = item;
+    let (amount, set_amount) = amount;
+
+    // Exclusions only apply to the main planner
+    let ignore_toggle = Callback::new(|_: String| {});
+
+    view! {
+        <div class="compare-panel">
+            <h3>{move || localizer.get().get_ui(label_key)}</h3>
+
+            <div class="form-group">
+                <label class="form-label">{move || localizer.get().get_ui("item_select")}</label>
+                <select
+                    class="form-input"
+                    on:change=move |ev| set_item.set(event_target_value(&ev))
+                >
+                    {move || {
+                        let localizer = localizer.get();
+                        let selected = item.get();
+                        let mut sorted = items.get_value();
+                        sorted.sort_by(|a, b| localizer.compare_by_reading(a, b));
+
+                        sorted.into_iter().map(|item_id| {
+                            let is_selected = item_id == selected;
+                            let name = game_data.with_value(|data| localizer.get_name(&item_id, data));
+                            view! { <option value=item_id selected=is_selected>{name}</option> }
+                        }).collect_view()
+                    }}
+                </select>
+            </div>
+
+            <div class="form-group">
+                <label class="form-label">{move || localizer.get().get_ui("amount_per_min")}</label>
+                <input
+                    type="number"
+                    min="1"
+                    class="form-input"
+                    prop:value=move || amount.get()
+                    on:input=move |ev| {
+                        if let Ok(val) = event_target_value(&ev).parse::<u32>() {
+                            set_amount.set(val);
+                        }
+                    }
+                    on:blur=move |ev| {
+                        let current = amount.get_untracked();
+                        set_amount.set(resolve_amount_input(&event_target_value(&ev), current));
+                    }
+                />
+            </div>
+
+            <div class="summary-card">
+                <h4>{move || localizer.get().get_ui("total_raw_materials")}</h4>
+                <div class="summary-card-content">
+                    {move || {
+                        let localizer = localizer.get();
+                        let materials: Vec<_> = plan.with(|node| {
+                            game_data.with_value(|data| {
+                                localizer.localize_amounts(node.total_source_materials(), data).collect()
+                            })
+                        });
+
+                        if materials.is_empty() {
+                            view! { <div class="empty">{localizer.get_ui("none")}</div> }.into_any()
+                        } else {
+                            view! {
+                                <ul>
+                                    {materials.into_iter().map(|(display_name, count)| {
+                                        let count = format_rate(count as f64, RATE_PRECISION, &localizer);
+                                        view! { <li>{display_name} ": " <strong>{count}</strong></li> }
+                                    }).collect_view()}
+                                </ul>
+                            }.into_any()
+                        }
+                    }}
+                </div>
+            </div>
+
+            <div class="production-tree">
+                {move || {
+                    view! {
+                        <TreeView
+                            node=plan.get()
+                            localizer=localizer.get()
+                            game_data=game_data
+                            alternatives=alternatives
+                            on_toggle_exclude=ignore_toggle
+                        />
+                    }
+                }}
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,68 @@
+use endfield_planner_core::config::{DataWarning, GameData};
+use endfield_planner_core::i18n::Localizer;
+use leptos::prelude::*;
+use std::collections::BTreeMap;
+
+use crate::utils::localization::localize_data_warning;
+
+/// Header indicator that opens a panel listing problems found in the data.
+///
+/// Renders nothing when the data loaded without warnings.
+#[component]
+pub fn data_warnings(
+    warnings: Vec<DataWarning>,
+    localizer: Memo<Localizer>,
+    game_data: StoredValue<GameData>,
+) -> impl IntoView {
+    if warnings.is_empty() {
+        return ().into_any();
+    }
+
+    let count = warnings.len();
+    let mut groups: BTreeMap<&'static str, Vec<DataWarning>> = BTreeMap::new();
+    for warning in warnings {
+        groups.entry(warning.kind()).or_default().push(warning);
+    }
+    let groups = StoredValue::new(groups);
+
+    let (panel_open, set_panel_open) = signal(false);
+
+    view! {
+        <div class="data-warnings">
+            <button
+                class="data-warnings-toggle"
+                title=move || localizer.get().get_ui("data_warnings")
+                on:click=move |_| set_panel_open.update(|open| *open = !*open)
+            >
+                "⚠ " {count}
+            </button>
+            <Show when=move || panel_open.get()>
+                <div class="data-warnings-panel">
+                    <h4>{move || localizer.get().get_ui("data_warnings")}</h4>
+                    {move || {
+                        let localizer = localizer.get();
+                        groups.with_value(|groups| {
+                            groups.iter().map(|(kind, warnings)| {
+                                let title = localizer.get_ui(&format!("data_warning_kind_{}", kind));
+                                view! {
+                                    <div class="data-warnings-group">
+                                        <h5>{title} " (" {warnings.len()} ")"</h5>
+                                        <ul>
+                                            {warnings.iter().map(|warning| {
+                                                let message = game_data.with_value(|data| {
+                                                    localize_data_warning(warning, &localizer, data)
+                                                });
+                                                view! { <li>{message}</li> }
+                                            }).collect_view()}
+                                        </ul>
+                                    </div>
+                                }
+                            }).collect_view()
+                        })
+                    }}
+                </div>
+            </Show>
+        </div>
+    }
+    .into_any()
+}
@@ -0,0 +1,119 @@
+use endfield_planner_core::config::GameData;
+use endfield_planner_core::i18n::Localizer;
+use endfield_planner_core::models::ProductionNode;
+use endfield_planner_core::output::{FLOW_NODE_WIDTH, layout_flow};
+use leptos::prelude::*;
+
+/// Horizontal room left of the diagram for raw material labels.
+const LABEL_MARGIN: f64 = 160.0;
+/// Padding around the diagram.
+const PADDING: f64 = 16.0;
+
+/// Flow (Sankey-style) diagram of the production plan.
+///
+/// Hovering a node highlights it and everything feeding into it.
+#[component]
+pub fn flow_view(
+    node: Memo<ProductionNode>,
+    localizer: Memo<Localizer>,
+    game_data: StoredValue<GameData>,
+) -> impl IntoView {
+    let diagram = Memo::new(move |_| node.with(layout_flow));
+    let (hovered, set_hovered) = signal(None::<usize>);
+    let highlighted = Memo::new(move |_| {
+        hovered
+            .get()
+            .map(|index| diagram.with(|diagram| diagram.upstream(index)))
+    });
+
+    let is_dimmed = move |index: usize| {
+        highlighted.with(|highlighted| {
+            highlighted
+                .as_ref()
+                .is_some_and(|chain| !chain.contains(&index))
+        })
+    };
+
+    view! {
+        <div class="flow-view" on:mouseleave=move |_| set_hovered.set(None)>
+            {move || {
+                let localizer = localizer.get();
+                diagram.with(|diagram| {
+                    let width = diagram.width + LABEL_MARGIN + PADDING * 2.0;
+                    let height = diagram.height + PADDING * 2.0;
+
+                    let links = diagram.links.iter().map(|link| {
+                        let path = diagram.link_path(link);
+                        let (source, target) = (link.source, link.target);
+                        let title = game_data.with_value(|data| {
+                            format!(
+                                "{} → {}: {}",
+                                localizer.get_name(diagram.nodes[source].item_id.as_str(), data),
+                                localizer.get_name(diagram.nodes[target].item_id.as_str(), data),
+                                link.amount,
+                            )
+                        });
+                        view! {
+                            <path
+                                class="flow-link"
+                                class:dimmed=move || is_dimmed(source) || is_dimmed(target)
+                                d=path
+                                stroke-width=link.thickness.to_string()
+                            >
+                                <title>{title}</title>
+                            </path>
+                        }
+                    }).collect_view();
+
+                    let nodes = diagram.nodes.iter().enumerate().map(|(index, flow_node)| {
+                        let name = game_data.with_value(|data| localizer.get_name(flow_node.item_id.as_str(), data));
+                        let title = format!(
+                            "{} ×{} {} · {} ×{}",
+                            name,
+                            flow_node.amount,
+                            localizer.get_ui("per_min"),
+                            localizer.get_ui("total_machine_count"),
+                            flow_node.machine_count,
+                        );
+                        let label_y = flow_node.y + flow_node.height / 2.0;
+                        view! {
+                            <g
+                                class="flow-node"
+                                class:dimmed=move || is_dimmed(index)
+                                on:mouseenter=move |_| set_hovered.set(Some(index))
+                            >
+                                <rect
+                                    x=flow_node.x.to_string()
+                                    y=flow_node.y.to_string()
+                                    width=FLOW_NODE_WIDTH.to_string()
+                                    height=flow_node.height.to_string()
+                                />
+                                <text
+                                    x=(flow_node.x - 4.0).to_string()
+                                    y=label_y.to_string()
+                                    text-anchor="end"
+                                    dominant-baseline="middle"
+                                >
+                                    {name}
+                                </text>
+                                <title>{title}</title>
+                            </g>
+                        }
+                    }).collect_view();
+
+                    view! {
+                        <svg
+                            class="flow-diagram"
+                            width=width.to_string()
+                            height=height.to_string()
+                            viewBox=format!("{} {} {} {}", -LABEL_MARGIN - PADDING, -PADDING, width, height)
+                        >
+                            <g class="flow-links">{links}</g>
+                            <g class="flow-nodes">{nodes}</g>
+                        </svg>
+                    }
+                })
+            }}
+        </div>
+    }
+}
@@ -0,0 +1,19 @@
+use endfield_planner_core::error::ProductionError;
+use endfield_planner_core::i18n::Localizer;
+use leptos::prelude::*;
+
+use crate::utils::error::describe_load_error;
+
+/// Full-page message shown when the game data cannot be loaded.
+#[component]
+pub fn load_error(error: ProductionError, localizer: Localizer) -> impl IntoView {
+    let (message_key, detail) = describe_load_error(&error);
+
+    view! {
+        <div class="load-error">
+            <h1>{localizer.get_ui("load_error_title")}</h1>
+            <p>{localizer.get_ui(message_key)}</p>
+            <pre class="load-error-detail">{detail}</pre>
+        </div>
+    }
+}
@@ -1,4 +1,8 @@
 pub mod app;
+pub mod compare_view;
+pub mod data_warnings;
+pub mod flow_view;
+pub mod load_error;
 pub mod tree_view;
 
 pub use app::App;
@@ -1,30 +1,101 @@
-use leptos::prelude::*;
+use endfield_planner_core::config::GameData;
 use endfield_planner_core::i18n::Localizer;
-use endfield_planner_core::models::ProductionNode;
-use std::collections::HashSet;
+use endfield_planner_core::models::{NodePath, ProductionNode};
+use endfield_planner_core::output::{RATE_PRECISION, format_rate};
+use leptos::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+use crate::utils::alternatives::alternative_machines;
+
+/// Badge with the number of recipes that can make an item, listing their
+/// machines in its tooltip. Hidden when there is no choice.
+#[component]
+pub fn alternatives_badge(
+    item_id: String,
+    localizer: Localizer,
+    game_data: StoredValue<GameData>,
+    /// Candidate recipe counts by item ID.
+    alternatives: StoredValue<HashMap<String, usize>>,
+) -> impl IntoView {
+    let count = alternatives.with_value(|counts| counts.get(&item_id).copied().unwrap_or(0));
+
+    (count > 1).then(|| {
+        let machines = game_data
+            .with_value(|data| alternative_machines(data, &item_id))
+            .iter()
+            .map(|machine_id| localizer.get_machine(machine_id))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let title = localizer
+            .get_ui("alternatives_tooltip")
+            .replace("{machines}", &machines);
 
-use crate::utils::localization::get_localized_name;
+        view! {
+            <span class="tree-alternatives" title=title>
+                {localizer.get_ui_plural("alternatives_badge", count as u64)}
+            </span>
+        }
+    })
+}
+
+/// Subtle note of the spare amount left by rounding machines up, e.g.
+/// "+1.8/min spare". Hidden when spare notes are off (`threshold` is
+/// `None`) or the spare is within the threshold.
+#[component]
+pub fn spare_note(
+    node: ProductionNode,
+    localizer: Localizer,
+    threshold: Option<f64>,
+) -> impl IntoView {
+    let spare = threshold.and_then(|threshold| node.notable_spare_amount(threshold));
+
+    spare.map(|spare| {
+        let text = localizer
+            .get_ui("spare_rate")
+            .replace("{rate}", &format_rate(spare, RATE_PRECISION, &localizer));
+        view! { <span class="tree-spare">{text}</span> }
+    })
+}
 
 #[component]
 pub fn tree_view(
     node: ProductionNode,
     localizer: Localizer,
-    machine_ids: StoredValue<HashSet<String>>,
+    game_data: StoredValue<GameData>,
+    /// Candidate recipe counts by item ID, for the alternatives badge.
+    alternatives: StoredValue<HashMap<String, usize>>,
+    /// Called with an item ID to toggle whether it is supplied externally.
+    on_toggle_exclude: Callback<String>,
+    /// Share of the requested rate above which spare capacity is noted,
+    /// or `None` to hide the notes.
+    #[prop(default = None)]
+    spare_threshold: Option<f64>,
+    /// Location of this node in the plan, for `collapsed`.
+    #[prop(default = NodePath::root())]
+    path: NodePath,
+    /// Nodes whose inputs are hidden, or `None` when the tree cannot be
+    /// collapsed.
+    #[prop(default = None)]
+    collapsed: Option<RwSignal<HashSet<NodePath>>>,
     #[prop(default = true)] is_last: bool,
     #[prop(default = vec![])] prefix: Vec<bool>,
 ) -> impl IntoView {
+    let machine_count = node.machine_count_label();
+    let spare_node = node.clone();
     match node {
         ProductionNode::Resolved {
             item_id,
             machine_id,
             amount,
-            machine_count,
             inputs,
             ..
         } => {
-            let item_name =
-                machine_ids.with_value(|ids| get_localized_name(&item_id, &localizer, ids));
-            let machine_name = localizer.get_machine(&machine_id);
+            let item_name = game_data.with_value(|data| localizer.get_name(item_id.as_str(), data));
+            let machine_name = localizer.get_machine(machine_id.as_str());
+            let badge_item_id = item_id.to_string();
+            let exclude_title = localizer.get_ui("exclude_item");
+            let expand_title = localizer.get_ui("expand");
+            let collapse_title = localizer.get_ui("collapse");
             let localizer_clone = localizer.clone();
             let child_count = inputs.len();
 
@@ -40,39 +111,88 @@ pub fn tree_view(
             let mut child_prefix = prefix.clone();
             child_prefix.push(!is_last);
 
+            let can_collapse = collapsed.is_some() && child_count > 0;
+            let is_collapsed = {
+                let path = path.clone();
+                Signal::derive(move || {
+                    collapsed.is_some_and(|collapsed| collapsed.with(|set| set.contains(&path)))
+                })
+            };
+            let toggle_path = path.clone();
+
             view! {
                 <div class="tree-line">
                     <span class="tree-prefix">{prefix_str}</span>
                     <span class="tree-connector">{connector}</span>
                     <span class="tree-item">
                         <strong>{item_name}</strong>
-                        " ×"{amount}
+                        " ×" {format_rate(amount as f64, RATE_PRECISION, &localizer)}
                     </span>
                     <span class="tree-machine">
                          {machine_name} " ×" {machine_count}
                     </span>
+                    <AlternativesBadge
+                        item_id=badge_item_id
+                        localizer=localizer.clone()
+                        game_data=game_data
+                        alternatives=alternatives
+                    />
+                    <SpareNote
+                        node=spare_node
+                        localizer=localizer.clone()
+                        threshold=spare_threshold
+                    />
+                    <button
+                        class="tree-exclude-toggle"
+                        title=exclude_title
+                        on:click=move |_| on_toggle_exclude.run(item_id.to_string())
+                    >
+                        "⊘"
+                    </button>
+                    {can_collapse.then(|| view! {
+                        <button
+                            class="tree-collapse-toggle"
+                            title=move || if is_collapsed.get() { expand_title.clone() } else { collapse_title.clone() }
+                            on:click=move |_| {
+                                if let Some(collapsed) = collapsed {
+                                    collapsed.update(|set| {
+                                        if !set.remove(&toggle_path) {
+                                            set.insert(toggle_path.clone());
+                                        }
+                                    });
+                                }
+                            }
+                        >
+                            {move || if is_collapsed.get() { "▸" } else { "▾" }}
+                        </button>
+                    })}
                 </div>
-                {
-                    inputs.into_iter().enumerate().map(move |(i, child)| {
-                        let is_last_child = i == child_count - 1;
-                        let child_prefix_clone = child_prefix.clone();
-                        view! {
-                            <TreeView
-                                node=child
-                                localizer=localizer_clone.clone()
-                                machine_ids=machine_ids
-                                is_last=is_last_child
-                                prefix=child_prefix_clone
-                            />
-                        }
-                    }).collect_view()
-                }
+                {move || {
+                    (!is_collapsed.get()).then(|| {
+                        inputs.iter().cloned().enumerate().map(|(i, child)| {
+                            let is_last_child = i == child_count - 1;
+                            view! {
+                                <TreeView
+                                    node=child
+                                    localizer=localizer_clone.clone()
+                                    game_data=game_data
+                                    alternatives=alternatives
+                                    on_toggle_exclude=on_toggle_exclude
+                                    spare_threshold=spare_threshold
+                                    path=path.child(i)
+                                    collapsed=collapsed
+                                    is_last=is_last_child
+                                    prefix=child_prefix.clone()
+                                />
+                            }
+                        }).collect_view()
+                    })
+                }}
             }
             .into_any()
         }
         ProductionNode::Unresolved { item_id, amount } => {
-            let item_name =
-                machine_ids.with_value(|ids| get_localized_name(&item_id, &localizer, ids));
+            let item_name = game_data.with_value(|data| localizer.get_name(item_id.as_str(), data));
             let missing_text = localizer.get_ui("missing_recipe");
 
             let prefix_str: String = prefix
@@ -88,7 +208,7 @@ pub fn tree_view(
                     <span class="tree-connector">{connector}</span>
                     <span class="tree-item">
                         <strong>{item_name}</strong>
-                        " ×" {amount}
+                        " ×" {format_rate(amount as f64, RATE_PRECISION, &localizer)}
                     </span>
                     <span class="tree-machine missing">
                         "[" {missing_text} "]"
@@ -97,5 +217,91 @@ pub fn tree_view(
             }
             .into_any()
         }
+        ProductionNode::Cycle { item_id, amount } => {
+            let item_name = game_data.with_value(|data| localizer.get_name(item_id.as_str(), data));
+            let cycle_text = localizer.get_ui("cycle_break");
+
+            let prefix_str: String = prefix
+                .iter()
+                .map(|&has_line| if has_line { "│   " } else { "    " })
+                .collect();
+
+            let connector = if is_last { "└── " } else { "├── " };
+
+            view! {
+                <div class="tree-line tree-cycle">
+                    <span class="tree-prefix">{prefix_str}</span>
+                    <span class="tree-connector">{connector}</span>
+                    <span class="tree-item">
+                        <strong>{item_name}</strong>
+                        " ×" {format_rate(amount as f64, RATE_PRECISION, &localizer)}
+                    </span>
+                    <span class="tree-machine cycle">
+                        "[" {cycle_text} "]"
+                    </span>
+                </div>
+            }
+            .into_any()
+        }
+        ProductionNode::Shared { item_id, amount } => {
+            let item_name = game_data.with_value(|data| localizer.get_name(item_id.as_str(), data));
+            let shared_text = localizer.get_ui("shared_in_plan");
+
+            let prefix_str: String = prefix
+                .iter()
+                .map(|&has_line| if has_line { "│   " } else { "    " })
+                .collect();
+
+            let connector = if is_last { "└── " } else { "├── " };
+
+            view! {
+                <div class="tree-line tree-shared">
+                    <span class="tree-prefix">{prefix_str}</span>
+                    <span class="tree-connector">{connector}</span>
+                    <span class="tree-item">
+                        <strong>{item_name}</strong>
+                        " ×" {format_rate(amount as f64, RATE_PRECISION, &localizer)}
+                    </span>
+                    <span class="tree-machine shared">
+                        "[" {shared_text} "]"
+                    </span>
+                </div>
+            }
+            .into_any()
+        }
+        ProductionNode::External { item_id, amount } => {
+            let item_name = game_data.with_value(|data| localizer.get_name(item_id.as_str(), data));
+            let external_text = localizer.get_ui("external_supply");
+            let include_title = localizer.get_ui("include_item");
+
+            let prefix_str: String = prefix
+                .iter()
+                .map(|&has_line| if has_line { "│   " } else { "    " })
+                .collect();
+
+            let connector = if is_last { "└── " } else { "├── " };
+
+            view! {
+                <div class="tree-line tree-external">
+                    <span class="tree-prefix">{prefix_str}</span>
+                    <span class="tree-connector">{connector}</span>
+                    <span class="tree-item">
+                        <strong>{item_name}</strong>
+                        " ×" {format_rate(amount as f64, RATE_PRECISION, &localizer)}
+                    </span>
+                    <span class="tree-machine external">
+                        "[" {external_text} "]"
+                    </span>
+                    <button
+                        class="tree-exclude-toggle"
+                        title=include_title
+                        on:click=move |_| on_toggle_exclude.run(item_id.to_string())
+                    >
+                        "↺"
+                    </button>
+                </div>
+            }
+            .into_any()
+        }
     }
 }
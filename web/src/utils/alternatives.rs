@@ -0,0 +1,83 @@
+use endfield_planner_core::config::GameData;
+use endfield_planner_core::planner::{PlanOptions, ranked_recipes};
+use std::collections::HashMap;
+
+/// Counts the candidate recipes of every craftable item.
+pub fn count_alternatives(data: &GameData) -> HashMap<String, usize> {
+    data.recipes_by_output
+        .iter()
+        .map(|(item_id, recipe_ids)| (item_id.to_string(), recipe_ids.len()))
+        .collect()
+}
+
+/// Lists the machines of an item's candidate recipes, best first, each
+/// machine once.
+pub fn alternative_machines(data: &GameData, item_id: &str) -> Vec<String> {
+    let mut machines: Vec<String> = Vec::new();
+    for recipe in ranked_recipes(data, item_id, &PlanOptions::default()) {
+        if !machines.iter().any(|machine| machine == recipe.by.as_str()) {
+            machines.push(recipe.by.to_string());
+        }
+    }
+    machines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RECIPES: &str = r#"
+[[recipes]]
+id = "originium_ore"
+by = ["portable_originium_rig", "electric_mining_rig"]
+time = 2
+out = 1
+is_source = true
+
+[[recipes]]
+id = "origocrust"
+by = "refining_unit"
+time = 2
+out = 1
+[recipes.inputs]
+originium_ore = 1
+"#;
+
+    const MACHINES: &str = r#"
+[[machines]]
+id = "portable_originium_rig"
+tier = 1
+power = 0
+
+[[machines]]
+id = "electric_mining_rig"
+tier = 2
+power = 5
+
+[[machines]]
+id = "refining_unit"
+tier = 1
+power = 5
+"#;
+
+    #[test]
+    fn test_count_alternatives() {
+        let data = GameData::new(RECIPES, MACHINES).unwrap();
+
+        let counts = count_alternatives(&data);
+
+        assert_eq!(counts["originium_ore"], 2);
+        assert_eq!(counts["origocrust"], 1);
+    }
+
+    #[test]
+    fn test_alternative_machines_best_first() {
+        let data = GameData::new(RECIPES, MACHINES).unwrap();
+
+        assert_eq!(
+            alternative_machines(&data, "originium_ore"),
+            vec!["electric_mining_rig", "portable_originium_rig"]
+        );
+        assert!(alternative_machines(&data, "unknown").is_empty());
+    }
+}
@@ -0,0 +1,38 @@
+/// Minimum amount accepted by the amount input.
+pub const MIN_AMOUNT: u32 = 1;
+
+/// Resolves the value the amount input should settle on after editing.
+///
+/// Parses the raw input text, falling back to the current amount when the
+/// field is empty or invalid, and clamps the result to `MIN_AMOUNT`.
+pub fn resolve_amount_input(input: &str, current: u32) -> u32 {
+    input
+        .trim()
+        .parse::<u32>()
+        .unwrap_or(current)
+        .max(MIN_AMOUNT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_amount_input_parses_trimmed_number() {
+        assert_eq!(resolve_amount_input("45", 30), 45);
+        assert_eq!(resolve_amount_input("  12 ", 30), 12);
+    }
+
+    #[test]
+    fn test_resolve_amount_input_keeps_current_on_empty_or_invalid() {
+        assert_eq!(resolve_amount_input("", 30), 30);
+        assert_eq!(resolve_amount_input("   ", 30), 30);
+        assert_eq!(resolve_amount_input("abc", 30), 30);
+        assert_eq!(resolve_amount_input("-5", 30), 30);
+    }
+
+    #[test]
+    fn test_resolve_amount_input_clamps_to_minimum() {
+        assert_eq!(resolve_amount_input("0", 30), MIN_AMOUNT);
+    }
+}
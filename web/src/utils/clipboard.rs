@@ -0,0 +1,18 @@
+/// Writes `text` to the clipboard, logging the outcome to the console.
+pub fn copy_to_clipboard(text: &str) {
+    let Some(window) = web_sys::window() else {
+        return;
+    };
+    let promise = window.navigator().clipboard().write_text(text);
+
+    wasm_bindgen_futures::spawn_local(async move {
+        match wasm_bindgen_futures::JsFuture::from(promise).await {
+            Ok(_) => {
+                web_sys::console::log_1(&"Copied to clipboard successfully!".into());
+            }
+            Err(err) => {
+                web_sys::console::error_2(&"Failed to copy to clipboard: ".into(), &err);
+            }
+        }
+    });
+}
@@ -0,0 +1,85 @@
+use endfield_planner_core::models::ProductionNode;
+
+/// One metric of the side-by-side plan comparison.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComparisonRow {
+    /// UI locale key of the metric label.
+    pub label_key: &'static str,
+    pub left: f64,
+    pub right: f64,
+}
+
+impl ComparisonRow {
+    /// How much the right plan differs from the left one.
+    pub fn difference(&self) -> f64 {
+        self.right - self.left
+    }
+}
+
+/// Headline metrics of two plans, in display order.
+pub fn compare_plans(left: &ProductionNode, right: &ProductionNode) -> Vec<ComparisonRow> {
+    let metrics: [(&'static str, fn(&ProductionNode) -> f64); 5] = [
+        ("peak_power", |node| node.total_power() as f64),
+        ("effective_power", |node| {
+            (node.total_effective_power() * 10.0).round() / 10.0
+        }),
+        ("total_machine_count", |node| {
+            node.total_machines().values().sum::<u32>() as f64
+        }),
+        ("raw_material_total", |node| {
+            node.total_source_materials().values().sum::<u32>() as f64
+        }),
+        ("utilization_rate", |node| node.utilization() as f64),
+    ];
+
+    metrics
+        .into_iter()
+        .map(|(label_key, metric)| ComparisonRow {
+            label_key,
+            left: metric(left),
+            right: metric(right),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use endfield_planner_core::models::RecipeUid;
+
+    fn plan(amount: u32, machine_count: u32, power_usage: u32) -> ProductionNode {
+        ProductionNode::Resolved {
+            item_id: "origocrust".into(),
+            machine_id: "refining_unit".into(),
+            recipe_id: RecipeUid::default(),
+            amount,
+            machine_count,
+            power_usage,
+            standby_power: 0,
+            load: 1.0,
+            inputs: vec![ProductionNode::Unresolved {
+                item_id: "originium_ore".into(),
+                amount,
+            }],
+            is_source: false,
+            blocks: vec![],
+        }
+    }
+
+    #[test]
+    fn test_compare_plans() {
+        let rows = compare_plans(&plan(30, 1, 5), &plan(60, 2, 10));
+
+        let row = |key: &str| rows.iter().find(|row| row.label_key == key).unwrap();
+        assert_eq!(row("peak_power").difference(), 5.0);
+        assert_eq!(row("total_machine_count").difference(), 1.0);
+        assert_eq!(
+            (
+                row("raw_material_total").left,
+                row("raw_material_total").right
+            ),
+            (30.0, 60.0)
+        );
+        assert_eq!(row("utilization_rate").difference(), 0.0);
+    }
+}
@@ -0,0 +1,135 @@
+use endfield_planner_core::config::GameData;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// How the item list is ordered.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ItemSort {
+    /// By localized reading, with section headers.
+    #[default]
+    Name,
+    /// Simplest production chain first, see `item_complexity`.
+    Complexity,
+}
+
+impl ItemSort {
+    pub const ALL: [ItemSort; 2] = [ItemSort::Name, ItemSort::Complexity];
+
+    pub fn code(self) -> &'static str {
+        match self {
+            ItemSort::Name => "name",
+            ItemSort::Complexity => "complexity",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Self> {
+        ItemSort::ALL.into_iter().find(|sort| sort.code() == code)
+    }
+
+    /// UI text key of the option label.
+    pub fn label_key(self) -> &'static str {
+        match self {
+            ItemSort::Name => "sort_by_name",
+            ItemSort::Complexity => "sort_by_complexity",
+        }
+    }
+}
+
+/// Computes how complex each item's production chain is, as the number of
+/// nodes in the plan for one item per minute. Computed once at load, since
+/// planning every item on each sort would be too slow.
+pub fn item_complexity(data: &GameData) -> HashMap<String, usize> {
+    data.plan_all(1)
+        .into_iter()
+        .map(|(item_id, node)| (item_id.to_string(), node.node_count()))
+        .collect()
+}
+
+/// Orders two items by complexity, unknown items last.
+pub fn compare_complexity(complexity: &HashMap<String, usize>, a: &str, b: &str) -> Ordering {
+    let of = |item: &str| complexity.get(item).copied().unwrap_or(usize::MAX);
+    of(a).cmp(&of(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RECIPES: &str = r#"
+[[recipes]]
+id = "originium_ore"
+by = "electric_mining_rig"
+time = 2
+out = 1
+is_source = true
+
+[[recipes]]
+id = "amethyst_ore"
+by = "electric_mining_rig"
+time = 2
+out = 1
+is_source = true
+
+[[recipes]]
+id = "origocrust"
+by = "refining_unit"
+time = 2
+out = 1
+[recipes.inputs]
+originium_ore = 1
+
+[[recipes]]
+id = "amethyst_component"
+by = "gearing_unit"
+time = 10
+out = 1
+[recipes.inputs]
+origocrust = 5
+amethyst_ore = 5
+"#;
+
+    const MACHINES: &str = r#"
+[[machines]]
+id = "electric_mining_rig"
+tier = 1
+power = 5
+
+[[machines]]
+id = "refining_unit"
+tier = 1
+power = 5
+
+[[machines]]
+id = "gearing_unit"
+tier = 1
+power = 10
+"#;
+
+    #[test]
+    fn test_item_complexity_counts_plan_nodes() {
+        let data = GameData::new(RECIPES, MACHINES).unwrap();
+
+        let complexity = item_complexity(&data);
+
+        assert_eq!(complexity["originium_ore"], 1);
+        assert_eq!(complexity["origocrust"], 2);
+        // amethyst_component, origocrust, originium_ore and amethyst_ore
+        assert_eq!(complexity["amethyst_component"], 4);
+        assert_eq!(
+            compare_complexity(&complexity, "amethyst_component", "origocrust"),
+            Ordering::Greater
+        );
+        assert_eq!(
+            compare_complexity(&complexity, "unknown", "origocrust"),
+            Ordering::Greater
+        );
+    }
+
+    #[test]
+    fn test_item_sort_codes_round_trip() {
+        for sort in ItemSort::ALL {
+            assert_eq!(ItemSort::from_code(sort.code()), Some(sort));
+        }
+        assert_eq!(ItemSort::from_code("unknown"), None);
+    }
+}
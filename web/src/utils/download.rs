@@ -0,0 +1,176 @@
+use endfield_planner_core::config::GameData;
+use endfield_planner_core::i18n::{LocalizedResolver, Localizer};
+use endfield_planner_core::models::AmountKind;
+use endfield_planner_core::output::{
+    SummaryOptions, TreeStyle, to_json, to_markdown, write_summary,
+};
+use endfield_planner_core::planner::PlanResult;
+use leptos::prelude::set_timeout;
+use std::time::Duration;
+use web_sys::js_sys::Array;
+use web_sys::wasm_bindgen::{JsCast, JsValue};
+use web_sys::{Blob, BlobPropertyBag, HtmlAnchorElement, Url};
+
+/// Delay before the object URL of a download is released.
+const REVOKE_DELAY_MS: u64 = 10_000;
+
+/// File formats a plan can be downloaded as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DownloadFormat {
+    /// ASCII tree and totals, as printed by the CLI.
+    Text,
+    Markdown,
+    Json,
+}
+
+impl DownloadFormat {
+    pub const ALL: [DownloadFormat; 3] = [
+        DownloadFormat::Text,
+        DownloadFormat::Markdown,
+        DownloadFormat::Json,
+    ];
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            DownloadFormat::Text => "txt",
+            DownloadFormat::Markdown => "md",
+            DownloadFormat::Json => "json",
+        }
+    }
+
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            DownloadFormat::Text => "text/plain;charset=utf-8",
+            DownloadFormat::Markdown => "text/markdown;charset=utf-8",
+            DownloadFormat::Json => "application/json",
+        }
+    }
+
+    /// UI text key of the menu entry.
+    pub fn label_key(self) -> &'static str {
+        match self {
+            DownloadFormat::Text => "download_text",
+            DownloadFormat::Markdown => "download_markdown",
+            DownloadFormat::Json => "download_json",
+        }
+    }
+}
+
+/// Builds a file name like `sc_valley_battery_12pm.md`. Characters other
+/// than ASCII letters, digits, `-` and `_` are replaced so the name is
+/// valid on every system.
+pub fn download_filename(item_id: &str, amount: u32, format: DownloadFormat) -> String {
+    let mut stem: String = item_id
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if stem.trim_matches('_').is_empty() {
+        stem = "plan".to_string();
+    }
+
+    format!("{}_{}pm.{}", stem, amount, format.extension())
+}
+
+/// Renders the plan in `format` with the core exporters.
+pub fn plan_file_contents(
+    format: DownloadFormat,
+    result: &PlanResult,
+    data: &GameData,
+    localizer: &Localizer,
+) -> String {
+    let names = LocalizedResolver::new(localizer, data);
+    match format {
+        DownloadFormat::Text => {
+            let deposits = result.warnings.deposits_needed();
+            let options = SummaryOptions {
+                localizer: Some(localizer),
+                names: &names,
+                tree_style: TreeStyle::Ascii,
+                deposits: Some(&deposits),
+                ..Default::default()
+            };
+            let mut out = Vec::new();
+            // Writing to memory cannot fail
+            let _ = write_summary(&mut out, &result.node, &options);
+            String::from_utf8_lossy(&out).into_owned()
+        }
+        DownloadFormat::Markdown => {
+            to_markdown(&result.node, localizer, &names, AmountKind::PerMinute)
+        }
+        DownloadFormat::Json => to_json(result, data, true),
+    }
+}
+
+/// Saves `contents` as a file through a temporary object URL, logging
+/// failures to the console.
+pub fn download_file(filename: &str, contents: &str, mime_type: &str) {
+    if let Err(err) = try_download_file(filename, contents, mime_type) {
+        web_sys::console::error_2(&"Failed to download file: ".into(), &err);
+    }
+}
+
+fn try_download_file(filename: &str, contents: &str, mime_type: &str) -> Result<(), JsValue> {
+    let document = web_sys::window()
+        .and_then(|window| window.document())
+        .ok_or("no document")?;
+
+    let parts = Array::of1(&contents.into());
+    let properties = BlobPropertyBag::new();
+    properties.set_type(mime_type);
+    let blob = Blob::new_with_str_sequence_and_options(&parts, &properties)?;
+    let url = Url::create_object_url_with_blob(&blob)?;
+
+    let anchor: HtmlAnchorElement = document.create_element("a")?.dyn_into()?;
+    anchor.set_href(&url);
+    anchor.set_download(filename);
+    anchor.click();
+
+    // Some browsers start the download only after `click` returns, so the
+    // URL has to outlive it for a while
+    set_timeout(
+        move || {
+            let _ = Url::revoke_object_url(&url);
+        },
+        Duration::from_millis(REVOKE_DELAY_MS),
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filename_includes_item_and_amount() {
+        assert_eq!(
+            download_filename("sc_valley_battery", 12, DownloadFormat::Markdown),
+            "sc_valley_battery_12pm.md"
+        );
+        assert_eq!(
+            download_filename("origocrust", 30, DownloadFormat::Json),
+            "origocrust_30pm.json"
+        );
+    }
+
+    #[test]
+    fn test_filename_sanitizes_odd_characters() {
+        assert_eq!(
+            download_filename("../item name:v2", 1, DownloadFormat::Text),
+            "___item_name_v2_1pm.txt"
+        );
+        assert_eq!(
+            download_filename("電池", 5, DownloadFormat::Text),
+            "plan_5pm.txt"
+        );
+        assert_eq!(
+            download_filename("", 5, DownloadFormat::Text),
+            "plan_5pm.txt"
+        );
+    }
+}
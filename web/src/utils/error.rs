@@ -0,0 +1,54 @@
+use endfield_planner_core::error::ProductionError;
+
+/// Maps a data loading or planning error to a UI locale key and the detail
+/// text to show.
+///
+/// The detail keeps the original message, which for parse errors already
+/// includes the TOML line and column.
+pub fn describe_load_error(error: &ProductionError) -> (&'static str, String) {
+    match error {
+        ProductionError::FileNotFound(path) => ("load_error_file_not_found", path.clone()),
+        ProductionError::ParseError(message) => ("load_error_parse", message.clone()),
+        ProductionError::RecipeNotFound(id) => ("load_error_recipe_not_found", id.clone()),
+        ProductionError::CyclicDependency(_) | ProductionError::UnresolvedItems(_) => {
+            ("load_error_plan", error.to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use endfield_planner_core::config::GameData;
+
+    #[test]
+    fn test_describe_parse_error_keeps_location() {
+        let error = GameData::new("[[recipes\n", "").err().unwrap();
+
+        let (key, detail) = describe_load_error(&error);
+
+        assert_eq!(key, "load_error_parse");
+        assert!(detail.contains("recipes.toml"));
+        assert!(detail.contains("line 1"));
+    }
+
+    #[test]
+    fn test_describe_file_not_found() {
+        let error = ProductionError::FileNotFound("res/recipes.toml".to_string());
+
+        assert_eq!(
+            describe_load_error(&error),
+            ("load_error_file_not_found", "res/recipes.toml".to_string())
+        );
+    }
+
+    #[test]
+    fn test_describe_cyclic_dependency() {
+        let error = ProductionError::CyclicDependency("origocrust".to_string());
+
+        let (key, detail) = describe_load_error(&error);
+
+        assert_eq!(key, "load_error_plan");
+        assert!(detail.contains("origocrust"));
+    }
+}
@@ -0,0 +1,123 @@
+use endfield_planner_core::i18n::{Locale, Localizer, OTHER_SECTION, section_key};
+
+/// Search results with fewer items than this are shown without section headers.
+pub const SECTION_HEADER_MIN_ITEMS: usize = 10;
+
+/// A row in the sidebar item list.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ItemListEntry {
+    Header(String),
+    Item(String),
+}
+
+impl ItemListEntry {
+    /// Returns a key that is unique among the entries of one list.
+    pub fn key(&self) -> String {
+        match self {
+            ItemListEntry::Header(section) => format!("section:{}", section),
+            ItemListEntry::Item(item_id) => item_id.clone(),
+        }
+    }
+}
+
+/// Whether a list sorted by name gets section headers: always, except for
+/// search results too short to need them.
+pub fn shows_section_headers(searching: bool, item_count: usize) -> bool {
+    !searching || item_count >= SECTION_HEADER_MIN_ITEMS
+}
+
+/// Groups already-sorted items under reading-based section headers.
+///
+/// Sections keep the order in which they first appear, except the
+/// catch-all `OTHER_SECTION`, which always comes last.
+pub fn with_section_headers(
+    items: Vec<String>,
+    locale: Locale,
+    localizer: &Localizer,
+) -> Vec<ItemListEntry> {
+    let mut sections: Vec<(String, Vec<String>)> = Vec::new();
+
+    for item in items {
+        let section = section_key(locale, &localizer.get_reading(&item));
+        match sections.iter_mut().find(|(key, _)| *key == section) {
+            Some((_, members)) => members.push(item),
+            None => sections.push((section, vec![item])),
+        }
+    }
+
+    sections.sort_by_key(|(key, _)| key == OTHER_SECTION);
+
+    sections
+        .into_iter()
+        .flat_map(|(section, members)| {
+            std::iter::once(ItemListEntry::Header(section))
+                .chain(members.into_iter().map(ItemListEntry::Item))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn items(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    fn header(section: &str) -> ItemListEntry {
+        ItemListEntry::Header(section.to_string())
+    }
+
+    fn item(id: &str) -> ItemListEntry {
+        ItemListEntry::Item(id.to_string())
+    }
+
+    #[test]
+    fn test_other_section_sorts_last() {
+        let entries = with_section_headers(
+            items(&["1st_ore", "carbon", "amethyst_fiber"]),
+            Locale::English,
+            &Localizer::empty(),
+        );
+
+        assert_eq!(
+            entries,
+            vec![
+                header("C"),
+                item("carbon"),
+                header("A"),
+                item("amethyst_fiber"),
+                header(OTHER_SECTION),
+                item("1st_ore"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sections_keep_first_appearance_order() {
+        let entries = with_section_headers(
+            items(&["steel", "carbon", "sandleaf", "copper"]),
+            Locale::English,
+            &Localizer::empty(),
+        );
+
+        assert_eq!(
+            entries,
+            vec![
+                header("S"),
+                item("steel"),
+                item("sandleaf"),
+                header("C"),
+                item("carbon"),
+                item("copper"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_no_headers_for_short_search_results() {
+        assert!(!shows_section_headers(true, SECTION_HEADER_MIN_ITEMS - 1));
+        assert!(shows_section_headers(true, SECTION_HEADER_MIN_ITEMS));
+        assert!(shows_section_headers(false, 1));
+    }
+}
@@ -1,16 +1,96 @@
+use endfield_planner_core::config::{DataWarning, GameData};
 use endfield_planner_core::i18n::Localizer;
-use std::collections::HashSet;
+use endfield_planner_core::models::ItemId;
+use endfield_planner_core::planner::PlanWarning;
 
-/// Helper function to get the localized name for an item ID.
-/// Checks if the ID is a machine and uses the appropriate localizer method.
-pub fn get_localized_name(
-    item_id: &str,
+/// Fills a pluralized UI template with an already formatted number, e.g.
+/// with digit grouping. Only exactly 1 takes the `one` form, so 1.5 is
+/// plural.
+pub fn localize_amount(localizer: &Localizer, key: &str, value: f64, formatted: &str) -> String {
+    let count = if value == 1.0 { 1 } else { 0 };
+    localizer
+        .get_ui_plural_template(key, count)
+        .replace("{count}", formatted)
+}
+
+/// Formats a planner warning using the localized UI templates.
+pub fn localize_warning(
+    warning: &PlanWarning,
+    localizer: &Localizer,
+    game_data: &GameData,
+) -> String {
+    let name = |id: &ItemId| localizer.get_name(id.as_str(), game_data);
+
+    match warning {
+        PlanWarning::MissingRecipe { item_id } => localizer
+            .get_ui("warning_missing_recipe")
+            .replace("{item}", &name(item_id)),
+        PlanWarning::MissingMachine {
+            item_id,
+            machine_id,
+            ..
+        } => localizer
+            .get_ui("warning_missing_machine")
+            .replace("{item}", &name(item_id))
+            .replace("{machine}", &localizer.get_machine(machine_id.as_str())),
+        PlanWarning::CyclicInputSkipped {
+            item_id, input_id, ..
+        } => localizer
+            .get_ui("warning_cyclic_input")
+            .replace("{item}", &name(item_id))
+            .replace("{input}", &name(input_id)),
+        PlanWarning::OutputDefaulted { item_id, .. } => localizer
+            .get_ui("warning_output_defaulted")
+            .replace("{item}", &name(item_id)),
+        PlanWarning::NoNetOutput { item_id, .. } => localizer
+            .get_ui("warning_no_net_output")
+            .replace("{item}", &name(item_id)),
+        PlanWarning::DepositsExceeded {
+            item_id,
+            amount,
+            max_rate,
+            deposits,
+            ..
+        } => localizer
+            .get_ui("warning_deposits_exceeded")
+            .replace("{item}", &name(item_id))
+            .replace("{amount}", &amount.to_string())
+            .replace("{max_rate}", &max_rate.to_string())
+            .replace("{deposits}", &deposits.to_string()),
+        PlanWarning::SharedAmountUnsettled {
+            item_id,
+            planned,
+            needed,
+        } => localizer
+            .get_ui("warning_shared_unsettled")
+            .replace("{item}", &name(item_id))
+            .replace("{planned}", &planned.to_string())
+            .replace("{needed}", &needed.to_string()),
+    }
+}
+
+/// Formats a data warning using the localized UI templates.
+pub fn localize_data_warning(
+    warning: &DataWarning,
     localizer: &Localizer,
-    machine_ids: &HashSet<String>,
+    game_data: &GameData,
 ) -> String {
-    if machine_ids.contains(item_id) {
-        localizer.get_machine(item_id)
-    } else {
-        localizer.get_item(item_id)
+    match warning {
+        DataWarning::MissingSelfOutput { recipe_id, item_id } => localizer
+            .get_ui("data_warning_missing_self_output")
+            .replace("{item}", &localizer.get_name(item_id.as_str(), game_data))
+            .replace("{recipe}", recipe_id.as_str()),
+        DataWarning::PortsExceeded {
+            recipe_id,
+            machine_id,
+            direction,
+            count,
+            ports,
+        } => localizer
+            .get_ui(&format!("data_warning_{}_ports_exceeded", direction))
+            .replace("{recipe}", recipe_id.as_str())
+            .replace("{machine}", &localizer.get_machine(machine_id.as_str()))
+            .replace("{count}", &count.to_string())
+            .replace("{ports}", &ports.to_string()),
     }
 }
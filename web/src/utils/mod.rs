@@ -1,2 +1,14 @@
+pub mod alternatives;
+pub mod amount;
+pub mod clipboard;
+pub mod compare;
+pub mod complexity;
+pub mod download;
+pub mod error;
+pub mod item_list;
 pub mod localization;
+pub mod number;
+pub mod power;
+pub mod scroll;
+pub mod storage;
 pub mod url;
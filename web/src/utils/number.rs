@@ -0,0 +1,7 @@
+use endfield_planner_core::i18n::Locale;
+use web_sys::js_sys::Number;
+
+/// Formats a number with the browser's locale-aware digit grouping.
+pub fn format_number(value: f64, locale: Locale) -> String {
+    Number::from(value).to_locale_string(locale.code()).into()
+}
@@ -0,0 +1,45 @@
+use endfield_planner_core::config::GameData;
+use endfield_planner_core::planner::{PlanOptions, plan_production_with_options};
+
+use crate::utils::amount::MIN_AMOUNT;
+
+/// Target amount multipliers shown in the power chart.
+pub const POWER_SCALE_FACTORS: [f64; 4] = [0.5, 1.0, 1.5, 2.0];
+
+/// Peak power and machine total of the plan at one scaled amount.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PowerSample {
+    pub amount: u32,
+    pub power: u32,
+    pub machines: u32,
+}
+
+/// Re-plans the target at each of `POWER_SCALE_FACTORS` times the amount.
+pub fn sample_power_scaling(
+    game_data: &GameData,
+    item_id: &str,
+    amount: u32,
+    options: &PlanOptions,
+) -> Vec<PowerSample> {
+    POWER_SCALE_FACTORS
+        .iter()
+        .map(|factor| {
+            let scaled = ((amount as f64 * factor).round() as u32).max(MIN_AMOUNT);
+            let node = plan_production_with_options(game_data, item_id, scaled, options)
+                .map(|result| result.node);
+
+            match node {
+                Ok(node) => PowerSample {
+                    amount: scaled,
+                    power: node.total_power(),
+                    machines: node.total_machines().values().sum(),
+                },
+                Err(_) => PowerSample {
+                    amount: scaled,
+                    power: 0,
+                    machines: 0,
+                },
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,83 @@
+use leptos::html;
+use leptos::prelude::*;
+use std::collections::HashMap;
+use web_sys::js_sys::Date;
+
+/// Minimum time between two saved scroll positions while scrolling.
+const SCROLL_SAVE_INTERVAL_MS: f64 = 100.0;
+
+/// Remembers the scroll position of a container so it can be restored after
+/// the container is hidden or its content is re-rendered.
+///
+/// Positions are saved per key (e.g. the search query) and only restored
+/// for the same key, since offsets into a different list are meaningless.
+#[derive(Clone, Copy)]
+pub struct ScrollMemory {
+    pub node_ref: NodeRef<html::Div>,
+    offsets: RwSignal<HashMap<String, i32>>,
+    last_saved_at: StoredValue<f64>,
+}
+
+/// Creates a `ScrollMemory`. Attach `node_ref` to the scrolling element and
+/// call `on_scroll` from its scroll handler.
+pub fn use_scroll_memory() -> ScrollMemory {
+    ScrollMemory {
+        node_ref: NodeRef::new(),
+        offsets: RwSignal::new(HashMap::new()),
+        last_saved_at: StoredValue::new(0.0),
+    }
+}
+
+impl ScrollMemory {
+    /// Saves the current position, at most once per `SCROLL_SAVE_INTERVAL_MS`.
+    pub fn on_scroll(&self, key: &str) {
+        let now = Date::now();
+        if now - self.last_saved_at.get_value() >= SCROLL_SAVE_INTERVAL_MS {
+            self.save(key);
+        }
+    }
+
+    /// Saves the current position immediately.
+    pub fn save(&self, key: &str) {
+        if let Some(element) = self.node_ref.get_untracked() {
+            let offset = element.scroll_top();
+            self.offsets.update_untracked(|offsets| {
+                offsets.insert(key.to_string(), offset);
+            });
+            self.last_saved_at.set_value(Date::now());
+        }
+    }
+
+    /// Restores the position saved under `key`, if any.
+    ///
+    /// Runs on the next animation frame so freshly rendered content has
+    /// its final height.
+    pub fn restore(&self, key: &str) {
+        let Some(offset) = self
+            .offsets
+            .with_untracked(|offsets| offsets.get(key).copied())
+        else {
+            return;
+        };
+
+        let node_ref = self.node_ref;
+        request_animation_frame(move || {
+            if let Some(element) = node_ref.get_untracked() {
+                element.set_scroll_top(offset);
+            }
+        });
+    }
+
+    /// Scrolls the first descendant matching `selector` into view.
+    pub fn reveal(&self, selector: &'static str) {
+        let node_ref = self.node_ref;
+        request_animation_frame(move || {
+            let target = node_ref
+                .get_untracked()
+                .and_then(|element| element.query_selector(selector).ok().flatten());
+            if let Some(target) = target {
+                target.scroll_into_view_with_bool(false);
+            }
+        });
+    }
+}
@@ -0,0 +1,102 @@
+use endfield_planner_core::i18n::Locale;
+use web_sys::window;
+
+/// localStorage key for the recently selected items.
+const RECENT_ITEMS_KEY: &str = "endfield_planner.recent_items";
+
+/// localStorage key for the language chosen in the settings.
+const LOCALE_KEY: &str = "endfield_planner.locale";
+
+/// Maximum number of recently selected items to remember.
+pub const RECENT_ITEMS_LIMIT: usize = 5;
+
+fn local_storage() -> Option<web_sys::Storage> {
+    window()?.local_storage().ok()?
+}
+
+/// Loads the recently selected items from localStorage (most recent first).
+pub fn load_recent_items() -> Vec<String> {
+    let Some(storage) = local_storage() else {
+        return Vec::new();
+    };
+
+    let Ok(Some(value)) = storage.get_item(RECENT_ITEMS_KEY) else {
+        return Vec::new();
+    };
+
+    value
+        .split(',')
+        .filter(|item| !item.is_empty())
+        .map(|item| item.to_string())
+        .take(RECENT_ITEMS_LIMIT)
+        .collect()
+}
+
+/// Saves the recently selected items to localStorage.
+pub fn save_recent_items(items: &[String]) {
+    if let Some(storage) = local_storage() {
+        let _ = storage.set_item(RECENT_ITEMS_KEY, &items.join(","));
+    }
+}
+
+/// Loads the language chosen in the settings, if any.
+pub fn load_locale() -> Option<Locale> {
+    let value = local_storage()?.get_item(LOCALE_KEY).ok()??;
+    Locale::from_code(&value)
+}
+
+/// Remembers the language chosen in the settings.
+pub fn save_locale(locale: Locale) {
+    if let Some(storage) = local_storage() {
+        let _ = storage.set_item(LOCALE_KEY, locale.code());
+    }
+}
+
+/// Lists the browser's preferred languages, most preferred first.
+pub fn browser_languages() -> Vec<String> {
+    window()
+        .map(|window| {
+            window
+                .navigator()
+                .languages()
+                .iter()
+                .filter_map(|language| language.as_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Moves `item` to the front of `items`, dropping duplicates and
+/// anything beyond `limit`.
+pub fn push_recent_item(items: &mut Vec<String>, item: &str, limit: usize) {
+    items.retain(|existing| existing != item);
+    items.insert(0, item.to_string());
+    items.truncate(limit);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn items(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[test]
+    fn test_push_recent_item_moves_duplicate_to_front() {
+        let mut recent = items(&["origocrust", "amethyst_fiber", "carbon"]);
+
+        push_recent_item(&mut recent, "carbon", RECENT_ITEMS_LIMIT);
+
+        assert_eq!(recent, items(&["carbon", "origocrust", "amethyst_fiber"]));
+    }
+
+    #[test]
+    fn test_push_recent_item_truncates_at_limit() {
+        let mut recent = items(&["origocrust", "amethyst_fiber", "carbon"]);
+
+        push_recent_item(&mut recent, "steel", 3);
+
+        assert_eq!(recent, items(&["steel", "origocrust", "amethyst_fiber"]));
+    }
+}
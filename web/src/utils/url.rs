@@ -1,10 +1,64 @@
+use endfield_planner_core::models::{NodePath, canonical_id};
+use endfield_planner_core::planner::{ShareState, decode_node_paths, encode_node_paths};
 use web_sys::{wasm_bindgen, window};
 
+/// Delay before plan changes are written to the URL, so toggling many tree
+/// nodes in a row rewrites it once.
+pub const URL_UPDATE_DELAY_MS: u64 = 300;
+
 /// URL parameters for production planning.
 #[derive(Debug, Clone, Default)]
 pub struct UrlParams {
     pub item: Option<String>,
     pub amount: Option<u32>,
+    pub excluded: Vec<String>,
+    /// Item of the second plan in compare mode.
+    pub compare_item: Option<String>,
+    pub compare_amount: Option<u32>,
+    /// Collapsed nodes of the production tree.
+    pub collapsed: Vec<NodePath>,
+}
+
+/// The second plan shown in compare mode.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompareUrlState {
+    pub item: String,
+    pub amount: u32,
+}
+
+/// Plan state mirrored into the URL: what a factory code holds, plus
+/// compare mode.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlanUrlState {
+    pub plan: ShareState,
+    /// Set while compare mode is open.
+    pub compare: Option<CompareUrlState>,
+}
+
+/// Returns true if the URL has to be rewritten to reflect `next`, i.e. it
+/// differs from the state written last time.
+pub fn should_update_url(last_written: Option<&PlanUrlState>, next: &PlanUrlState) -> bool {
+    last_written != Some(next)
+}
+
+/// Keeps the excluded items that exist in `all_items`, sorted and without
+/// duplicates, since they may come from a hand-edited URL or code.
+pub fn known_excluded(
+    excluded: impl IntoIterator<Item = String>,
+    all_items: &[String],
+) -> Vec<String> {
+    let mut excluded: Vec<String> = excluded
+        .into_iter()
+        .filter(|item| all_items.contains(item))
+        .collect();
+    excluded.sort();
+    excluded.dedup();
+    excluded
+}
+
+/// Parses a positive amount parameter.
+fn parse_amount(value: &str) -> Option<u32> {
+    value.parse::<u32>().ok().filter(|amount| *amount > 0)
 }
 
 /// Parses URL parameters from the current browser URL.
@@ -24,26 +78,80 @@ pub fn parse_url_params() -> UrlParams {
     };
     let search_params = url.search_params();
 
+    // IDs may be typed by hand, so they are brought into the data's form
     if let Some(item) = search_params.get("item") {
+        let item = canonical_id(&item);
         if !item.is_empty() {
-            params.item = Some(item);
+            params.item = Some(item.into_owned());
         }
     }
 
     if let Some(amount_str) = search_params.get("amount") {
-        if let Ok(amount) = amount_str.parse::<u32>() {
-            if amount > 0 {
-                params.amount = Some(amount);
-            }
+        params.amount = parse_amount(&amount_str);
+    }
+
+    if let Some(excluded) = search_params.get("exclude") {
+        params.excluded = excluded
+            .split(',')
+            .map(|item| canonical_id(item).into_owned())
+            .filter(|item| !item.is_empty())
+            .collect();
+    }
+
+    if let Some(item) = search_params.get("compare_item") {
+        let item = canonical_id(&item);
+        if !item.is_empty() {
+            params.compare_item = Some(item.into_owned());
         }
     }
 
+    if let Some(amount_str) = search_params.get("compare_amount") {
+        params.compare_amount = parse_amount(&amount_str);
+    }
+
+    if let Some(collapsed) = search_params.get("collapse") {
+        params.collapsed = decode_node_paths(&collapsed);
+    }
+
     params
 }
 
+/// Writes the plan parameters into URL search params.
+/// The `exclude` and `collapse` parameters are omitted when empty, and the
+/// `compare_*` parameters when compare mode is closed.
+fn set_plan_params(search_params: &web_sys::UrlSearchParams, state: &PlanUrlState) {
+    let plan = &state.plan;
+    search_params.set("item", plan.item.as_str());
+    search_params.set("amount", &plan.amount.to_string());
+
+    if plan.excluded.is_empty() {
+        search_params.delete("exclude");
+    } else {
+        let excluded: Vec<&str> = plan.excluded.iter().map(|item| item.as_str()).collect();
+        search_params.set("exclude", &excluded.join(","));
+    }
+
+    match &state.compare {
+        Some(compare) => {
+            search_params.set("compare_item", &compare.item);
+            search_params.set("compare_amount", &compare.amount.to_string());
+        }
+        None => {
+            search_params.delete("compare_item");
+            search_params.delete("compare_amount");
+        }
+    }
+
+    if plan.collapsed.is_empty() {
+        search_params.delete("collapse");
+    } else {
+        search_params.set("collapse", &encode_node_paths(&plan.collapsed));
+    }
+}
+
 /// Updates the browser URL with the given parameters without reloading.
 /// Uses History API's replaceState to update URL silently.
-pub fn update_url_params(item: &str, amount: u32) {
+pub fn update_url_params(state: &PlanUrlState) {
     let Some(window) = window() else {
         return;
     };
@@ -57,8 +165,7 @@ pub fn update_url_params(item: &str, amount: u32) {
     };
 
     let search_params = url.search_params();
-    search_params.set("item", item);
-    search_params.set("amount", &amount.to_string());
+    set_plan_params(&search_params, state);
 
     let new_url = format!("{}?{}", url.pathname(), search_params.to_string());
 
@@ -68,14 +175,13 @@ pub fn update_url_params(item: &str, amount: u32) {
 }
 
 /// Generates a shareable URL string for the given parameters.
-pub fn generate_share_url(item: &str, amount: u32) -> Option<String> {
+pub fn generate_share_url(state: &PlanUrlState) -> Option<String> {
     let window = window()?;
     let location = window.location().href().ok()?;
     let url = web_sys::Url::new(&location).ok()?;
 
     let search_params = url.search_params();
-    search_params.set("item", item);
-    search_params.set("amount", &amount.to_string());
+    set_plan_params(&search_params, state);
 
     Some(format!(
         "{}//{}{}?{}",
@@ -85,3 +191,70 @@ pub fn generate_share_url(item: &str, amount: u32) -> Option<String> {
         search_params.to_string()
     ))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(item: &str, amount: u32, excluded: &[&str]) -> PlanUrlState {
+        PlanUrlState {
+            plan: ShareState {
+                excluded: excluded.iter().map(|item| (*item).into()).collect(),
+                ..ShareState::new(item, amount)
+            },
+            compare: None,
+        }
+    }
+
+    #[test]
+    fn test_should_update_url() {
+        let current = state("origocrust", 30, &["originium_ore"]);
+
+        assert!(should_update_url(None, &current));
+        assert!(!should_update_url(Some(&current), &current.clone()));
+        assert!(should_update_url(
+            Some(&current),
+            &state("origocrust", 60, &["originium_ore"])
+        ));
+        assert!(should_update_url(
+            Some(&current),
+            &state("amethyst_fiber", 30, &["originium_ore"])
+        ));
+        assert!(should_update_url(
+            Some(&current),
+            &state("origocrust", 30, &[])
+        ));
+
+        let comparing = PlanUrlState {
+            compare: Some(CompareUrlState {
+                item: "amethyst_fiber".to_string(),
+                amount: 30,
+            }),
+            ..current.clone()
+        };
+        assert!(should_update_url(Some(&current), &comparing));
+
+        let mut collapsed = current.clone();
+        collapsed.plan.collapsed = vec![NodePath(vec![0]), NodePath(vec![1, 2])];
+        assert!(should_update_url(Some(&current), &collapsed));
+    }
+
+    #[test]
+    fn test_known_excluded() {
+        let all_items = vec!["amethyst_fiber".to_string(), "origocrust".to_string()];
+        let excluded =
+            ["origocrust", "unknown_item", "amethyst_fiber", "origocrust"].map(String::from);
+
+        assert_eq!(
+            known_excluded(excluded, &all_items),
+            vec!["amethyst_fiber".to_string(), "origocrust".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_parse_amount() {
+        assert_eq!(parse_amount("30"), Some(30));
+        assert_eq!(parse_amount("0"), None);
+        assert_eq!(parse_amount("abc"), None);
+    }
+}